@@ -0,0 +1,10 @@
+//! Bakes the git state into the binary as `OBSERVABILITY_GIT_*` variables,
+//! which `release_info::ReleaseInfo::built` reads.
+
+#[allow(dead_code)]
+#[path = "src/release_info.rs"]
+mod release_info;
+
+fn main() {
+    release_info::emit_build_env();
+}
@@ -0,0 +1,356 @@
+//! Attribute and derive macros for the Bugsink/Sentry Rust example.
+//!
+//! Expansions refer to the example's modules through `crate::` paths, so the
+//! macros are used through the re-exports in `rust_example`, not directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    parse::{ParseStream, Parser},
+    parse_macro_input,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Data, DeriveInput, Error, Fields, FnArg, Ident, ItemFn, LitStr, Meta, MetaNameValue, Pat, Result, ReturnType,
+    Token, Type,
+};
+
+/// Capture the `Err` of a function returning `Result` before it is
+/// propagated, tagged with the function name, module path and the arguments
+/// listed in `tags(...)` (which must implement `Display`).
+///
+/// ```ignore
+/// #[captured(tags(user_id, region))]
+/// pub async fn load_user(user_id: u64, region: &str, token: &str) -> Result<User, AppError> {
+///     let user = db::find(user_id, token).await?;
+///     Ok(user)
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn captured(args: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    expand_captured(args.into(), function)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_captured(args: TokenStream2, function: ItemFn) -> Result<TokenStream2> {
+    let mut tags = Vec::new();
+    for meta in Punctuated::<Meta, Token![,]>::parse_terminated.parse2(args)? {
+        match meta {
+            Meta::List(list) if list.path.is_ident("tags") => {
+                tags.extend(list.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)?);
+            }
+            other => return Err(Error::new(other.span(), "expected `tags(argument, ...)`")),
+        }
+    }
+    let arguments = argument_names(&function);
+    if let Some(tag) = tags.iter().find(|tag| !arguments.contains(tag)) {
+        return Err(Error::new(
+            tag.span(),
+            format!("`{}` is not an argument of this function", tag),
+        ));
+    }
+    let ret = result_type(&function, "#[captured]")?;
+    let run = run_body(&function, ret);
+    let name = function.sig.ident.to_string();
+    let keys = tags.iter().map(|tag| format!("arg.{}", tag));
+    let count = tags.len();
+    let ItemFn { attrs, vis, sig, .. } = &function;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __tags: [(&str, String); #count] = [#((#keys, #tags.to_string())),*];
+            #[allow(clippy::redundant_closure_call)]
+            let __result: #ret = #run;
+            crate::capture::CaptureResultExt::capture_with(__result, |scope| {
+                scope.set_tag("function", #name);
+                scope.set_tag("module", module_path!());
+                for (key, value) in &__tags {
+                    scope.set_tag(key, value);
+                }
+            })
+        }
+    })
+}
+
+/// Run a sync or async function inside a child of the active span, or a new
+/// transaction when there is none. The name defaults to the function path and
+/// the op to `Op::Function`:
+///
+/// ```ignore
+/// #[traced_transaction(op = Op::DbQuery, name = "users.load")]
+/// pub async fn load_user(&self, id: u64) -> Result<User, AppError> { ... }
+/// ```
+#[proc_macro_attribute]
+pub fn traced_transaction(args: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    expand_traced_transaction(args.into(), function)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_traced_transaction(args: TokenStream2, function: ItemFn) -> Result<TokenStream2> {
+    let fn_name = function.sig.ident.to_string();
+    let mut name = quote!(concat!(module_path!(), "::", #fn_name));
+    let mut op = quote!(crate::ops::Op::Function);
+    for MetaNameValue { path, value, .. } in Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(args)? {
+        if path.is_ident("name") {
+            name = value.into_token_stream();
+        } else if path.is_ident("op") {
+            op = value.into_token_stream();
+        } else {
+            return Err(Error::new(path.span(), "expected `op = ...` or `name = ...`"));
+        }
+    }
+    let ret = match &function.sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => ty.to_token_stream(),
+    };
+    let body = &function.block;
+    let run = if function.sig.asyncness.is_some() {
+        quote!(crate::traced::run_async::<#ret, _>(__span, async move #body).await)
+    } else {
+        quote!(crate::traced::run(__span, move || -> #ret #body))
+    };
+    let ItemFn { attrs, vis, sig, .. } = &function;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __span = crate::traced::start(#name, #op);
+            #run
+        }
+    })
+}
+
+/// Wrap a job function returning `Result` in a cron check-in: started on
+/// entry, `ok` / `error` from the result, failed on panic. An optional
+/// crontab schedule is sent along so missed runs are detected. The error
+/// type must implement `std::error::Error`.
+///
+/// ```ignore
+/// #[monitored_job("nightly-backup", schedule = "0 3 * * *")]
+/// pub async fn nightly_backup(target: &Path) -> Result<u64, BackupError> { ... }
+/// ```
+#[proc_macro_attribute]
+pub fn monitored_job(args: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    expand_monitored_job(args.into(), function)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_monitored_job(args: TokenStream2, function: ItemFn) -> Result<TokenStream2> {
+    let (slug, schedule) = (|input: ParseStream| {
+        let slug: LitStr = input.parse()?;
+        let mut schedule = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key != "schedule" {
+                return Err(Error::new(key.span(), "expected `schedule = \"...\"`"));
+            }
+            input.parse::<Token![=]>()?;
+            schedule = Some(input.parse::<LitStr>()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok((slug, schedule))
+    })
+    .parse2(args)?;
+    let monitor = match schedule {
+        None => quote!(crate::cron::CronMonitor::new()),
+        Some(schedule) => quote! {
+            crate::cron::CronMonitor::new()
+                .schedule(#slug, #schedule)
+                .unwrap_or_else(|e| {
+                    eprintln!("Invalid schedule for monitor {}: {}", #slug, e);
+                    crate::cron::CronMonitor::new()
+                })
+        },
+    };
+    let ret = result_type(&function, "#[monitored_job]")?;
+    let run = run_body(&function, ret);
+    let ItemFn { attrs, vis, sig, .. } = &function;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __check_in = #monitor.check_in_start(#slug);
+            #[allow(clippy::redundant_closure_call)]
+            let __result: #ret = #run;
+            __check_in.finish_with(&__result);
+            __result
+        }
+    })
+}
+
+/// Names of the arguments bound to a plain identifier.
+fn argument_names(function: &ItemFn) -> Vec<Ident> {
+    let inputs = function.sig.inputs.iter();
+    inputs
+        .filter_map(|input| match input {
+            FnArg::Typed(typed) => match &*typed.pat {
+                Pat::Ident(pat) => Some(pat.ident.clone()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+fn result_type<'a>(function: &'a ItemFn, macro_name: &str) -> Result<&'a Type> {
+    match &function.sig.output {
+        ReturnType::Type(_, ty) => Ok(ty),
+        ReturnType::Default => Err(Error::new(
+            function.sig.span(),
+            format!("{} functions must return a `Result`", macro_name),
+        )),
+    }
+}
+
+/// The function body as an expression of type `ret`, with `return` and `?`
+/// leaving the body rather than the function.
+fn run_body(function: &ItemFn, ret: &Type) -> TokenStream2 {
+    let body = &function.block;
+    match function.sig.asyncness {
+        Some(_) => quote!(async move #body.await),
+        None => quote!((move || -> #ret #body)()),
+    }
+}
+
+/// Implement `TypedContext` for a struct with named fields. Each field
+/// becomes a key of the context, `None` fields are left out; field types
+/// must be `Clone` and convert into `serde_json::Value`.
+///
+/// ```ignore
+/// #[derive(Clone, TypedContext)]
+/// #[context("tenant")]
+/// pub struct TenantContext {
+///     pub id: String,
+///     pub plan: Option<String>,
+/// }
+/// ```
+#[proc_macro_derive(TypedContext, attributes(context))]
+pub fn derive_typed_context(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_typed_context(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_typed_context(input: DeriveInput) -> Result<TokenStream2> {
+    let context = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("context"))
+        .ok_or_else(|| Error::new(input.ident.span(), "missing `#[context(\"name\")]`"))?
+        .parse_args::<LitStr>()?;
+    let fields: Vec<&Ident> = named_fields(&input)?
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .collect();
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics crate::contexts::TypedContext for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #context;
+
+            fn to_map(&self) -> ::std::collections::BTreeMap<String, ::sentry::protocol::Value> {
+                let mut map = ::std::collections::BTreeMap::new();
+                #(
+                    let value: ::sentry::protocol::Value = self.#fields.clone().into();
+                    if !value.is_null() {
+                        map.insert(stringify!(#fields).to_string(), value);
+                    }
+                )*
+                map
+            }
+        }
+    })
+}
+
+/// Implement `Redact` and a redacting `Debug` for a struct with named
+/// fields; do not derive `Debug` as well. Fields marked `#[redact]` are
+/// replaced, `#[redact(hash)]` hashed and `#[redact(last4)]` cut to their
+/// last four characters. Field types must be `Clone` and convert into
+/// `serde_json::Value`.
+///
+/// ```ignore
+/// #[derive(Clone, Redact)]
+/// pub struct Customer {
+///     pub id: u64,
+///     #[redact(hash)]
+///     pub email: String,
+///     #[redact(last4)]
+///     pub iban: String,
+///     #[redact]
+///     pub date_of_birth: Option<String>,
+/// }
+/// ```
+#[proc_macro_derive(Redact, attributes(redact))]
+pub fn derive_redact(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_redact(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand_redact(input: DeriveInput) -> Result<TokenStream2> {
+    let mut inserts = Vec::new();
+    for field in named_fields(&input)? {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut value = quote!(self.#ident.clone().into());
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("redact")) {
+            let strategy = match &attr.meta {
+                Meta::Path(_) => quote!(Full),
+                Meta::List(list) => {
+                    let strategy: Ident = list.parse_args()?;
+                    match strategy.to_string().as_str() {
+                        "hash" => quote!(Hash),
+                        "last4" => quote!(Last4),
+                        _ => return Err(Error::new(strategy.span(), "expected `hash` or `last4`")),
+                    }
+                }
+                Meta::NameValue(meta) => {
+                    return Err(Error::new(meta.span(), "expected `#[redact]` or `#[redact(...)]`"))
+                }
+            };
+            value = quote!(crate::redact::Strategy::#strategy.apply(#value));
+        }
+        inserts.push(quote!(map.insert(stringify!(#ident).to_string(), #value);));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics crate::redact::Redact for #ident #ty_generics #where_clause {
+            fn redacted(&self) -> ::std::collections::BTreeMap<String, ::sentry::protocol::Value> {
+                let mut map = ::std::collections::BTreeMap::new();
+                #(#inserts)*
+                map
+            }
+        }
+
+        impl #impl_generics ::std::fmt::Debug for #ident #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                let redacted = crate::redact::Redact::redacted(self);
+                let mut debug = f.debug_struct(stringify!(#ident));
+                for (field, value) in &redacted {
+                    debug.field(field, &format_args!("{}", value));
+                }
+                debug.finish()
+            }
+        }
+    })
+}
+
+fn named_fields(input: &DeriveInput) -> Result<&Punctuated<syn::Field, Token![,]>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(Error::new(
+                input.ident.span(),
+                "only structs with named fields are supported",
+            )),
+        },
+        _ => Err(Error::new(
+            input.ident.span(),
+            "only structs with named fields are supported",
+        )),
+    }
+}
@@ -0,0 +1,306 @@
+//! Middleware for actix-web apps (feature `actix`).
+//!
+//! Like the axum layer it runs every request on its own hub and in a
+//! `http.server` transaction named after the route pattern
+//! (`GET /api/users/{id}`), continued from the caller's `sentry-trace`, with
+//! the request's [correlation ID](crate::correlation) as a tag, a request
+//! extension and a response header. 5xx
+//! responses are reported as events unless something was already captured
+//! for the request, with the handler's error message if it returned one.
+//!
+//! Request bodies are not captured by default. With
+//! [`Sentry::capture_body`](actix_integration::Sentry::capture_body), bodies of
+//! allowlisted content types up to `max_bytes` (by `Content-Length`) are
+//! buffered, attached to the request context and handed on to the handler;
+//! the scrubbing in `before_send` applies to them like to any other field.
+//! With [`Sentry::error_response`](actix_integration::Sentry::error_response)
+//! reported 5xx responses carry the event ID.
+//!
+//! ```ignore
+//! HttpServer::new(|| {
+//!     App::new()
+//!         .wrap(actix_integration::Sentry::new().capture_body(BodyCapture::default()))
+//!         .route("/api/users/{id}", web::get().to(get_user))
+//! })
+//! .bind("127.0.0.1:8080")?
+//! .run()
+//! .await
+//! ```
+
+use crate::{
+    correlation::CorrelationId,
+    error_response::ErrorResponse,
+    ops::{self, Op},
+    propagation, sessions,
+};
+use actix_web::{
+    body::{BoxBody, EitherBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorPayloadTooLarge,
+    http::{header, StatusCode},
+    web, Error, HttpMessage,
+};
+use sentry::{
+    protocol::{self, Event},
+    types::Uuid,
+    Hub, Level, SentryFutureExt,
+};
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+};
+use tracing::Instrument;
+
+/// Which request bodies are attached to events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyCapture {
+    /// Bodies with a larger (or unknown) `Content-Length` are skipped.
+    pub max_bytes: usize,
+    /// Media types whose bodies are captured; `type/*` matches a whole type.
+    pub content_types: Vec<String>,
+}
+
+impl Default for BodyCapture {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024,
+            content_types: vec![
+                "application/json".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+                "text/*".to_string(),
+            ],
+        }
+    }
+}
+
+impl BodyCapture {
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn content_types(mut self, content_types: &[&str]) -> Self {
+        self.content_types = content_types.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn allows(&self, content_type: &str) -> bool {
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        self.content_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(prefix) => essence
+                    .split_once('/')
+                    .is_some_and(|(ty, _)| ty.eq_ignore_ascii_case(prefix)),
+                None => essence.eq_ignore_ascii_case(allowed),
+            })
+    }
+
+    fn applies(&self, request: &ServiceRequest) -> Option<usize> {
+        let header = |name| request.headers().get(name).and_then(|value| value.to_str().ok());
+        let length = header(header::CONTENT_LENGTH)?.parse::<usize>().ok()?;
+        let allowed = length > 0 && length <= self.max_bytes && self.allows(header(header::CONTENT_TYPE)?);
+        allowed.then_some(length)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sentry {
+    body: Option<BodyCapture>,
+    capture_server_errors: bool,
+    error_response: Option<ErrorResponse>,
+}
+
+impl Default for Sentry {
+    fn default() -> Self {
+        Self {
+            body: None,
+            capture_server_errors: true,
+            error_response: None,
+        }
+    }
+}
+
+impl Sentry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn capture_body(mut self, capture: BodyCapture) -> Self {
+        self.body = Some(capture);
+        self
+    }
+
+    /// Report 5xx responses as events (default on).
+    pub fn capture_server_errors(mut self, enabled: bool) -> Self {
+        self.capture_server_errors = enabled;
+        self
+    }
+
+    /// Put the event ID into 5xx responses that were reported (see [`crate::error_response`]).
+    pub fn error_response(mut self, response: ErrorResponse) -> Self {
+        self.error_response = Some(response);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Sentry
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = SentryMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SentryMiddleware {
+            service: Rc::new(service),
+            options: Rc::new(self.clone()),
+        }))
+    }
+}
+
+pub struct SentryMiddleware<S> {
+    service: Rc<S>,
+    options: Rc<Sentry>,
+}
+
+impl<S, B> Service<ServiceRequest> for SentryMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut request: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let options = self.options.clone();
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+
+        let future = async move {
+            let hub = Hub::current();
+            let mut context = request_context(&request);
+            if let Some(limit) = options.body.as_ref().and_then(|capture| capture.applies(&request)) {
+                let body = match request.extract::<web::Payload>().await?.to_bytes_limited(limit).await {
+                    Ok(body) => body?,
+                    Err(e) => return Err(ErrorPayloadTooLarge(e)),
+                };
+                context.data = Some(String::from_utf8_lossy(&body).into_owned());
+                request.set_payload(body.into());
+            }
+
+            let correlation = CorrelationId::from_headers(request.headers());
+            correlation.bind(&hub);
+            request.extensions_mut().insert(correlation.clone());
+
+            let route = request.match_pattern().unwrap_or_else(|| request.path().to_string());
+            let name = ops::naming::http_transaction(request.method().as_str(), &route);
+            let ctx = propagation::continue_from_headers(&name, Op::HttpServer.as_str(), request.headers());
+            let transaction = hub.start_transaction(ctx);
+            sessions::start_request(&hub);
+            transaction.set_request(context.clone());
+            hub.configure_scope(|scope| {
+                scope.set_span(Some(transaction.clone().into()));
+                scope.set_transaction(Some(&name));
+                scope.add_event_processor(move |mut event| {
+                    if event.request.is_none() {
+                        event.request = Some(context.clone());
+                    }
+                    Some(event)
+                });
+            });
+
+            let last_event = hub.last_event_id();
+            let mut result = service.call(request).instrument(correlation.span()).await;
+            if let Ok(response) = &mut result {
+                correlation.inject(response.headers_mut());
+            }
+            let (status, error) = match &result {
+                Ok(response) => (response.status(), response.response().error()),
+                Err(e) => (e.as_response_error().status_code(), Some(e)),
+            };
+            if options.capture_server_errors && status.is_server_error() && hub.last_event_id() == last_event {
+                hub.capture_event(server_error_event(status, error));
+            }
+            let event_id = hub
+                .last_event_id()
+                .filter(|event_id| status.is_server_error() && Some(*event_id) != last_event);
+
+            transaction.set_data("http.response.status_code", status.as_u16().into());
+            transaction.set_status(ops::http_span_status(status.as_u16()));
+            transaction.finish();
+            sessions::end_request(&hub);
+            match (result, options.error_response.as_ref().zip(event_id)) {
+                (Ok(response), Some((template, event_id))) => Ok(with_event_id(response, template, event_id)),
+                (result, _) => result.map(ServiceResponse::map_into_left_body),
+            }
+        };
+        Box::pin(future.bind_hub(hub))
+    }
+}
+
+fn with_event_id<B>(
+    response: ServiceResponse<B>,
+    options: &ErrorResponse,
+    event_id: Uuid,
+) -> ServiceResponse<EitherBody<B>> {
+    let mut response = match options.render_body(event_id) {
+        Some(body) => response.map_body(|head, _| {
+            if let Ok(content_type) = header::HeaderValue::from_str(&options.content_type) {
+                head.headers_mut().insert(header::CONTENT_TYPE, content_type);
+            }
+            EitherBody::right(BoxBody::new(body))
+        }),
+        None => response.map_into_left_body(),
+    };
+    if let Some(name) = &options.header {
+        if let (Ok(name), Ok(value)) = (
+            header::HeaderName::from_bytes(name.as_bytes()),
+            header::HeaderValue::from_str(&event_id.to_string()),
+        ) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+fn request_context(request: &ServiceRequest) -> protocol::Request {
+    let info = request.connection_info();
+    let url = format!("{}://{}{}", info.scheme(), info.host(), request.uri());
+    protocol::Request {
+        url: url.parse().ok(),
+        method: Some(request.method().to_string()),
+        query_string: request.uri().query().map(str::to_string),
+        headers: request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn server_error_event(status: StatusCode, error: Option<&Error>) -> Event<'static> {
+    let message = match error {
+        Some(error) => format!("HTTP {}: {}", status, error),
+        None => format!("HTTP {}", status),
+    };
+    let mut event = Event {
+        level: Level::Error,
+        message: Some(message),
+        ..Default::default()
+    };
+    event
+        .tags
+        .insert("http.status_code".to_string(), status.as_u16().to_string());
+    event
+}
@@ -0,0 +1,242 @@
+//! One event per window for chatty error sources instead of one per error.
+//!
+//! Events matching an [`AggregationRule`](aggregation::AggregationRule) are
+//! held back and bucketed by issue. When a bucket's window ends, its first
+//! event is sent once, carrying the stack trace of that sample and the extras
+//! `aggregation.count`, `aggregation.first_seen` and `aggregation.last_seen`
+//! (seconds since the epoch). Meant for edge agents on metered links, where a
+//! failing sensor must not produce thousands of requests.
+//!
+//! Rules match by fingerprint rule name (the `fingerprint.rule` tag set by
+//! [`fingerprint`]) and exception type:
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .fingerprint_rule(FingerprintRule::new("sensor-read", &["sensor-read"]).module("agent::sensors"))
+//!     .aggregation_rule(AggregationRule::new(Duration::from_secs(300)).fingerprint("sensor-read"))
+//!     .build();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [[aggregation]]
+//! fingerprint = "sensor-read"
+//! window_secs = 300
+//! ```
+//!
+//! Pending buckets are sent by `SentryService::flush` and `close`.
+
+use super::fingerprint;
+use sentry::{protocol::Event, Hub};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex, OnceLock, RwLock, Weak},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Extra with the number of events in the window.
+pub const EXTRA_COUNT: &str = "aggregation.count";
+
+/// How often the background thread looks for finished windows, at most.
+const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregationRule {
+    /// Name of the fingerprint rule that grouped the event.
+    pub fingerprint: Option<String>,
+    pub exception_type: Option<String>,
+    pub window: Duration,
+}
+
+impl AggregationRule {
+    /// A rule that matches every event until conditions are added.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            fingerprint: None,
+            exception_type: None,
+            window,
+        }
+    }
+
+    pub fn fingerprint(mut self, rule_name: &str) -> Self {
+        self.fingerprint = Some(rule_name.to_string());
+        self
+    }
+
+    pub fn exception_type(mut self, ty: &str) -> Self {
+        self.exception_type = Some(ty.to_string());
+        self
+    }
+
+    pub fn matches(&self, event: &Event<'_>) -> bool {
+        if let Some(name) = &self.fingerprint {
+            if event.tags.get(fingerprint::TAG) != Some(name) {
+                return false;
+            }
+        }
+        self.exception_type
+            .as_ref()
+            .is_none_or(|ty| event.exception.values.iter().any(|exception| &exception.ty == ty))
+    }
+}
+
+struct Bucket {
+    started: Instant,
+    window: Duration,
+    sample: Event<'static>,
+    count: u64,
+    last_seen: SystemTime,
+}
+
+impl Bucket {
+    fn into_event(self) -> Event<'static> {
+        let mut event = self.sample;
+        let first_seen = event.timestamp;
+        event.timestamp = self.last_seen;
+        event.extra.insert(EXTRA_COUNT.to_string(), self.count.into());
+        event
+            .extra
+            .insert("aggregation.first_seen".to_string(), unix_secs(first_seen).into());
+        event
+            .extra
+            .insert("aggregation.last_seen".to_string(), unix_secs(self.last_seen).into());
+        event
+    }
+}
+
+/// Seconds since the epoch, like event timestamps on the wire.
+fn unix_secs(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
+
+pub struct Aggregator {
+    rules: Vec<AggregationRule>,
+    buckets: Mutex<HashMap<u64, Bucket>>,
+}
+
+impl Aggregator {
+    pub fn new(rules: Vec<AggregationRule>) -> Self {
+        Self {
+            rules,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add the event to its bucket; false when no rule matches and the
+    /// event should be sent as usual.
+    pub fn aggregate(&self, event: &Event<'static>) -> bool {
+        let Some((index, rule)) = self.rules.iter().enumerate().find(|(_, rule)| rule.matches(event)) else {
+            return false;
+        };
+        let mut hasher = DefaultHasher::new();
+        (index, fingerprint::issue_key(event)).hash(&mut hasher);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(hasher.finish()).or_insert_with(|| Bucket {
+            started: Instant::now(),
+            window: rule.window,
+            sample: event.clone(),
+            count: 0,
+            last_seen: event.timestamp,
+        });
+        bucket.count += 1;
+        bucket.last_seen = bucket.last_seen.max(event.timestamp);
+        true
+    }
+
+    /// Summaries of the buckets whose window has ended.
+    pub fn take_due(&self) -> Vec<Event<'static>> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let due: Vec<u64> = buckets
+            .iter()
+            .filter(|(_, bucket)| now.duration_since(bucket.started) >= bucket.window)
+            .map(|(key, _)| *key)
+            .collect();
+        due.into_iter()
+            .filter_map(|key| buckets.remove(&key))
+            .map(Bucket::into_event)
+            .collect()
+    }
+
+    /// Summaries of all buckets, finished or not.
+    pub fn take_all(&self) -> Vec<Event<'static>> {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.drain().map(|(_, bucket)| bucket.into_event()).collect()
+    }
+
+    fn tick(&self) -> Duration {
+        self.rules
+            .iter()
+            .map(|rule| rule.window)
+            .min()
+            .unwrap_or(TICK)
+            .min(TICK)
+    }
+}
+
+fn state() -> &'static RwLock<Option<Arc<Aggregator>>> {
+    static STATE: OnceLock<RwLock<Option<Arc<Aggregator>>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Aggregate events matching `rules`; an empty list turns aggregation off.
+/// Buckets of the previous rules are sent right away.
+pub fn install(rules: Vec<AggregationRule>) {
+    let aggregator = (!rules.is_empty()).then(|| Arc::new(Aggregator::new(rules)));
+    if let Some(aggregator) = &aggregator {
+        let weak = Arc::downgrade(aggregator);
+        let hub = Hub::current();
+        let spawned = thread::Builder::new()
+            .name("error-aggregation".to_string())
+            .spawn(move || report(weak, hub));
+        if let Err(e) = spawned {
+            eprintln!("Cannot start error aggregation: {}", e);
+        }
+    }
+    let previous = std::mem::replace(&mut *state().write().unwrap(), aggregator);
+    if let Some(previous) = previous {
+        send(&Hub::current(), previous.take_all());
+    }
+}
+
+/// Send finished windows until the aggregator is replaced.
+fn report(aggregator: Weak<Aggregator>, hub: Arc<Hub>) {
+    loop {
+        let Some(tick) = aggregator.upgrade().map(|aggregator| aggregator.tick()) else {
+            return;
+        };
+        thread::sleep(tick);
+        let Some(aggregator) = aggregator.upgrade() else {
+            return;
+        };
+        send(&hub, aggregator.take_due());
+    }
+}
+
+/// The summaries already went through `before_send`; hand them to the
+/// transport directly.
+fn send(hub: &Hub, events: Vec<Event<'static>>) {
+    if let Some(client) = hub.client() {
+        for event in events {
+            client.send_envelope(event.into());
+        }
+    }
+}
+
+/// Send all pending buckets now.
+pub fn flush() {
+    let aggregator = state().read().unwrap().clone();
+    if let Some(aggregator) = aggregator {
+        send(&Hub::main(), aggregator.take_all());
+    }
+}
+
+/// Whether `before_send` should drop the event into a bucket.
+pub(crate) fn aggregate(event: &Event<'static>) -> bool {
+    let aggregator = state().read().unwrap().clone();
+    aggregator.is_some_and(|aggregator| aggregator.aggregate(event))
+}
@@ -0,0 +1,274 @@
+//! Webhook alerts driven by locally observed error rates (feature `alerting`).
+//!
+//! For Bugsink instances without alerting configured, or networks where the
+//! application can reach chat but not Bugsink. Events are counted per rule and
+//! [issue](fingerprint::issue_key) in `before_send`; crossing a threshold posts
+//! a (throttled) message from a background thread, so capture never blocks on
+//! the webhook. Counts older than a rule's window are dropped as events come in.
+//!
+//! ```ignore
+//! alerting::install(AlertDispatcher::new(
+//!     vec![Webhook::slack("https://hooks.slack.com/services/...")],
+//!     vec![AlertRule::new("error-burst", 20, Duration::from_secs(60))],
+//!     Duration::from_secs(600),
+//! )?);
+//! ```
+
+use super::{fingerprint::issue_key, issue_title};
+use sentry::{protocol::Event, Level};
+use serde_json::json;
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    sync::{mpsc, Arc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookKind {
+    Slack,
+    Teams,
+    Generic,
+}
+
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub kind: WebhookKind,
+    pub url: String,
+    /// Message template; placeholders: {rule} {count} {window_secs} {issue} {environment}.
+    pub template: Option<String>,
+}
+
+impl Webhook {
+    pub fn slack(url: &str) -> Self {
+        Self {
+            kind: WebhookKind::Slack,
+            url: url.to_string(),
+            template: None,
+        }
+    }
+
+    pub fn teams(url: &str) -> Self {
+        Self {
+            kind: WebhookKind::Teams,
+            url: url.to_string(),
+            template: None,
+        }
+    }
+
+    pub fn generic(url: &str) -> Self {
+        Self {
+            kind: WebhookKind::Generic,
+            url: url.to_string(),
+            template: None,
+        }
+    }
+
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = Some(template.to_string());
+        self
+    }
+
+    fn payload(&self, alert: &Alert) -> serde_json::Value {
+        let text = alert.render(self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE));
+        match self.kind {
+            WebhookKind::Slack => json!({ "text": text }),
+            WebhookKind::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": format!("Alert: {}", alert.rule),
+                "themeColor": "D70000",
+                "text": text,
+            }),
+            WebhookKind::Generic => json!({
+                "text": text,
+                "rule": alert.rule,
+                "count": alert.count,
+                "window_secs": alert.window.as_secs(),
+                "issue": alert.issue,
+                "environment": alert.environment,
+            }),
+        }
+    }
+}
+
+const DEFAULT_TEMPLATE: &str = "[{environment}] {rule}: {count} occurrences in {window_secs}s of \"{issue}\"";
+
+/// Alert when `threshold` matching events occur within `window`.
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    pub name: String,
+    pub threshold: usize,
+    pub window: Duration,
+    /// Only count events at or above this level.
+    pub min_level: Level,
+    /// Only count events whose issue title (exception type and value, or message) contains this string.
+    pub issue_contains: Option<String>,
+}
+
+impl AlertRule {
+    pub fn new(name: &str, threshold: usize, window: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            threshold,
+            window,
+            min_level: Level::Error,
+            issue_contains: None,
+        }
+    }
+
+    pub fn min_level(mut self, level: Level) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    pub fn issue_contains(mut self, pattern: &str) -> Self {
+        self.issue_contains = Some(pattern.to_string());
+        self
+    }
+
+    fn matches(&self, event: &Event<'_>, issue: &str) -> bool {
+        event.level >= self.min_level && self.issue_contains.as_deref().is_none_or(|p| issue.contains(p))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Alert {
+    rule: String,
+    count: usize,
+    window: Duration,
+    issue: String,
+    environment: String,
+}
+
+impl Alert {
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{rule}", &self.rule)
+            .replace("{count}", &self.count.to_string())
+            .replace("{window_secs}", &self.window.as_secs().to_string())
+            .replace("{issue}", &self.issue)
+            .replace("{environment}", &self.environment)
+    }
+}
+
+/// Per rule and [issue](issue_key): recent occurrences and the last alert sent.
+#[derive(Default)]
+struct State {
+    occurrences: HashMap<(usize, u64), VecDeque<Instant>>,
+    last_alert: HashMap<(usize, u64), Instant>,
+}
+
+impl State {
+    /// Forget occurrences outside their rule's window and alerts past the throttle.
+    fn prune(&mut self, rules: &[AlertRule], throttle: Duration, now: Instant) {
+        self.occurrences.retain(|(index, _), window| {
+            while window
+                .front()
+                .is_some_and(|t| now.duration_since(*t) > rules[*index].window)
+            {
+                window.pop_front();
+            }
+            !window.is_empty()
+        });
+        self.last_alert.retain(|_, at| now.duration_since(*at) < throttle);
+    }
+}
+
+pub struct AlertDispatcher {
+    rules: Vec<AlertRule>,
+    throttle: Duration,
+    state: Mutex<State>,
+    sender: Mutex<mpsc::Sender<Alert>>,
+}
+
+impl AlertDispatcher {
+    /// Create a dispatcher; at most one alert per rule and issue is sent per `throttle`.
+    /// Fails if the thread posting to the webhooks cannot be started.
+    pub fn new(webhooks: Vec<Webhook>, rules: Vec<AlertRule>, throttle: Duration) -> io::Result<Arc<Self>> {
+        let (sender, receiver) = mpsc::channel::<Alert>();
+
+        thread::Builder::new()
+            .name("alert-dispatcher".to_string())
+            .spawn(move || {
+                let client = reqwest::blocking::Client::builder()
+                    .timeout(Duration::from_secs(10))
+                    .build()
+                    .unwrap_or_default();
+                for alert in receiver {
+                    for webhook in &webhooks {
+                        let result = client.post(&webhook.url).json(&webhook.payload(&alert)).send();
+                        if let Err(e) = result.and_then(|r| r.error_for_status()) {
+                            eprintln!("Alert webhook {} failed: {}", webhook.url, e);
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Arc::new(Self {
+            rules,
+            throttle,
+            state: Mutex::new(State::default()),
+            sender: Mutex::new(sender),
+        }))
+    }
+
+    /// Rule and issue pairs with occurrences inside their window.
+    pub fn tracked_issues(&self) -> usize {
+        self.state.lock().unwrap().occurrences.len()
+    }
+
+    /// Count an outgoing event and queue alerts for every rule it pushes over its threshold.
+    pub fn observe(&self, event: &Event<'_>) {
+        let issue = issue_title(event);
+        let issue_key = issue_key(event);
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.prune(&self.rules, self.throttle, now);
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.matches(event, &issue) {
+                continue;
+            }
+
+            let key = (index, issue_key);
+            let window = state.occurrences.entry(key).or_default();
+            window.push_back(now);
+            let count = window.len();
+
+            if count < rule.threshold {
+                continue;
+            }
+            if state
+                .last_alert
+                .get(&key)
+                .is_some_and(|t| now.duration_since(*t) < self.throttle)
+            {
+                continue;
+            }
+            state.last_alert.insert(key, now);
+
+            let _ = self.sender.lock().unwrap().send(Alert {
+                rule: rule.name.clone(),
+                count,
+                window: rule.window,
+                issue: issue.clone(),
+                environment: event.environment.as_deref().unwrap_or("unknown").to_string(),
+            });
+        }
+    }
+}
+
+static DISPATCHER: OnceLock<Arc<AlertDispatcher>> = OnceLock::new();
+
+/// Install the process-wide dispatcher consulted by `before_send`.
+pub fn install(dispatcher: Arc<AlertDispatcher>) {
+    let _ = DISPATCHER.set(dispatcher);
+}
+
+pub(crate) fn observe(event: &Event<'_>) {
+    if let Some(dispatcher) = DISPATCHER.get() {
+        dispatcher.observe(event);
+    }
+}
@@ -0,0 +1,170 @@
+//! Files shipped alongside an event: the offending request payload, a config
+//! dump, a generated report.
+//!
+//! ```ignore
+//! sentry.capture_error_with_attachments(&err, &[
+//!     Attachment::bytes("request.json", body.to_vec()),
+//!     Attachment::from_path("/etc/app/pricing.toml")?,
+//! ]);
+//! ```
+//!
+//! The content type follows the file extension unless set explicitly.
+//! Attachments over [`AttachmentLimits::max_bytes`](attachments::AttachmentLimits)
+//! are cut to size when they are text and left out when they are binary;
+//! once `max_total_bytes` is used up the remaining ones are left out. Names of
+//! omitted attachments are recorded on the event.
+
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    sync::{OnceLock, RwLock},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachmentLimits {
+    pub max_bytes: usize,
+    /// Budget for all attachments of one event.
+    pub max_total_bytes: usize,
+}
+
+impl Default for AttachmentLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 1024 * 1024,
+            max_total_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+fn state() -> &'static RwLock<AttachmentLimits> {
+    static LIMITS: OnceLock<RwLock<AttachmentLimits>> = OnceLock::new();
+    LIMITS.get_or_init(Default::default)
+}
+
+pub fn configure(limits: AttachmentLimits) {
+    *state().write().unwrap() = limits;
+}
+
+pub fn limits() -> AttachmentLimits {
+    *state().read().unwrap()
+}
+
+const TRUNCATED: &[u8] = b"\n[truncated]\n";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn bytes(filename: &str, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            content_type: content_type_for(filename).to_string(),
+            data: data.into(),
+        }
+    }
+
+    /// Pretty-printed JSON, e.g. a config dump.
+    pub fn json(filename: &str, value: &Value) -> Self {
+        let data = serde_json::to_vec_pretty(value).unwrap_or_default();
+        Self::bytes(filename, data).content_type("application/json")
+    }
+
+    /// Read a file, at most one byte past the configured limit.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut data = Vec::new();
+        File::open(path)?
+            .take(limits().max_bytes as u64 + 1)
+            .read_to_end(&mut data)?;
+        let filename = path
+            .file_name()
+            .map_or_else(|| "attachment".to_string(), |name| name.to_string_lossy().into_owned());
+        Ok(Self::bytes(&filename, data))
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = content_type.to_string();
+        self
+    }
+
+    pub fn is_text(&self) -> bool {
+        let essence = self.content_type.split(';').next().unwrap_or_default().trim();
+        essence.starts_with("text/")
+            || matches!(
+                essence,
+                "application/json" | "application/xml" | "application/yaml" | "application/toml"
+            )
+    }
+
+    /// Fit into `max_bytes`: text is cut at a character boundary and
+    /// marked, binary content does not fit at all.
+    pub fn limited(mut self, max_bytes: usize) -> Option<Self> {
+        if self.data.len() <= max_bytes {
+            return Some(self);
+        }
+        if !self.is_text() || max_bytes < TRUNCATED.len() {
+            return None;
+        }
+        let mut end = max_bytes - TRUNCATED.len();
+        // Back off to the start of a UTF-8 sequence
+        while end > 0 && (self.data[end] & 0xC0) == 0x80 {
+            end -= 1;
+        }
+        self.data.truncate(end);
+        self.data.extend_from_slice(TRUNCATED);
+        Some(self)
+    }
+
+    pub fn into_sentry(self) -> sentry::protocol::Attachment {
+        sentry::protocol::Attachment {
+            buffer: self.data,
+            filename: self.filename,
+            content_type: Some(self.content_type),
+            ty: None,
+        }
+    }
+}
+
+pub fn content_type_for(filename: &str) -> &'static str {
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("json") => "application/json",
+        Some("txt" | "log") => "text/plain",
+        Some("csv") => "text/csv",
+        Some("html" | "htm") => "text/html",
+        Some("xml") => "application/xml",
+        Some("yaml" | "yml") => "application/yaml",
+        Some("toml") => "application/toml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("pdf") => "application/pdf",
+        Some("gz") => "application/gzip",
+        Some("zip") => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Apply the [limits] to the attachments of one event. Returns what is
+/// sent and the names of what was left out.
+pub fn prepare(attachments: &[Attachment]) -> (Vec<sentry::protocol::Attachment>, Vec<String>) {
+    let limits = limits();
+    let mut budget = limits.max_total_bytes;
+    let mut kept = Vec::new();
+    let mut omitted = Vec::new();
+    for attachment in attachments {
+        match attachment.clone().limited(limits.max_bytes.min(budget)) {
+            Some(attachment) => {
+                budget -= attachment.data.len();
+                kept.push(attachment.into_sentry());
+            }
+            None => omitted.push(attachment.filename.clone()),
+        }
+    }
+    (kept, omitted)
+}
@@ -0,0 +1,281 @@
+//! Tower layer for axum routers (feature `axum`).
+//!
+//! Every request runs on its own hub forked from the current one, so scope
+//! changes made by a handler (tags, user, breadcrumbs) stay with that request.
+//! A `http.server` transaction named after the matched route template
+//! (`GET /api/users/:id`) continues the caller's trace from `sentry-trace` /
+//! `baggage`, carries the request context and ends with the response status;
+//! events captured during the request get the same name and request context.
+//! The request's [correlation ID](crate::correlation) is tagged, available to
+//! handlers as an `Extension<CorrelationId>` and returned in the response.
+//! Handler panics become 500 responses; the panic is captured once, by the
+//! panic hook if one is installed, otherwise by the layer. If the request
+//! future is dropped before the response is ready (the client went away),
+//! the transaction finishes as cancelled and the session still ends.
+//!
+//! ```ignore
+//! let app = Router::new()
+//!     .route("/", get(index))
+//!     .route("/api/users/:id", get(get_user))
+//!     .layer(axum_integration::SentryLayer::new());
+//!
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+//! axum::serve(listener, app).await?;
+//! ```
+//!
+//! With [`SentryLayer::error_response`](axum_integration::SentryLayer::error_response)
+//! reported 5xx responses carry the event ID.
+//!
+//! Add the layer with `Router::layer` or `route_layer` so it runs after
+//! routing; as an outer service it only sees the raw path.
+
+use crate::{
+    correlation::CorrelationId,
+    error_response::ErrorResponse,
+    ops::{self, Op},
+    propagation, sessions,
+};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sentry::{
+    protocol::{self, Event, Exception, Mechanism, SpanStatus},
+    types::Uuid,
+    Hub, Level, SentryFutureExt, Transaction,
+};
+use std::{
+    any::Any,
+    convert::Infallible,
+    future::Future,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::Instrument;
+
+#[derive(Debug, Clone, Default)]
+pub struct SentryLayer {
+    error_response: Option<Arc<ErrorResponse>>,
+}
+
+impl SentryLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Put the event ID into 5xx responses that were reported (see [`crate::error_response`]).
+    pub fn error_response(mut self, response: ErrorResponse) -> Self {
+        self.error_response = Some(Arc::new(response));
+        self
+    }
+}
+
+impl<S> Layer<S> for SentryLayer {
+    type Service = SentryMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SentryMiddleware {
+            inner,
+            error_response: self.error_response.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SentryMiddleware<S> {
+    inner: S,
+    error_response: Option<Arc<ErrorResponse>>,
+}
+
+impl<S> Service<Request> for SentryMiddleware<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // The clone is not necessarily ready; call the one that was polled
+        let clone = self.inner.clone();
+        let mut inner = mem::replace(&mut self.inner, clone);
+
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        let correlation = CorrelationId::from_headers(request.headers());
+        correlation.bind(&hub);
+        request.extensions_mut().insert(correlation.clone());
+        let route = match request.extensions().get::<MatchedPath>() {
+            Some(path) => path.as_str().to_string(),
+            None => request.uri().path().to_string(),
+        };
+        let name = ops::naming::http_transaction(request.method().as_str(), &route);
+        let ctx = propagation::continue_from_headers(&name, Op::HttpServer.as_str(), request.headers());
+        let transaction = hub.start_transaction(ctx);
+        sessions::start_request(&hub);
+
+        let context = request_context(&request);
+        transaction.set_request(context.clone());
+        hub.configure_scope(|scope| {
+            scope.set_span(Some(transaction.clone().into()));
+            scope.set_transaction(Some(&name));
+            scope.add_event_processor(move |mut event| {
+                if event.request.is_none() {
+                    event.request = Some(context.clone());
+                }
+                Some(event)
+            });
+        });
+
+        let error_response = self.error_response.clone();
+        let future = CatchUnwind(Box::pin(inner.call(request).instrument(correlation.span())));
+        let finish = Finish {
+            hub: hub.clone(),
+            transaction,
+            status: None,
+        };
+        Box::pin(
+            async move {
+                // Moves the whole guard in, so dropping the future runs it
+                let mut finish = finish;
+                let hub = Hub::current();
+                let last_event = hub.last_event_id();
+                let mut response = match future.await {
+                    Ok(Ok(response)) => response,
+                    Ok(Err(infallible)) => match infallible {},
+                    Err(payload) => {
+                        if hub.last_event_id() == last_event {
+                            hub.capture_event(panic_event(payload.as_ref()));
+                        }
+                        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                    }
+                };
+
+                let event_id = hub.last_event_id();
+                if let (Some(options), Some(event_id)) = (&error_response, event_id) {
+                    if response.status().is_server_error() && Some(event_id) != last_event {
+                        response = with_event_id(response, options, event_id);
+                    }
+                }
+                correlation.inject(response.headers_mut());
+                finish.status = Some(response.status().as_u16());
+                Ok(response)
+            }
+            .bind_hub(hub),
+        )
+    }
+}
+
+/// Finishes the transaction and ends the request session when dropped: with
+/// the response status, or as cancelled if the request future was dropped
+/// before a response was ready.
+struct Finish {
+    hub: Arc<Hub>,
+    transaction: Transaction,
+    status: Option<u16>,
+}
+
+impl Drop for Finish {
+    fn drop(&mut self) {
+        match self.status {
+            Some(status) => {
+                self.transaction.set_data("http.response.status_code", status.into());
+                self.transaction.set_status(ops::http_span_status(status));
+            }
+            None => self.transaction.set_status(SpanStatus::Cancelled),
+        }
+        self.transaction.clone().finish();
+        sessions::end_request(&self.hub);
+    }
+}
+
+fn with_event_id(mut response: Response, options: &ErrorResponse, event_id: Uuid) -> Response {
+    if let Some(name) = &options.header {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&event_id.to_string()),
+        ) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    if let Some(body) = options.render_body(event_id) {
+        let headers = response.headers_mut();
+        headers.remove(header::CONTENT_LENGTH);
+        if let Ok(content_type) = HeaderValue::from_str(&options.content_type) {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        *response.body_mut() = Body::from(body);
+    }
+    response
+}
+
+fn request_context(request: &Request) -> protocol::Request {
+    let uri = request.uri();
+    let url = match (uri.scheme(), request.headers().get("host")) {
+        (Some(_), _) => Some(uri.to_string()),
+        (None, Some(host)) => host.to_str().ok().map(|host| format!("http://{}{}", host, uri)),
+        (None, None) => None,
+    };
+    protocol::Request {
+        url: url.and_then(|url| url.parse().ok()),
+        method: Some(request.method().to_string()),
+        query_string: uri.query().map(str::to_string),
+        headers: request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn panic_event(payload: &(dyn Any + Send)) -> Event<'static> {
+    let message = match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "Box<dyn Any>".to_string()),
+    };
+    Event {
+        level: Level::Fatal,
+        exception: vec![Exception {
+            ty: "panic".to_string(),
+            value: Some(message),
+            mechanism: Some(Mechanism {
+                ty: "panic".to_string(),
+                handled: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]
+        .into(),
+        ..Default::default()
+    }
+}
+
+/// Resolves to `Err(payload)` if polling the handler future panicked.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.0.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
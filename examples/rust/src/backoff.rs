@@ -0,0 +1,286 @@
+//! Backoff state driven by Bugsink's responses, shared by this crate's HTTP
+//! senders ([`http`] and the spool worker).
+//!
+//! - `429` responses close the affected categories for `Retry-After` seconds
+//!   (or per category from `X-Sentry-Rate-Limits`); nothing in those
+//!   categories is sent until the window has passed.
+//! - Consecutive server errors or network failures open a circuit breaker;
+//!   after the cooldown a single probe request is let through (half-open), and
+//!   its outcome closes or re-opens the circuit.
+//!
+//! Transient failures are retried by the [`http`] transport according to the
+//! installed [`RetryPolicy`](backoff::RetryPolicy).
+//!
+//! The SDK's built-in transports keep their own, internal rate-limit state;
+//! [`SentryService::transport_status`] reports what this crate's senders see.
+
+use super::ratelimit::{Category, RateLimitStats};
+use std::{
+    sync::{Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Retry window when a 429 carries no usable `Retry-After`.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    /// Cooldown elapsed; the next request is a probe.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackoffStatus {
+    /// Remaining retry window for error events.
+    pub errors_limited_for: Option<Duration>,
+    /// Remaining retry window for transactions.
+    pub transactions_limited_for: Option<Duration>,
+    pub circuit: CircuitState,
+    pub consecutive_failures: u32,
+}
+
+/// Client and server side limiter state, for health endpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportStatus {
+    /// `None` while client-side rate limiting is off.
+    pub client: Option<RateLimitStats>,
+    pub server: BackoffStatus,
+}
+
+struct State {
+    errors_until: Option<Instant>,
+    transactions_until: Option<Instant>,
+    all_until: Option<Instant>,
+    failures: u32,
+    opened: Option<Instant>,
+    probing: bool,
+}
+
+pub struct ServerBackoff {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl ServerBackoff {
+    /// Open the circuit after `failure_threshold` consecutive failures, for `cooldown`.
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(State {
+                errors_until: None,
+                transactions_until: None,
+                all_until: None,
+                failures: 0,
+                opened: None,
+                probing: false,
+            }),
+        }
+    }
+
+    /// Time until the category may be sent again; `None` if it may be sent now.
+    /// While half-open, the first caller gets through as the probe.
+    pub fn delay(&self, category: Category) -> Option<Duration> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        let category_until = match category {
+            Category::Error => state.errors_until,
+            Category::Transaction => state.transactions_until,
+            Category::Other => None,
+        };
+        let limited = [category_until, state.all_until]
+            .into_iter()
+            .flatten()
+            .filter(|until| *until > now)
+            .max();
+        if let Some(until) = limited {
+            return Some(until - now);
+        }
+
+        if let Some(opened) = state.opened {
+            let reopen = opened + self.cooldown;
+            if now < reopen {
+                return Some(reopen - now);
+            }
+            if state.probing {
+                return Some(self.cooldown);
+            }
+            state.probing = true;
+        }
+        None
+    }
+
+    pub fn allows(&self, category: Category) -> bool {
+        self.delay(category).is_none()
+    }
+
+    /// Record an HTTP response with its `Retry-After` and `X-Sentry-Rate-Limits` headers.
+    pub fn on_response(&self, status: u16, retry_after: Option<&str>, rate_limits: Option<&str>) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(rate_limits) = rate_limits {
+            for (delay, categories) in parse_rate_limits(rate_limits) {
+                let until = Some(now + delay);
+                if categories.is_empty() {
+                    state.all_until = state.all_until.max(until);
+                }
+                for category in categories {
+                    match category {
+                        Category::Error => state.errors_until = state.errors_until.max(until),
+                        Category::Transaction => state.transactions_until = state.transactions_until.max(until),
+                        Category::Other => {}
+                    }
+                }
+            }
+        } else if status == 429 {
+            let delay = retry_after
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs_f64);
+            state.all_until = state.all_until.max(Some(now + delay));
+        }
+
+        if status >= 500 {
+            self.fail(&mut state, now);
+        } else {
+            state.failures = 0;
+            state.opened = None;
+            state.probing = false;
+        }
+    }
+
+    /// Record a request that failed without a response.
+    pub fn on_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.fail(&mut state, Instant::now());
+    }
+
+    fn fail(&self, state: &mut State, now: Instant) {
+        state.failures += 1;
+        if state.probing || state.failures >= self.failure_threshold {
+            state.opened = Some(now);
+            state.probing = false;
+        }
+    }
+
+    pub fn status(&self) -> BackoffStatus {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        let remaining = |until: Option<Instant>| {
+            [until, state.all_until]
+                .into_iter()
+                .flatten()
+                .filter(|until| *until > now)
+                .max()
+                .map(|until| until - now)
+        };
+        let circuit = match state.opened {
+            None => CircuitState::Closed,
+            Some(opened) if now < opened + self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        };
+        BackoffStatus {
+            errors_limited_for: remaining(state.errors_until),
+            transactions_limited_for: remaining(state.transactions_until),
+            circuit,
+            consecutive_failures: state.failures,
+        }
+    }
+}
+
+/// Parse `X-Sentry-Rate-Limits`: `retry_after:categories:scope[:reason], ...`,
+/// where an empty category list means all categories.
+fn parse_rate_limits(header: &str) -> Vec<(Duration, Vec<Category>)> {
+    header
+        .split(',')
+        .filter_map(|limit| {
+            let mut parts = limit.trim().split(':');
+            let delay = parts.next()?.parse::<f64>().ok()?;
+            let categories = parts
+                .next()
+                .unwrap_or_default()
+                .split(';')
+                .filter_map(|category| match category {
+                    "error" | "default" => Some(Category::Error),
+                    "transaction" => Some(Category::Transaction),
+                    _ => None,
+                })
+                .collect();
+            Some((Duration::from_secs_f64(delay), categories))
+        })
+        .collect()
+}
+
+/// Retries of a single envelope after transient failures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one; 1 disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further one.
+    pub base: Duration,
+    /// Upper bound of a single delay; a longer `Retry-After` ends the retries.
+    pub max_delay: Duration,
+    /// Fraction (0.0 - 1.0) of each delay that is randomized.
+    pub jitter: f32,
+    /// Response statuses worth retrying; other non-2xx responses are final.
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            retryable_statuses: vec![408, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay after the given failed attempt (1-based), with jitter applied.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        let random = (sentry::types::random_uuid().as_u128() % 1000) as f64 / 1000.0;
+        delay.mul_f64(1.0 - f64::from(self.jitter.clamp(0.0, 1.0)) * random)
+    }
+
+    pub fn is_retryable(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+}
+
+fn retry_state() -> &'static RwLock<RetryPolicy> {
+    static POLICY: OnceLock<RwLock<RetryPolicy>> = OnceLock::new();
+    POLICY.get_or_init(Default::default)
+}
+
+/// Set the policy used by transports created afterwards.
+pub fn install_retry_policy(policy: RetryPolicy) {
+    *retry_state().write().unwrap() = policy;
+}
+
+pub fn retry_policy() -> RetryPolicy {
+    retry_state().read().unwrap().clone()
+}
+
+/// Process-wide state: circuit opens after 5 consecutive failures, for 30 seconds.
+pub fn global() -> &'static ServerBackoff {
+    static BACKOFF: OnceLock<ServerBackoff> = OnceLock::new();
+    BACKOFF.get_or_init(|| ServerBackoff::new(5, Duration::from_secs(30)))
+}
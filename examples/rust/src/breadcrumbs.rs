@@ -0,0 +1,463 @@
+//! Pluggable breadcrumb storage with per-category retention.
+//!
+//! Every breadcrumb accepted by `before_breadcrumb` is also written to the
+//! installed store. The in-memory store is the default; the mmap-file
+//! (feature `breadcrumbs-mmap`) and sqlite (feature `breadcrumbs-sqlite`)
+//! backends keep the trail across crashes and restarts.
+//!
+//! ```ignore
+//! let retention = Retention::new(20).category("auth", 10).category("http", 30);
+//! breadcrumbs::install(Box::new(MmapStore::open("/var/lib/app/breadcrumbs.bin", retention, 512)?));
+//! ```
+//!
+//! The same limits apply to the trail sent with each event, so a request
+//! making a hundred HTTP calls still reports its authentication steps:
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .max_breadcrumbs(10)    // shared by categories without a limit
+//!     .breadcrumb_limit("http", 30)
+//!     .breadcrumb_limit("db", 10)
+//!     .breadcrumb_limit("auth", 10)
+//!     .build();
+//! ```
+//!
+//! The SDK keeps a single buffer per scope, so the limits are applied when the
+//! event is sent; the buffer is enlarged to [`SCOPE_HEADROOM`](breadcrumbs::SCOPE_HEADROOM)
+//! times the total so one busy category doesn't push out the others first.
+
+use sentry::protocol::{Breadcrumb, Event};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex, OnceLock, RwLock},
+};
+
+/// Scope buffer size relative to the sum of all limits.
+pub const SCOPE_HEADROOM: usize = 4;
+
+pub trait BreadcrumbStore: Send + Sync {
+    fn push(&self, breadcrumb: &Breadcrumb);
+
+    /// Retained breadcrumbs, oldest first.
+    fn load(&self) -> Vec<Breadcrumb>;
+
+    fn clear(&self);
+}
+
+/// Retention limits. Categories without an explicit limit share a pool of `default` entries.
+#[derive(Debug, Clone)]
+pub struct Retention {
+    pub default: usize,
+    pub per_category: BTreeMap<String, usize>,
+}
+
+impl Retention {
+    pub fn new(default: usize) -> Self {
+        Self {
+            default,
+            per_category: BTreeMap::new(),
+        }
+    }
+
+    pub fn category(mut self, category: &str, limit: usize) -> Self {
+        self.per_category.insert(category.to_string(), limit);
+        self
+    }
+
+    /// Retention bucket of a category: the category itself if configured, else the shared pool.
+    fn bucket<'a>(&self, category: Option<&'a str>) -> Option<&'a str> {
+        category.filter(|c| self.per_category.contains_key(*c))
+    }
+
+    fn limit(&self, bucket: Option<&str>) -> usize {
+        bucket
+            .and_then(|c| self.per_category.get(c).copied())
+            .unwrap_or(self.default)
+    }
+
+    /// Breadcrumbs retained at most, over all buckets.
+    pub fn capacity(&self) -> usize {
+        self.default + self.per_category.values().sum::<usize>()
+    }
+
+    /// `max_breadcrumbs` for the SDK's per-scope buffer.
+    pub fn scope_capacity(&self) -> usize {
+        if self.per_category.is_empty() {
+            self.default
+        } else {
+            self.capacity() * SCOPE_HEADROOM
+        }
+    }
+
+    /// Drop the oldest breadcrumbs of every bucket over its limit, keeping the order.
+    pub fn trim(&self, breadcrumbs: &mut Vec<Breadcrumb>) {
+        let mut counts = BTreeMap::new();
+        let keep: Vec<bool> = breadcrumbs
+            .iter()
+            .rev()
+            .map(|b| {
+                let bucket = self.bucket(b.category.as_deref());
+                let count = counts.entry(bucket).or_insert(0);
+                *count += 1;
+                *count <= self.limit(bucket)
+            })
+            .collect();
+        let mut keep = keep.into_iter().rev();
+        breadcrumbs.retain(|_| keep.next().unwrap_or(true));
+    }
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self::new(50)
+    }
+}
+
+/// Default in-memory store.
+pub struct MemoryStore {
+    retention: Retention,
+    entries: Mutex<VecDeque<Breadcrumb>>,
+}
+
+impl MemoryStore {
+    pub fn new(retention: Retention) -> Self {
+        Self {
+            retention,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl BreadcrumbStore for MemoryStore {
+    fn push(&self, breadcrumb: &Breadcrumb) {
+        let bucket = self.retention.bucket(breadcrumb.category.as_deref());
+        let in_bucket = |b: &Breadcrumb| self.retention.bucket(b.category.as_deref()) == bucket;
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(breadcrumb.clone());
+        if entries.iter().filter(|b| in_bucket(b)).count() > self.retention.limit(bucket) {
+            if let Some(oldest) = entries.iter().position(in_bucket) {
+                entries.remove(oldest);
+            }
+        }
+    }
+
+    fn load(&self) -> Vec<Breadcrumb> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+static STORE: OnceLock<Box<dyn BreadcrumbStore>> = OnceLock::new();
+
+/// Install the process-wide store. Must happen before the first breadcrumb is recorded;
+/// returns the store back if one is already installed.
+pub fn install(store: Box<dyn BreadcrumbStore>) -> Result<(), Box<dyn BreadcrumbStore>> {
+    STORE.set(store)
+}
+
+/// The installed store (in-memory with default retention if none was installed).
+pub fn store() -> &'static dyn BreadcrumbStore {
+    STORE
+        .get_or_init(|| Box::new(MemoryStore::new(Retention::default())))
+        .as_ref()
+}
+
+pub(crate) fn record(breadcrumb: &Breadcrumb) {
+    store().push(breadcrumb);
+}
+
+fn event_limits_state() -> &'static RwLock<Option<Arc<Retention>>> {
+    static STATE: OnceLock<RwLock<Option<Arc<Retention>>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Limit the breadcrumbs sent with each event per category; `None` leaves
+/// only the SDK's `max_breadcrumbs`.
+pub fn set_event_limits(retention: Option<Retention>) {
+    *event_limits_state().write().unwrap() = retention.map(Arc::new);
+}
+
+pub fn event_limits() -> Option<Arc<Retention>> {
+    event_limits_state().read().unwrap().clone()
+}
+
+pub(crate) fn trim_event(event: &mut Event<'_>) {
+    if let Some(retention) = event_limits() {
+        retention.trim(&mut event.breadcrumbs.values);
+    }
+}
+
+/// Serialize a breadcrumb into at most `max` bytes, dropping data and then
+/// truncating the message when it does not fit.
+#[cfg(any(feature = "breadcrumbs-mmap", feature = "breadcrumbs-sqlite"))]
+fn encode(breadcrumb: &Breadcrumb, max: usize) -> Option<Vec<u8>> {
+    let json = serde_json::to_vec(breadcrumb).ok()?;
+    if json.len() <= max {
+        return Some(json);
+    }
+
+    let mut slim = breadcrumb.clone();
+    slim.data.clear();
+    let json = serde_json::to_vec(&slim).ok()?;
+    if json.len() <= max {
+        return Some(json);
+    }
+
+    let overflow = json.len() - max;
+    let message = slim.message.take().unwrap_or_default();
+    let keep = message.len().checked_sub(overflow + 3)?;
+    let cut = (0..=keep).rev().find(|i| message.is_char_boundary(*i))?;
+    slim.message = Some(format!("{}...", &message[..cut]));
+    serde_json::to_vec(&slim).ok().filter(|json| json.len() <= max)
+}
+
+/// Memory-mapped ring file; writes land in the page cache and survive process crashes.
+///
+/// Each retention bucket owns a fixed region of slots, so a flood of one category
+/// never evicts another. The file is reset when the retention layout changes.
+#[cfg(feature = "breadcrumbs-mmap")]
+pub struct MmapStore {
+    slot_size: usize,
+    regions: Vec<Region>,
+    state: Mutex<MmapState>,
+}
+
+#[cfg(feature = "breadcrumbs-mmap")]
+struct Region {
+    bucket: Option<String>,
+    offset: usize,
+    slots: usize,
+}
+
+#[cfg(feature = "breadcrumbs-mmap")]
+struct MmapState {
+    map: memmap2::MmapMut,
+    seq: u64,
+}
+
+#[cfg(feature = "breadcrumbs-mmap")]
+impl MmapStore {
+    const MAGIC: &'static [u8; 4] = b"BCR1";
+    const HEADER: usize = 16;
+    /// Per slot: sequence number (u64) and payload length (u32).
+    const SLOT_HEADER: usize = 12;
+
+    pub fn open(path: impl AsRef<std::path::Path>, retention: Retention, slot_size: usize) -> std::io::Result<Self> {
+        let slot_size = slot_size.max(Self::SLOT_HEADER + 64);
+
+        let mut buckets: Vec<(Option<String>, usize)> = retention
+            .per_category
+            .iter()
+            .map(|(category, limit)| (Some(category.clone()), *limit))
+            .collect();
+        buckets.push((None, retention.default));
+
+        // Each region starts with its write cursor (u64)
+        let mut regions = Vec::new();
+        let mut offset = Self::HEADER;
+        for (bucket, slots) in buckets {
+            let slots = slots.max(1);
+            regions.push(Region { bucket, offset, slots });
+            offset += 8 + slots * slot_size;
+        }
+        let total = offset;
+
+        // FNV-1a over the layout, so a changed retention config resets the file
+        let mut layout = 0xcbf2_9ce4_8422_2325_u64;
+        for region in &regions {
+            let name = region.bucket.as_deref().unwrap_or("*");
+            for byte in name
+                .bytes()
+                .chain(region.slots.to_le_bytes())
+                .chain(slot_size.to_le_bytes())
+            {
+                layout = (layout ^ byte as u64).wrapping_mul(0x100_0000_01b3);
+            }
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let valid = file.metadata()?.len() == total as u64;
+        if !valid {
+            file.set_len(0)?;
+            file.set_len(total as u64)?;
+        }
+
+        // SAFETY: the file is owned by this process for its lifetime
+        let mut map = unsafe { memmap2::MmapMut::map_mut(&file)? };
+        let header_matches = &map[..4] == Self::MAGIC
+            && map[4..8] == (slot_size as u32).to_le_bytes()
+            && map[8..16] == layout.to_le_bytes();
+        if !valid || !header_matches {
+            map.fill(0);
+            map[..4].copy_from_slice(Self::MAGIC);
+            map[4..8].copy_from_slice(&(slot_size as u32).to_le_bytes());
+            map[8..16].copy_from_slice(&layout.to_le_bytes());
+        }
+
+        let store = Self {
+            slot_size,
+            regions,
+            state: Mutex::new(MmapState { map, seq: 0 }),
+        };
+        let seq = store.entries().iter().map(|(seq, _)| *seq).max().unwrap_or(0);
+        store.state.lock().unwrap().seq = seq;
+        Ok(store)
+    }
+
+    fn entries(&self) -> Vec<(u64, Breadcrumb)> {
+        let state = self.state.lock().unwrap();
+        let map = &state.map;
+        let mut entries = Vec::new();
+
+        for region in &self.regions {
+            for index in 0..region.slots {
+                let at = region.offset + 8 + index * self.slot_size;
+                let seq = u64::from_le_bytes(map[at..at + 8].try_into().unwrap());
+                let len = u32::from_le_bytes(map[at + 8..at + 12].try_into().unwrap()) as usize;
+                if seq == 0 || len > self.slot_size - Self::SLOT_HEADER {
+                    continue;
+                }
+                let payload = &map[at + Self::SLOT_HEADER..at + Self::SLOT_HEADER + len];
+                if let Ok(breadcrumb) = serde_json::from_slice(payload) {
+                    entries.push((seq, breadcrumb));
+                }
+            }
+        }
+
+        entries.sort_by_key(|(seq, _)| *seq);
+        entries
+    }
+}
+
+#[cfg(feature = "breadcrumbs-mmap")]
+impl BreadcrumbStore for MmapStore {
+    fn push(&self, breadcrumb: &Breadcrumb) {
+        let Some(payload) = encode(breadcrumb, self.slot_size - Self::SLOT_HEADER) else {
+            return;
+        };
+        let category = breadcrumb.category.as_deref();
+        let Some(region) = self
+            .regions
+            .iter()
+            .find(|r| r.bucket.as_deref() == category)
+            .or_else(|| self.regions.last())
+        else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.seq += 1;
+        let seq = state.seq;
+        let map = &mut state.map;
+
+        let cursor = u64::from_le_bytes(map[region.offset..region.offset + 8].try_into().unwrap());
+        let at = region.offset + 8 + (cursor as usize % region.slots) * self.slot_size;
+
+        // Invalidate, write payload, then publish the sequence number last
+        map[at..at + 8].copy_from_slice(&0u64.to_le_bytes());
+        map[at + 8..at + 12].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+        map[at + Self::SLOT_HEADER..at + Self::SLOT_HEADER + payload.len()].copy_from_slice(&payload);
+        map[at..at + 8].copy_from_slice(&seq.to_le_bytes());
+        map[region.offset..region.offset + 8].copy_from_slice(&(cursor + 1).to_le_bytes());
+    }
+
+    fn load(&self) -> Vec<Breadcrumb> {
+        self.entries().into_iter().map(|(_, breadcrumb)| breadcrumb).collect()
+    }
+
+    fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.map[Self::HEADER..].fill(0);
+        state.seq = 0;
+    }
+}
+
+/// SQLite-backed store, for deployments that already ship a writable data directory.
+#[cfg(feature = "breadcrumbs-sqlite")]
+pub struct SqliteStore {
+    retention: Retention,
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "breadcrumbs-sqlite")]
+impl SqliteStore {
+    /// Max stored size per breadcrumb.
+    const MAX_SIZE: usize = 16 * 1024;
+
+    pub fn open(path: impl AsRef<std::path::Path>, retention: Retention) -> rusqlite::Result<Self> {
+        let conn = rusqlite::Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS breadcrumbs (
+                     seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                     bucket TEXT NOT NULL,
+                     json BLOB NOT NULL
+                 )",
+        )?;
+        Ok(Self {
+            retention,
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+#[cfg(feature = "breadcrumbs-sqlite")]
+impl BreadcrumbStore for SqliteStore {
+    fn push(&self, breadcrumb: &Breadcrumb) {
+        let Some(json) = encode(breadcrumb, Self::MAX_SIZE) else {
+            return;
+        };
+        let bucket = self.retention.bucket(breadcrumb.category.as_deref());
+        let limit = self.retention.limit(bucket) as i64;
+        let bucket = bucket.unwrap_or("");
+
+        let conn = self.conn.lock().unwrap();
+        let result = conn
+            .execute(
+                "INSERT INTO breadcrumbs (bucket, json) VALUES (?1, ?2)",
+                rusqlite::params![bucket, json],
+            )
+            .and_then(|_| {
+                conn.execute(
+                    "DELETE FROM breadcrumbs WHERE bucket = ?1 AND seq NOT IN
+                             (SELECT seq FROM breadcrumbs WHERE bucket = ?1 ORDER BY seq DESC LIMIT ?2)",
+                    rusqlite::params![bucket, limit],
+                )
+            });
+        if let Err(e) = result {
+            eprintln!("Failed to store breadcrumb: {}", e);
+        }
+    }
+
+    fn load(&self) -> Vec<Breadcrumb> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = match conn.prepare("SELECT json FROM breadcrumbs ORDER BY seq") {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                eprintln!("Failed to load breadcrumbs: {}", e);
+                return Vec::new();
+            }
+        };
+        let rows = stmt.query_map([], |row| row.get::<_, Vec<u8>>(0));
+        rows.map(|rows| {
+            rows.filter_map(|json| serde_json::from_slice(&json.ok()?).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    fn clear(&self) {
+        if let Err(e) = self.conn.lock().unwrap().execute("DELETE FROM breadcrumbs", []) {
+            eprintln!("Failed to clear breadcrumbs: {}", e);
+        }
+    }
+}
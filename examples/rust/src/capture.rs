@@ -0,0 +1,41 @@
+//! Capture errors where they surface without breaking `?` chains:
+//!
+//! ```ignore
+//! let user = db.load_user(id).capture()?;
+//! let order = db
+//!     .load_order(order_id)
+//!     .capture_with(|scope| scope.set_tag("order.id", order_id))?;
+//! ```
+
+use super::error_chain;
+use sentry::Scope;
+
+pub trait CaptureResultExt<T, E> {
+    /// Capture the error, if any, on the current hub and return the result unchanged.
+    fn capture(self) -> Result<T, E>;
+
+    /// Like `capture`, with scope changes applied to this event only.
+    /// `configure` is not called for `Ok` results.
+    fn capture_with<F>(self, configure: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Scope);
+}
+
+impl<T, E: std::error::Error + 'static> CaptureResultExt<T, E> for Result<T, E> {
+    fn capture(self) -> Result<T, E> {
+        if let Err(error) = &self {
+            error_chain::capture_error(error);
+        }
+        self
+    }
+
+    fn capture_with<F>(self, configure: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Scope),
+    {
+        if let Err(error) = &self {
+            sentry::with_scope(configure, || error_chain::capture_error(error));
+        }
+        self
+    }
+}
@@ -0,0 +1,236 @@
+//! Region, zone, instance type and account of the VM, from the cloud
+//! provider's instance metadata service.
+//!
+//! [`enrich`](cloud::enrich) asks the AWS (IMDSv2), GCP and Azure endpoints
+//! at `169.254.169.254` in parallel; whichever answers within the timeout
+//! fills the `cloud` context that
+//! [`CloudIntegration`](cloud::CloudIntegration) adds to every event. Outside
+//! a cloud VM nothing answers and events are left alone. With
+//! [`Config::cloud_metadata`](config::Config::cloud_metadata) set,
+//! `init_sentry` runs it in the background on the current tokio runtime.
+
+use sentry::{protocol::Event, ClientOptions, Integration};
+use serde_json::Value;
+use std::{collections::BTreeMap, net::SocketAddr, sync::OnceLock, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+const METADATA_ADDR: ([u8; 4], u16) = ([169, 254, 169, 254], 80);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    Aws,
+    Gcp,
+    Azure,
+}
+
+impl CloudProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CloudProvider::Aws => "aws",
+            CloudProvider::Gcp => "gcp",
+            CloudProvider::Azure => "azure",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloudInfo {
+    pub provider: CloudProvider,
+    pub region: Option<String>,
+    pub availability_zone: Option<String>,
+    pub instance_type: Option<String>,
+    /// AWS account, GCP project or Azure subscription.
+    pub account_id: Option<String>,
+    pub instance_id: Option<String>,
+}
+
+impl CloudInfo {
+    pub fn to_context(&self) -> sentry::protocol::Context {
+        let mut map = BTreeMap::new();
+        map.insert("provider".to_string(), self.provider.as_str().into());
+        let fields = [
+            ("region", &self.region),
+            ("availability_zone", &self.availability_zone),
+            ("instance_type", &self.instance_type),
+            ("account_id", &self.account_id),
+            ("instance_id", &self.instance_id),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                map.insert(key.to_string(), value.as_str().into());
+            }
+        }
+        sentry::protocol::Context::Other(map)
+    }
+}
+
+static DETECTED: OnceLock<CloudInfo> = OnceLock::new();
+
+/// Query the metadata service and keep the result for [`CloudIntegration`].
+pub async fn enrich(timeout: Duration) -> Option<CloudInfo> {
+    let info = detect_at(SocketAddr::from(METADATA_ADDR), timeout).await?;
+    Some(DETECTED.get_or_init(|| info).clone())
+}
+
+pub fn detected() -> Option<&'static CloudInfo> {
+    DETECTED.get()
+}
+
+/// Ask all providers at `addr`; the first complete answer wins.
+pub async fn detect_at(addr: SocketAddr, timeout: Duration) -> Option<CloudInfo> {
+    let probes = async {
+        let (aws, gcp, azure) = tokio::join!(aws(addr), gcp(addr), azure(addr));
+        aws.or(gcp).or(azure)
+    };
+    tokio::time::timeout(timeout, probes).await.ok().flatten()
+}
+
+async fn aws(addr: SocketAddr) -> Option<CloudInfo> {
+    let token = get(
+        addr,
+        "PUT",
+        "/latest/api/token",
+        &[("X-aws-ec2-metadata-token-ttl-seconds", "60")],
+    )
+    .await?;
+    let document = get(
+        addr,
+        "GET",
+        "/latest/dynamic/instance-identity/document",
+        &[("X-aws-ec2-metadata-token", token.trim())],
+    )
+    .await?;
+    let document: Value = serde_json::from_str(&document).ok()?;
+    let field = |key: &str| document[key].as_str().map(String::from);
+    Some(CloudInfo {
+        provider: CloudProvider::Aws,
+        region: field("region"),
+        availability_zone: field("availabilityZone"),
+        instance_type: field("instanceType"),
+        account_id: field("accountId"),
+        instance_id: field("instanceId"),
+    })
+}
+
+async fn gcp(addr: SocketAddr) -> Option<CloudInfo> {
+    let flavor = [("Metadata-Flavor", "Google")];
+    let instance = get(addr, "GET", "/computeMetadata/v1/instance/?recursive=true", &flavor).await?;
+    let instance: Value = serde_json::from_str(&instance).ok()?;
+    let project = get(addr, "GET", "/computeMetadata/v1/project/project-id", &flavor).await;
+    // "projects/123/zones/europe-west1-b", "projects/123/machineTypes/e2-medium"
+    let last_segment = |key: &str| {
+        instance[key]
+            .as_str()
+            .and_then(|path| path.rsplit('/').next())
+            .map(String::from)
+    };
+    let zone = last_segment("zone");
+    Some(CloudInfo {
+        provider: CloudProvider::Gcp,
+        region: zone
+            .as_deref()
+            .and_then(|zone| zone.rsplit_once('-'))
+            .map(|(region, _)| region.to_string()),
+        availability_zone: zone,
+        instance_type: last_segment("machineType"),
+        account_id: project.map(|project| project.trim().to_string()),
+        instance_id: instance["id"].as_u64().map(|id| id.to_string()),
+    })
+}
+
+async fn azure(addr: SocketAddr) -> Option<CloudInfo> {
+    let compute = get(
+        addr,
+        "GET",
+        "/metadata/instance/compute?api-version=2021-02-01",
+        &[("Metadata", "true")],
+    )
+    .await?;
+    let compute: Value = serde_json::from_str(&compute).ok()?;
+    let field = |key: &str| {
+        compute[key]
+            .as_str()
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+    };
+    Some(CloudInfo {
+        provider: CloudProvider::Azure,
+        region: field("location"),
+        availability_zone: field("zone"),
+        instance_type: field("vmSize"),
+        account_id: field("subscriptionId"),
+        instance_id: field("vmId"),
+    })
+}
+
+/// Minimal HTTP/1.1 request; the body of a 200 response.
+async fn get(addr: SocketAddr, method: &str, path: &str, headers: &[(&str, &str)]) -> Option<String> {
+    let mut stream = TcpStream::connect(addr).await.ok()?;
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: 0\r\n",
+        method,
+        path,
+        addr.ip()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await.ok()?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.ok()?;
+    let response = String::from_utf8(response).ok()?;
+    let (head, body) = response.split_once("\r\n\r\n")?;
+    let mut lines = head.lines();
+    if lines.next()?.split_whitespace().nth(1) != Some("200") {
+        return None;
+    }
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.eq_ignore_ascii_case("transfer-encoding") && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+    if chunked {
+        dechunk(body)
+    } else {
+        Some(body.to_string())
+    }
+}
+
+fn dechunk(mut body: &str) -> Option<String> {
+    let mut out = String::new();
+    loop {
+        let (size, rest) = body.split_once("\r\n")?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        if size == 0 {
+            return Some(out);
+        }
+        out.push_str(rest.get(..size)?);
+        body = rest.get(size..)?.strip_prefix("\r\n")?;
+    }
+}
+
+/// Adds the `cloud` context once [`enrich`] found the instance.
+#[derive(Debug, Default)]
+pub struct CloudIntegration;
+
+impl Integration for CloudIntegration {
+    fn name(&self) -> &'static str {
+        "cloud-metadata"
+    }
+
+    fn process_event(&self, mut event: Event<'static>, _options: &ClientOptions) -> Option<Event<'static>> {
+        if let Some(info) = detected() {
+            event
+                .contexts
+                .entry("cloud".to_string())
+                .or_insert_with(|| info.to_context());
+        }
+        Some(event)
+    }
+}
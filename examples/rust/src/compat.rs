@@ -0,0 +1,82 @@
+//! Incremental migration for code that initializes and calls the `sentry`
+//! crate directly.
+//!
+//! Either pass existing options through `compat::apply` before `sentry::init`,
+//! or call `compat::install_on_global_hub` after it. Direct
+//! `sentry::capture_error` calls then run through this module's scrubbing,
+//! filtering, sampling defaults and transport.
+
+use super::{before_breadcrumb_handler, before_send_handler, runtime_config, transport};
+use sentry::{Client, ClientOptions, Hub};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Layer this crate's pipeline onto existing client options.
+/// Existing `before_send` / `before_breadcrumb` hooks run after ours.
+pub fn apply(mut options: ClientOptions) -> ClientOptions {
+    let their_before_send = options.before_send.take();
+    options.before_send = Some(Arc::new(move |event| {
+        let event = before_send_handler(event)?;
+        match &their_before_send {
+            Some(before_send) => before_send(event),
+            None => Some(event),
+        }
+    }));
+
+    let their_before_breadcrumb = options.before_breadcrumb.take();
+    options.before_breadcrumb = Some(Arc::new(move |breadcrumb| {
+        let breadcrumb = before_breadcrumb_handler(breadcrumb)?;
+        match &their_before_breadcrumb {
+            Some(before_breadcrumb) => before_breadcrumb(breadcrumb),
+            None => Some(breadcrumb),
+        }
+    }));
+
+    if options.transport.is_none() {
+        options.transport = transport::factory();
+    }
+    if options.traces_sample_rate == 0.0 {
+        let production = options.environment.as_deref() == Some("production");
+        options.traces_sample_rate = if production { 0.1 } else { 1.0 };
+    }
+    // before_send_handler samples errors using the runtime rate
+    runtime_config::ConfigHandle.set_sample_rate(options.sample_rate);
+    options.sample_rate = 1.0;
+    options.send_default_pii = false;
+    options
+}
+
+/// Flushes and closes the replacement client when dropped.
+pub struct CompatGuard(Arc<Client>);
+
+impl Drop for CompatGuard {
+    fn drop(&mut self) {
+        self.0.close(Some(Duration::from_secs(2)));
+    }
+}
+
+/// Rebind the client of an already initialized global hub with the pipeline applied.
+/// Returns `None` if `sentry::init` has not been called or the pipeline is already installed.
+pub fn install_on_global_hub() -> Option<CompatGuard> {
+    let existing = Hub::main().client()?;
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+    let client = Arc::new(Client::from(apply(existing.options().clone())));
+
+    // Deliver what the old client already queued before swapping it out
+    existing.flush(Some(Duration::from_secs(2)));
+    Hub::main().bind_client(Some(Arc::clone(&client)));
+    if !Arc::ptr_eq(&Hub::current(), &Hub::main()) {
+        Hub::current().bind_client(Some(Arc::clone(&client)));
+    }
+
+    Some(CompatGuard(client))
+}
@@ -0,0 +1,1227 @@
+use std::{collections::BTreeMap, env, sync::Arc, time::Duration};
+
+/// Settings used to initialize the SDK.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub dsn: String,
+    pub environment: String,
+    pub release: String,
+    /// Error event sample rate.
+    pub sample_rate: f32,
+    /// Per-error-class rates overriding `sample_rate` (see [`crate::error_sampling`]).
+    pub error_sampling: Vec<crate::error_sampling::ErrorSamplingRule>,
+    /// Transaction sample rate; defaults to 10% in production, 100% elsewhere.
+    pub traces_sample_rate: Option<f32>,
+    /// Per-transaction rates overriding `traces_sample_rate` (see [`crate::sampling`]).
+    pub sampling: crate::sampling::SamplingRules,
+    /// Breadcrumbs per event; with `breadcrumb_limits`, those of categories without a limit.
+    pub max_breadcrumbs: usize,
+    /// Breadcrumbs per event for single categories such as `http` (see [`crate::breadcrumbs`]).
+    pub breadcrumb_limits: BTreeMap<String, usize>,
+    /// SDK debug logging; defaults to on outside production.
+    pub debug: Option<bool>,
+    /// Tags set on the global scope at init.
+    pub tags: BTreeMap<String, String>,
+    /// Request headers redacted before sending.
+    pub sensitive_headers: Vec<String>,
+    /// Additional field names redacted everywhere in the event (see [`crate::scrubbing`]).
+    pub scrub_keys: Vec<String>,
+    /// Additional named regex patterns replaced inside strings.
+    pub scrub_patterns: BTreeMap<String, String>,
+    /// Additional URL query parameters whose values are redacted.
+    pub scrub_query_params: Vec<String>,
+    /// Zero the host part of client IP addresses before sending.
+    pub anonymize_ip: bool,
+    /// Replace strings that look like credentials (see [`crate::secrets`]).
+    pub detect_secrets: bool,
+    /// Error patterns that are never sent (see [`crate::filters`]).
+    pub ignored_error_types: Vec<String>,
+    /// Transaction name patterns that are never sampled.
+    pub ignored_transactions: Vec<String>,
+    /// Logger (`tracing`/`log` target) patterns whose events and breadcrumbs are dropped.
+    pub ignored_loggers: Vec<String>,
+    /// Own vs. third-party crates by module prefix (see [`crate::origins`]).
+    pub origins: crate::origins::OriginPolicy,
+    /// Event and span decisions for the tracing layer; install `tracing.clone().build()`
+    /// (see [`crate::tracing_layer`]).
+    pub tracing: crate::tracing_layer::TracingLayer,
+    /// Custom grouping rules (see [`crate::fingerprint`]).
+    pub fingerprint_rules: Vec<crate::fingerprint::FingerprintRule>,
+    /// Team tags by module path or error code prefix (see [`crate::ownership`]).
+    pub ownership_rules: Vec<crate::ownership::OwnershipRule>,
+    /// Event levels for the application's severities (see [`crate::severity`]).
+    pub severity: crate::severity::SeverityMapper,
+    /// Suppress identical events within this window; `None` disables deduplication.
+    pub dedupe_window: Option<Duration>,
+    /// Identical events sent per dedupe window.
+    pub dedupe_limit: usize,
+    /// Send one summary per window for matching errors (see [`crate::aggregation`]).
+    pub aggregation: Vec<crate::aggregation::AggregationRule>,
+    /// Downsample issues whose event rate spikes (see [`crate::spike`]).
+    pub spike_protection: Option<crate::spike::SpikeOptions>,
+    /// Client-side rate limits per category; `None` sends everything.
+    pub rate_limits: Option<crate::ratelimit::RateLimits>,
+    /// Retries of transient delivery failures (feature `http-transport`).
+    pub retry_policy: crate::backoff::RetryPolicy,
+    /// Batching and compression of the HTTP transport.
+    #[cfg(feature = "http-transport")]
+    pub http: crate::http::HttpOptions,
+    /// CA bundle, client certificate and minimum version (see [`crate::tls`]).
+    #[cfg(any(feature = "rustls", feature = "http-transport"))]
+    pub tls: crate::tls::TlsOptions,
+    /// Local envelope output replacing the network transport (see [`crate::local`]).
+    pub transport: Option<Arc<dyn crate::local::ObservabilityTransport>>,
+    /// Other projects for matching events and transactions (see [`crate::routing`]).
+    pub routes: Vec<crate::routing::Route>,
+    /// Also write every outgoing envelope here for replay (see [`crate::replay`]).
+    pub record_dir: Option<std::path::PathBuf>,
+    /// Release health sessions per process or per request (see [`crate::sessions`]).
+    pub session_tracking: crate::sessions::SessionTracking,
+    /// Size limits for event attachments (see [`crate::attachments`]).
+    pub attachment_limits: crate::attachments::AttachmentLimits,
+    /// Attach the last this many bytes of log output to error events (see [`crate::log_tail`]).
+    pub log_tail_bytes: Option<usize>,
+    /// Memory usage warnings and the memory context on errors (see [`crate::memory`]).
+    pub memory_monitor: Option<crate::memory::MemoryOptions>,
+    /// Look up the cloud instance at startup (see [`crate::cloud`]).
+    pub cloud_metadata: bool,
+    /// Publish envelopes to Kafka instead of the DSN (see [`crate::kafka`]).
+    #[cfg(feature = "kafka")]
+    pub kafka: Option<crate::kafka::KafkaOptions>,
+    /// Capture queries slower than this as warnings (see [`crate::sqlx_integration`]).
+    #[cfg(feature = "sqlx")]
+    pub slow_query_threshold: Option<Duration>,
+    /// Per-subject error capture for NATS handlers (see [`crate::nats_integration`]).
+    #[cfg(feature = "nats")]
+    pub nats: crate::nats_integration::NatsOptions,
+    /// Share of sampled transactions that are profiled (see [`crate::profiling`]).
+    #[cfg(feature = "profiling")]
+    pub profiles_sample_rate: f32,
+}
+
+impl Config {
+    /// Read settings from SENTRY_DSN, ENVIRONMENT and APP_VERSION.
+    pub fn from_env() -> Self {
+        Self {
+            dsn: dsn(),
+            environment: environment(),
+            release: release(),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_production(&self) -> bool {
+        self.environment == "production"
+    }
+
+    pub fn traces_sample_rate(&self) -> f32 {
+        self.traces_sample_rate
+            .unwrap_or(if self.is_production() { 0.1 } else { 1.0 })
+    }
+
+    pub fn debug(&self) -> bool {
+        self.debug.unwrap_or(!self.is_production())
+    }
+
+    pub fn breadcrumb_retention(&self) -> crate::breadcrumbs::Retention {
+        crate::breadcrumbs::Retention {
+            default: self.max_breadcrumbs,
+            per_category: self.breadcrumb_limits.clone(),
+        }
+    }
+}
+
+impl Default for Config {
+    /// No DSN (error tracking disabled) in the development environment,
+    /// with the release baked in at build time (see
+    /// [`ReleaseInfo::built`](crate::release_info::ReleaseInfo::built)).
+    /// Neither the environment nor `git` is consulted.
+    fn default() -> Self {
+        Self {
+            dsn: String::new(),
+            environment: "development".to_string(),
+            release: crate::release_info::ReleaseInfo::built().version(),
+            sample_rate: 1.0,
+            error_sampling: Vec::new(),
+            traces_sample_rate: None,
+            sampling: Default::default(),
+            max_breadcrumbs: 50,
+            breadcrumb_limits: BTreeMap::new(),
+            debug: None,
+            tags: BTreeMap::new(),
+            sensitive_headers: vec![
+                "Authorization".to_string(),
+                "Cookie".to_string(),
+                "X-API-Key".to_string(),
+            ],
+            scrub_keys: Vec::new(),
+            scrub_patterns: BTreeMap::new(),
+            scrub_query_params: Vec::new(),
+            anonymize_ip: false,
+            detect_secrets: false,
+            ignored_error_types: vec!["ExpectedBusinessError".to_string()],
+            ignored_transactions: Vec::new(),
+            ignored_loggers: Vec::new(),
+            origins: Default::default(),
+            tracing: Default::default(),
+            fingerprint_rules: Vec::new(),
+            ownership_rules: Vec::new(),
+            severity: Default::default(),
+            dedupe_window: None,
+            dedupe_limit: 1,
+            aggregation: Vec::new(),
+            spike_protection: None,
+            rate_limits: None,
+            retry_policy: Default::default(),
+            #[cfg(feature = "http-transport")]
+            http: Default::default(),
+            #[cfg(any(feature = "rustls", feature = "http-transport"))]
+            tls: Default::default(),
+            transport: None,
+            routes: Vec::new(),
+            record_dir: None,
+            session_tracking: Default::default(),
+            attachment_limits: Default::default(),
+            log_tail_bytes: None,
+            memory_monitor: None,
+            cloud_metadata: false,
+            #[cfg(feature = "kafka")]
+            kafka: None,
+            #[cfg(feature = "sqlx")]
+            slow_query_threshold: None,
+            #[cfg(feature = "nats")]
+            nats: Default::default(),
+            #[cfg(feature = "profiling")]
+            profiles_sample_rate: 0.0,
+        }
+    }
+}
+
+pub fn dsn() -> String {
+    env::var("SENTRY_DSN")
+        .unwrap_or_else(|_| "https://your-project-key@errors.observability.app.bauer-group.com/1".to_string())
+}
+
+pub fn environment() -> String {
+    env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string())
+}
+
+/// APP_VERSION, or the version detected from the build, CI variables or
+/// `git` (see [`crate::release_info::detected`]).
+pub fn release() -> String {
+    env::var("APP_VERSION")
+        .ok()
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| crate::release_info::detected().version())
+}
+
+/// Optional PEM bundle with additional root certificates (e.g. an internal CA).
+#[cfg(any(feature = "rustls", feature = "http-transport"))]
+pub fn ca_bundle() -> Option<String> {
+    env::var("SENTRY_CA_BUNDLE").ok().filter(|path| !path.is_empty())
+}
+
+/// Directory for the offline envelope spool; unset disables spooling.
+#[cfg(feature = "offline-spool")]
+pub fn spool_dir() -> Option<std::path::PathBuf> {
+    env::var_os("SENTRY_SPOOL_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(Into::into)
+}
+
+/// Spool for envelopes the HTTP transport gave up on. Unlike SENTRY_SPOOL_DIR,
+/// envelopes are only written to disk once their retries are exhausted.
+#[cfg(feature = "offline-spool")]
+pub fn spool_fallback_dir() -> Option<std::path::PathBuf> {
+    env::var_os("SENTRY_SPOOL_FALLBACK_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(Into::into)
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Cannot read config file {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Invalid config file {0}: {1}")]
+    Parse(String, String),
+
+    #[error("Unsupported config file format: {0}")]
+    UnsupportedFormat(String),
+}
+
+/// Load settings from `observability.toml` / `observability.yaml`.
+///
+/// Values from the file are overridden by SENTRY_DSN, ENVIRONMENT, APP_VERSION,
+/// SENTRY_SAMPLE_RATE, SENTRY_TRACES_SAMPLE_RATE and SENTRY_DEBUG when set.
+///
+/// ```toml
+/// dsn = "https://key@errors.observability.app.bauer-group.com/7"
+/// environment = "production"
+/// traces_sample_rate = 0.2
+/// record_dir = "/var/lib/app/envelope-recording"    # optional, see `replay`
+/// sessions = "request"    # or "process", "off"
+/// log_tail_kb = 64
+/// cloud_metadata = true
+/// profiles_sample_rate = 0.1    # feature "profiling"
+///
+/// [tags]
+/// team = "payments"
+///
+/// [breadcrumb_limits]    # other categories share max_breadcrumbs
+/// http = 30
+/// db = 10
+/// auth = 10
+///
+/// [scrubbing]
+/// headers = ["Authorization", "Cookie", "X-API-Key", "X-Session-Token"]
+/// keys = ["customer_number"]
+/// query_params = ["invite_code"]
+/// anonymize_ip = true
+/// detect_secrets = true
+///
+/// [scrubbing.patterns]
+/// order_token = '\bot_[A-Za-z0-9]{24}\b'
+///
+/// [filters]
+/// error_types = ["ExpectedBusinessError", "hyper::Error: connection reset*"]
+/// transactions = ["GET /health"]
+/// loggers = ["rdkafka::*"]
+///
+/// [origins]
+/// in_app = ["my_app"]
+/// only_in_app = true
+/// demote_third_party_panics = true
+///
+/// [tracing]
+/// spans = true
+/// skip_span_targets = ["tower_http::*"]
+///
+/// [[tracing.targets]]
+/// target = "sqlx::query"
+/// action = "breadcrumb"
+///
+/// [attachments]
+/// max_kb = 1024
+/// max_total_kb = 5120
+///
+/// [memory]
+/// enabled = true
+/// thresholds_percent = [80, 95]    # of the cgroup memory limit
+/// thresholds_mb = [1536]
+///
+/// [dedupe]
+/// window_secs = 60
+/// limit = 1
+///
+/// [spike_protection]
+/// enabled = true
+/// events_per_window = 60
+/// window_secs = 60
+/// summary_interval_secs = 300
+///
+/// [rate_limit]
+/// errors_per_minute = 600
+/// transactions_per_minute = 3000
+/// overflow = "queue"    # or "drop", "sample"
+/// queue_size = 500
+///
+/// [retry]
+/// max_attempts = 5
+/// base_ms = 500
+/// max_delay_ms = 30000
+/// jitter = 0.2
+/// statuses = [429, 502, 503, 504]
+///
+/// [transport]
+/// kind = "file"
+/// path = "/var/log/app/events.ndjson"
+///
+/// [[routes]]
+/// dsn = "https://key@errors.observability.app.bauer-group.com/3"
+/// kind = "transaction"
+///
+/// [http]
+/// proxy = "http://proxy.internal:3128"
+/// proxy_username = "svc-observability"
+/// proxy_password = "..."
+/// no_proxy = "localhost,.svc.cluster.local"
+///
+/// [tls]
+/// ca_bundle = "/etc/ssl/internal-ca.pem"
+/// client_cert = "/etc/app/tls/client.pem"
+/// client_key = "/etc/app/tls/client.key"
+/// min_version = "1.2"
+///
+/// [sqlx]
+/// slow_query_ms = 500
+///
+/// [[nats.subjects]]
+/// pattern = "telemetry.>"
+/// capture_errors = false
+///
+/// [[fingerprint]]
+/// name = "db-timeouts"
+/// exception_type = "DatabaseError"
+/// message = "timed? ?out"
+/// fingerprint = ["database-timeout"]
+///
+/// [[ownership]]
+/// team = "payments"
+/// code = "E-PAY-"
+///
+/// [severity.levels]
+/// degraded = "warning"
+///
+/// [[severity.escalate]]
+/// severity = "degraded"
+/// threshold = 100
+/// window_secs = 60
+/// level = "error"
+///
+/// [[sampling]]
+/// transaction = "GET /health"
+/// rate = 0.0
+///
+/// [[error_sampling]]
+/// exception_type = "ValidationError"
+/// rate = 0.01
+///
+/// [[aggregation]]
+/// fingerprint = "db-timeouts"
+/// window_secs = 300
+/// ```
+#[cfg(feature = "config-file")]
+pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Config, ConfigError> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    let text = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(name.clone(), e))?;
+
+    let file: FileConfig = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&text).map_err(|e| ConfigError::Parse(name.clone(), e.to_string()))?,
+        Some("yaml" | "yml") => {
+            serde_yaml::from_str(&text).map_err(|e| ConfigError::Parse(name.clone(), e.to_string()))?
+        }
+        _ => return Err(ConfigError::UnsupportedFormat(name)),
+    };
+    for (key, feature) in file.unsupported_keys() {
+        eprintln!("{}: ignoring `{}`, built without the `{}` feature", name, key, feature);
+    }
+
+    let mut config = file.into_config().map_err(|e| ConfigError::Parse(name, e))?;
+    apply_env_overrides(&mut config);
+    Ok(config)
+}
+
+/// Load the first `observability.{toml,yaml,yml}` in the working directory,
+/// falling back to environment variables only.
+#[cfg(feature = "config-file")]
+pub fn discover() -> Result<Config, ConfigError> {
+    ["observability.toml", "observability.yaml", "observability.yml"]
+        .into_iter()
+        .map(std::path::Path::new)
+        .find(|path| path.is_file())
+        .map_or_else(|| Ok(Config::from_env()), from_file)
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    dsn: Option<String>,
+    environment: Option<String>,
+    release: Option<String>,
+    sample_rate: Option<f32>,
+    traces_sample_rate: Option<f32>,
+    max_breadcrumbs: Option<usize>,
+    breadcrumb_limits: BTreeMap<String, usize>,
+    debug: Option<bool>,
+    record_dir: Option<std::path::PathBuf>,
+    sessions: Option<String>,
+    log_tail_kb: Option<usize>,
+    cloud_metadata: bool,
+    #[cfg(feature = "profiling")]
+    profiles_sample_rate: Option<f32>,
+    #[cfg(not(feature = "profiling"))]
+    profiles_sample_rate: Option<serde::de::IgnoredAny>,
+    tags: BTreeMap<String, String>,
+    scrubbing: ScrubbingConfig,
+    filters: FiltersConfig,
+    origins: OriginsConfig,
+    tracing: TracingConfig,
+    attachments: AttachmentsConfig,
+    memory: MemoryConfig,
+    dedupe: DedupeConfig,
+    spike_protection: SpikeConfig,
+    rate_limit: RateLimitConfig,
+    retry: RetryConfig,
+    transport: TransportConfig,
+    routes: Vec<RouteConfig>,
+    // Sections of features this build lacks are accepted and ignored, so one
+    // file serves every build; see `unsupported_keys`
+    #[cfg(feature = "http-transport")]
+    http: HttpConfig,
+    #[cfg(not(feature = "http-transport"))]
+    http: Option<serde::de::IgnoredAny>,
+    #[cfg(any(feature = "rustls", feature = "http-transport"))]
+    tls: TlsConfig,
+    #[cfg(not(any(feature = "rustls", feature = "http-transport")))]
+    tls: Option<serde::de::IgnoredAny>,
+    #[cfg(feature = "kafka")]
+    kafka: Option<KafkaConfig>,
+    #[cfg(not(feature = "kafka"))]
+    kafka: Option<serde::de::IgnoredAny>,
+    #[cfg(feature = "sqlx")]
+    sqlx: SqlxConfig,
+    #[cfg(not(feature = "sqlx"))]
+    sqlx: Option<serde::de::IgnoredAny>,
+    #[cfg(feature = "nats")]
+    nats: NatsConfig,
+    #[cfg(not(feature = "nats"))]
+    nats: Option<serde::de::IgnoredAny>,
+    fingerprint: Vec<FingerprintRuleConfig>,
+    ownership: Vec<OwnershipRuleConfig>,
+    severity: SeverityConfig,
+    sampling: Vec<SamplingRuleConfig>,
+    error_sampling: Vec<ErrorSamplingRuleConfig>,
+    aggregation: Vec<AggregationRuleConfig>,
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ScrubbingConfig {
+    headers: Option<Vec<String>>,
+    keys: Vec<String>,
+    query_params: Vec<String>,
+    anonymize_ip: bool,
+    detect_secrets: bool,
+    patterns: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FiltersConfig {
+    error_types: Option<Vec<String>>,
+    transactions: Vec<String>,
+    loggers: Vec<String>,
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OriginsConfig {
+    in_app: Vec<String>,
+    deny: Vec<String>,
+    only_in_app: bool,
+    demote_third_party_panics: bool,
+}
+
+#[cfg(feature = "config-file")]
+impl OriginsConfig {
+    fn into_policy(self) -> crate::origins::OriginPolicy {
+        crate::origins::OriginPolicy {
+            in_app: self.in_app,
+            deny: self.deny,
+            only_in_app: self.only_in_app,
+            demote_third_party_panics: self.demote_third_party_panics,
+        }
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TracingConfig {
+    spans: Option<bool>,
+    span_attributes: bool,
+    skip_span_targets: Vec<String>,
+    targets: Vec<TracingTargetConfig>,
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TracingTargetConfig {
+    target: String,
+    level: Option<String>,
+    action: String,
+}
+
+#[cfg(feature = "config-file")]
+impl TracingConfig {
+    fn into_layer(self) -> Result<crate::tracing_layer::TracingLayer, String> {
+        use crate::tracing_layer::{parse_action, TargetRule, TracingLayer};
+
+        let mut layer = TracingLayer::new()
+            .spans(self.spans.unwrap_or(true))
+            .span_attributes(self.span_attributes);
+        for target in self.skip_span_targets {
+            layer = layer.span_target(&target, false);
+        }
+        for rule in self.targets {
+            let level = rule
+                .level
+                .map(|level| level.parse::<tracing::Level>())
+                .transpose()
+                .map_err(|e| format!("tracing target {}: {}", rule.target, e))?;
+            let action = parse_action(&rule.action)
+                .ok_or_else(|| format!("tracing target {}: unknown action {:?}", rule.target, rule.action))?;
+            layer = layer.rule(TargetRule {
+                target: rule.target,
+                level,
+                action,
+            });
+        }
+        Ok(layer)
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DedupeConfig {
+    window_secs: Option<u64>,
+    limit: Option<usize>,
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SpikeConfig {
+    enabled: bool,
+    events_per_window: Option<u64>,
+    window_secs: Option<u64>,
+    summary_interval_secs: Option<u64>,
+}
+
+#[cfg(feature = "config-file")]
+impl SpikeConfig {
+    fn into_options(self) -> Option<crate::spike::SpikeOptions> {
+        if !self.enabled {
+            return None;
+        }
+        let defaults = crate::spike::SpikeOptions::default();
+        Some(crate::spike::SpikeOptions {
+            events_per_window: self.events_per_window.unwrap_or(defaults.events_per_window),
+            window: self.window_secs.map_or(defaults.window, Duration::from_secs),
+            summary_interval: self
+                .summary_interval_secs
+                .map_or(defaults.summary_interval, Duration::from_secs),
+        })
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RateLimitConfig {
+    errors_per_minute: Option<u32>,
+    transactions_per_minute: Option<u32>,
+    burst: Option<u32>,
+    overflow: Option<String>,
+    sample_rate: Option<f32>,
+    queue_size: Option<usize>,
+}
+
+#[cfg(feature = "config-file")]
+impl RateLimitConfig {
+    fn into_limits(self) -> Result<Option<crate::ratelimit::RateLimits>, String> {
+        use crate::ratelimit::{Limit, Overflow, RateLimits};
+
+        if self.errors_per_minute.is_none() && self.transactions_per_minute.is_none() {
+            return Ok(None);
+        }
+        let limit = |per_minute| {
+            let limit = Limit::per_minute(per_minute);
+            self.burst.map_or(limit, |burst| limit.burst(burst))
+        };
+        let overflow = match self.overflow.as_deref().unwrap_or("drop") {
+            "drop" => Overflow::Drop,
+            "sample" => Overflow::Sample(self.sample_rate.unwrap_or(0.1)),
+            "queue" => Overflow::Queue(self.queue_size.unwrap_or(100)),
+            other => return Err(format!("unknown rate_limit overflow policy {:?}", other)),
+        };
+        Ok(Some(RateLimits {
+            errors: self.errors_per_minute.map(limit),
+            transactions: self.transactions_per_minute.map(limit),
+            overflow,
+        }))
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RetryConfig {
+    max_attempts: Option<u32>,
+    base_ms: Option<u64>,
+    max_delay_ms: Option<u64>,
+    jitter: Option<f32>,
+    statuses: Option<Vec<u16>>,
+}
+
+#[cfg(feature = "config-file")]
+impl RetryConfig {
+    fn into_policy(self) -> crate::backoff::RetryPolicy {
+        let defaults = crate::backoff::RetryPolicy::default();
+        crate::backoff::RetryPolicy {
+            max_attempts: self.max_attempts.unwrap_or(defaults.max_attempts),
+            base: self.base_ms.map_or(defaults.base, Duration::from_millis),
+            max_delay: self.max_delay_ms.map_or(defaults.max_delay, Duration::from_millis),
+            jitter: self.jitter.unwrap_or(defaults.jitter),
+            retryable_statuses: self.statuses.unwrap_or(defaults.retryable_statuses),
+        }
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AttachmentsConfig {
+    max_kb: Option<usize>,
+    max_total_kb: Option<usize>,
+}
+
+#[cfg(feature = "config-file")]
+impl AttachmentsConfig {
+    fn into_limits(self) -> crate::attachments::AttachmentLimits {
+        let defaults = crate::attachments::AttachmentLimits::default();
+        crate::attachments::AttachmentLimits {
+            max_bytes: self.max_kb.map_or(defaults.max_bytes, |kb| kb * 1024),
+            max_total_bytes: self.max_total_kb.map_or(defaults.max_total_bytes, |kb| kb * 1024),
+        }
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MemoryConfig {
+    enabled: bool,
+    interval_secs: Option<u64>,
+    thresholds_mb: Vec<u64>,
+    thresholds_percent: Vec<f64>,
+}
+
+#[cfg(feature = "config-file")]
+impl MemoryConfig {
+    fn into_options(self) -> Option<crate::memory::MemoryOptions> {
+        use crate::memory::{MemoryOptions, MemoryThreshold};
+
+        if !self.enabled {
+            return None;
+        }
+        let defaults = MemoryOptions::default();
+        let mut thresholds: Vec<MemoryThreshold> = self
+            .thresholds_mb
+            .into_iter()
+            .map(|mb| MemoryThreshold::Bytes(mb * 1024 * 1024))
+            .chain(
+                self.thresholds_percent
+                    .into_iter()
+                    .map(|percent| MemoryThreshold::LimitRatio(percent / 100.0)),
+            )
+            .collect();
+        if thresholds.is_empty() {
+            thresholds = defaults.thresholds;
+        }
+        Some(MemoryOptions {
+            interval: self.interval_secs.map_or(defaults.interval, Duration::from_secs),
+            thresholds,
+        })
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TransportConfig {
+    kind: Option<String>,
+    path: Option<std::path::PathBuf>,
+    max_bytes: Option<u64>,
+    max_files: Option<usize>,
+}
+
+#[cfg(feature = "config-file")]
+impl TransportConfig {
+    fn into_transport(self) -> Result<Option<Arc<dyn crate::local::ObservabilityTransport>>, String> {
+        use crate::local::{FileTransport, StdoutTransport};
+
+        match self.kind.as_deref().unwrap_or("http") {
+            "http" => Ok(None),
+            "stdout" => Ok(Some(Arc::new(StdoutTransport))),
+            "file" => {
+                let path = self.path.ok_or("transport kind \"file\" needs a path")?;
+                let mut file = FileTransport::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                if let Some(max_bytes) = self.max_bytes {
+                    file = file.max_bytes(max_bytes);
+                }
+                if let Some(max_files) = self.max_files {
+                    file = file.max_files(max_files);
+                }
+                Ok(Some(Arc::new(file)))
+            }
+            other => Err(format!("unknown transport kind {:?}", other)),
+        }
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteConfig {
+    dsn: String,
+    kind: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "config-file")]
+impl RouteConfig {
+    fn into_route(self) -> Result<crate::routing::Route, String> {
+        use crate::routing::ItemKind;
+
+        let dsn = self
+            .dsn
+            .parse()
+            .map_err(|e| format!("route dsn {:?}: {}", self.dsn, e))?;
+        let kind = match self.kind.as_deref() {
+            None => None,
+            Some("event") => Some(ItemKind::Event),
+            Some("transaction") => Some(ItemKind::Transaction),
+            Some(other) => return Err(format!("unknown route kind {:?}", other)),
+        };
+        Ok(crate::routing::Route {
+            dsn,
+            kind,
+            tags: self.tags,
+        })
+    }
+}
+
+#[cfg(all(feature = "config-file", feature = "http-transport"))]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HttpConfig {
+    proxy: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    no_proxy: Option<String>,
+    unix_socket: Option<std::path::PathBuf>,
+}
+
+#[cfg(all(feature = "config-file", feature = "http-transport"))]
+impl HttpConfig {
+    fn into_options(self) -> crate::http::HttpOptions {
+        let proxy = self.proxy.map(|url| crate::http::ProxyOptions {
+            username: self.proxy_username,
+            password: self.proxy_password,
+            no_proxy: self.no_proxy,
+            ..crate::http::ProxyOptions::new(&url)
+        });
+        crate::http::HttpOptions {
+            proxy,
+            unix_socket: self.unix_socket,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(all(feature = "config-file", any(feature = "rustls", feature = "http-transport")))]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TlsConfig {
+    ca_bundle: Option<std::path::PathBuf>,
+    client_cert: Option<std::path::PathBuf>,
+    client_key: Option<std::path::PathBuf>,
+    min_version: Option<String>,
+}
+
+#[cfg(all(feature = "config-file", any(feature = "rustls", feature = "http-transport")))]
+impl TlsConfig {
+    fn into_options(self) -> Result<crate::tls::TlsOptions, String> {
+        Ok(crate::tls::TlsOptions {
+            ca_bundle: self.ca_bundle,
+            client_cert: self.client_cert,
+            client_key: self.client_key,
+            min_version: self.min_version.as_deref().map(str::parse).transpose()?,
+        })
+    }
+}
+
+#[cfg(all(feature = "config-file", feature = "kafka"))]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KafkaConfig {
+    brokers: String,
+    topic: String,
+    serialization: Option<String>,
+    #[serde(default)]
+    properties: BTreeMap<String, String>,
+}
+
+#[cfg(all(feature = "config-file", feature = "kafka"))]
+impl KafkaConfig {
+    fn into_options(self) -> Result<crate::kafka::KafkaOptions, String> {
+        use crate::kafka::{KafkaOptions, Serialization};
+
+        let serialization = match self.serialization.as_deref().unwrap_or("envelope") {
+            "envelope" => Serialization::Envelope,
+            "json" => Serialization::Json,
+            other => return Err(format!("unknown kafka serialization {:?}", other)),
+        };
+        Ok(KafkaOptions {
+            serialization,
+            properties: self.properties,
+            ..KafkaOptions::new(&self.brokers, &self.topic)
+        })
+    }
+}
+
+#[cfg(all(feature = "config-file", feature = "sqlx"))]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SqlxConfig {
+    slow_query_ms: Option<u64>,
+}
+
+#[cfg(all(feature = "config-file", feature = "nats"))]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NatsConfig {
+    subjects: Vec<NatsSubjectConfig>,
+}
+
+#[cfg(all(feature = "config-file", feature = "nats"))]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NatsSubjectConfig {
+    pattern: String,
+    capture_errors: bool,
+}
+
+#[cfg(all(feature = "config-file", feature = "nats"))]
+impl NatsConfig {
+    fn into_options(self) -> crate::nats_integration::NatsOptions {
+        use crate::nats_integration::{NatsOptions, SubjectRule};
+
+        NatsOptions {
+            subjects: self
+                .subjects
+                .into_iter()
+                .map(|subject| SubjectRule::new(&subject.pattern, subject.capture_errors))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FingerprintRuleConfig {
+    name: String,
+    exception_type: Option<String>,
+    message: Option<String>,
+    module: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+    fingerprint: Vec<String>,
+}
+
+#[cfg(feature = "config-file")]
+impl FingerprintRuleConfig {
+    fn into_rule(self) -> Result<crate::fingerprint::FingerprintRule, String> {
+        let fingerprint: Vec<&str> = self.fingerprint.iter().map(String::as_str).collect();
+        let mut rule = crate::fingerprint::FingerprintRule::new(&self.name, &fingerprint);
+        rule.exception_type = self.exception_type;
+        rule.module = self.module;
+        rule.tags = self.tags;
+        match self.message {
+            Some(message) => rule
+                .message(&message)
+                .map_err(|e| format!("fingerprint rule {}: {}", self.name, e)),
+            None => Ok(rule),
+        }
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SamplingRuleConfig {
+    transaction: Option<String>,
+    op: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+    parent_sampled: Option<bool>,
+    rate: f32,
+}
+
+#[cfg(feature = "config-file")]
+impl SamplingRuleConfig {
+    fn into_rule(self) -> Result<crate::sampling::SamplingRule, String> {
+        if !(0.0..=1.0).contains(&self.rate) {
+            return Err(format!("sampling rate {} is not between 0 and 1", self.rate));
+        }
+        Ok(crate::sampling::SamplingRule {
+            tags: self.tags,
+            transaction: self.transaction,
+            op: self.op,
+            parent_sampled: self.parent_sampled,
+            ..crate::sampling::SamplingRule::new(self.rate)
+        })
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ErrorSamplingRuleConfig {
+    exception_type: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+    rate: f32,
+}
+
+#[cfg(feature = "config-file")]
+impl ErrorSamplingRuleConfig {
+    fn into_rule(self) -> Result<crate::error_sampling::ErrorSamplingRule, String> {
+        if !(0.0..=1.0).contains(&self.rate) {
+            return Err(format!("error sampling rate {} is not between 0 and 1", self.rate));
+        }
+        Ok(crate::error_sampling::ErrorSamplingRule {
+            exception_type: self.exception_type,
+            tags: self.tags,
+            rate: self.rate,
+        })
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OwnershipRuleConfig {
+    team: String,
+    module: Option<String>,
+    code: Option<String>,
+}
+
+#[cfg(feature = "config-file")]
+impl OwnershipRuleConfig {
+    fn into_rule(self) -> crate::ownership::OwnershipRule {
+        crate::ownership::OwnershipRule {
+            team: self.team,
+            module: self.module,
+            code: self.code,
+        }
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SeverityConfig {
+    levels: BTreeMap<String, String>,
+    escalate: Vec<SeverityEscalationConfig>,
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeverityEscalationConfig {
+    severity: String,
+    threshold: usize,
+    window_secs: u64,
+    level: String,
+}
+
+#[cfg(feature = "config-file")]
+impl SeverityConfig {
+    fn into_mapper(self) -> Result<crate::severity::SeverityMapper, String> {
+        let level = |name: &str| {
+            name.parse::<sentry::Level>()
+                .map_err(|_| format!("unknown severity level {:?}", name))
+        };
+        let mut mapper = crate::severity::SeverityMapper::new();
+        for (severity, name) in &self.levels {
+            mapper = mapper.map(severity, level(name)?);
+        }
+        for escalation in self.escalate {
+            if escalation.window_secs == 0 {
+                return Err("severity escalation window_secs must be positive".to_string());
+            }
+            mapper = mapper.escalate(
+                &escalation.severity,
+                escalation.threshold,
+                Duration::from_secs(escalation.window_secs),
+                level(&escalation.level)?,
+            );
+        }
+        Ok(mapper)
+    }
+}
+
+#[cfg(feature = "config-file")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AggregationRuleConfig {
+    fingerprint: Option<String>,
+    exception_type: Option<String>,
+    window_secs: u64,
+}
+
+#[cfg(feature = "config-file")]
+impl AggregationRuleConfig {
+    fn into_rule(self) -> Result<crate::aggregation::AggregationRule, String> {
+        if self.window_secs == 0 {
+            return Err("aggregation window_secs must be positive".to_string());
+        }
+        Ok(crate::aggregation::AggregationRule {
+            fingerprint: self.fingerprint,
+            exception_type: self.exception_type,
+            window: Duration::from_secs(self.window_secs),
+        })
+    }
+}
+
+#[cfg(feature = "config-file")]
+impl FileConfig {
+    /// Keys present in the file whose feature is not compiled in, with that feature.
+    fn unsupported_keys(&self) -> Vec<(&'static str, &'static str)> {
+        let keys: &[(&str, &str, bool)] = &[
+            #[cfg(not(feature = "profiling"))]
+            ("profiles_sample_rate", "profiling", self.profiles_sample_rate.is_some()),
+            #[cfg(not(feature = "http-transport"))]
+            ("http", "http-transport", self.http.is_some()),
+            #[cfg(not(any(feature = "rustls", feature = "http-transport")))]
+            ("tls", "rustls", self.tls.is_some()),
+            #[cfg(not(feature = "kafka"))]
+            ("kafka", "kafka", self.kafka.is_some()),
+            #[cfg(not(feature = "sqlx"))]
+            ("sqlx", "sqlx", self.sqlx.is_some()),
+            #[cfg(not(feature = "nats"))]
+            ("nats", "nats", self.nats.is_some()),
+        ];
+        keys.iter()
+            .filter(|(_, _, present)| *present)
+            .map(|&(key, feature, _)| (key, feature))
+            .collect()
+    }
+
+    fn into_config(self) -> Result<Config, String> {
+        let defaults = Config::default();
+        let fingerprint_rules = self
+            .fingerprint
+            .into_iter()
+            .map(FingerprintRuleConfig::into_rule)
+            .collect::<Result<_, _>>()?;
+        let sampling_rules = self
+            .sampling
+            .into_iter()
+            .map(SamplingRuleConfig::into_rule)
+            .collect::<Result<_, _>>()?;
+        let error_sampling = self
+            .error_sampling
+            .into_iter()
+            .map(ErrorSamplingRuleConfig::into_rule)
+            .collect::<Result<_, _>>()?;
+        let aggregation = self
+            .aggregation
+            .into_iter()
+            .map(AggregationRuleConfig::into_rule)
+            .collect::<Result<_, _>>()?;
+        let rate_limits = self.rate_limit.into_limits()?;
+        let transport = self.transport.into_transport()?;
+        let routes = self
+            .routes
+            .into_iter()
+            .map(RouteConfig::into_route)
+            .collect::<Result<_, _>>()?;
+        let session_tracking = match self.sessions.as_deref().unwrap_or("off") {
+            "off" => crate::sessions::SessionTracking::Disabled,
+            "process" => crate::sessions::SessionTracking::Process,
+            "request" => crate::sessions::SessionTracking::Request,
+            other => return Err(format!("unknown session tracking {:?}", other)),
+        };
+        Ok(Config {
+            dsn: self.dsn.unwrap_or(defaults.dsn),
+            environment: self.environment.unwrap_or(defaults.environment),
+            release: self.release.unwrap_or_else(release),
+            sample_rate: self.sample_rate.unwrap_or(defaults.sample_rate),
+            error_sampling,
+            traces_sample_rate: self.traces_sample_rate,
+            sampling: crate::sampling::SamplingRules::new(sampling_rules),
+            max_breadcrumbs: self.max_breadcrumbs.unwrap_or(defaults.max_breadcrumbs),
+            breadcrumb_limits: self.breadcrumb_limits,
+            debug: self.debug,
+            tags: self.tags,
+            sensitive_headers: self.scrubbing.headers.unwrap_or(defaults.sensitive_headers),
+            scrub_keys: self.scrubbing.keys,
+            scrub_patterns: self.scrubbing.patterns,
+            scrub_query_params: self.scrubbing.query_params,
+            anonymize_ip: self.scrubbing.anonymize_ip,
+            detect_secrets: self.scrubbing.detect_secrets,
+            ignored_error_types: self.filters.error_types.unwrap_or(defaults.ignored_error_types),
+            ignored_transactions: self.filters.transactions,
+            ignored_loggers: self.filters.loggers,
+            origins: self.origins.into_policy(),
+            tracing: self.tracing.into_layer()?,
+            fingerprint_rules,
+            ownership_rules: self.ownership.into_iter().map(OwnershipRuleConfig::into_rule).collect(),
+            severity: self.severity.into_mapper()?,
+            dedupe_window: self.dedupe.window_secs.map(Duration::from_secs),
+            dedupe_limit: self.dedupe.limit.unwrap_or(defaults.dedupe_limit),
+            aggregation,
+            spike_protection: self.spike_protection.into_options(),
+            rate_limits,
+            retry_policy: self.retry.into_policy(),
+            #[cfg(feature = "http-transport")]
+            http: self.http.into_options(),
+            #[cfg(any(feature = "rustls", feature = "http-transport"))]
+            tls: self.tls.into_options()?,
+            transport,
+            routes,
+            record_dir: self.record_dir,
+            session_tracking,
+            attachment_limits: self.attachments.into_limits(),
+            log_tail_bytes: self.log_tail_kb.map(|kb| kb * 1024),
+            memory_monitor: self.memory.into_options(),
+            cloud_metadata: self.cloud_metadata,
+            #[cfg(feature = "kafka")]
+            kafka: self.kafka.map(KafkaConfig::into_options).transpose()?,
+            #[cfg(feature = "sqlx")]
+            slow_query_threshold: self.sqlx.slow_query_ms.map(Duration::from_millis),
+            #[cfg(feature = "nats")]
+            nats: self.nats.into_options(),
+            #[cfg(feature = "profiling")]
+            profiles_sample_rate: self.profiles_sample_rate.unwrap_or(defaults.profiles_sample_rate),
+        })
+    }
+}
+
+#[cfg(feature = "config-file")]
+fn apply_env_overrides(config: &mut Config) {
+    let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+
+    if let Some(dsn) = var("SENTRY_DSN") {
+        config.dsn = dsn;
+    }
+    if let Some(environment) = var("ENVIRONMENT") {
+        config.environment = environment;
+    }
+    if let Some(release) = var("APP_VERSION") {
+        config.release = release;
+    }
+    if let Some(rate) = var("SENTRY_SAMPLE_RATE").and_then(|v| v.parse().ok()) {
+        config.sample_rate = rate;
+    }
+    if let Some(rate) = var("SENTRY_TRACES_SAMPLE_RATE").and_then(|v| v.parse().ok()) {
+        config.traces_sample_rate = Some(rate);
+    }
+    if let Some(debug) = var("SENTRY_DEBUG") {
+        config.debug = Some(matches!(debug.as_str(), "1" | "true" | "yes"));
+    }
+}
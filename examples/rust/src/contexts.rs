@@ -0,0 +1,74 @@
+//! Typed replacements for `set_context(name, BTreeMap<String, Value>)`.
+//!
+//! `#[derive(TypedContext)]` names the context next to the struct; each
+//! field becomes a key of the context, `None` fields are left out:
+//!
+//! ```ignore
+//! #[derive(Clone, TypedContext)]
+//! #[context("tenant")]
+//! pub struct TenantContext {
+//!     pub id: String,
+//!     pub plan: Option<String>,
+//! }
+//!
+//! sentry.set_typed_context(&TenantContext { id: "acme".into(), plan: None });
+//! ```
+
+use sentry::protocol::{Context, Value};
+use std::collections::BTreeMap;
+
+pub use rust_example_macros::TypedContext;
+
+pub trait TypedContext {
+    /// Key of the context on the event.
+    const NAME: &'static str;
+
+    fn to_map(&self) -> BTreeMap<String, Value>;
+
+    fn to_context(&self) -> Context {
+        Context::Other(self.to_map())
+    }
+}
+
+/// Database a failing operation talked to.
+#[derive(Debug, Clone, Default, PartialEq, TypedContext)]
+#[context("database")]
+pub struct DatabaseContext {
+    /// `postgresql`, `mysql`, `redis`, ...
+    pub system: String,
+    pub name: String,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub operation: Option<String>,
+}
+
+/// Inbound request being handled, for events without a full `Request` interface.
+#[derive(Debug, Clone, Default, PartialEq, TypedContext)]
+#[context("http_request")]
+pub struct HttpRequestContext {
+    pub method: String,
+    pub url: String,
+    pub route: Option<String>,
+    pub status_code: Option<u16>,
+    pub duration_ms: Option<u64>,
+}
+
+/// Background job or queue message being processed.
+#[derive(Debug, Clone, Default, PartialEq, TypedContext)]
+#[context("job")]
+pub struct JobContext {
+    pub queue: String,
+    pub job_id: String,
+    pub attempt: u32,
+    pub scheduled_at: Option<String>,
+}
+
+/// Where this instance runs.
+#[derive(Debug, Clone, Default, PartialEq, TypedContext)]
+#[context("deployment")]
+pub struct DeploymentContext {
+    pub region: String,
+    pub cluster: Option<String>,
+    pub instance: Option<String>,
+    pub commit: Option<String>,
+}
@@ -0,0 +1,106 @@
+//! Request correlation through `X-Correlation-ID`.
+//!
+//! The axum and actix-web middleware take the ID from the incoming request or
+//! generate one, set it as the `correlation_id` tag on the request's scope
+//! (so every event and the transaction carry it), run the handler inside a
+//! `correlation` tracing span with a `correlation_id` field and echo it in the
+//! response header. The reqwest middleware passes it on to downstream
+//! services. Handlers put it into error bodies, so support can search Bugsink
+//! for the ID a customer reports:
+//!
+//! ```ignore
+//! async fn create_order(...) -> Result<Json<Order>, ApiError> {
+//!     let order = orders.create(request).await.map_err(|_| ApiError {
+//!         message: "Could not create the order".into(),
+//!         correlation_id: correlation::current_correlation_id().map(|id| id.to_string()),
+//!     })?;
+//!     ...
+//! }
+//! ```
+//!
+//! Incoming IDs longer than 128 characters or with characters other than
+//! letters, digits, `-`, `_`, `.` and `:` are replaced with a fresh one, so
+//! the ID is safe to embed in headers, JSON and HTML. With the sentry tracing
+//! layer, `TracingLayer::span_target("correlation", false)` keeps the span
+//! out of the transaction.
+
+use super::{propagation::HeaderCarrier, scope_debug::ScopeSnapshot};
+use sentry::Hub;
+use std::fmt;
+
+pub const HEADER: &str = "x-correlation-id";
+
+/// Tag carrying the ID on events and transactions.
+pub const TAG: &str = "correlation_id";
+
+const MAX_LEN: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CorrelationId(String);
+
+impl CorrelationId {
+    /// A fresh random ID.
+    pub fn new() -> Self {
+        Self(sentry::types::random_uuid().to_string())
+    }
+
+    /// Accept an ID from a header value; `None` if it is empty, too long
+    /// or has other characters than `[A-Za-z0-9._:-]`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let valid = !value.is_empty()
+            && value.len() <= MAX_LEN
+            && value.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(&b));
+        valid.then(|| Self(value.to_string()))
+    }
+
+    /// The caller's ID, or a new one.
+    pub fn from_headers(headers: &(impl HeaderCarrier + ?Sized)) -> Self {
+        headers.get_header(HEADER).and_then(Self::parse).unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Tag events and transactions of `hub` with the ID.
+    pub fn bind(&self, hub: &Hub) {
+        hub.configure_scope(|scope| scope.set_tag(TAG, &self.0));
+    }
+
+    /// Span to run the request in, so log lines and tracing events carry the ID.
+    pub fn span(&self) -> tracing::Span {
+        tracing::info_span!(target: "correlation", "correlation", correlation_id = %self.0)
+    }
+
+    /// Set the header unless `headers` already has one.
+    pub fn inject(&self, headers: &mut (impl HeaderCarrier + ?Sized)) {
+        if headers.get_header(HEADER).is_none() {
+            headers.set_header(HEADER, self.0.clone());
+        }
+    }
+}
+
+impl Default for CorrelationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The ID bound to the current hub, if any.
+pub fn current_correlation_id() -> Option<CorrelationId> {
+    ScopeSnapshot::current().tags.remove(TAG).map(CorrelationId)
+}
+
+/// Pass the current ID on in outgoing `headers`.
+pub fn inject_correlation_id(headers: &mut (impl HeaderCarrier + ?Sized)) {
+    if let Some(id) = current_correlation_id() {
+        id.inject(headers);
+    }
+}
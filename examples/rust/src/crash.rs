@@ -0,0 +1,508 @@
+//! Panic capture that survives the process going down, and detection of
+//! previous runs that died without any hook running.
+//!
+//! The SDK's panic integration captures the panic but returns immediately, so
+//! a binary that exits (or aborts with `panic = "abort"`) right after usually
+//! loses the event. This hook captures a fatal event with the backtrace, the
+//! panicking thread and the current scope, then waits for the transport.
+//!
+//! SIGKILL and the OOM killer leave no chance to report anything. For those,
+//! a session marker holding the last known scope is written at startup and
+//! refreshed by sent events, at most once per second; a clean shutdown
+//! removes it, and so does the panic hook once the panic is reported.
+//! Finding the marker at the next startup means the previous session crashed
+//! without any report.
+
+use sentry::{
+    integrations::panic::PanicIntegration,
+    protocol::{Event, Thread, Values},
+    types::Uuid,
+    Hub, Level,
+};
+use std::{
+    fs, panic,
+    path::{Path, PathBuf},
+    sync::{Mutex, Once, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Minimum time between two marker refreshes.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+static MARKER: OnceLock<PathBuf> = OnceLock::new();
+
+/// Report a crashed previous session if its marker is still present, then
+/// write the marker for this session. Only the first call per process has
+/// an effect; returns the id of the crash event, if one was sent.
+pub fn arm(path: impl Into<PathBuf>) -> Option<Uuid> {
+    let path = path.into();
+    if MARKER.set(path.clone()).is_err() {
+        return None;
+    }
+    rearm(&path)
+}
+
+/// [`arm`] without the once-per-process check.
+pub(crate) fn rearm(path: &Path) -> Option<Uuid> {
+    let previous = fs::read(path).ok().map(|bytes| {
+        let last_scope = serde_json::from_slice::<Event<'static>>(&bytes).unwrap_or_default();
+        Hub::current().capture_event(crash_event(last_scope))
+    });
+
+    let current = sentry::configure_scope(|scope| scope.apply_to_event(Event::default()));
+    write_marker(path, &current.unwrap_or_default());
+    previous
+}
+
+/// Remove the marker on clean shutdown.
+pub fn disarm() {
+    if let Some(path) = MARKER.get() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Refresh the marker with the scope carried by an event being sent, at
+/// most once per [`CHECKPOINT_INTERVAL`] so an error storm does not turn
+/// into a storm of disk writes.
+pub(crate) fn checkpoint(event: &Event<'static>) {
+    static LAST: Mutex<Option<Instant>> = Mutex::new(None);
+    let Some(path) = MARKER.get() else {
+        return;
+    };
+    {
+        let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+        if !last.is_none_or(|last| last.elapsed() >= CHECKPOINT_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+    }
+    write_marker(path, event);
+}
+
+/// "Previous session crashed" event carrying the scope last seen in that session.
+fn crash_event(last_scope: Event<'static>) -> Event<'static> {
+    let mut event = Event {
+        level: Level::Fatal,
+        message: Some("Previous session crashed".to_string()),
+        user: last_scope.user,
+        tags: last_scope.tags,
+        extra: last_scope.extra,
+        contexts: last_scope.contexts,
+        breadcrumbs: last_scope.breadcrumbs,
+        transaction: last_scope.transaction,
+        ..Default::default()
+    };
+    event.tags.insert("session.crashed".to_string(), "true".to_string());
+    event
+}
+
+/// Only the scope is persisted: no exception, message or request data.
+fn write_marker(path: &Path, event: &Event<'static>) {
+    let scope = Event {
+        user: event.user.clone(),
+        tags: event.tags.clone(),
+        extra: event.extra.clone(),
+        contexts: event.contexts.clone(),
+        breadcrumbs: event.breadcrumbs.clone(),
+        transaction: event.transaction.clone(),
+        ..Default::default()
+    };
+    let Ok(json) = serde_json::to_vec(&scope) else {
+        return;
+    };
+    let tmp = path.with_extension("tmp");
+    if fs::write(&tmp, json).and_then(|_| fs::rename(&tmp, path)).is_err() {
+        eprintln!("Cannot write session marker {}", path.display());
+    }
+}
+
+/// Install the panic hook once per process; later calls are ignored.
+///
+/// The hook set before (the application's, or the standard one printing
+/// the panic message) still runs after the event is captured. The SDK's
+/// own panic integration is always left out of the client by
+/// `SentryService::init_sentry`, so a panic is reported once, and only
+/// after [`SentryService::install_panic_handler`](crate::SentryService::install_panic_handler)
+/// installed this hook.
+pub fn install_panic_hook(flush_timeout: Duration) {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| panic::set_hook(chained_hook(flush_timeout)));
+}
+
+/// A hook capturing the panic as a fatal event, then running the hook that is set now.
+pub(crate) fn chained_hook(flush_timeout: Duration) -> Box<dyn Fn(&panic::PanicHookInfo<'_>) + Send + Sync> {
+    let previous = panic::take_hook();
+    Box::new(move |info| {
+        let mut event = PanicIntegration::new().event_from_panic_info(info);
+        event.level = Level::Fatal;
+
+        let current = thread::current();
+        let name = current.name().unwrap_or("<unnamed>").to_string();
+        event.tags.insert("thread.name".to_string(), name.clone());
+        event.threads = Values::from(vec![Thread {
+            name: Some(name),
+            crashed: true,
+            current: true,
+            ..Default::default()
+        }]);
+
+        let hub = Hub::current();
+        hub.capture_event(event);
+        if let Some(client) = hub.client() {
+            client.flush(Some(flush_timeout));
+        }
+        // Reported: with `panic = "abort"` the process dies next, and the
+        // next start must not report it again. If the panic is caught, a
+        // later sent event writes the marker again.
+        disarm();
+
+        previous(info);
+    })
+}
+
+/// Opt-in handlers for fatal signals the panic hook never sees (SIGSEGV,
+/// SIGBUS, SIGABRT from native code or `std::process::abort`).
+///
+/// The handler only makes async-signal-safe calls. Everything that needs
+/// allocation (event id, release, spool path) is prepared at install time;
+/// the event is formatted into a static buffer and written with `open`,
+/// `write` and `rename` as an envelope into the spool directory, where the
+/// spool transport or `replay` picks it up on the next run. Afterwards the
+/// previous action is restored and its handler, if any, called; then the
+/// signal is raised again with the default action, so the process ends and
+/// dumps core even when the signal was sent with `kill()`.
+///
+/// Frames are raw instruction addresses found by following the frame
+/// pointer chain from the interrupted context, not by the system unwinder.
+/// Stack memory is read with `process_vm_readv`, so a corrupt chain ends
+/// the walk instead of faulting again. Code built without frame pointers
+/// yields only the faulting address; build with
+/// `RUSTFLAGS="-C force-frame-pointers=yes"` for full stacks.
+#[cfg(all(target_os = "linux", feature = "crash-signals"))]
+pub mod signals {
+    use sentry::Hub;
+    use std::{
+        cell::UnsafeCell,
+        ffi::c_void,
+        fs, io, mem,
+        os::unix::ffi::OsStrExt,
+        path::Path,
+        ptr,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            OnceLock,
+        },
+    };
+
+    const SIGNALS: [(libc::c_int, &str, &str); 3] = [
+        (libc::SIGSEGV, "SIGSEGV", "Segmentation fault"),
+        (libc::SIGBUS, "SIGBUS", "Bus error"),
+        (libc::SIGABRT, "SIGABRT", "Abort"),
+    ];
+    const MAX_FRAMES: usize = 64;
+    const BUFFER_SIZE: usize = 16 * 1024;
+
+    struct Prepared {
+        /// Spool directory with a trailing slash.
+        dir: Vec<u8>,
+        /// Envelope and item headers plus the fixed event fields.
+        head: Vec<u8>,
+        pid: u32,
+    }
+
+    struct Buffer(UnsafeCell<[u8; BUFFER_SIZE]>);
+
+    // Only the thread that wins HANDLING touches the buffer
+    unsafe impl Sync for Buffer {}
+
+    static PREPARED: OnceLock<Prepared> = OnceLock::new();
+    static PREVIOUS: OnceLock<Vec<(libc::c_int, libc::sigaction)>> = OnceLock::new();
+    static HANDLING: AtomicBool = AtomicBool::new(false);
+    static BUFFER: Buffer = Buffer(UnsafeCell::new([0; BUFFER_SIZE]));
+    static PATH: Buffer = Buffer(UnsafeCell::new([0; BUFFER_SIZE]));
+
+    /// Install the handlers; crash envelopes go to `spool_dir`. Only the first call has an effect.
+    pub fn install(spool_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(spool_dir)?;
+        let mut dir = spool_dir.as_os_str().as_bytes().to_vec();
+        dir.push(b'/');
+        if dir.len() > BUFFER_SIZE / 2 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "spool path too long"));
+        }
+
+        let event_id = sentry::types::random_uuid().simple().to_string();
+        let (release, environment) = Hub::current()
+            .client()
+            .map(|client| (client.options().release.clone(), client.options().environment.clone()))
+            .unwrap_or_default();
+        let head = format!(
+            "{{\"event_id\":\"{id}\"}}\n{{\"type\":\"event\"}}\n{{\"event_id\":\"{id}\",\"level\":\"fatal\",\
+                 \"platform\":\"native\",\"release\":{},\"environment\":{},",
+            serde_json::Value::from(release.as_deref()),
+            serde_json::Value::from(environment.as_deref()),
+            id = event_id,
+        );
+
+        let prepared = Prepared {
+            dir,
+            head: head.into_bytes(),
+            pid: std::process::id(),
+        };
+        if PREPARED.set(prepared).is_err() {
+            return Ok(());
+        }
+
+        let mut previous = Vec::new();
+        for (signal, _, _) in SIGNALS {
+            // SAFETY: plain sigaction calls with zero-initialized structs
+            unsafe {
+                let mut action: libc::sigaction = mem::zeroed();
+                action.sa_sigaction = handle as *const () as usize;
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut old: libc::sigaction = mem::zeroed();
+                if libc::sigaction(signal, &action, &mut old) == 0 {
+                    previous.push((signal, old));
+                }
+            }
+        }
+        let _ = PREVIOUS.set(previous);
+        Ok(())
+    }
+
+    extern "C" fn handle(signal: libc::c_int, info: *mut libc::siginfo_t, context: *mut c_void) {
+        if let Some(prepared) = PREPARED.get() {
+            if !HANDLING.swap(true, Ordering::SeqCst) {
+                let mut frames = [0usize; MAX_FRAMES];
+                // SAFETY: the kernel passes a valid siginfo and context for SA_SIGINFO handlers
+                let (address, count) = unsafe { ((*info).si_addr() as usize, walk_stack(context, &mut frames)) };
+                // SAFETY: HANDLING guarantees exclusive access to the static buffers
+                unsafe { write_crash(prepared, signal, address, &frames[..count]) };
+            }
+        }
+
+        let previous = PREVIOUS
+            .get()
+            .and_then(|previous| previous.iter().find(|(s, _)| *s == signal))
+            .map(|(_, action)| *action);
+        // SAFETY: restoring and calling a sigaction saved at install time
+        unsafe {
+            if let Some(action) = previous {
+                libc::sigaction(signal, &action, ptr::null_mut());
+                // Run a previous handler in place: std's stack overflow handler, for
+                // one, only resets the action and relies on the fault happening again
+                match action.sa_sigaction {
+                    libc::SIG_DFL | libc::SIG_IGN => {}
+                    handler if action.sa_flags & libc::SA_SIGINFO != 0 => {
+                        let handler: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut c_void) =
+                            mem::transmute(handler);
+                        handler(signal, info, context);
+                    }
+                    handler => {
+                        let handler: extern "C" fn(libc::c_int) = mem::transmute(handler);
+                        handler(signal);
+                    }
+                }
+            }
+            // Then end the process with the default action, also for signals sent
+            // with kill() that do not fault again; delivered once the handler returns
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+
+    /// Instruction addresses of the interrupted code, innermost first: the
+    /// program counter of `context` (a `ucontext_t` passed to an
+    /// `SA_SIGINFO` handler), then the return address of every frame on the
+    /// frame pointer chain. Returns the number of entries filled in.
+    ///
+    /// Async-signal-safe: nothing is allocated and stack memory is only
+    /// read through `process_vm_readv`, which fails instead of faulting.
+    pub(crate) unsafe fn walk_stack(context: *mut c_void, frames: &mut [usize]) -> usize {
+        let Some((pc, mut fp, sp)) = registers(context) else {
+            return 0;
+        };
+        if frames.is_empty() || pc == 0 {
+            return 0;
+        }
+        frames[0] = pc;
+        let mut count = 1;
+        // The chain must point up the stack, to aligned addresses, and end somewhere
+        while count < frames.len() && fp >= sp && fp % mem::align_of::<usize>() == 0 {
+            // Frame record: saved frame pointer, then the return address
+            let mut record = [0usize; 2];
+            if !read_stack(fp, &mut record) || record[1] == 0 {
+                break;
+            }
+            frames[count] = record[1];
+            count += 1;
+            if record[0] <= fp {
+                break;
+            }
+            fp = record[0];
+        }
+        count
+    }
+
+    /// Program counter, frame pointer and stack pointer saved in a `ucontext_t`.
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn registers(context: *mut c_void) -> Option<(usize, usize, usize)> {
+        let context = (context as *const libc::ucontext_t).as_ref()?;
+        let registers = &context.uc_mcontext.gregs;
+        Some((
+            registers[libc::REG_RIP as usize] as usize,
+            registers[libc::REG_RBP as usize] as usize,
+            registers[libc::REG_RSP as usize] as usize,
+        ))
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    unsafe fn registers(context: *mut c_void) -> Option<(usize, usize, usize)> {
+        let context = (context as *const libc::ucontext_t).as_ref()?;
+        let registers = &context.uc_mcontext;
+        Some((
+            registers.pc as usize,
+            registers.regs[29] as usize,
+            registers.sp as usize,
+        ))
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    unsafe fn registers(_context: *mut c_void) -> Option<(usize, usize, usize)> {
+        None
+    }
+
+    /// Copy words from `address` in this process; false if the memory is not readable.
+    unsafe fn read_stack(address: usize, out: &mut [usize]) -> bool {
+        let len = mem::size_of_val(out);
+        let local = libc::iovec {
+            iov_base: out.as_mut_ptr() as *mut c_void,
+            iov_len: len,
+        };
+        let remote = libc::iovec {
+            iov_base: address as *mut c_void,
+            iov_len: len,
+        };
+        libc::process_vm_readv(libc::getpid(), &local, 1, &remote, 1, 0) == len as isize
+    }
+
+    /// Format the crash envelope into the static buffer and write it to the spool.
+    unsafe fn write_crash(prepared: &Prepared, signal: libc::c_int, address: usize, frames: &[usize]) {
+        let (name, description) = SIGNALS
+            .iter()
+            .find(|(s, _, _)| *s == signal)
+            .map(|(_, name, description)| (*name, *description))
+            .unwrap_or(("SIGNAL", "Fatal signal"));
+
+        let mut now: libc::timespec = mem::zeroed();
+        libc::clock_gettime(libc::CLOCK_REALTIME, &mut now);
+
+        let mut out = Writer::new(&mut *BUFFER.0.get());
+        out.push(&prepared.head);
+        out.push(b"\"timestamp\":");
+        out.push_dec(now.tv_sec as u64);
+        out.push(b",\"tags\":{\"crash.signal\":\"");
+        out.push(name.as_bytes());
+        out.push(b"\"},\"exception\":{\"values\":[{\"type\":\"");
+        out.push(name.as_bytes());
+        out.push(b"\",\"value\":\"");
+        out.push(description.as_bytes());
+        out.push(b" at ");
+        out.push_hex(address);
+        out.push(b"\",\"mechanism\":{\"type\":\"signalhandler\",\"handled\":false,\"meta\":{\"signal\":{\"number\":");
+        out.push_dec(signal as u64);
+        out.push(b",\"name\":\"");
+        out.push(name.as_bytes());
+        out.push(b"\"}}},\"stacktrace\":{\"frames\":[");
+        // Sentry lists frames oldest first
+        for (i, ip) in frames.iter().rev().enumerate() {
+            if i > 0 {
+                out.push(b",");
+            }
+            out.push(b"{\"instruction_addr\":\"");
+            out.push_hex(*ip);
+            out.push(b"\"}");
+        }
+        out.push(b"]}}]}}\n");
+
+        // <dir><millis>-<pid>-crash.tmp, renamed to .envelope once complete
+        let millis = now.tv_sec as u64 * 1000 + now.tv_nsec as u64 / 1_000_000;
+        let mut path = Writer::new(&mut *PATH.0.get());
+        let mut starts = [0; 2];
+        for (start, extension) in starts.iter_mut().zip([&b"tmp\0"[..], &b"envelope\0"[..]]) {
+            *start = path.len;
+            path.push(&prepared.dir);
+            path.push_dec_padded(millis, 13);
+            path.push(b"-");
+            path.push_dec(prepared.pid as u64);
+            path.push(b"-crash.");
+            path.push(extension);
+        }
+        let tmp = path.buf[starts[0]..].as_ptr() as *const libc::c_char;
+        let target = path.buf[starts[1]..].as_ptr() as *const libc::c_char;
+
+        let fd = libc::open(tmp, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o644);
+        if fd < 0 {
+            return;
+        }
+        let data = &out.buf[..out.len];
+        let mut written = 0;
+        while written < data.len() {
+            let n = libc::write(fd, data[written..].as_ptr() as *const c_void, data.len() - written);
+            if n <= 0 {
+                break;
+            }
+            written += n as usize;
+        }
+        libc::fsync(fd);
+        libc::close(fd);
+        if written == data.len() {
+            libc::rename(tmp, target);
+        }
+    }
+
+    /// Allocation-free formatting into a fixed buffer; output is truncated when full.
+    struct Writer<'a> {
+        buf: &'a mut [u8],
+        len: usize,
+    }
+
+    impl<'a> Writer<'a> {
+        fn new(buf: &'a mut [u8]) -> Self {
+            Self { buf, len: 0 }
+        }
+
+        fn push(&mut self, bytes: &[u8]) {
+            let n = bytes.len().min(self.buf.len() - self.len);
+            self.buf[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+            self.len += n;
+        }
+
+        fn push_dec(&mut self, value: u64) {
+            self.push_dec_padded(value, 1);
+        }
+
+        fn push_dec_padded(&mut self, mut value: u64, width: usize) {
+            let mut digits = [b'0'; 20];
+            let mut i = digits.len();
+            while value > 0 || digits.len() - i < width {
+                i -= 1;
+                digits[i] = b'0' + (value % 10) as u8;
+                value /= 10;
+            }
+            self.push(&digits[i..]);
+        }
+
+        fn push_hex(&mut self, value: usize) {
+            let mut digits = [0u8; 2 + 2 * mem::size_of::<usize>()];
+            digits[0] = b'0';
+            digits[1] = b'x';
+            for (i, digit) in digits[2..].iter_mut().enumerate() {
+                let nibble = (value >> (4 * (2 * mem::size_of::<usize>() - 1 - i))) & 0xf;
+                *digit = b"0123456789abcdef"[nibble];
+            }
+            self.push(&digits);
+        }
+    }
+}
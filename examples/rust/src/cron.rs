@@ -0,0 +1,139 @@
+//! Check-ins for scheduled jobs, so Bugsink alerts when a job is late,
+//! missing or failing instead of it failing silently.
+//!
+//! ```ignore
+//! let monitor = CronMonitor::new().schedule("nightly-backup", "0 3 * * *")?;
+//! let check_in = monitor.check_in_start("nightly-backup");
+//! match run_backup() {
+//!     Ok(_) => check_in.ok(),
+//!     Err(e) => check_in.error(&e),
+//! }
+//! ```
+//!
+//! A check-in dropped without `ok()` / `error()` (early return, panic) is
+//! reported as failed. With a schedule the monitor configuration is sent on
+//! every check-in, so the server knows when the next run is due. For plain
+//! functions [`#[monitored_job]`](crate::monitored_job) does the bookkeeping.
+
+use sentry::{
+    protocol::{CrontabParseError, MonitorCheckIn, MonitorCheckInStatus, MonitorConfig, MonitorSchedule},
+    types::Uuid,
+    Hub,
+};
+use std::{collections::HashMap, error::Error, sync::Arc, time::Instant};
+
+#[derive(Debug, Clone, Default)]
+pub struct CronMonitor {
+    configs: HashMap<String, MonitorConfig>,
+}
+
+impl CronMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect `slug` to run on the crontab schedule `crontab`.
+    pub fn schedule(self, slug: &str, crontab: &str) -> Result<Self, CrontabParseError> {
+        let config = MonitorConfig {
+            schedule: MonitorSchedule::from_crontab(crontab)?,
+            checkin_margin: None,
+            max_runtime: None,
+            timezone: None,
+            failure_issue_threshold: None,
+            recovery_threshold: None,
+        };
+        Ok(self.config(slug, config))
+    }
+
+    /// Full monitor configuration for `slug` (margins, time zone, thresholds).
+    pub fn config(mut self, slug: &str, config: MonitorConfig) -> Self {
+        self.configs.insert(slug.to_string(), config);
+        self
+    }
+
+    /// Report `slug` as started; finish with [`CheckIn::ok`] or [`CheckIn::error`].
+    pub fn check_in_start(&self, slug: &str) -> CheckIn {
+        let check_in = CheckIn {
+            id: sentry::types::random_uuid(),
+            slug: slug.to_string(),
+            config: self.configs.get(slug).cloned(),
+            hub: Hub::current(),
+            started: Instant::now(),
+            finished: false,
+        };
+        check_in.send(MonitorCheckInStatus::InProgress);
+        check_in
+    }
+}
+
+/// A running job; see the [module docs](self).
+#[must_use = "a check-in dropped without ok() or error() is reported as failed"]
+pub struct CheckIn {
+    id: Uuid,
+    slug: String,
+    config: Option<MonitorConfig>,
+    hub: Arc<Hub>,
+    started: Instant,
+    finished: bool,
+}
+
+impl CheckIn {
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    pub fn ok(mut self) {
+        self.finish(MonitorCheckInStatus::Ok);
+    }
+
+    /// Capture `error` tagged with the monitor slug and report the run as failed.
+    pub fn error<E: Error + ?Sized>(mut self, error: &E) {
+        self.hub.with_scope(
+            |scope| scope.set_tag("monitor.slug", &self.slug),
+            || self.hub.capture_error(error),
+        );
+        self.finish(MonitorCheckInStatus::Error);
+    }
+
+    /// `ok()` or `error()` depending on `result`.
+    pub fn finish_with<T, E: Error>(self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.ok(),
+            Err(e) => self.error(e),
+        }
+    }
+
+    fn finish(&mut self, status: MonitorCheckInStatus) {
+        self.finished = true;
+        self.send(status);
+    }
+
+    fn send(&self, status: MonitorCheckInStatus) {
+        let Some(client) = self.hub.client() else {
+            return;
+        };
+        let duration = match status {
+            MonitorCheckInStatus::InProgress => None,
+            _ => Some(self.started.elapsed().as_secs_f64()),
+        };
+        client.send_envelope(
+            MonitorCheckIn {
+                check_in_id: self.id,
+                monitor_slug: self.slug.clone(),
+                status,
+                environment: client.options().environment.as_ref().map(|env| env.to_string()),
+                duration,
+                monitor_config: self.config.clone(),
+            }
+            .into(),
+        );
+    }
+}
+
+impl Drop for CheckIn {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish(MonitorCheckInStatus::Error);
+        }
+    }
+}
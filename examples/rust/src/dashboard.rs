@@ -0,0 +1,211 @@
+//! Tiny local web UI over the most recent events (feature `dashboard`).
+//!
+//! Keeps the last events with their breadcrumb trails in an in-memory ring
+//! buffer. With the `axum` or `actix` feature a single handler serves the
+//! page, or its JSON form for `?format=json`:
+//!
+//! ```ignore
+//! // axum
+//! .route("/_observability", get(dashboard::axum_handler))
+//! // actix-web
+//! .route("/_observability", web::get().to(dashboard::actix_handler))
+//! ```
+//!
+//! Other frameworks can serve [`render_html`] and [`render_json`] directly.
+
+use super::{issue_title, stats};
+use sentry::{protocol::Event, Hub};
+use serde_json::json;
+use std::{collections::VecDeque, sync::Mutex, time::SystemTime};
+
+/// Number of events kept in memory.
+pub const CAPACITY: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    pub event_id: String,
+    pub timestamp: SystemTime,
+    pub level: String,
+    pub title: String,
+    pub tags: Vec<(String, String)>,
+    pub breadcrumbs: Vec<String>,
+}
+
+static EVENTS: Mutex<VecDeque<RecordedEvent>> = Mutex::new(VecDeque::new());
+
+pub(crate) fn record(event: &Event<'_>) {
+    let title = issue_title(event);
+    let breadcrumbs = event
+        .breadcrumbs
+        .values
+        .iter()
+        .map(|b| {
+            format!(
+                "[{}] {}: {}",
+                b.level,
+                b.category.as_deref().unwrap_or("default"),
+                b.message.as_deref().unwrap_or_default()
+            )
+        })
+        .collect();
+
+    let mut events = EVENTS.lock().unwrap();
+    if events.len() == CAPACITY {
+        events.pop_front();
+    }
+    events.push_back(RecordedEvent {
+        event_id: event.event_id.to_string(),
+        timestamp: event.timestamp,
+        level: event.level.to_string(),
+        title,
+        tags: event.tags.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        breadcrumbs,
+    });
+}
+
+/// Recorded events, newest first.
+pub fn recent_events() -> Vec<RecordedEvent> {
+    EVENTS.lock().unwrap().iter().rev().cloned().collect()
+}
+
+fn transport_status() -> (&'static str, String) {
+    match Hub::current().client() {
+        Some(client) if client.is_enabled() => (
+            "enabled",
+            client.dsn().map(|dsn| dsn.host().to_string()).unwrap_or_default(),
+        ),
+        Some(_) => ("disabled", String::new()),
+        None => ("not initialized", String::new()),
+    }
+}
+
+/// Dashboard data as JSON, for tooling.
+pub fn render_json() -> String {
+    let (status, host) = transport_status();
+    let events: Vec<_> = recent_events()
+        .into_iter()
+        .map(|e| {
+            json!({
+                "event_id": e.event_id,
+                "timestamp": humantime(e.timestamp),
+                "level": e.level,
+                "title": e.title,
+                "tags": e.tags,
+                "breadcrumbs": e.breadcrumbs,
+            })
+        })
+        .collect();
+    let stats: Vec<_> = stats::global()
+        .snapshot()
+        .into_iter()
+        .map(|s| {
+            json!({
+                "issue": s.issue,
+                "error_type": s.error_type,
+                "total": s.total,
+                "in_window": s.in_window,
+                "per_minute": s.per_minute,
+            })
+        })
+        .collect();
+    json!({ "transport": { "status": status, "host": host }, "stats": stats, "events": events }).to_string()
+}
+
+/// Dashboard as a self-contained HTML page.
+pub fn render_html() -> String {
+    let (status, host) = transport_status();
+    let mut html = String::from(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>Observability</title>\
+             <style>body{font-family:monospace;margin:2em}details{margin:.5em 0}\
+             .error,.fatal{color:#b00}.warning{color:#a60}</style></head><body>",
+    );
+    html.push_str(&format!(
+        "<h1>Recent events</h1><p>Transport: {} {}</p>",
+        escape(status),
+        escape(&host)
+    ));
+
+    let stats = stats::global();
+    html.push_str(&format!(
+        "<h2>Error rates ({}s window)</h2><table><tr><th>issue</th><th>window</th><th>total</th><th>/min</th></tr>",
+        stats.window().as_secs()
+    ));
+    for issue in stats.snapshot() {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1}</td></tr>",
+            escape(&issue.issue),
+            issue.in_window,
+            issue.total,
+            issue.per_minute
+        ));
+    }
+    html.push_str("</table><h2>Events</h2>");
+
+    for event in recent_events() {
+        html.push_str(&format!(
+            "<details><summary class=\"{level}\">{time} [{level}] {title} <small>{id}</small></summary>",
+            level = escape(&event.level),
+            time = humantime(event.timestamp),
+            title = escape(&event.title),
+            id = escape(&event.event_id),
+        ));
+        html.push_str("<p>");
+        for (key, value) in &event.tags {
+            html.push_str(&format!("{}={} ", escape(key), escape(value)));
+        }
+        html.push_str("</p><ol>");
+        for crumb in &event.breadcrumbs {
+            html.push_str(&format!("<li>{}</li>", escape(crumb)));
+        }
+        html.push_str("</ol></details>");
+    }
+
+    html.push_str("</body></html>");
+    html
+}
+
+/// Dashboard route for axum.
+#[cfg(feature = "axum")]
+pub async fn axum_handler(uri: axum::http::Uri) -> axum::response::Response {
+    use axum::{
+        http::header,
+        response::{Html, IntoResponse},
+    };
+
+    if wants_json(uri.query()) {
+        ([(header::CONTENT_TYPE, "application/json")], render_json()).into_response()
+    } else {
+        Html(render_html()).into_response()
+    }
+}
+
+/// Dashboard route for actix-web.
+#[cfg(feature = "actix")]
+pub async fn actix_handler(request: actix_web::HttpRequest) -> actix_web::HttpResponse {
+    let mut response = actix_web::HttpResponse::Ok();
+    if wants_json(Some(request.query_string())) {
+        response.content_type("application/json").body(render_json())
+    } else {
+        response.content_type("text/html; charset=utf-8").body(render_html())
+    }
+}
+
+#[cfg(any(feature = "axum", feature = "actix"))]
+fn wants_json(query: Option<&str>) -> bool {
+    query.is_some_and(|query| query.split('&').any(|pair| pair == "format=json"))
+}
+
+fn humantime(timestamp: SystemTime) -> String {
+    let secs = timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    format!("{:02}:{:02}:{:02}Z", secs / 3600 % 24, secs / 60 % 60, secs % 60)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,102 @@
+//! Client-side suppression of identical events.
+//!
+//! Events are keyed by their [issue](fingerprint::issue_key), so repeats of
+//! one Bugsink issue count together. At most `limit` events per key are sent
+//! within each window; the rest are counted, and the first event sent in the
+//! next window carries the count as the `dedupe.suppressed` extra. This keeps
+//! retry storms from burning through the Bugsink quota while still showing how
+//! often the error happened.
+//!
+//! ```ignore
+//! let sentry = SentryService::builder()
+//!     .dedupe(Duration::from_secs(60), 1)
+//!     .build();
+//! ```
+
+use super::fingerprint::issue_key;
+use sentry::protocol::Event;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Extra carrying the number of events suppressed in the previous window.
+pub const EXTRA: &str = "dedupe.suppressed";
+
+/// Keys tracked before expired windows are purged.
+const MAX_KEYS: usize = 1000;
+
+struct Window {
+    started: Instant,
+    sent: usize,
+    suppressed: u64,
+}
+
+pub struct DedupePolicy {
+    window: Duration,
+    limit: usize,
+    windows: Mutex<HashMap<u64, Window>>,
+}
+
+impl DedupePolicy {
+    /// Send at most `limit` identical events per `window`.
+    pub fn new(window: Duration, limit: usize) -> Self {
+        Self {
+            window,
+            limit: limit.max(1),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count the event; returns whether it should be sent.
+    pub fn check(&self, event: &mut Event<'_>) -> bool {
+        let key = issue_key(event);
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+
+        if windows.len() >= MAX_KEYS && !windows.contains_key(&key) {
+            windows.retain(|_, window| now.duration_since(window.started) < self.window);
+        }
+
+        let window = windows.entry(key).or_insert_with(|| Window {
+            started: now,
+            sent: 0,
+            suppressed: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            if window.suppressed > 0 {
+                event.extra.insert(EXTRA.to_string(), window.suppressed.into());
+            }
+            *window = Window {
+                started: now,
+                sent: 0,
+                suppressed: 0,
+            };
+        }
+
+        if window.sent < self.limit {
+            window.sent += 1;
+            true
+        } else {
+            window.suppressed += 1;
+            false
+        }
+    }
+}
+
+fn state() -> &'static RwLock<Option<Arc<DedupePolicy>>> {
+    static STATE: OnceLock<RwLock<Option<Arc<DedupePolicy>>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Enable (`Some`) or disable (`None`) deduplication in `before_send`.
+pub fn install(policy: Option<DedupePolicy>) {
+    *state().write().unwrap() = policy.map(Arc::new);
+}
+
+/// Whether the event should be sent; always true while deduplication is off.
+pub(crate) fn check(event: &mut Event<'_>) -> bool {
+    let policy = state().read().unwrap().clone();
+    policy.is_none_or(|policy| policy.check(event))
+}
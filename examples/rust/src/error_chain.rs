@@ -0,0 +1,305 @@
+//! Event conversion for error types that don't implement `std::error::Error`
+//! or carry more than a source chain (backtraces, attachments, span traces).
+//!
+//! Exceptions are ordered like `sentry::event_from_error`: root cause first,
+//! the error as reported last, so Bugsink shows the outermost context as the
+//! issue title and the full chain below it.
+//!
+//! With the `spantrace` feature, a `tracing_error::SpanTrace` carried anywhere
+//! in the chain is attached as the `spantrace` context: the logical call path
+//! through instrumented async code, which the OS backtrace rarely shows.
+
+use sentry::protocol::{Event, Mechanism};
+use std::backtrace::{Backtrace, BacktraceStatus};
+
+/// `sentry::event_from_error` plus the enrichment this module adds for plain errors.
+pub fn event_from_error(error: &(dyn std::error::Error + 'static)) -> Event<'static> {
+    #[allow(unused_mut)]
+    let mut event = sentry::event_from_error(error);
+    #[cfg(feature = "spantrace")]
+    set_spantrace(&mut event, error);
+    event
+}
+
+/// Capture an error on the current hub.
+pub fn capture_error(error: &(dyn std::error::Error + 'static)) -> sentry::types::Uuid {
+    sentry::Hub::current().capture_event(event_from_error(error))
+}
+
+/// Mark the primary exception as reported through `mechanism`.
+pub fn set_mechanism(event: &mut Event<'static>, mechanism: &str) {
+    if let Some(exception) = event.exception.values.last_mut() {
+        exception.mechanism = Some(Mechanism {
+            ty: mechanism.to_string(),
+            handled: Some(true),
+            ..Default::default()
+        });
+    }
+}
+
+/// Use a backtrace captured by the error itself as the primary exception's stack trace.
+pub fn set_backtrace(event: &mut Event<'static>, backtrace: &Backtrace) {
+    if backtrace.status() != BacktraceStatus::Captured {
+        return;
+    }
+    if let Some(exception) = event.exception.values.last_mut() {
+        exception.stacktrace = sentry::integrations::backtrace::parse_stacktrace(&format!("{:#}", backtrace));
+    }
+}
+
+/// Event for an `anyhow::Error`: one exception per context layer, plus the
+/// backtrace anyhow captured (requires `RUST_BACKTRACE=1` or `RUST_LIB_BACKTRACE=1`).
+#[cfg(feature = "anyhow")]
+pub fn event_from_anyhow(error: &anyhow::Error) -> Event<'static> {
+    let mut event = event_from_error(AsRef::<dyn std::error::Error>::as_ref(error));
+    set_mechanism(&mut event, "anyhow");
+    set_backtrace(&mut event, error.backtrace());
+    event
+}
+
+/// Capture an `anyhow::Error` on the current hub.
+#[cfg(feature = "anyhow")]
+pub fn capture_anyhow(error: &anyhow::Error) -> sentry::types::Uuid {
+    sentry::Hub::current().capture_event(event_from_anyhow(error))
+}
+
+/// Event for an `eyre::Report`. With a `color_eyre` handler installed, the
+/// handler's backtrace becomes the stack trace and its sections (notes,
+/// warnings, suggestions, custom sections) become `eyre.*` extras.
+#[cfg(feature = "eyre")]
+pub fn event_from_eyre(report: &eyre::Report) -> Event<'static> {
+    let mut event = event_from_error(AsRef::<dyn std::error::Error>::as_ref(report));
+    set_mechanism(&mut event, "eyre");
+
+    #[cfg(feature = "color-eyre")]
+    if let Some(handler) = report.handler().downcast_ref::<color_eyre::Handler>() {
+        if let (Some(backtrace), Some(exception)) = (handler.backtrace(), event.exception.values.last_mut()) {
+            exception.stacktrace = sentry::integrations::backtrace::backtrace_to_stacktrace(backtrace);
+        }
+        for (key, value) in eyre_sections(&format!("{:?}", report)) {
+            event.extra.insert(key, value.into());
+        }
+        #[cfg(feature = "spantrace")]
+        if let Some(context) = handler.span_trace().and_then(spantrace_context) {
+            event.contexts.entry("spantrace".to_string()).or_insert(context);
+        }
+    }
+
+    event
+}
+
+/// Capture an `eyre::Report` on the current hub.
+#[cfg(feature = "eyre")]
+pub fn capture_eyre(report: &eyre::Report) -> sentry::types::Uuid {
+    sentry::Hub::current().capture_event(event_from_eyre(report))
+}
+
+/// Event for an `error_stack::Report`. Every context frame becomes an
+/// exception; `mechanism.data` carries `frame_id` / `parent_id` so the
+/// frame tree survives when a report has several sources. Printable
+/// attachments and locations go to the `error_stack.attachments` extra,
+/// a captured backtrace becomes the stack trace of the context it was
+/// attached to.
+#[cfg(feature = "error-stack")]
+pub fn event_from_report<C>(report: &error_stack::Report<C>) -> Event<'static> {
+    let mut walk = ReportWalk::default();
+    for frame in report.current_frames() {
+        walk.visit(frame, None);
+    }
+
+    // Frames were visited outermost first; Sentry lists the root cause first
+    let mut exceptions = walk.exceptions;
+    exceptions.reverse();
+    let mut event = Event {
+        exception: exceptions.into(),
+        level: sentry::Level::Error,
+        ..Default::default()
+    };
+    if let Some(exception) = event.exception.values.last_mut() {
+        if let Some(mechanism) = exception.mechanism.as_mut() {
+            mechanism.handled = Some(true);
+        }
+    }
+    if !walk.attachments.is_empty() {
+        event
+            .extra
+            .insert("error_stack.attachments".to_string(), walk.attachments.into());
+    }
+    event
+}
+
+/// Attach the deepest span trace in the error's source chain (closest to where it originated).
+#[cfg(feature = "spantrace")]
+pub fn set_spantrace(event: &mut Event<'static>, error: &(dyn std::error::Error + 'static)) {
+    use tracing_error::ExtractSpanTrace;
+
+    let deepest = std::iter::successors(Some(error), |e| e.source())
+        .filter_map(|e| e.span_trace())
+        .last();
+    if let Some(context) = deepest.and_then(spantrace_context) {
+        event.contexts.insert("spantrace".to_string(), context);
+    }
+}
+
+/// `{"frames": [{name, target, fields, file, line}, ...]}`, innermost span first.
+#[cfg(feature = "spantrace")]
+pub fn spantrace_context(span_trace: &tracing_error::SpanTrace) -> Option<sentry::protocol::Context> {
+    let mut frames = Vec::new();
+    span_trace.with_spans(|metadata, fields| {
+        frames.push(serde_json::json!({
+            "name": metadata.name(),
+            "target": metadata.target(),
+            "fields": fields,
+            "file": metadata.file(),
+            "line": metadata.line(),
+        }));
+        true
+    });
+    if frames.is_empty() {
+        return None;
+    }
+    let mut context = std::collections::BTreeMap::new();
+    context.insert("frames".to_string(), frames.into());
+    Some(sentry::protocol::Context::Other(context))
+}
+
+/// Capture an `error_stack::Report` on the current hub.
+#[cfg(feature = "error-stack")]
+pub fn capture_report<C>(report: &error_stack::Report<C>) -> sentry::types::Uuid {
+    sentry::Hub::current().capture_event(event_from_report(report))
+}
+
+#[cfg(feature = "error-stack")]
+#[derive(Default)]
+struct ReportWalk {
+    exceptions: Vec<sentry::protocol::Exception>,
+    attachments: Vec<sentry::protocol::Value>,
+}
+
+#[cfg(feature = "error-stack")]
+impl ReportWalk {
+    /// Depth-first walk; `context` is the index of the closest context frame above.
+    fn visit(&mut self, frame: &error_stack::Frame, context: Option<usize>) {
+        use error_stack::{AttachmentKind, FrameKind};
+        use serde_json::json;
+
+        let mut context = context;
+        match frame.kind() {
+            FrameKind::Context(inner) => {
+                let id = self.exceptions.len();
+                let mut data = std::collections::BTreeMap::new();
+                data.insert("frame_id".to_string(), json!(id));
+                if let Some(parent) = context {
+                    data.insert("parent_id".to_string(), json!(parent));
+                }
+                self.exceptions.push(sentry::protocol::Exception {
+                    ty: sentry::parse_type_from_debug(&inner).to_string(),
+                    value: Some(inner.to_string()),
+                    mechanism: Some(Mechanism {
+                        ty: "error-stack".to_string(),
+                        data,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                });
+                context = Some(id);
+            }
+            FrameKind::Attachment(AttachmentKind::Printable(attachment)) => {
+                self.attach(context, attachment.to_string());
+            }
+            FrameKind::Attachment(AttachmentKind::Opaque(_)) => {
+                if let Some(location) = frame.downcast_ref::<std::panic::Location<'static>>() {
+                    self.attach(context, format!("at {}", location));
+                } else if let Some(backtrace) = frame.downcast_ref::<Backtrace>() {
+                    let exception = context.and_then(|id| self.exceptions.get_mut(id));
+                    if let (Some(exception), BacktraceStatus::Captured) = (exception, backtrace.status()) {
+                        exception.stacktrace =
+                            sentry::integrations::backtrace::parse_stacktrace(&format!("{:#}", backtrace));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for source in frame.sources() {
+            self.visit(source, context);
+        }
+    }
+
+    fn attach(&mut self, context: Option<usize>, value: String) {
+        let context = context
+            .and_then(|id| self.exceptions.get(id))
+            .and_then(|e| e.value.clone());
+        self.attachments
+            .push(serde_json::json!({ "context": context, "value": value }));
+    }
+}
+
+/// Split a rendered color-eyre report into its sections. The handler keeps
+/// them private, so they are recovered from the `Debug` output: blocks are
+/// separated by blank lines, the first one is the error chain.
+#[cfg(feature = "color-eyre")]
+fn eyre_sections(rendered: &str) -> Vec<(String, String)> {
+    let plain = strip_ansi(rendered);
+    let mut sections: Vec<(String, String)> = Vec::new();
+    let mut push = |name: &str, body: String| {
+        let key = format!("eyre.{}", name.trim().to_lowercase().replace(' ', "_"));
+        match sections.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => {
+                existing.push('\n');
+                existing.push_str(&body);
+            }
+            None => sections.push((key, body)),
+        }
+    };
+
+    for block in plain.split("\n\n").skip(1).map(str::trim_end) {
+        let mut lines = block.lines();
+        let Some(first) = lines.next() else { continue };
+        let first = first.trim();
+        // Backtrace/span trace frames and the RUST_BACKTRACE hints are not sections
+        if first.is_empty()
+            || first.starts_with('━')
+            || first.starts_with("Backtrace ")
+            || first.starts_with("Run with ")
+        {
+            continue;
+        }
+
+        if ["Note: ", "Warning: ", "Suggestion: "]
+            .iter()
+            .any(|p| first.starts_with(p))
+        {
+            // Help lines are grouped into one block
+            for line in block.lines() {
+                if let Some((help, text)) = line.trim().split_once(": ") {
+                    push(help, text.to_string());
+                }
+            }
+            continue;
+        }
+
+        let body: Vec<&str> = lines.map(str::trim).collect();
+        push(first.trim_end_matches(':'), body.join("\n"));
+    }
+    sections
+}
+
+#[cfg(feature = "color-eyre")]
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // CSI sequence: ESC [ parameters final-byte
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
@@ -0,0 +1,168 @@
+//! Stable error codes (`E-PAY-0042`) shared across services, so issues are
+//! classified by what went wrong rather than by message wording.
+//!
+//! Codes are declared with [`error_codes!`] and registered at startup. Every
+//! event carrying a registered code gets the `error.code` and `error.owner`
+//! tags, the code's level, an `error_code` context with its title, and the
+//! fingerprint `["error-code", <code>]`, so one code is one Bugsink issue no
+//! matter how the message changes:
+//!
+//! ```ignore
+//! error_codes! {
+//!     pub mod payment_codes {
+//!         /// The issuer refused the card; nothing to fix on our side.
+//!         DECLINED = "E-PAY-0042" { level: Warning, owner: "payments", title: "Card declined by the issuer" };
+//!         LEDGER_MISMATCH = "E-PAY-0100" { level: Fatal, owner: "payments-core", title: "Ledger out of balance" };
+//!     }
+//! }
+//!
+//! error_codes::register(payment_codes::ALL);
+//!
+//! #[derive(Debug, thiserror::Error)]
+//! #[error("{code}: card declined for order {order_id}")]
+//! struct Declined { code: ErrorCode, order_id: u64 }
+//!
+//! impl Coded for Declined {
+//!     fn error_code(&self) -> ErrorCode { self.code }
+//! }
+//!
+//! error_codes::capture_error(&Declined { code: payment_codes::DECLINED, order_id });
+//! ```
+//!
+//! The code is taken from the `error.code` tag (set by
+//! [`capture_error`](error_codes::capture_error) or the scope), or else from
+//! the first registered code appearing in an exception value or the message,
+//! so errors that merely print their code are classified too. Events with a
+//! custom fingerprint keep it. The owner tag can drive
+//! [project routing](routing).
+
+use regex::Regex;
+use sentry::{
+    protocol::{Context, Event},
+    Hub, Level,
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{OnceLock, RwLock},
+};
+
+/// Tag carrying the code.
+pub const TAG: &str = "error.code";
+
+/// Tag carrying the owning team.
+pub const OWNER_TAG: &str = "error.owner";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode {
+    /// Upper-case segments joined by `-`, e.g. `E-PAY-0042`.
+    pub code: &'static str,
+    pub level: Level,
+    pub owner: &'static str,
+    pub title: &'static str,
+}
+
+impl ErrorCode {
+    pub const fn new(code: &'static str, level: Level, owner: &'static str, title: &'static str) -> Self {
+        Self {
+            code,
+            level,
+            owner,
+            title,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code)
+    }
+}
+
+/// Errors that know their code.
+pub trait Coded {
+    fn error_code(&self) -> ErrorCode;
+}
+
+fn state() -> &'static RwLock<HashMap<&'static str, ErrorCode>> {
+    static CODES: OnceLock<RwLock<HashMap<&'static str, ErrorCode>>> = OnceLock::new();
+    CODES.get_or_init(Default::default)
+}
+
+/// Add `codes` to the registry; registering a code again replaces it.
+pub fn register(codes: &[ErrorCode]) {
+    let mut registry = state().write().unwrap();
+    for code in codes {
+        registry.insert(code.code, *code);
+    }
+}
+
+/// Remove `codes` from the registry.
+pub fn unregister(codes: &[ErrorCode]) {
+    let mut registry = state().write().unwrap();
+    for code in codes {
+        registry.remove(code.code);
+    }
+}
+
+pub fn lookup(code: &str) -> Option<ErrorCode> {
+    state().read().unwrap().get(code).copied()
+}
+
+/// Registered codes, sorted.
+pub fn registered() -> Vec<ErrorCode> {
+    let mut codes: Vec<_> = state().read().unwrap().values().copied().collect();
+    codes.sort_by_key(|code| code.code);
+    codes
+}
+
+/// Capture `error` on the current hub with its code as the `error.code` tag.
+pub fn capture_error<E: std::error::Error + Coded + ?Sized>(error: &E) -> sentry::types::Uuid {
+    let hub = Hub::current();
+    hub.with_scope(
+        |scope| scope.set_tag(TAG, error.error_code().code),
+        || hub.capture_error(error),
+    )
+}
+
+fn code_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b[A-Z][A-Z0-9]*(?:-[A-Z0-9]+)+\b").unwrap())
+}
+
+/// The registered code of `event`: its tag, or the first one in its exceptions or message.
+pub fn find(event: &Event<'_>) -> Option<ErrorCode> {
+    if let Some(code) = event.tags.get(TAG) {
+        return lookup(code);
+    }
+    let texts = event
+        .exception
+        .values
+        .iter()
+        .rev()
+        .filter_map(|exception| exception.value.as_deref())
+        .chain(event.message.as_deref())
+        .chain(event.logentry.as_ref().map(|entry| entry.message.as_str()));
+    texts
+        .flat_map(|text| code_pattern().find_iter(text))
+        .find_map(|found| lookup(found.as_str()))
+}
+
+/// Tag, level, context and fingerprint from the event's code, if any.
+pub(crate) fn apply(event: &mut Event<'_>) {
+    let Some(code) = find(event) else {
+        return;
+    };
+    event.tags.insert(TAG.to_string(), code.code.to_string());
+    event.tags.insert(OWNER_TAG.to_string(), code.owner.to_string());
+    event.level = code.level;
+    let mut context = BTreeMap::new();
+    context.insert("code".to_string(), code.code.into());
+    context.insert("title".to_string(), code.title.into());
+    context.insert("owner".to_string(), code.owner.into());
+    event.contexts.insert("error_code".to_string(), Context::Other(context));
+    if event.fingerprint.len() == 1 && event.fingerprint[0] == "{{ default }}" {
+        event.fingerprint = Cow::Owned(vec!["error-code".into(), code.code.to_string().into()]);
+    }
+}
@@ -0,0 +1,80 @@
+//! The captured event's ID in HTTP 5xx responses, so the number a customer
+//! reads out leads straight to the Bugsink event.
+//!
+//! Opt-in on the axum and actix-web middleware. When a request fails with a
+//! 5xx status and an event was captured for it (a panic, the actix 5xx
+//! capture, or the handler's own `capture_error`), the event ID is set as a
+//! header and the body is replaced with the rendered template:
+//!
+//! ```ignore
+//! let errors = ErrorResponse::default()
+//!     .body(r#"{"error":"Something went wrong","reference":"{reference}","event_id":"{event_id}"}"#);
+//! let app = Router::new().route("/orders", post(create_order)).layer(SentryLayer::new().error_response(errors));
+//! ```
+//!
+//! Templates may use `{event_id}`, `{reference}` (the short
+//! [error reference](reference)) and `{correlation_id}` (empty without one).
+//! Without a body template the handler's body is kept and only the header is
+//! added.
+
+use super::{correlation, reference::ErrorReference};
+use sentry::types::Uuid;
+
+pub const DEFAULT_HEADER: &str = "x-event-id";
+pub const DEFAULT_BODY: &str = r#"{"error":"internal_error","event_id":"{event_id}","reference":"{reference}"}"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorResponse {
+    /// Header carrying the event ID; `None` leaves it out.
+    pub header: Option<String>,
+    /// Body template; `None` keeps the handler's body.
+    pub body: Option<String>,
+    pub content_type: String,
+}
+
+impl Default for ErrorResponse {
+    fn default() -> Self {
+        Self {
+            header: Some(DEFAULT_HEADER.to_string()),
+            body: Some(DEFAULT_BODY.to_string()),
+            content_type: "application/json".to_string(),
+        }
+    }
+}
+
+impl ErrorResponse {
+    pub fn header(mut self, name: Option<&str>) -> Self {
+        self.header = name.map(str::to_string);
+        self
+    }
+
+    pub fn body(mut self, template: &str) -> Self {
+        self.body = Some(template.to_string());
+        self
+    }
+
+    /// Keep the handler's body, only add the header.
+    pub fn keep_body(mut self) -> Self {
+        self.body = None;
+        self
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = content_type.to_string();
+        self
+    }
+
+    /// The body for `event_id`, if a template is set.
+    pub fn render_body(&self, event_id: Uuid) -> Option<String> {
+        self.body.as_deref().map(|template| render(template, event_id))
+    }
+}
+
+/// Replace `{event_id}`, `{reference}` and `{correlation_id}` in `template`.
+pub fn render(template: &str, event_id: Uuid) -> String {
+    let correlation_id = correlation::current_correlation_id().map(|id| id.to_string());
+    template
+        .replace("{event_id}", &event_id.to_string())
+        .replace("{reference}", ErrorReference::from_event_id(event_id).as_str())
+        .replace("{correlation_id}", correlation_id.as_deref().unwrap_or_default())
+}
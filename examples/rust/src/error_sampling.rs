@@ -0,0 +1,129 @@
+//! Sample rates per error class instead of one `sample_rate` for all errors.
+//!
+//! A rule matches by exception type (any exception in the chain) and tag
+//! values; the first matching rule sets the rate, unmatched events keep the
+//! global `sample_rate`. Sampling is deterministic per issue: of every `1 /
+//! rate` events with the same fingerprint the first is sent, so a noisy but
+//! expected error still shows up in Bugsink, just with fewer events. Sent
+//! events carry the rate as the `error.sample_rate` tag.
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .error_sampling_rule(ErrorSamplingRule::new(0.01).exception_type("ValidationError"))
+//!     .error_sampling_rule(ErrorSamplingRule::new(1.0).exception_type("DatabaseError"))
+//!     .build();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [[error_sampling]]
+//! exception_type = "ValidationError"
+//! rate = 0.01
+//! ```
+
+use super::fingerprint::issue_key;
+use sentry::protocol::Event;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, OnceLock, RwLock},
+};
+
+/// Tag carrying the rate of sampled error classes.
+pub const TAG: &str = "error.sample_rate";
+
+/// Issues counted before the counters start over.
+const MAX_KEYS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorSamplingRule {
+    pub exception_type: Option<String>,
+    pub tags: BTreeMap<String, String>,
+    pub rate: f32,
+}
+
+impl ErrorSamplingRule {
+    /// A rule that matches every event until conditions are added.
+    pub fn new(rate: f32) -> Self {
+        Self {
+            exception_type: None,
+            tags: BTreeMap::new(),
+            rate: rate.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn exception_type(mut self, ty: &str) -> Self {
+        self.exception_type = Some(ty.to_string());
+        self
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn matches(&self, event: &Event<'_>) -> bool {
+        if let Some(ty) = &self.exception_type {
+            if !event.exception.values.iter().any(|exception| &exception.ty == ty) {
+                return false;
+            }
+        }
+        self.tags.iter().all(|(key, value)| event.tags.get(key) == Some(value))
+    }
+}
+
+pub struct ErrorSampler {
+    rules: Vec<ErrorSamplingRule>,
+    seen: Mutex<HashMap<u64, u64>>,
+}
+
+impl ErrorSampler {
+    pub fn new(rules: Vec<ErrorSamplingRule>) -> Self {
+        Self {
+            rules,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rate of the first matching rule.
+    pub fn rate(&self, event: &Event<'_>) -> Option<f32> {
+        self.rules.iter().find(|rule| rule.matches(event)).map(|rule| rule.rate)
+    }
+
+    /// Whether to send the event; `None` when no rule matches.
+    pub fn check(&self, event: &mut Event<'_>) -> Option<bool> {
+        let rate = self.rate(event)?;
+        if rate <= 0.0 {
+            return Some(false);
+        }
+        let keep = rate >= 1.0 || {
+            let every = (1.0 / rate).round() as u64;
+            let mut seen = self.seen.lock().unwrap();
+            if seen.len() >= MAX_KEYS {
+                seen.clear();
+            }
+            let count = seen.entry(issue_key(event)).or_insert(0);
+            *count += 1;
+            (*count - 1).is_multiple_of(every)
+        };
+        if keep {
+            event.tags.insert(TAG.to_string(), rate.to_string());
+        }
+        Some(keep)
+    }
+}
+
+fn state() -> &'static RwLock<Option<Arc<ErrorSampler>>> {
+    static STATE: OnceLock<RwLock<Option<Arc<ErrorSampler>>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+pub fn install(rules: Vec<ErrorSamplingRule>) {
+    *state().write().unwrap() = (!rules.is_empty()).then(|| Arc::new(ErrorSampler::new(rules)));
+}
+
+/// Decision of the installed rules; `None` leaves it to `sample_rate`.
+pub(crate) fn check(event: &mut Event<'_>) -> Option<bool> {
+    let sampler = state().read().unwrap().clone();
+    sampler?.check(event)
+}
@@ -0,0 +1,150 @@
+//! Escalate slow-burning problems: when an issue at a low level keeps
+//! recurring, the next occurrence is raised (e.g. warning -> error) and tagged
+//! `escalated`, which client-side samplers treat as "always keep".
+//!
+//! ```ignore
+//! escalation::install(EscalationPolicy::new(vec![
+//!     EscalationRule::new("retry-exhausted", 50, Duration::from_secs(600)).issue_contains("retry"),
+//! ]));
+//! ```
+
+use super::{fingerprint::issue_key, issue_title};
+use sentry::{protocol::Event, Level};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// Tag set on escalated events (value: rule name).
+pub const TAG: &str = "escalated";
+
+#[derive(Debug, Clone)]
+pub struct EscalationRule {
+    pub name: String,
+    /// Level of the events counted by this rule.
+    pub from_level: Level,
+    /// Level the escalated occurrence is raised to.
+    pub to_level: Level,
+    /// Escalate once more than `threshold` occurrences fall within `window`.
+    pub threshold: usize,
+    pub window: Duration,
+    /// Only count issues whose title (exception type and value, or message) contains this string.
+    pub issue_contains: Option<String>,
+    /// Only count events with all of these tag values.
+    pub tags: BTreeMap<String, String>,
+}
+
+impl EscalationRule {
+    /// Warning -> error after more than `threshold` occurrences within `window`.
+    pub fn new(name: &str, threshold: usize, window: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            from_level: Level::Warning,
+            to_level: Level::Error,
+            threshold,
+            window,
+            issue_contains: None,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    pub fn levels(mut self, from: Level, to: Level) -> Self {
+        self.from_level = from;
+        self.to_level = to;
+        self
+    }
+
+    pub fn issue_contains(mut self, pattern: &str) -> Self {
+        self.issue_contains = Some(pattern.to_string());
+        self
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    fn matches(&self, event: &Event<'_>, issue: &str) -> bool {
+        event.level == self.from_level
+            && self.issue_contains.as_deref().is_none_or(|p| issue.contains(p))
+            && self.tags.iter().all(|(key, value)| event.tags.get(key) == Some(value))
+    }
+}
+
+pub struct EscalationPolicy {
+    rules: Vec<EscalationRule>,
+    /// Per rule and [issue](issue_key), occurrences inside the rule's window.
+    occurrences: Mutex<HashMap<(usize, u64), VecDeque<Instant>>>,
+}
+
+impl EscalationPolicy {
+    pub fn new(rules: Vec<EscalationRule>) -> Self {
+        Self {
+            rules,
+            occurrences: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count the event and escalate it if a rule's threshold is exceeded.
+    /// The window restarts after each escalation. Returns whether the event was escalated.
+    pub fn apply(&self, event: &mut Event<'_>) -> bool {
+        let issue = issue_title(event);
+        let issue_key = issue_key(event);
+        let now = Instant::now();
+        let mut occurrences = self.occurrences.lock().unwrap();
+        occurrences.retain(|(index, _), window| {
+            while window
+                .front()
+                .is_some_and(|t| now.duration_since(*t) > self.rules[*index].window)
+            {
+                window.pop_front();
+            }
+            !window.is_empty()
+        });
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.matches(event, &issue) {
+                continue;
+            }
+
+            let window = occurrences.entry((index, issue_key)).or_default();
+            window.push_back(now);
+
+            if window.len() > rule.threshold {
+                event.level = rule.to_level;
+                event.tags.insert(TAG.to_string(), rule.name.clone());
+                event
+                    .extra
+                    .insert("escalation.occurrences".to_string(), window.len().into());
+                occurrences.remove(&(index, issue_key));
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Rule and issue pairs with occurrences inside their window.
+    pub fn tracked_issues(&self) -> usize {
+        self.occurrences.lock().unwrap().len()
+    }
+}
+
+static POLICY: OnceLock<EscalationPolicy> = OnceLock::new();
+
+/// Install the process-wide policy consulted by `before_send`.
+pub fn install(policy: EscalationPolicy) {
+    let _ = POLICY.set(policy);
+}
+
+pub(crate) fn apply(event: &mut Event<'_>) {
+    if let Some(policy) = POLICY.get() {
+        policy.apply(event);
+    }
+}
+
+/// Whether an event was escalated and must not be sampled out.
+pub fn is_escalated(event: &Event<'_>) -> bool {
+    event.tags.contains_key(TAG)
+}
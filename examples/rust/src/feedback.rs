@@ -0,0 +1,63 @@
+//! Customer-provided descriptions attached to the event they saw.
+//!
+//! Support tooling that has the event ID behind an [error reference](reference)
+//! forwards what the customer wrote:
+//!
+//! ```ignore
+//! sentry.capture_user_feedback(event_id, "Jane Doe", "jane@example.com", "Clicked pay twice, got charged once");
+//! ```
+//!
+//! Sent as a `user_report` envelope item, which the SDK has no type for, so
+//! the envelope is serialized here. Fields are cut to the server's limits.
+
+use sentry::{
+    protocol::Envelope,
+    types::{Dsn, Uuid},
+};
+use serde_json::json;
+
+const MAX_NAME: usize = 128;
+const MAX_EMAIL: usize = 75;
+const MAX_COMMENTS: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserFeedback {
+    pub event_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub comments: String,
+}
+
+impl UserFeedback {
+    pub fn new(event_id: Uuid, name: &str, email: &str, comments: &str) -> Self {
+        Self {
+            event_id,
+            name: truncate(name.trim(), MAX_NAME),
+            email: truncate(email.trim(), MAX_EMAIL),
+            comments: truncate(comments.trim(), MAX_COMMENTS),
+        }
+    }
+
+    /// Envelope with a single `user_report` item.
+    pub fn to_envelope(&self, dsn: Option<&Dsn>) -> Envelope {
+        let mut header = json!({ "event_id": self.event_id.simple().to_string() });
+        if let Some(dsn) = dsn {
+            header["dsn"] = dsn.to_string().into();
+        }
+        let payload = json!({
+            "event_id": self.event_id.simple().to_string(),
+            "name": self.name,
+            "email": self.email,
+            "comments": self.comments,
+        })
+        .to_string();
+        let item_header = json!({ "type": "user_report", "length": payload.len() });
+
+        let bytes = format!("{}\n{}\n{}\n", header, item_header, payload).into_bytes();
+        Envelope::from_bytes_raw(bytes).expect("raw envelopes are not parsed")
+    }
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
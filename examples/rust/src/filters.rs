@@ -0,0 +1,135 @@
+//! Declarative ignore lists, compiled into one matcher per list.
+//!
+//! - errors: matched against every exception in the chain as `Type` and as
+//!   `Type: value`, and against the message of message events
+//! - transactions: matched against the transaction name; ignored transactions
+//!   are never sampled, so they cost nothing
+//! - loggers: matched against the event's logger and the breadcrumb category,
+//!   i.e. the `tracing`/`log` target
+//!
+//! Patterns are globs: `*` matches any run of characters, `?` exactly one.
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .ignore_errors(["ExpectedBusinessError", "hyper::Error: connection reset*"])
+//!     .ignore_transactions(["GET /health", "GET /metrics"])
+//!     .ignore_loggers(["rdkafka::*"])
+//!     .build();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [filters]
+//! error_types = ["ExpectedBusinessError", "hyper::Error: connection reset*"]
+//! transactions = ["GET /health"]
+//! loggers = ["rdkafka::*"]
+//! ```
+//!
+//! The lists are part of the [runtime config](runtime_config) and take effect
+//! immediately when changed there.
+
+use super::sampling::glob_match;
+use sentry::protocol::{Breadcrumb, Event};
+use std::sync::{Arc, OnceLock, RwLock};
+
+#[derive(Debug, Clone)]
+pub struct EventFilters {
+    errors: Globs,
+    transactions: Globs,
+    loggers: Globs,
+}
+
+impl Default for EventFilters {
+    fn default() -> Self {
+        Self::new(&[], &[], &[])
+    }
+}
+
+impl EventFilters {
+    pub fn new(errors: &[String], transactions: &[String], loggers: &[String]) -> Self {
+        Self {
+            errors: Globs(errors.to_vec()),
+            transactions: Globs(transactions.to_vec()),
+            loggers: Globs(loggers.to_vec()),
+        }
+    }
+
+    pub fn ignores_error(&self, event: &Event<'_>) -> bool {
+        if self.errors.is_empty() {
+            return false;
+        }
+        let exception_matches = event.exception.values.iter().any(|exception| {
+            self.errors.is_match(&exception.ty)
+                || exception
+                    .value
+                    .as_ref()
+                    .is_some_and(|value| self.errors.is_match(&format!("{}: {}", exception.ty, value)))
+        });
+        exception_matches
+            || (event.exception.values.is_empty()
+                && event
+                    .message
+                    .as_ref()
+                    .is_some_and(|message| self.errors.is_match(message)))
+    }
+
+    pub fn ignores_transaction(&self, name: &str) -> bool {
+        self.transactions.is_match(name)
+    }
+
+    pub fn ignores_logger(&self, logger: &str) -> bool {
+        self.loggers.is_match(logger)
+    }
+
+    /// Whether the event matches the error or logger list.
+    pub fn ignores_event(&self, event: &Event<'_>) -> bool {
+        self.ignores_error(event)
+            || event
+                .logger
+                .as_deref()
+                .is_some_and(|logger| self.ignores_logger(logger))
+    }
+
+    pub fn ignores_breadcrumb(&self, breadcrumb: &Breadcrumb) -> bool {
+        breadcrumb
+            .category
+            .as_deref()
+            .is_some_and(|category| self.ignores_logger(category))
+    }
+}
+
+/// Patterns matched with the same [glob syntax](glob_match) as the sampling rules.
+#[derive(Debug, Clone)]
+struct Globs(Vec<String>);
+
+impl Globs {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        self.0.iter().any(|pattern| glob_match(pattern, text))
+    }
+}
+
+fn state() -> &'static RwLock<Arc<EventFilters>> {
+    static STATE: OnceLock<RwLock<Arc<EventFilters>>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        let runtime = super::runtime_config::current();
+        RwLock::new(Arc::new(EventFilters::new(
+            &runtime.ignored_error_types,
+            &runtime.ignored_transactions,
+            &runtime.ignored_loggers,
+        )))
+    })
+}
+
+/// Replace the filters consulted by the pipeline and the traces sampler.
+pub fn install(filters: EventFilters) {
+    *state().write().unwrap() = Arc::new(filters);
+}
+
+pub fn current() -> Arc<EventFilters> {
+    state().read().unwrap().clone()
+}
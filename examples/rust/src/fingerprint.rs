@@ -0,0 +1,215 @@
+//! Declarative grouping: rules that assign a custom fingerprint to matching
+//! events, so related errors end up in one Bugsink issue (or one issue is
+//! split) without a hand-written `before_send` per rule.
+//!
+//! A rule matches when all of its conditions hold: exception type (any
+//! exception in the chain), message regex (exception value or message),
+//! module path prefix (exception module or any stack frame) and tag values.
+//! The first matching rule wins; `{{ type }}` in the fingerprint is replaced
+//! with the primary exception type, and `{{ default }}` is passed through for
+//! the server's default grouping.
+//!
+//! ```ignore
+//! fingerprint::install(FingerprintRules::new(vec![
+//!     FingerprintRule::new("db-timeouts", &["database-timeout", "{{ type }}"])
+//!         .module("my_app::db")
+//!         .message(r"timed? ?out")?,
+//! ]));
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [[fingerprint]]
+//! name = "db-timeouts"
+//! module = "my_app::db"
+//! message = "timed? ?out"
+//! fingerprint = ["database-timeout", "{{ type }}"]
+//! ```
+//!
+//! [`issue_key`](fingerprint::issue_key) is the client-side counterpart of
+//! that grouping; deduplication, sampling, spike protection, aggregation,
+//! escalation, alerting and the error statistics all count per issue key.
+
+use regex::Regex;
+use sentry::protocol::Event;
+use std::{
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, OnceLock, RwLock},
+};
+
+/// Tag naming the rule that set the fingerprint.
+pub const TAG: &str = "fingerprint.rule";
+
+/// Innermost frames identifying an issue without a custom fingerprint.
+const KEY_FRAMES: usize = 5;
+
+/// The issue an event is grouped into: its custom fingerprint, or else the
+/// exception types and innermost frames (the message for events without
+/// exceptions), approximating the server's default grouping.
+pub fn issue_key(event: &Event<'_>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    if event.fingerprint.iter().any(|part| part != "{{ default }}") {
+        event.fingerprint.hash(&mut hasher);
+        return hasher.finish();
+    }
+    for exception in &event.exception.values {
+        exception.ty.hash(&mut hasher);
+    }
+    let stacktrace = event
+        .exception
+        .values
+        .last()
+        .and_then(|exception| exception.stacktrace.as_ref())
+        .or(event.stacktrace.as_ref());
+    match stacktrace {
+        Some(stacktrace) => {
+            for frame in stacktrace.frames.iter().rev().take(KEY_FRAMES) {
+                (&frame.function, &frame.module).hash(&mut hasher);
+            }
+        }
+        None if event.exception.values.is_empty() => event.message.hash(&mut hasher),
+        None => {}
+    }
+    hasher.finish()
+}
+
+#[derive(Debug, Clone)]
+pub struct FingerprintRule {
+    pub name: String,
+    pub exception_type: Option<String>,
+    pub message: Option<Regex>,
+    pub module: Option<String>,
+    pub tags: BTreeMap<String, String>,
+    pub fingerprint: Vec<String>,
+}
+
+impl FingerprintRule {
+    /// A rule that matches every event until conditions are added.
+    pub fn new(name: &str, fingerprint: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            exception_type: None,
+            message: None,
+            module: None,
+            tags: BTreeMap::new(),
+            fingerprint: fingerprint.iter().map(|part| part.to_string()).collect(),
+        }
+    }
+
+    pub fn exception_type(mut self, ty: &str) -> Self {
+        self.exception_type = Some(ty.to_string());
+        self
+    }
+
+    pub fn message(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.message = Some(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Module path prefix, e.g. `my_app::db`.
+    pub fn module(mut self, prefix: &str) -> Self {
+        self.module = Some(prefix.to_string());
+        self
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn matches(&self, event: &Event<'_>) -> bool {
+        let exceptions = &event.exception.values;
+
+        if let Some(ty) = &self.exception_type {
+            if !exceptions.iter().any(|exception| &exception.ty == ty) {
+                return false;
+            }
+        }
+
+        if let Some(message) = &self.message {
+            let mut texts = exceptions
+                .iter()
+                .filter_map(|exception| exception.value.as_deref())
+                .chain(event.message.as_deref())
+                .chain(event.logentry.as_ref().map(|logentry| logentry.message.as_str()));
+            if !texts.any(|text| message.is_match(text)) {
+                return false;
+            }
+        }
+
+        if let Some(prefix) = &self.module {
+            let frames = exceptions
+                .iter()
+                .filter_map(|exception| exception.stacktrace.as_ref())
+                .chain(event.stacktrace.as_ref())
+                .flat_map(|stacktrace| &stacktrace.frames);
+            let mut modules = exceptions
+                .iter()
+                .filter_map(|exception| exception.module.as_deref())
+                .chain(frames.flat_map(|frame| frame.module.as_deref().into_iter().chain(frame.function.as_deref())));
+            if !modules.any(|module| module.starts_with(prefix.as_str())) {
+                return false;
+            }
+        }
+
+        self.tags.iter().all(|(key, value)| event.tags.get(key) == Some(value))
+    }
+
+    fn render(&self, event: &Event<'_>) -> Vec<Cow<'static, str>> {
+        let ty = event
+            .exception
+            .values
+            .last()
+            .map_or("", |exception| exception.ty.as_str());
+        self.fingerprint
+            .iter()
+            .map(|part| Cow::Owned(part.replace("{{ type }}", ty)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintRules {
+    rules: Vec<FingerprintRule>,
+}
+
+impl FingerprintRules {
+    pub fn new(rules: Vec<FingerprintRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn rules(&self) -> &[FingerprintRule] {
+        &self.rules
+    }
+
+    /// Set the fingerprint of the first matching rule; returns the rule name.
+    /// Events that already carry a custom fingerprint are left alone.
+    pub fn apply(&self, event: &mut Event<'_>) -> Option<&str> {
+        let default = event.fingerprint.len() == 1 && event.fingerprint[0] == "{{ default }}";
+        if !default {
+            return None;
+        }
+        let rule = self.rules.iter().find(|rule| rule.matches(event))?;
+        event.fingerprint = Cow::Owned(rule.render(event));
+        event.tags.insert(TAG.to_string(), rule.name.clone());
+        Some(&rule.name)
+    }
+}
+
+fn state() -> &'static RwLock<Arc<FingerprintRules>> {
+    static STATE: OnceLock<RwLock<Arc<FingerprintRules>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Replace the rules consulted by `before_send`.
+pub fn install(rules: FingerprintRules) {
+    *state().write().unwrap() = Arc::new(rules);
+}
+
+pub(crate) fn apply(event: &mut Event<'_>) {
+    let rules = state().read().unwrap().clone();
+    rules.apply(event);
+}
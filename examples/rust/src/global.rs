@@ -0,0 +1,76 @@
+//! Process-wide SentryService for small binaries that don't want to thread an
+//! `Arc<SentryService>` through every struct.
+//!
+//! Also reachable as `observability`:
+//!
+//! ```ignore
+//! observability::init(config::Config::from_env());
+//! observability::set_user("user-123", Some("developer@example.com"), None);
+//! observability::error!("Payment failed for order {}", order_id);
+//! observability::shutdown(Duration::from_secs(2));
+//! ```
+
+use super::{config::Config, SentryService};
+use sentry::{protocol::Value, types::Uuid, Level};
+use std::{collections::BTreeMap, sync::OnceLock, time::Duration};
+
+pub use crate::error;
+
+static SERVICE: OnceLock<SentryService> = OnceLock::new();
+
+/// Initialize the global service. Only the first call initializes the SDK;
+/// later calls return the already running service.
+pub fn init(config: Config) -> &'static SentryService {
+    SERVICE.get_or_init(|| SentryService::with_config(config))
+}
+
+/// The global service, if `init` has been called.
+pub fn service() -> Option<&'static SentryService> {
+    SERVICE.get()
+}
+
+/// Set user context.
+pub fn set_user(id: &str, email: Option<&str>, username: Option<&str>) {
+    if let Some(service) = service() {
+        service.set_user(id, email, username, None);
+    }
+}
+
+/// Clear user context.
+pub fn clear_user() {
+    if let Some(service) = service() {
+        service.clear_user();
+    }
+}
+
+/// Set a tag.
+pub fn set_tag(key: &str, value: &str) {
+    if let Some(service) = service() {
+        service.set_tag(key, value);
+    }
+}
+
+/// Add a breadcrumb.
+pub fn add_breadcrumb(message: &str, category: &str, level: Level, data: Option<BTreeMap<String, Value>>) {
+    if let Some(service) = service() {
+        service.add_breadcrumb(message, category, level, data);
+    }
+}
+
+/// Capture an error.
+pub fn capture_error<E: std::error::Error + ?Sized>(error: &E) -> Option<Uuid> {
+    service().map(|service| service.capture_error(error))
+}
+
+/// Capture a message.
+pub fn capture_message(message: &str, level: Level) -> Option<Uuid> {
+    service().map(|service| service.capture_message(message, level))
+}
+
+/// Flush and close like [`SentryService::close`]. The service inside a
+/// static is never dropped, so binaries using the facade must call this
+/// before exiting.
+pub fn shutdown(timeout: Duration) -> bool {
+    crate::crash::disarm();
+    service().is_none_or(|service| service.close(timeout))
+}
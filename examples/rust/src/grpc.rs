@@ -0,0 +1,196 @@
+//! Tower layer for tonic servers (feature `grpc`).
+//!
+//! Each RPC runs on its own hub in a `grpc.server` transaction named after
+//! the method (`orders.v1.Orders/Get`), continued from the `sentry-trace` /
+//! `baggage` metadata of the caller. The peer address is attached to the
+//! transaction and to events as the `grpc` context. Responses whose status
+//! code is in [`capture_codes`](grpc::SentryGrpcLayer::capture_codes)
+//! (`INTERNAL` by default) are captured as events with the status message,
+//! unless the handler already captured something.
+//!
+//! tonic's `Interceptor` only sees the request, so this is a layer:
+//!
+//! ```ignore
+//! Server::builder()
+//!     .layer(grpc::SentryGrpcLayer::new())
+//!     .add_service(OrdersServer::new(orders))
+//!     .serve(addr)
+//!     .await?;
+//! ```
+//!
+//! The status is read from the response headers, where tonic puts it for
+//! handlers returning `Err(Status)`; statuses sent as trailers at the end of a
+//! stream are not seen.
+
+use crate::{ops::Op, propagation, sessions};
+use sentry::{
+    protocol::{Context, Event, SpanStatus},
+    Hub, Level, SentryFutureExt,
+};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+use tonic::{
+    codegen::http::{Request, Response},
+    transport::server::TcpConnectInfo,
+    Code, Status,
+};
+use tower::{Layer, Service};
+
+#[derive(Debug, Clone)]
+pub struct SentryGrpcLayer {
+    capture_codes: Arc<[Code]>,
+}
+
+impl Default for SentryGrpcLayer {
+    fn default() -> Self {
+        Self {
+            capture_codes: Arc::new([Code::Internal]),
+        }
+    }
+}
+
+impl SentryGrpcLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Status codes reported as events.
+    pub fn capture_codes(mut self, codes: &[Code]) -> Self {
+        self.capture_codes = codes.into();
+        self
+    }
+}
+
+impl<S> Layer<S> for SentryGrpcLayer {
+    type Service = SentryGrpcService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SentryGrpcService {
+            inner,
+            capture_codes: self.capture_codes.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SentryGrpcService<S> {
+    inner: S,
+    capture_codes: Arc<[Code]>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SentryGrpcService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // The clone is not necessarily ready; call the one that was polled
+        let clone = self.inner.clone();
+        let mut inner = mem::replace(&mut self.inner, clone);
+        let capture_codes = self.capture_codes.clone();
+
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        let method = request.uri().path().trim_start_matches('/').to_string();
+        let ctx = propagation::continue_from_headers(&method, Op::GrpcServer.as_str(), request.headers());
+        let transaction = hub.start_transaction(ctx);
+        sessions::start_request(&hub);
+
+        let mut grpc = BTreeMap::new();
+        if let Some((service, name)) = method.split_once('/') {
+            grpc.insert("service".to_string(), service.into());
+            grpc.insert("method".to_string(), name.into());
+            transaction.set_data("rpc.service", service.into());
+            transaction.set_data("rpc.method", name.into());
+        }
+        let peer = request
+            .extensions()
+            .get::<TcpConnectInfo>()
+            .and_then(TcpConnectInfo::remote_addr);
+        if let Some(peer) = peer {
+            grpc.insert("peer".to_string(), peer.to_string().into());
+            transaction.set_data("net.peer.ip", peer.ip().to_string().into());
+            transaction.set_data("net.peer.port", peer.port().into());
+        }
+        hub.configure_scope(|scope| {
+            scope.set_span(Some(transaction.clone().into()));
+            scope.set_transaction(Some(&method));
+            scope.set_context("grpc", Context::Other(grpc));
+        });
+
+        let future = inner.call(request);
+        Box::pin(
+            async move {
+                let hub = Hub::current();
+                let last_event = hub.last_event_id();
+                let result = future.await;
+                let code = match &result {
+                    Ok(response) => match Status::from_header_map(response.headers()) {
+                        Some(status) => {
+                            if capture_codes.contains(&status.code()) && hub.last_event_id() == last_event {
+                                hub.capture_event(status_event(&status));
+                            }
+                            status.code()
+                        }
+                        None => Code::Ok,
+                    },
+                    Err(_) => Code::Unknown,
+                };
+                transaction.set_data("rpc.grpc.status_code", (code as i32).into());
+                transaction.set_status(span_status(code));
+                transaction.finish();
+                sessions::end_request(&hub);
+                result
+            }
+            .bind_hub(hub),
+        )
+    }
+}
+
+fn status_event(status: &Status) -> Event<'static> {
+    let mut event = Event {
+        level: Level::Error,
+        message: Some(format!("gRPC {:?}: {}", status.code(), status.message())),
+        ..Default::default()
+    };
+    event
+        .tags
+        .insert("rpc.grpc.status_code".to_string(), (status.code() as i32).to_string());
+    event
+}
+
+pub fn span_status(code: Code) -> SpanStatus {
+    match code {
+        Code::Ok => SpanStatus::Ok,
+        Code::Cancelled => SpanStatus::Cancelled,
+        Code::Unknown => SpanStatus::UnknownError,
+        Code::InvalidArgument => SpanStatus::InvalidArgument,
+        Code::DeadlineExceeded => SpanStatus::DeadlineExceeded,
+        Code::NotFound => SpanStatus::NotFound,
+        Code::AlreadyExists => SpanStatus::AlreadyExists,
+        Code::PermissionDenied => SpanStatus::PermissionDenied,
+        Code::ResourceExhausted => SpanStatus::ResourceExhausted,
+        Code::FailedPrecondition => SpanStatus::FailedPrecondition,
+        Code::Aborted => SpanStatus::Aborted,
+        Code::OutOfRange => SpanStatus::OutOfRange,
+        Code::Unimplemented => SpanStatus::Unimplemented,
+        Code::Internal => SpanStatus::InternalError,
+        Code::Unavailable => SpanStatus::Unavailable,
+        Code::DataLoss => SpanStatus::DataLoss,
+        Code::Unauthenticated => SpanStatus::Unauthenticated,
+    }
+}
@@ -0,0 +1,386 @@
+//! Heartbeat watchdog for hung services.
+//!
+//! The main loop calls [`pet`](HangWatchdog::pet) on every iteration;
+//! when it stops doing so for `timeout` a separate thread captures an error
+//! event describing every thread of the process, so a deadlocked or spinning
+//! service shows up in Bugsink instead of just going quiet. One event is sent
+//! per stall; petting again re-arms the watchdog.
+//!
+//! Thread names come from `/proc` on Linux. With the `crash-signals`
+//! feature each thread is also interrupted with a real-time signal to record
+//! its stack by walking frame pointers (syscalls interrupted by it are
+//! restarted). With the
+//! `deadlock-detection` feature the watchdog additionally checks for lock
+//! cycles between `parking_lot` mutexes and reports each one with the
+//! backtraces of the threads involved; `std::sync` locks are not covered.
+//!
+//! ```ignore
+//! let watchdog = hang::HangWatchdog::start(Duration::from_secs(30))?;
+//! loop {
+//!     watchdog.pet();
+//!     process_next_batch()?;
+//! }
+//! ```
+
+use sentry::{
+    protocol::{Thread, ThreadId, Values},
+    Hub, Level,
+};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+struct HangState {
+    last_pet: Mutex<Instant>,
+    /// OS thread id of the last caller of `pet`, 0 if unknown.
+    petting_thread: AtomicI64,
+    stopped: AtomicBool,
+}
+
+pub struct HangWatchdog {
+    state: Arc<HangState>,
+    timeout: Duration,
+}
+
+impl HangWatchdog {
+    /// Start the watchdog thread. Events are captured on the current hub.
+    pub fn start(timeout: Duration) -> io::Result<Self> {
+        #[cfg(all(target_os = "linux", feature = "crash-signals"))]
+        dump::install()?;
+
+        let state = Arc::new(HangState {
+            last_pet: Mutex::new(Instant::now()),
+            petting_thread: AtomicI64::new(0),
+            stopped: AtomicBool::new(false),
+        });
+        let hub = Hub::current();
+        let thread_state = Arc::clone(&state);
+        thread::Builder::new()
+            .name("hang-watchdog".to_string())
+            .spawn(move || run(thread_state, hub, timeout))?;
+        Ok(Self { state, timeout })
+    }
+
+    /// Signal that the main loop is alive.
+    pub fn pet(&self) {
+        *self.state.last_pet.lock().unwrap() = Instant::now();
+        self.state.petting_thread.store(os_thread_id(), Ordering::Relaxed);
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl Drop for HangWatchdog {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+fn run(state: Arc<HangState>, hub: Arc<Hub>, timeout: Duration) {
+    let tick = (timeout / 4).min(Duration::from_secs(1));
+    let mut reported = false;
+
+    while !state.stopped.load(Ordering::SeqCst) {
+        #[cfg(feature = "deadlock-detection")]
+        for event in deadlock_events() {
+            hub.capture_event(event);
+        }
+
+        let stalled_for = state.last_pet.lock().unwrap().elapsed();
+        if stalled_for < timeout {
+            reported = false;
+        } else if !reported {
+            let petting_thread = state.petting_thread.load(Ordering::Relaxed);
+            hub.with_scope(
+                |scope| {
+                    scope.set_tag("watchdog", "hang");
+                    scope.set_extra("stalled_ms", (stalled_for.as_millis() as u64).into());
+                    scope.set_extra("timeout_ms", (timeout.as_millis() as u64).into());
+                },
+                || hub.capture_event(hang_event(all_threads(petting_thread))),
+            );
+            if let Some(client) = hub.client() {
+                client.flush(Some(tick));
+            }
+            reported = true;
+        }
+
+        thread::sleep(tick);
+    }
+}
+
+/// Error event listing `threads`, the one that stopped petting marked as current.
+pub fn hang_event(threads: Vec<Thread>) -> sentry::protocol::Event<'static> {
+    sentry::protocol::Event {
+        level: Level::Error,
+        message: Some("Main loop stopped responding".to_string()),
+        threads: Values::from(threads),
+        ..Default::default()
+    }
+}
+
+/// Every thread of the process except the watchdog itself.
+#[cfg(target_os = "linux")]
+fn all_threads(petting_thread: i64) -> Vec<Thread> {
+    let Ok(tasks) = std::fs::read_dir("/proc/self/task") else {
+        return Vec::new();
+    };
+    let own = os_thread_id();
+    let mut tids: Vec<i64> = tasks
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .filter(|tid| *tid != own)
+        .collect();
+    tids.sort_unstable();
+    tids.into_iter()
+        .map(|tid| Thread {
+            id: Some(ThreadId::Int(tid as u64)),
+            name: std::fs::read_to_string(format!("/proc/self/task/{}/comm", tid))
+                .ok()
+                .map(|name| name.trim_end().to_string()),
+            #[cfg(feature = "crash-signals")]
+            stacktrace: dump::stacktrace(tid as libc::pid_t),
+            current: tid == petting_thread,
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn all_threads(_petting_thread: i64) -> Vec<Thread> {
+    Vec::new()
+}
+
+/// Kernel thread id, from the `/proc/thread-self` link (`<pid>/task/<tid>`).
+#[cfg(target_os = "linux")]
+fn os_thread_id() -> i64 {
+    thread_local! {
+        static TID: i64 = std::fs::read_link("/proc/thread-self")
+            .ok()
+            .and_then(|path| path.file_name()?.to_str()?.parse().ok())
+            .unwrap_or(0);
+    }
+    TID.with(|tid| *tid)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn os_thread_id() -> i64 {
+    0
+}
+
+/// One fatal event per `parking_lot` lock cycle found since the last call.
+#[cfg(feature = "deadlock-detection")]
+pub fn deadlock_events() -> Vec<sentry::protocol::Event<'static>> {
+    // The element type is private to parking_lot, so it is never named here
+    parking_lot::deadlock::check_deadlock()
+        .iter()
+        .map(|cycle| {
+            deadlock_event(
+                cycle
+                    .iter()
+                    .map(|thread| Thread {
+                        id: Some(ThreadId::String(format!("{:?}", thread.thread_id()))),
+                        stacktrace: sentry::integrations::backtrace::backtrace_to_stacktrace(thread.backtrace()),
+                        crashed: true,
+                        ..Default::default()
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Fatal event for one lock cycle between `threads`.
+#[cfg(feature = "deadlock-detection")]
+fn deadlock_event(threads: Vec<Thread>) -> sentry::protocol::Event<'static> {
+    let mut event = sentry::protocol::Event {
+        level: Level::Fatal,
+        message: Some(format!("Deadlock detected between {} threads", threads.len())),
+        threads: Values::from(threads),
+        ..Default::default()
+    };
+    event.tags.insert("watchdog".to_string(), "deadlock".to_string());
+    event
+}
+
+/// Stack capture of other threads: the target thread records its own
+/// instruction addresses in a signal handler, the watchdog resolves them.
+///
+/// The handler only walks the frame pointer chain of the interrupted
+/// context (see [`crate::crash::signals`]), which is async-signal-safe.
+/// Every request carries a sequence number and the id of the target
+/// thread; a handler that runs for an abandoned request, or on another
+/// thread, leaves the slot alone, so late answers never mix into the next dump.
+#[cfg(all(target_os = "linux", feature = "crash-signals"))]
+mod dump {
+    use crate::crash::signals::walk_stack;
+    use sentry::protocol::{Frame, Stacktrace};
+    use std::{
+        ffi::c_void,
+        io, mem,
+        sync::{
+            atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering},
+            Mutex, Once,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const MAX_FRAMES: usize = 64;
+    const WAIT: Duration = Duration::from_millis(100);
+
+    /// Low bits of [`Slot::state`]; the sequence number sits above them.
+    const IDLE: u64 = 0;
+    const REQUESTED: u64 = 1;
+    const WRITING: u64 = 2;
+    const DONE: u64 = 3;
+    const PHASE: u64 = 0b11;
+
+    struct Slot {
+        /// `sequence << 2 | phase`
+        state: AtomicU64,
+        target: AtomicI64,
+        frames: [AtomicUsize; MAX_FRAMES],
+        count: AtomicUsize,
+    }
+
+    static SLOT: Slot = Slot {
+        state: AtomicU64::new(IDLE),
+        target: AtomicI64::new(0),
+        frames: [const { AtomicUsize::new(0) }; MAX_FRAMES],
+        count: AtomicUsize::new(0),
+    };
+    /// One thread is dumped at a time.
+    static DUMPING: Mutex<()> = Mutex::new(());
+
+    fn signal() -> libc::c_int {
+        libc::SIGRTMIN() + 1
+    }
+
+    pub fn install() -> io::Result<()> {
+        static INSTALLED: Once = Once::new();
+        let mut result = Ok(());
+        INSTALLED.call_once(|| {
+            // SAFETY: plain sigaction call with a zero-initialized struct
+            unsafe {
+                let mut action: libc::sigaction = mem::zeroed();
+                action.sa_sigaction = handle as *const () as usize;
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART | libc::SA_ONSTACK;
+                libc::sigemptyset(&mut action.sa_mask);
+                if libc::sigaction(signal(), &action, std::ptr::null_mut()) != 0 {
+                    result = Err(io::Error::last_os_error());
+                }
+            }
+        });
+        result
+    }
+
+    extern "C" fn handle(_signal: libc::c_int, _info: *mut libc::siginfo_t, context: *mut c_void) {
+        let state = SLOT.state.load(Ordering::Acquire);
+        // SAFETY: gettid has no preconditions
+        let tid = unsafe { libc::syscall(libc::SYS_gettid) };
+        if state & PHASE != REQUESTED || SLOT.target.load(Ordering::Acquire) != tid {
+            return;
+        }
+        let sequence = state & !PHASE;
+        // Claim the slot; fails if the watchdog gave up on this request meanwhile
+        if SLOT
+            .state
+            .compare_exchange(state, sequence | WRITING, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return;
+        }
+        let mut frames = [0usize; MAX_FRAMES];
+        // SAFETY: the kernel passes a valid context for SA_SIGINFO handlers
+        let count = unsafe { walk_stack(context, &mut frames) };
+        for (slot, ip) in SLOT.frames.iter().zip(&frames[..count]) {
+            slot.store(*ip, Ordering::Relaxed);
+        }
+        SLOT.count.store(count, Ordering::Relaxed);
+        SLOT.state.store(sequence | DONE, Ordering::Release);
+    }
+
+    /// Instruction addresses of thread `tid`, innermost first; `None` if it
+    /// did not answer in time.
+    fn capture(tid: libc::pid_t) -> Option<Vec<usize>> {
+        let _dumping = DUMPING.lock().unwrap();
+        // A handler still writing for an abandoned request finishes quickly
+        let mut state = SLOT.state.load(Ordering::Acquire);
+        while state & PHASE == WRITING {
+            thread::yield_now();
+            state = SLOT.state.load(Ordering::Acquire);
+        }
+        let sequence = (state & !PHASE).wrapping_add(PHASE + 1);
+        SLOT.target.store(tid as i64, Ordering::Release);
+        SLOT.state.store(sequence | REQUESTED, Ordering::Release);
+
+        // SAFETY: tgkill with our own pid; an exited tid fails with ESRCH
+        let sent = unsafe { libc::syscall(libc::SYS_tgkill, libc::getpid(), tid, signal()) };
+        let deadline = Instant::now() + WAIT;
+        loop {
+            let state = SLOT.state.load(Ordering::Acquire);
+            if state == sequence | DONE {
+                break;
+            }
+            if (sent != 0 || Instant::now() > deadline)
+                && SLOT
+                    .state
+                    .compare_exchange(
+                        sequence | REQUESTED,
+                        sequence | IDLE,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+            {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        let count = SLOT.count.load(Ordering::Relaxed);
+        let ips = SLOT.frames[..count]
+            .iter()
+            .map(|ip| ip.load(Ordering::Relaxed))
+            .collect();
+        SLOT.state.store(sequence | IDLE, Ordering::Release);
+        Some(ips)
+    }
+
+    pub fn stacktrace(tid: libc::pid_t) -> Option<Stacktrace> {
+        let ips = capture(tid)?;
+        let mut frames: Vec<Frame> = ips.into_iter().map(resolve).collect();
+        // Sentry lists frames oldest first
+        frames.reverse();
+        Some(Stacktrace {
+            frames,
+            ..Default::default()
+        })
+    }
+
+    fn resolve(ip: usize) -> Frame {
+        let mut frame = Frame {
+            instruction_addr: Some(ip.into()),
+            ..Default::default()
+        };
+        backtrace::resolve(ip as *mut c_void, |symbol| {
+            if frame.function.is_none() {
+                frame.function = symbol.name().map(|name| format!("{:#}", name));
+                frame.abs_path = symbol.filename().map(|path| path.display().to_string());
+                frame.filename = symbol
+                    .filename()
+                    .and_then(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().into_owned());
+                frame.lineno = symbol.lineno().map(u64::from);
+            }
+        });
+        frame
+    }
+}
@@ -0,0 +1,112 @@
+//! Counters for the observability pipeline itself, so a failing pipeline can
+//! be alerted on instead of going quiet.
+//!
+//! Events dropped by the `before_send` chain (filters, sampling, dedupe, the
+//! application hook) and by the client-side rate limiter are always counted.
+//! Delivery results, queue depth and transport errors come from this crate's
+//! transports (`http-transport`, [`local`], [`kafka`]); the SDK's built-in
+//! transport does not report them.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// Filtered, sampled or deduplicated before sending.
+    BeforeSend,
+    /// Client-side rate limit, or a server rate limit outlasting the retries.
+    RateLimit,
+    /// Transport queue full.
+    QueueFull,
+    /// Delivery failed after all retries and no fallback took the envelope.
+    Network,
+    /// Permanently rejected by the server (e.g. 400, 413).
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Dropped {
+    pub before_send: u64,
+    pub rate_limit: u64,
+    pub queue_full: u64,
+    pub network: u64,
+    pub rejected: u64,
+}
+
+impl Dropped {
+    pub fn total(&self) -> u64 {
+        self.before_send + self.rate_limit + self.queue_full + self.network + self.rejected
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportError {
+    pub message: String,
+    pub at: SystemTime,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SdkStats {
+    /// Envelopes accepted by the server (or written by a local transport).
+    pub sent: u64,
+    pub dropped: Dropped,
+    /// Envelopes waiting in transport and rate-limit queues.
+    pub queue_depth: usize,
+    pub last_transport_error: Option<TransportError>,
+}
+
+static SENT: AtomicU64 = AtomicU64::new(0);
+static DROPPED: [AtomicU64; 5] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static LAST_ERROR: Mutex<Option<TransportError>> = Mutex::new(None);
+
+pub fn record_sent() {
+    SENT.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_dropped(reason: DropReason) {
+    DROPPED[reason as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_error(message: impl Into<String>) {
+    *LAST_ERROR.lock().unwrap() = Some(TransportError {
+        message: message.into(),
+        at: SystemTime::now(),
+    });
+}
+
+/// Queue depth accounting for transports with their own queue.
+pub fn queued(count: usize) {
+    QUEUED.fetch_add(count, Ordering::Relaxed);
+}
+
+pub fn dequeued(count: usize) {
+    QUEUED.fetch_sub(count, Ordering::Relaxed);
+}
+
+pub fn snapshot() -> SdkStats {
+    let dropped = |reason: DropReason| DROPPED[reason as usize].load(Ordering::Relaxed);
+    SdkStats {
+        sent: SENT.load(Ordering::Relaxed),
+        dropped: Dropped {
+            before_send: dropped(DropReason::BeforeSend),
+            rate_limit: dropped(DropReason::RateLimit),
+            queue_full: dropped(DropReason::QueueFull),
+            network: dropped(DropReason::Network),
+            rejected: dropped(DropReason::Rejected),
+        },
+        queue_depth: QUEUED.load(Ordering::Relaxed) + crate::ratelimit::stats().map_or(0, |stats| stats.queued),
+        last_transport_error: LAST_ERROR.lock().unwrap().clone(),
+    }
+}
@@ -0,0 +1,530 @@
+//! Envelope delivery over blocking reqwest (feature `http-transport`), with
+//! the [`backoff`] state applied: requests in a rate-limited category or
+//! while the circuit is open are not made at all.
+//!
+//! Failed sends are retried per the [`RetryPolicy`](backoff::RetryPolicy);
+//! envelopes that exhaust their retries go to the fallback transport, which
+//! is a disk spool in `SENTRY_SPOOL_FALLBACK_DIR` with `offline-spool`.
+//!
+//! [`HttpOptions`](http::HttpOptions) add batching and compression for
+//! high-throughput services. The envelope protocol allows only one event or
+//! transaction per envelope, so a batch is collected over the flush interval
+//! and sent back to back on one keep-alive connection, with standalone items
+//! (sessions, check-ins) coalesced into a single envelope. Bodies are
+//! compressed with gzip (feature `gzip`) or zstd (feature `zstd`).
+//!
+//! Networks that only reach Bugsink through a proxy set
+//! [`ProxyOptions`](http::ProxyOptions), with basic auth and a NO_PROXY list;
+//! without them reqwest honors the usual proxy environment variables. A local
+//! relay can also be reached over a Unix socket. A client that cannot be built
+//! (e.g. an invalid proxy URL) is reported at init instead of dropping events
+//! silently.
+
+use super::{
+    backoff::{self, RetryPolicy},
+    health::{self, DropReason},
+    ratelimit::Category,
+};
+use sentry::{protocol::Envelope, transports::DefaultTransportFactory, ClientOptions, Transport, TransportFactory};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, OnceLock, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Envelopes waiting in the channel before new ones are dropped.
+const QUEUE_SIZE: usize = 100;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Batching {
+    /// How long the first envelope of a batch waits for company.
+    pub interval: Duration,
+    pub max_envelopes: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    fn encoding(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Some("gzip"),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+
+    pub fn compress(self, body: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(body),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Write;
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&body)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::encode_all(body.as_slice(), 3),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyOptions {
+    /// `http://` or `https://` proxy URL; credentials may be embedded.
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Comma-separated hosts, domains and CIDRs reached directly; `None`
+    /// reads the NO_PROXY environment variable.
+    pub no_proxy: Option<String>,
+}
+
+impl ProxyOptions {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            username: None,
+            password: None,
+            no_proxy: None,
+        }
+    }
+
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.username = Some(username.to_string());
+        self.password = Some(password.to_string());
+        self
+    }
+
+    pub fn no_proxy(mut self, hosts: &str) -> Self {
+        self.no_proxy = Some(hosts.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpOptions {
+    /// `None` sends every envelope as soon as it arrives.
+    pub batching: Option<Batching>,
+    pub compression: Compression,
+    pub proxy: Option<ProxyOptions>,
+    /// Send to a relay on this Unix socket instead of the DSN host.
+    pub unix_socket: Option<PathBuf>,
+}
+
+impl HttpOptions {
+    /// Check that a client can be built from these options.
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.proxy {
+            Some(options) if self.unix_socket.is_none() => proxy(options).map(drop),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn options_state() -> &'static RwLock<HttpOptions> {
+    static OPTIONS: OnceLock<RwLock<HttpOptions>> = OnceLock::new();
+    OPTIONS.get_or_init(Default::default)
+}
+
+/// Set the options used by transports created afterwards.
+pub fn configure(options: HttpOptions) {
+    *options_state().write().unwrap() = options;
+}
+
+pub fn options() -> HttpOptions {
+    options_state().read().unwrap().clone()
+}
+
+/// Merge envelopes without an event or transaction into one envelope;
+/// event and transaction envelopes are kept as they are.
+pub fn coalesce(envelopes: Vec<Envelope>) -> Vec<Envelope> {
+    let mut standalone = Envelope::new();
+    let mut coalesced = Vec::with_capacity(envelopes.len());
+    for envelope in envelopes {
+        // Raw envelopes (read from disk) expose no items and are passed through
+        if envelope.uuid().is_some() || envelope.items().next().is_none() {
+            coalesced.push(envelope);
+        } else {
+            envelope.items().for_each(|item| standalone.add_item(item.clone()));
+        }
+    }
+    if standalone.items().next().is_some() {
+        coalesced.push(standalone);
+    }
+    coalesced
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Accepted, or rejected with a status the retry policy considers final.
+    Done,
+    /// Not sent or not accepted; worth retrying after the delay, if known.
+    Retry(Option<Duration>),
+}
+
+/// Posts serialized envelopes to the DSN's envelope endpoint.
+pub struct Sender {
+    url: String,
+    auth: String,
+    connector: Connector,
+    policy: RetryPolicy,
+    compression: Compression,
+}
+
+enum Connector {
+    Http(reqwest::blocking::Client),
+    /// Plain HTTP/1.1 to a relay listening on a Unix socket.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Status and backoff headers of an envelope POST.
+struct Response {
+    status: u16,
+    retry_after: Option<String>,
+    rate_limits: Option<String>,
+}
+
+impl Sender {
+    /// `None` without a DSN or if the HTTP client cannot be built.
+    pub fn new(options: &ClientOptions) -> Option<Self> {
+        Self::with_options(options, &self::options())
+    }
+
+    pub fn with_options(options: &ClientOptions, http: &HttpOptions) -> Option<Self> {
+        let dsn = options.dsn.as_ref()?;
+        let connector = match &http.unix_socket {
+            #[cfg(unix)]
+            Some(path) => Connector::Unix(path.clone()),
+            #[cfg(not(unix))]
+            Some(_) => {
+                eprintln!("Unix socket transport is not supported on this platform, events will not be sent");
+                return None;
+            }
+            None => match client(http.proxy.as_ref()) {
+                Ok(client) => Connector::Http(client),
+                Err(e) => {
+                    eprintln!("Cannot build HTTP client, events will not be sent: {}", e);
+                    return None;
+                }
+            },
+        };
+        Some(Self {
+            url: dsn.envelope_api_url().to_string(),
+            auth: dsn.to_auth(Some(&options.user_agent)).to_string(),
+            connector,
+            policy: backoff::retry_policy(),
+            compression: http.compression,
+        })
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn send(&self, category: Category, body: Vec<u8>) -> Outcome {
+        let backoff = backoff::global();
+        if let Some(delay) = backoff.delay(category) {
+            return Outcome::Retry(Some(delay));
+        }
+
+        let (body, encoding) = match self.compression.compress(body.clone()) {
+            Ok(compressed) => (compressed, self.compression.encoding()),
+            Err(_) => (body, None),
+        };
+        let response = match &self.connector {
+            Connector::Http(client) => self.post(client, body, encoding),
+            #[cfg(unix)]
+            Connector::Unix(path) => self.post_unix(path, &body, encoding),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                health::record_error(format!("{}: {}", self.url, e));
+                backoff.on_failure();
+                return Outcome::Retry(None);
+            }
+        };
+
+        backoff.on_response(
+            response.status,
+            response.retry_after.as_deref(),
+            response.rate_limits.as_deref(),
+        );
+
+        if (200..300).contains(&response.status) {
+            health::record_sent();
+            return Outcome::Done;
+        }
+        health::record_error(format!("{}: HTTP {}", self.url, response.status));
+        if !self.policy.is_retryable(response.status) {
+            health::record_dropped(DropReason::Rejected);
+            Outcome::Done
+        } else {
+            Outcome::Retry(backoff.delay(category))
+        }
+    }
+
+    fn post(&self, client: &reqwest::blocking::Client, body: Vec<u8>, encoding: Option<&str>) -> io::Result<Response> {
+        let mut request = client
+            .post(&self.url)
+            .header("X-Sentry-Auth", &self.auth)
+            .header("Content-Type", "application/x-sentry-envelope");
+        if let Some(encoding) = encoding {
+            request = request.header("Content-Encoding", encoding);
+        }
+        let response = request.body(body).send().map_err(io::Error::other)?;
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        };
+        Ok(Response {
+            status: response.status().as_u16(),
+            retry_after: header("Retry-After"),
+            rate_limits: header("X-Sentry-Rate-Limits"),
+        })
+    }
+
+    #[cfg(unix)]
+    fn post_unix(&self, path: &Path, body: &[u8], encoding: Option<&str>) -> io::Result<Response> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            os::unix::net::UnixStream,
+        };
+
+        let url = reqwest::Url::parse(&self.url).map_err(io::Error::other)?;
+        let mut stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut head = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nX-Sentry-Auth: {}\r\n\
+                 Content-Type: application/x-sentry-envelope\r\nContent-Length: {}\r\nConnection: close\r\n",
+            url.path(),
+            url.host_str().unwrap_or("localhost"),
+            self.auth,
+            body.len()
+        );
+        if let Some(encoding) = encoding {
+            head.push_str(&format!("Content-Encoding: {}\r\n", encoding));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let status = line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP status line"))?;
+
+        let mut response = Response {
+            status,
+            retry_after: None,
+            rate_limits: None,
+        };
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = Some(value.trim().to_string());
+                if name.eq_ignore_ascii_case("Retry-After") {
+                    response.retry_after = value;
+                } else if name.eq_ignore_ascii_case("X-Sentry-Rate-Limits") {
+                    response.rate_limits = value;
+                }
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Blocking client with the configured proxy. Without one, reqwest uses
+/// HTTP_PROXY / HTTPS_PROXY / NO_PROXY from the environment.
+fn client(proxy: Option<&ProxyOptions>) -> Result<reqwest::blocking::Client, String> {
+    let builder = reqwest::blocking::Client::builder().timeout(TIMEOUT);
+    let mut builder = crate::tls::apply!(&crate::tls::options(), builder)?;
+    if let Some(options) = proxy {
+        builder = builder.proxy(self::proxy(options)?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn proxy(options: &ProxyOptions) -> Result<reqwest::Proxy, String> {
+    let mut proxy = reqwest::Proxy::all(&options.url).map_err(|e| format!("proxy {}: {}", options.url, e))?;
+    if let Some(username) = &options.username {
+        proxy = proxy.basic_auth(username, options.password.as_deref().unwrap_or(""));
+    }
+    let no_proxy = match &options.no_proxy {
+        Some(hosts) => reqwest::NoProxy::from_string(hosts),
+        None => reqwest::NoProxy::from_env(),
+    };
+    Ok(proxy.no_proxy(no_proxy))
+}
+
+/// Transport sending from a background thread through a [`Sender`].
+#[derive(Debug)]
+pub struct HttpTransport {
+    queue: mpsc::SyncSender<Envelope>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl HttpTransport {
+    pub fn new(options: &ClientOptions) -> io::Result<Self> {
+        Self::with_fallback(options, None)
+    }
+
+    /// `fallback` receives envelopes whose retries are exhausted.
+    pub fn with_fallback(options: &ClientOptions, fallback: Option<Arc<dyn Transport>>) -> io::Result<Self> {
+        let (queue, rx) = mpsc::sync_channel::<Envelope>(QUEUE_SIZE);
+        let pending = Arc::new(AtomicUsize::new(0));
+        let sender = Sender::new(options);
+        let batching = self::options().batching;
+        let worker_pending = pending.clone();
+        thread::Builder::new().name("sentry-http".to_string()).spawn(move || {
+            while let Ok(first) = rx.recv() {
+                let mut batch = vec![first];
+                if let Some(batching) = batching {
+                    let deadline = Instant::now() + batching.interval;
+                    while batch.len() < batching.max_envelopes {
+                        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                            Ok(envelope) => batch.push(envelope),
+                            Err(_) => break,
+                        }
+                    }
+                }
+                let received = batch.len();
+
+                for envelope in coalesce(batch) {
+                    let Some(sender) = &sender else {
+                        health::record_dropped(DropReason::Network);
+                        continue;
+                    };
+                    if !deliver(sender, &envelope) {
+                        match &fallback {
+                            Some(fallback) => fallback.send_envelope(envelope),
+                            None => {
+                                // Still limited after the retries: the server's rate limit outlasted them
+                                let reason = match backoff::global().delay(Category::of(&envelope)) {
+                                    Some(_) => DropReason::RateLimit,
+                                    None => DropReason::Network,
+                                };
+                                health::record_dropped(reason);
+                                eprintln!("Dropping envelope after {} attempts", sender.policy.max_attempts)
+                            }
+                        }
+                    }
+                }
+                worker_pending.fetch_sub(received, Ordering::SeqCst);
+                health::dequeued(received);
+            }
+        })?;
+        Ok(Self { queue, pending })
+    }
+}
+
+/// Send with retries; false once the policy gives up.
+fn deliver(sender: &Sender, envelope: &Envelope) -> bool {
+    let mut body = Vec::new();
+    if envelope.to_writer(&mut body).is_err() {
+        return true;
+    }
+    let category = Category::of(envelope);
+    let policy = sender.policy();
+    let attempts = policy.max_attempts.max(1);
+    for attempt in 1..=attempts {
+        match sender.send(category, body.clone()) {
+            Outcome::Done => return true,
+            Outcome::Retry(_) if attempt == attempts => break,
+            Outcome::Retry(retry_after) => {
+                let delay = retry_after.unwrap_or_else(|| policy.delay(attempt));
+                if delay > policy.max_delay {
+                    break;
+                }
+                thread::sleep(delay);
+            }
+        }
+    }
+    false
+}
+
+impl Transport for HttpTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.queue.try_send(envelope).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            health::record_dropped(DropReason::QueueFull);
+            eprintln!("HTTP transport queue full, dropping envelope");
+        } else {
+            health::queued(1);
+        }
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.pending.load(Ordering::SeqCst) > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        true
+    }
+}
+
+/// Falls back to the SDK's default transport if the sending thread cannot be started.
+pub fn factory() -> Arc<dyn TransportFactory> {
+    Arc::new(|options: &ClientOptions| -> Arc<dyn Transport> {
+        match HttpTransport::with_fallback(options, fallback(options)) {
+            Ok(transport) => Arc::new(transport),
+            Err(e) => {
+                eprintln!("Cannot start HTTP transport: {}, delivering without it", e);
+                DefaultTransportFactory.create_transport(options)
+            }
+        }
+    })
+}
+
+#[cfg(feature = "offline-spool")]
+fn fallback(options: &ClientOptions) -> Option<Arc<dyn Transport>> {
+    use crate::spool::{Spool, SpoolLimits, SpoolTransport};
+
+    let dir = crate::config::spool_fallback_dir()?;
+    match Spool::open(&dir, SpoolLimits::default()).and_then(|spool| SpoolTransport::new(options, spool)) {
+        Ok(transport) => Some(Arc::new(transport)),
+        Err(e) => {
+            eprintln!("Cannot open fallback spool {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "offline-spool"))]
+fn fallback(_options: &ClientOptions) -> Option<Arc<dyn Transport>> {
+    None
+}
@@ -0,0 +1,139 @@
+//! Scope inheritance for background jobs.
+//!
+//! [`task`] forks the hub for work spawned right away on the same runtime.
+//! Jobs handed to a queue, a thread pool or a worker running its own hub
+//! need the request's context as a value instead: a
+//! [`JobContext`](jobs::JobContext) holds the user, tags and trace of the
+//! scope it was captured from, is cheap to clone and `Send`, and is applied
+//! again on the worker:
+//!
+//! ```ignore
+//! // in the request handler
+//! jobs.send(Job { order_id, context: JobContext::capture() })?;
+//!
+//! // on the worker
+//! while let Some(job) = jobs.recv().await {
+//!     let transaction = sentry::start_transaction(job.context.transaction("send-receipt", "queue.task"));
+//!     job.context.bind(send_receipt(job.order_id)).await;
+//!     transaction.finish();
+//! }
+//! ```
+//!
+//! Breadcrumbs, extras and other contexts stay with the request. Tags include
+//! the [correlation ID](correlation), and the request's transaction name is
+//! kept as the `job.origin` tag. Events captured in the job belong to the
+//! request's trace; a transaction started from
+//! [`transaction`](jobs::JobContext::transaction) continues it.
+
+use super::{
+    propagation::{TraceParent, SENTRY_TRACE},
+    scope_debug::{ScopeSnapshot, ScopeSnapshotExt},
+};
+use sentry::{
+    protocol::{Context, SpanId, TraceContext, User},
+    Hub, Scope, SentryFuture, SentryFutureExt, TransactionContext,
+};
+use std::{collections::BTreeMap, future::Future, sync::Arc};
+
+/// Tag carrying the transaction the job was enqueued from.
+pub const ORIGIN_TAG: &str = "job.origin";
+
+/// The parts of a scope a job inherits.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobContext {
+    pub user: Option<User>,
+    pub tags: BTreeMap<String, String>,
+    /// The span that was active when the job was enqueued.
+    pub trace: Option<TraceParent>,
+    /// Transaction name of the enqueuing scope.
+    pub origin: Option<String>,
+}
+
+impl JobContext {
+    /// Context of the current hub's scope, including its active span.
+    pub fn capture() -> Self {
+        let (snapshot, trace) = Hub::current().configure_scope(|scope| {
+            let trace = scope.get_span().and_then(|span| {
+                span.iter_headers()
+                    .find(|(name, _)| *name == SENTRY_TRACE)
+                    .and_then(|(_, value)| TraceParent::parse_sentry_trace(&value))
+            });
+            (scope.snapshot(), trace)
+        });
+        let mut context = Self::from_snapshot(&snapshot);
+        if trace.is_some() {
+            context.trace = trace;
+        }
+        context
+    }
+
+    /// Context from a snapshot; the trace comes from its `trace` context,
+    /// without a sampling decision.
+    pub fn from_snapshot(snapshot: &ScopeSnapshot) -> Self {
+        let trace = match snapshot.contexts.get("trace") {
+            Some(Context::Trace(trace)) => Some(TraceParent {
+                trace_id: trace.trace_id,
+                parent_span_id: trace.span_id,
+                sampled: None,
+            }),
+            _ => None,
+        };
+        Self {
+            user: snapshot.user.clone(),
+            tags: snapshot.tags.clone(),
+            trace,
+            origin: snapshot.transaction.clone(),
+        }
+    }
+
+    /// Set user, tags and trace on `scope`. Tags already set there keep
+    /// their value unless the job has the same key.
+    pub fn apply(&self, scope: &mut Scope) {
+        if self.user.is_some() {
+            scope.set_user(self.user.clone());
+        }
+        for (key, value) in &self.tags {
+            scope.set_tag(key, value);
+        }
+        if let Some(origin) = &self.origin {
+            scope.set_tag(ORIGIN_TAG, origin);
+        }
+        // A transaction bound to the scope later replaces this
+        if let Some(trace) = &self.trace {
+            scope.set_context(
+                "trace",
+                Context::Trace(Box::new(TraceContext {
+                    trace_id: trace.trace_id,
+                    span_id: SpanId::default(),
+                    parent_span_id: Some(trace.parent_span_id),
+                    ..Default::default()
+                })),
+            );
+        }
+    }
+
+    /// A fork of the current hub with the context applied.
+    pub fn hub(&self) -> Arc<Hub> {
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        hub.configure_scope(|scope| self.apply(scope));
+        hub
+    }
+
+    /// Run `f` on [`hub`](Self::hub).
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        Hub::run(self.hub(), f)
+    }
+
+    /// Poll `future` on [`hub`](Self::hub), forked now.
+    pub fn bind<F: Future>(&self, future: F) -> SentryFuture<F> {
+        future.bind_hub(self.hub())
+    }
+
+    /// Transaction context for the job, continuing the enqueuing trace if there was one.
+    pub fn transaction(&self, name: &str, op: &str) -> TransactionContext {
+        match &self.trace {
+            Some(trace) => trace.transaction(name, op),
+            None => TransactionContext::new(name, op),
+        }
+    }
+}
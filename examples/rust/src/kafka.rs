@@ -0,0 +1,183 @@
+//! Envelope delivery to a Kafka topic instead of HTTP (feature `kafka`), for
+//! platforms that route all observability traffic through their Kafka
+//! ingestion pipeline before it reaches Bugsink.
+//!
+//! Messages are keyed by the DSN's project id, so each project's events stay
+//! in one partition and keep their order. The payload is either the envelope
+//! wire format, which a consumer can POST unchanged to the project's envelope
+//! endpoint, or the JSON line of [`local::to_json`]. librdkafka properties
+//! (`security.protocol`, `sasl.*`, `compression.type`, ...) are passed through.
+//!
+//! ```toml
+//! [kafka]
+//! brokers = "kafka-1:9092,kafka-2:9092"
+//! topic = "observability.envelopes"
+//! serialization = "envelope"    # or "json"
+//!
+//! [kafka.properties]
+//! "security.protocol" = "SASL_SSL"
+//! ```
+
+use rdkafka::{
+    message::{Header, OwnedHeaders},
+    producer::{BaseRecord, DefaultProducerContext, Producer, ThreadedProducer},
+    ClientConfig,
+};
+use sentry::{protocol::Envelope, ClientOptions, Transport, TransportFactory};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Serialization {
+    /// Sentry envelope wire format (`application/x-sentry-envelope`).
+    #[default]
+    Envelope,
+    /// One JSON document per envelope.
+    Json,
+}
+
+impl Serialization {
+    fn content_type(self) -> &'static str {
+        match self {
+            Serialization::Envelope => "application/x-sentry-envelope",
+            Serialization::Json => "application/json",
+        }
+    }
+
+    pub fn serialize(self, envelope: &Envelope) -> std::io::Result<Vec<u8>> {
+        match self {
+            Serialization::Envelope => {
+                let mut body = Vec::new();
+                envelope.to_writer(&mut body)?;
+                Ok(body)
+            }
+            Serialization::Json => Ok(serde_json::to_vec(&crate::local::to_json(envelope))?),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaOptions {
+    /// Comma-separated `host:port` list.
+    pub brokers: String,
+    pub topic: String,
+    pub serialization: Serialization,
+    /// Additional librdkafka producer properties.
+    pub properties: BTreeMap<String, String>,
+}
+
+impl KafkaOptions {
+    pub fn new(brokers: &str, topic: &str) -> Self {
+        Self {
+            brokers: brokers.to_string(),
+            topic: topic.to_string(),
+            serialization: Serialization::default(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    pub fn serialization(mut self, serialization: Serialization) -> Self {
+        self.serialization = serialization;
+        self
+    }
+
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.properties.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Publishes envelopes from librdkafka's background thread.
+pub struct KafkaTransport {
+    producer: ThreadedProducer<DefaultProducerContext>,
+    topic: String,
+    serialization: Serialization,
+    /// Message key; the project id of the client's DSN.
+    key: String,
+}
+
+impl KafkaTransport {
+    pub fn new(options: &KafkaOptions, key: &str) -> Result<Self, rdkafka::error::KafkaError> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &options.brokers);
+        for (key, value) in &options.properties {
+            config.set(key, value);
+        }
+        Ok(Self {
+            producer: config.create()?,
+            topic: options.topic.clone(),
+            serialization: options.serialization,
+            key: key.to_string(),
+        })
+    }
+}
+
+impl Transport for KafkaTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        let payload = match self.serialization.serialize(&envelope) {
+            Ok(payload) => payload,
+            Err(e) => return eprintln!("Cannot serialize envelope for Kafka: {}", e),
+        };
+        let headers = OwnedHeaders::new().insert(Header {
+            key: "content-type",
+            value: Some(self.serialization.content_type()),
+        });
+        let record = BaseRecord::to(&self.topic)
+            .key(&self.key)
+            .payload(&payload)
+            .headers(headers);
+        match self.producer.send(record) {
+            Ok(()) => crate::health::record_sent(),
+            Err((e, _)) => {
+                crate::health::record_error(format!("kafka {}: {}", self.topic, e));
+                crate::health::record_dropped(crate::health::DropReason::QueueFull);
+                eprintln!("Dropping envelope, Kafka producer rejected it: {}", e);
+            }
+        }
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        self.producer.flush(timeout).is_ok()
+    }
+}
+
+fn state() -> &'static RwLock<Option<KafkaOptions>> {
+    static OPTIONS: OnceLock<RwLock<Option<KafkaOptions>>> = OnceLock::new();
+    OPTIONS.get_or_init(Default::default)
+}
+
+/// Publish to Kafka (`Some`) or use the HTTP transport (`None`) in clients created afterwards.
+pub fn configure(options: Option<KafkaOptions>) {
+    *state().write().unwrap() = options;
+}
+
+pub fn options() -> Option<KafkaOptions> {
+    state().read().unwrap().clone()
+}
+
+/// Transport factory publishing to Kafka; falls back to the SDK's default
+/// transport if the producer cannot be created.
+pub fn factory(options: KafkaOptions) -> Arc<dyn TransportFactory> {
+    use sentry::transports::DefaultTransportFactory;
+
+    Arc::new(move |client_options: &ClientOptions| -> Arc<dyn Transport> {
+        let key = client_options
+            .dsn
+            .as_ref()
+            .map(|dsn| dsn.project_id().to_string())
+            .unwrap_or_default();
+        match KafkaTransport::new(&options, &key) {
+            Ok(transport) => Arc::new(transport),
+            Err(e) => {
+                eprintln!(
+                    "Cannot create Kafka producer for {}: {}, using HTTP",
+                    options.brokers, e
+                );
+                DefaultTransportFactory.create_transport(client_options)
+            }
+        }
+    })
+}
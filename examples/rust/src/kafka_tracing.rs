@@ -0,0 +1,200 @@
+//! Tracing for application Kafka traffic through rdkafka (feature `kafka`);
+//! unrelated to the [Kafka transport](kafka), which ships envelopes.
+//!
+//! [`send`](kafka_tracing::send) publishes in a `queue.publish` span (a
+//! transaction when nothing is active) and writes `sentry-trace` / `baggage`
+//! into the message headers; delivery failures are captured with the topic
+//! and partition. On the consuming side [`process`](kafka_tracing::process)
+//! runs the handler in a `queue.process` transaction continued from those
+//! headers, on its own hub, and captures the handler's error:
+//!
+//! ```ignore
+//! let consumer: StreamConsumer<SentryConsumerContext> =
+//!     config.create_with_context(SentryConsumerContext)?;
+//! while let Ok(message) = consumer.recv().await {
+//!     kafka_tracing::process(&message, handle_order(&message)).await.ok();
+//! }
+//! ```
+//!
+//! [`SentryConsumerContext`](kafka_tracing::SentryConsumerContext) turns
+//! partition assignments into breadcrumbs and captures rebalance errors.
+
+use crate::{ops::Op, propagation, traced};
+use rdkafka::{
+    consumer::{ConsumerContext, Rebalance},
+    error::{KafkaError, KafkaResult},
+    message::{Header, Headers, Message, OwnedHeaders, ToBytes},
+    producer::{future_producer::OwnedDeliveryResult, FutureProducer, FutureRecord},
+    util::Timeout,
+    ClientContext, TopicPartitionList,
+};
+use sentry::{
+    protocol::{Breadcrumb, SpanStatus},
+    Hub, Level, SentryFutureExt, TransactionContext, TransactionOrSpan,
+};
+use std::{error::Error, future::Future, sync::Arc};
+
+/// `headers` with the trace headers of `span` appended.
+pub fn inject(span: &TransactionOrSpan, headers: Option<OwnedHeaders>) -> OwnedHeaders {
+    let mut headers = headers.unwrap_or_default();
+    for (key, value) in propagation::headers(span) {
+        headers = headers.insert(Header {
+            key,
+            value: Some(&value),
+        });
+    }
+    headers
+}
+
+/// Message headers with UTF-8 values, for continuing a trace.
+pub fn header_pairs<M: Message>(message: &M) -> Vec<(String, String)> {
+    let Some(headers) = message.headers() else {
+        return Vec::new();
+    };
+    headers
+        .iter()
+        .filter_map(|header| {
+            let value = std::str::from_utf8(header.value?).ok()?;
+            Some((header.key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Publish `record` with trace headers; see the [module docs](self).
+pub async fn send<K, P>(
+    producer: &FutureProducer,
+    mut record: FutureRecord<'_, K, P>,
+    queue_timeout: impl Into<Timeout>,
+) -> OwnedDeliveryResult
+where
+    K: ToBytes + ?Sized,
+    P: ToBytes + ?Sized,
+{
+    let topic = record.topic.to_string();
+    let span = traced::start(&topic, Op::QueuePublish);
+    span.set_data("messaging.system", "kafka".into());
+    span.set_data("messaging.destination.name", topic.clone().into());
+    record.headers = Some(inject(&span, record.headers.take()));
+    let partition = record.partition;
+
+    let result = producer.send(record, queue_timeout).await;
+    match &result {
+        Ok((partition, offset)) => {
+            span.set_data("messaging.kafka.partition", (*partition).into());
+            span.set_data("messaging.kafka.offset", (*offset).into());
+            span.set_status(SpanStatus::Ok);
+        }
+        Err((error, _)) => {
+            span.set_status(SpanStatus::InternalError);
+            capture(&Hub::current(), error, &topic, partition);
+        }
+    }
+    span.finish();
+    result
+}
+
+/// Run `handler` for `message` in a `queue.process` transaction; see the [module docs](self).
+pub async fn process<M, T, E, F>(message: &M, handler: F) -> Result<T, E>
+where
+    M: Message,
+    E: Error,
+    F: Future<Output = Result<T, E>>,
+{
+    let headers = header_pairs(message);
+    let headers = headers.iter().map(|(key, value)| (key.as_str(), value.as_str()));
+    let ctx = TransactionContext::continue_from_headers(message.topic(), Op::QueueProcess.as_str(), headers);
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    let transaction = hub.start_transaction(ctx);
+    transaction.set_data("messaging.system", "kafka".into());
+    transaction.set_data("messaging.kafka.offset", message.offset().into());
+    hub.configure_scope(|scope| {
+        scope.set_span(Some(transaction.clone().into()));
+        scope.set_tag("messaging.destination", message.topic());
+        scope.set_tag("messaging.kafka.partition", message.partition());
+    });
+
+    let result = handler.bind_hub(hub.clone()).await;
+    match &result {
+        Ok(_) => transaction.set_status(SpanStatus::Ok),
+        Err(e) => {
+            transaction.set_status(SpanStatus::InternalError);
+            hub.with_scope(
+                |scope| scope.set_extra("messaging.kafka.offset", message.offset().into()),
+                || hub.capture_error(e),
+            );
+        }
+    }
+    transaction.finish();
+    result
+}
+
+fn capture(hub: &Hub, error: &KafkaError, topic: &str, partition: Option<i32>) {
+    hub.with_scope(
+        |scope| {
+            scope.set_tag("messaging.destination", topic);
+            if let Some(partition) = partition {
+                scope.set_tag("messaging.kafka.partition", partition);
+            }
+        },
+        || hub.capture_error(error),
+    );
+}
+
+/// Consumer context reporting rebalances and commit failures.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SentryConsumerContext;
+
+impl ClientContext for SentryConsumerContext {
+    fn error(&self, error: KafkaError, reason: &str) {
+        Hub::current().add_breadcrumb(Breadcrumb {
+            category: Some("kafka".to_string()),
+            message: Some(format!("{}: {}", error, reason)),
+            level: Level::Error,
+            ..Default::default()
+        });
+    }
+}
+
+impl ConsumerContext for SentryConsumerContext {
+    fn post_rebalance(&self, rebalance: &Rebalance<'_>) {
+        let hub = Hub::current();
+        let (action, partitions) = match rebalance {
+            Rebalance::Assign(partitions) => ("assigned", partitions),
+            Rebalance::Revoke(partitions) => ("revoked", partitions),
+            Rebalance::Error(error) => {
+                return hub.with_scope(
+                    |scope| scope.set_tag("kafka.rebalance", "error"),
+                    || {
+                        hub.capture_error(error);
+                    },
+                )
+            }
+        };
+        hub.add_breadcrumb(Breadcrumb {
+            category: Some("kafka".to_string()),
+            message: Some(format!("Partitions {}: {}", action, describe(partitions))),
+            ..Default::default()
+        });
+    }
+
+    fn commit_callback(&self, result: KafkaResult<()>, offsets: &TopicPartitionList) {
+        if let Err(error) = result {
+            Hub::current().add_breadcrumb(Breadcrumb {
+                category: Some("kafka".to_string()),
+                message: Some(format!("Offset commit failed for {}: {}", describe(offsets), error)),
+                level: Level::Warning,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// `orders[0], orders[3]`
+fn describe(partitions: &TopicPartitionList) -> String {
+    partitions
+        .elements()
+        .iter()
+        .map(|element| format!("{}[{}]", element.topic(), element.partition()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,134 @@
+//! Workload details for services running in Kubernetes.
+//!
+//! Detected once from the service account mount and the environment; expose
+//! the pod fields through the downward API in the deployment manifest:
+//!
+//! ```yaml
+//! env:
+//!   - name: POD_NAME
+//!     valueFrom: { fieldRef: { fieldPath: metadata.name } }
+//!   - name: POD_NAMESPACE
+//!     valueFrom: { fieldRef: { fieldPath: metadata.namespace } }
+//!   - name: NODE_NAME
+//!     valueFrom: { fieldRef: { fieldPath: spec.nodeName } }
+//!   - name: CONTAINER_IMAGE
+//!     value: registry.example.com/app:1.4.2
+//! ```
+//!
+//! Without `POD_NAME` the hostname (the pod name by default) is used, and the
+//! namespace falls back to the service account's. The deployment comes from
+//! `DEPLOYMENT_NAME` or is derived from the pod name (`<deployment>-<replicaset
+//! hash>-<suffix>`). Events get a `kubernetes` context and `k8s.*` tags.
+
+use sentry::{protocol::Event, ClientOptions, Integration};
+use std::{collections::BTreeMap, env, fs, path::Path, sync::OnceLock};
+
+const SERVICE_ACCOUNT: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KubernetesInfo {
+    pub pod: Option<String>,
+    pub namespace: Option<String>,
+    pub node: Option<String>,
+    pub deployment: Option<String>,
+    pub container_image: Option<String>,
+}
+
+impl KubernetesInfo {
+    /// `None` outside Kubernetes.
+    pub fn detect() -> Option<Self> {
+        Self::from_env(
+            |name| env::var(name).ok().filter(|value| !value.is_empty()),
+            Path::new(SERVICE_ACCOUNT),
+        )
+    }
+
+    /// Detection with the environment lookup and service account
+    /// directory passed in.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>, service_account: &Path) -> Option<Self> {
+        if var("KUBERNETES_SERVICE_HOST").is_none() && !service_account.exists() {
+            return None;
+        }
+        let pod = var("POD_NAME").or_else(|| var("HOSTNAME"));
+        let namespace = var("POD_NAMESPACE").or_else(|| {
+            fs::read_to_string(service_account.join("namespace"))
+                .ok()
+                .map(|namespace| namespace.trim().to_string())
+                .filter(|namespace| !namespace.is_empty())
+        });
+        let deployment = var("DEPLOYMENT_NAME").or_else(|| pod.as_deref().and_then(deployment_from_pod));
+        Some(Self {
+            pod,
+            namespace,
+            node: var("NODE_NAME"),
+            deployment,
+            container_image: var("CONTAINER_IMAGE"),
+        })
+    }
+
+    fn fields(&self) -> [(&'static str, &Option<String>); 5] {
+        [
+            ("pod", &self.pod),
+            ("namespace", &self.namespace),
+            ("node", &self.node),
+            ("deployment", &self.deployment),
+            ("container_image", &self.container_image),
+        ]
+    }
+}
+
+/// `checkout-7d9f8c6b5-x2kqp` -> `checkout`; `None` for names that do
+/// not look like a Deployment's pods.
+pub fn deployment_from_pod(pod: &str) -> Option<String> {
+    let (rest, suffix) = pod.rsplit_once('-')?;
+    let (deployment, hash) = rest.rsplit_once('-')?;
+    let alphanumeric = |s: &str| s.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+    let plausible = suffix.len() == 5 && (6..=10).contains(&hash.len()) && alphanumeric(suffix) && alphanumeric(hash);
+    (plausible && !deployment.is_empty()).then(|| deployment.to_string())
+}
+
+#[derive(Debug, Default)]
+pub struct KubernetesIntegration {
+    info: OnceLock<Option<KubernetesInfo>>,
+}
+
+impl KubernetesIntegration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use fixed workload details instead of detecting them.
+    pub fn with_info(info: KubernetesInfo) -> Self {
+        Self {
+            info: OnceLock::from(Some(info)),
+        }
+    }
+}
+
+impl Integration for KubernetesIntegration {
+    fn name(&self) -> &'static str {
+        "kubernetes"
+    }
+
+    fn process_event(&self, mut event: Event<'static>, _options: &ClientOptions) -> Option<Event<'static>> {
+        let Some(info) = self.info.get_or_init(KubernetesInfo::detect) else {
+            return Some(event);
+        };
+        let mut context = BTreeMap::new();
+        for (key, value) in info.fields() {
+            if let Some(value) = value {
+                context.insert(key.to_string(), value.as_str().into());
+                // Tags set by the application win
+                event
+                    .tags
+                    .entry(format!("k8s.{}", key))
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        event
+            .contexts
+            .entry("kubernetes".to_string())
+            .or_insert(sentry::protocol::Context::Other(context));
+        Some(event)
+    }
+}
@@ -0,0 +1,343 @@
+//! Detection of tasks blocking the tokio runtime, the async counterpart of
+//! ANR detection.
+//!
+//! A probe task sleeps for `interval` in a loop and records how late it
+//! wakes up (scheduler lag). A monitor thread outside the runtime looks at the
+//! polls of [watched](lag::BlockingDetectExt::watch_blocking) futures: one
+//! running longer than `threshold` is blocking its worker thread, and a warning
+//! event is captured while it still is, on the task's hub and with the span
+//! it runs in (the span trace with the `spantrace` feature).
+//!
+//! ```ignore
+//! let _detector = lag::LagDetector::start(lag::LagOptions::default());
+//! tokio::spawn(handle_connection(socket).watch_blocking().instrument(span));
+//! ```
+//!
+//! When the probe stalls and no watched poll is to blame, a single event
+//! reports the runtime lag. Each blocking poll is reported once, runtime lag
+//! at most once per `report_interval`.
+//!
+//! Polls are recorded in a slot owned by the worker thread, so workers never
+//! contend on a shared lock. The hub and span a watched future runs with are
+//! taken once, on its first poll, not on every poll.
+
+use sentry::{Hub, Level};
+use std::{
+    collections::HashSet,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, Weak,
+    },
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LagOptions {
+    /// Probe and monitor period.
+    pub interval: Duration,
+    /// Polls and scheduler lag above this are reported.
+    pub threshold: Duration,
+    /// Minimum time between two runtime lag events.
+    pub report_interval: Duration,
+}
+
+impl Default for LagOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(100),
+            threshold: Duration::from_millis(500),
+            report_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Hub and span of a watched future, taken on its first poll.
+struct PollContext {
+    span: tracing::Span,
+    hub: Arc<Hub>,
+}
+
+/// The watched poll running on one worker thread, if any.
+struct WorkerSlot {
+    /// Start of the current poll in milliseconds since [`epoch`] plus one; 0 while idle.
+    started: AtomicU64,
+    /// Watched polls started on this thread, so each one is reported once.
+    polls: AtomicU64,
+    /// Only locked by this thread and by the monitor, never by other workers.
+    context: Mutex<Option<Arc<PollContext>>>,
+    thread: Option<String>,
+}
+
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// Slots of the threads that polled a watched future; dead threads are pruned by the monitor.
+fn slots() -> &'static Mutex<Vec<Weak<WorkerSlot>>> {
+    static SLOTS: OnceLock<Mutex<Vec<Weak<WorkerSlot>>>> = OnceLock::new();
+    SLOTS.get_or_init(Default::default)
+}
+
+thread_local! {
+    static SLOT: Arc<WorkerSlot> = {
+        let slot = Arc::new(WorkerSlot {
+            started: AtomicU64::new(0),
+            polls: AtomicU64::new(0),
+            context: Mutex::new(None),
+            thread: thread::current().name().map(String::from),
+        });
+        slots().lock().unwrap().push(Arc::downgrade(&slot));
+        slot
+    };
+}
+
+/// Number of running detectors; watched polls are only tracked while there is one.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+struct LagState {
+    options: LagOptions,
+    /// Last probe wake-up, milliseconds since `started`.
+    last_tick_ms: AtomicU64,
+    max_lag_ms: AtomicU64,
+    started: Instant,
+    stopped: AtomicBool,
+}
+
+pub struct LagDetector {
+    state: Arc<LagState>,
+}
+
+impl LagDetector {
+    /// Spawn the probe on the current runtime and start the monitor thread.
+    /// Must be called from within a tokio runtime.
+    pub fn start(options: LagOptions) -> Self {
+        let state = Arc::new(LagState {
+            options,
+            last_tick_ms: AtomicU64::new(0),
+            max_lag_ms: AtomicU64::new(0),
+            started: Instant::now(),
+            stopped: AtomicBool::new(false),
+        });
+        ACTIVE.fetch_add(1, Ordering::SeqCst);
+
+        tokio::spawn(probe(Arc::clone(&state)));
+        let hub = Hub::current();
+        let monitor_state = Arc::clone(&state);
+        let spawned = thread::Builder::new()
+            .name("runtime-lag".to_string())
+            .spawn(move || monitor(monitor_state, hub));
+        if let Err(e) = spawned {
+            eprintln!("Cannot start runtime lag monitor: {}", e);
+        }
+
+        Self { state }
+    }
+
+    /// Largest scheduler lag seen since the last call.
+    pub fn take_max_lag(&self) -> Duration {
+        Duration::from_millis(self.state.max_lag_ms.swap(0, Ordering::SeqCst))
+    }
+}
+
+impl Drop for LagDetector {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, Ordering::SeqCst);
+        ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+async fn probe(state: Arc<LagState>) {
+    let interval = state.options.interval;
+    while !state.stopped.load(Ordering::SeqCst) {
+        let before = Instant::now();
+        tokio::time::sleep(interval).await;
+        let lag = before.elapsed().saturating_sub(interval);
+        state.max_lag_ms.fetch_max(lag.as_millis() as u64, Ordering::SeqCst);
+        state
+            .last_tick_ms
+            .store(state.started.elapsed().as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+fn monitor(state: Arc<LagState>, hub: Arc<Hub>) {
+    let LagOptions {
+        interval,
+        threshold,
+        report_interval,
+    } = state.options;
+    let mut reported = HashSet::new();
+    let mut last_lag_report: Option<Instant> = None;
+
+    while !state.stopped.load(Ordering::SeqCst) {
+        thread::sleep(interval);
+
+        let mut blamed = false;
+        let mut blocking = Vec::new();
+        let mut running = HashSet::new();
+        let now_ms = epoch().elapsed().as_millis() as u64 + 1;
+        slots().lock().unwrap().retain(|slot| {
+            let Some(slot) = slot.upgrade() else {
+                return false;
+            };
+            let started = slot.started.load(Ordering::Acquire);
+            if started != 0 {
+                // The same thread and poll count identify one poll
+                let id = (Arc::as_ptr(&slot) as usize, slot.polls.load(Ordering::Acquire));
+                running.insert(id);
+                let blocked_for = Duration::from_millis(now_ms.saturating_sub(started));
+                if blocked_for >= threshold {
+                    blamed = true;
+                    if !reported.contains(&id) {
+                        blocking.push((id, slot, blocked_for));
+                    }
+                }
+            }
+            true
+        });
+        reported.retain(|id| running.contains(id));
+        // Capture outside the lock; the worker may finish its poll meanwhile
+        for (id, slot, blocked_for) in blocking {
+            let context = slot.context.lock().unwrap().clone();
+            if let Some(context) = context {
+                report_blocking(&context, slot.thread.as_deref(), blocked_for, threshold);
+                reported.insert(id);
+            }
+        }
+
+        let last_tick = Duration::from_millis(state.last_tick_ms.load(Ordering::SeqCst));
+        let stalled_for = state
+            .started
+            .elapsed()
+            .saturating_sub(last_tick)
+            .saturating_sub(interval);
+        let cooled_down = last_lag_report.is_none_or(|at| at.elapsed() >= report_interval);
+        if stalled_for >= threshold && !blamed && cooled_down {
+            hub.with_scope(
+                |scope| {
+                    scope.set_tag("watchdog", "tokio");
+                    scope.set_fingerprint(Some(&["tokio-runtime-lag"]));
+                    scope.set_extra("lag_ms", (stalled_for.as_millis() as u64).into());
+                    scope.set_extra("threshold_ms", (threshold.as_millis() as u64).into());
+                },
+                || hub.capture_message("Tokio runtime is not scheduling tasks", Level::Warning),
+            );
+            last_lag_report = Some(Instant::now());
+        }
+    }
+}
+
+fn report_blocking(poll: &PollContext, thread: Option<&str>, blocked_for: Duration, threshold: Duration) {
+    let name = poll.span.metadata().map_or("unknown", |metadata| metadata.name());
+    #[cfg(feature = "spantrace")]
+    let span_trace = poll.span.in_scope(tracing_error::SpanTrace::capture);
+    poll.hub.with_scope(
+        |scope| {
+            scope.set_tag("watchdog", "tokio");
+            scope.set_tag("blocking.span", name);
+            scope.set_fingerprint(Some(&["tokio-blocking", name]));
+            scope.set_extra("blocked_ms", (blocked_for.as_millis() as u64).into());
+            scope.set_extra("threshold_ms", (threshold.as_millis() as u64).into());
+            if let Some(thread) = thread {
+                scope.set_extra("thread", thread.into());
+            }
+            if let Some(metadata) = poll.span.metadata() {
+                scope.set_extra("span.target", metadata.target().into());
+                if let (Some(file), Some(line)) = (metadata.file(), metadata.line()) {
+                    scope.set_extra("span.location", format!("{}:{}", file, line).into());
+                }
+            }
+            #[cfg(feature = "spantrace")]
+            if let Some(context) = crate::error_chain::spantrace_context(&span_trace) {
+                scope.set_context("spantrace", context);
+            }
+        },
+        || {
+            poll.hub.capture_message(
+                &format!(
+                    "Task blocked the tokio runtime in {} for {} ms",
+                    name,
+                    blocked_for.as_millis()
+                ),
+                Level::Warning,
+            )
+        },
+    );
+}
+
+/// Future whose polls are timed by the running [`LagDetector`].
+pub struct Watched<F> {
+    future: Pin<Box<F>>,
+    context: Option<Arc<PollContext>>,
+}
+
+impl<F: Future> Future for Watched<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if ACTIVE.load(Ordering::Relaxed) == 0 {
+            return self.future.as_mut().poll(cx);
+        }
+        let context = self
+            .context
+            .get_or_insert_with(|| {
+                Arc::new(PollContext {
+                    span: tracing::Span::current(),
+                    hub: Hub::current(),
+                })
+            })
+            .clone();
+        SLOT.with(|slot| {
+            // Restores the outer poll when watched futures are nested
+            let _guard = Recording::start(slot, context);
+            self.future.as_mut().poll(cx)
+        })
+    }
+}
+
+/// A poll recorded in the worker's slot; the previous state is put back
+/// when the poll returns or unwinds.
+struct Recording<'a> {
+    slot: &'a WorkerSlot,
+    started: u64,
+    context: Option<Arc<PollContext>>,
+}
+
+impl<'a> Recording<'a> {
+    fn start(slot: &'a WorkerSlot, context: Arc<PollContext>) -> Self {
+        let previous = slot.context.lock().unwrap().replace(context);
+        slot.polls.fetch_add(1, Ordering::AcqRel);
+        let started = slot
+            .started
+            .swap(epoch().elapsed().as_millis() as u64 + 1, Ordering::AcqRel);
+        Self {
+            slot,
+            started,
+            context: previous,
+        }
+    }
+}
+
+impl Drop for Recording<'_> {
+    fn drop(&mut self) {
+        self.slot.started.store(self.started, Ordering::Release);
+        *self.slot.context.lock().unwrap() = self.context.take();
+    }
+}
+
+pub trait BlockingDetectExt: Future + Sized {
+    /// Report polls of this future that block the runtime.
+    /// Apply before `.instrument(span)`, so the span is entered when a poll starts.
+    fn watch_blocking(self) -> Watched<Self> {
+        Watched {
+            future: Box::pin(self),
+            context: None,
+        }
+    }
+}
+
+impl<F: Future> BlockingDetectExt for F {}
@@ -0,0 +1,161 @@
+//! Message-level tracing for RabbitMQ through lapin (feature `lapin`).
+//!
+//! [`publish`](lapin_integration::publish) sends in a `queue.publish` span
+//! and carries `sentry-trace` / `baggage` in the AMQP headers.
+//! [`process`](lapin_integration::process) runs one delivery in a
+//! `queue.process` transaction continued from those headers, then acks it on
+//! success or nacks it on failure, and records the outcome on the
+//! transaction. Handler errors are captured with the exchange and routing key
+//! as tags:
+//!
+//! ```ignore
+//! while let Some(delivery) = consumer.next().await {
+//!     let delivery = delivery?;
+//!     lapin_integration::process(&delivery, OnError::Reject, handle_invoice(&delivery.data)).await.ok();
+//! }
+//! ```
+
+use crate::{ops::Op, propagation, traced};
+use lapin::{
+    message::Delivery,
+    options::{BasicAckOptions, BasicNackOptions, BasicPublishOptions},
+    publisher_confirm::PublisherConfirm,
+    types::{AMQPValue, LongString},
+    BasicProperties, Channel,
+};
+use sentry::{protocol::SpanStatus, Hub, SentryFutureExt, TransactionContext, TransactionOrSpan};
+use std::{error::Error, future::Future, sync::Arc};
+
+/// What happens to a delivery whose handler failed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// Nack without requeue, so it goes to the dead-letter exchange if one is set.
+    #[default]
+    Reject,
+    /// Nack and requeue for another attempt.
+    Requeue,
+}
+
+/// `properties` with the trace headers of `span` added to its headers.
+pub fn inject(span: &TransactionOrSpan, properties: BasicProperties) -> BasicProperties {
+    let mut headers = properties.headers().clone().unwrap_or_default();
+    for (key, value) in propagation::headers(span) {
+        headers.insert(key.into(), AMQPValue::LongString(LongString::from(value)));
+    }
+    properties.with_headers(headers)
+}
+
+/// String-valued AMQP headers of `properties`, for continuing a trace.
+pub fn header_pairs(properties: &BasicProperties) -> Vec<(String, String)> {
+    let Some(headers) = properties.headers() else {
+        return Vec::new();
+    };
+    headers
+        .inner()
+        .iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                AMQPValue::LongString(value) => String::from_utf8(value.as_bytes().to_vec()).ok()?,
+                AMQPValue::ShortString(value) => value.to_string(),
+                _ => return None,
+            };
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Publish with trace headers; see the [module docs](self).
+pub async fn publish(
+    channel: &Channel,
+    exchange: &str,
+    routing_key: &str,
+    options: BasicPublishOptions,
+    payload: &[u8],
+    properties: BasicProperties,
+) -> lapin::Result<PublisherConfirm> {
+    let span = traced::start(&destination(exchange, routing_key), Op::QueuePublish);
+    span.set_data("messaging.system", "rabbitmq".into());
+    span.set_data("messaging.destination.name", exchange.into());
+    span.set_data("messaging.rabbitmq.routing_key", routing_key.into());
+    let properties = inject(&span, properties);
+
+    let result = channel
+        .basic_publish(exchange, routing_key, options, payload, properties)
+        .await;
+    if let Err(e) = &result {
+        span.set_status(SpanStatus::InternalError);
+        capture(&Hub::current(), e, exchange, routing_key);
+    } else {
+        span.set_status(SpanStatus::Ok);
+    }
+    span.finish();
+    result
+}
+
+/// Handle `delivery` in a `queue.process` transaction and ack or nack it;
+/// see the [module docs](self).
+pub async fn process<T, E, F>(delivery: &Delivery, on_error: OnError, handler: F) -> Result<T, E>
+where
+    E: Error,
+    F: Future<Output = Result<T, E>>,
+{
+    let exchange = delivery.exchange.as_str();
+    let routing_key = delivery.routing_key.as_str();
+    let headers = header_pairs(&delivery.properties);
+    let headers = headers.iter().map(|(key, value)| (key.as_str(), value.as_str()));
+    let name = destination(exchange, routing_key);
+    let ctx = TransactionContext::continue_from_headers(&name, Op::QueueProcess.as_str(), headers);
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    let transaction = hub.start_transaction(ctx);
+    transaction.set_data("messaging.system", "rabbitmq".into());
+    transaction.set_data("messaging.rabbitmq.redelivered", delivery.redelivered.into());
+    hub.configure_scope(|scope| {
+        scope.set_span(Some(transaction.clone().into()));
+        scope.set_tag("messaging.destination", exchange);
+        scope.set_tag("messaging.rabbitmq.routing_key", routing_key);
+    });
+
+    let result = handler.bind_hub(hub.clone()).await;
+    let (outcome, settled) = match &result {
+        Ok(_) => ("ack", delivery.ack(BasicAckOptions::default()).await),
+        Err(e) => {
+            hub.capture_error(e);
+            let requeue = on_error == OnError::Requeue;
+            let outcome = if requeue { "nack_requeue" } else { "nack" };
+            let options = BasicNackOptions {
+                requeue,
+                ..Default::default()
+            };
+            (outcome, delivery.nack(options).await)
+        }
+    };
+    transaction.set_data("messaging.rabbitmq.outcome", outcome.into());
+    transaction.set_status(match (&result, &settled) {
+        (Ok(_), Ok(_)) => SpanStatus::Ok,
+        _ => SpanStatus::InternalError,
+    });
+    if let Err(e) = &settled {
+        transaction.set_data("messaging.rabbitmq.settle_error", e.to_string().into());
+        capture(&hub, e, exchange, routing_key);
+    }
+    transaction.finish();
+    result
+}
+
+/// `exchange/routing.key`, or the routing key (queue) on the default exchange.
+fn destination(exchange: &str, routing_key: &str) -> String {
+    match exchange {
+        "" => routing_key.to_string(),
+        exchange => format!("{}/{}", exchange, routing_key),
+    }
+}
+
+fn capture(hub: &Hub, error: &lapin::Error, exchange: &str, routing_key: &str) {
+    hub.with_scope(
+        |scope| {
+            scope.set_tag("messaging.destination", exchange);
+            scope.set_tag("messaging.rabbitmq.routing_key", routing_key);
+        },
+        || hub.capture_error(error),
+    );
+}
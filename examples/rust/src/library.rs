@@ -0,0 +1,194 @@
+//! Error reporting for library crates that must not touch the host
+//! application's Sentry setup.
+//!
+//! A [`LibraryHub`](library::LibraryHub) has its own client, DSN, scope and
+//! rules. It installs no integrations (no panic hook, no global contexts) and
+//! never reads or changes the host's hub, so a library can report to its
+//! maintainers' project whether or not the host uses Sentry at all:
+//!
+//! ```ignore
+//! static HUB: LazyLock<LibraryHub> = LazyLock::new(|| {
+//!     LibraryHub::builder("billing-sdk", env!("BILLING_SDK_DSN"))
+//!         .release(concat!("billing-sdk@", env!("CARGO_PKG_VERSION")))
+//!         .sample_rate(0.1)
+//!         .build()
+//! });
+//!
+//! HUB.configure_scope(|scope| scope.set_tag("region", region));
+//! if let Err(e) = sync_invoices().await {
+//!     HUB.capture_error(&e);
+//! }
+//! ```
+//!
+//! Code run through [`run`](library::LibraryHub::run) or
+//! [`bind`](library::LibraryHub::bind) sees the library hub as
+//! `Hub::current()`, so `sentry::` calls made there, including those of
+//! instrumented dependencies, stay in the library. Outside of it a plain
+//! `sentry::configure_scope` would change the host's scope; libraries should
+//! forbid the global functions in their `clippy.toml`:
+//!
+//! ```toml
+//! disallowed-methods = [
+//!     { path = "sentry::init", reason = "libraries use LibraryHub" },
+//!     { path = "sentry::configure_scope", reason = "use LibraryHub::configure_scope" },
+//!     { path = "sentry::with_scope", reason = "use LibraryHub::with_scope" },
+//!     { path = "sentry::capture_error", reason = "use LibraryHub::capture_error" },
+//!     { path = "sentry::capture_message", reason = "use LibraryHub::capture_message" },
+//!     { path = "sentry::capture_event", reason = "use LibraryHub::capture_event" },
+//!     { path = "sentry::add_breadcrumb", reason = "use LibraryHub::add_breadcrumb" },
+//! ]
+//! ```
+
+use super::scrubbing::DataScrubber;
+use sentry::{
+    protocol::{Breadcrumb, Event},
+    types::Uuid,
+    Client, ClientOptions, Hub, Level, Scope, SentryFuture, SentryFutureExt, TransportFactory,
+};
+use std::{future::Future, sync::Arc, time::Duration};
+
+/// Tag naming the library on every event.
+pub const TAG: &str = "library";
+
+type BeforeSend = Arc<dyn Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync>;
+
+pub struct LibraryHubBuilder {
+    name: String,
+    dsn: String,
+    release: Option<String>,
+    environment: Option<String>,
+    sample_rate: f32,
+    scrubber: DataScrubber,
+    before_send: Option<BeforeSend>,
+    transport: Option<Arc<dyn TransportFactory>>,
+}
+
+impl LibraryHubBuilder {
+    pub fn release(mut self, release: &str) -> Self {
+        self.release = Some(release.to_string());
+        self
+    }
+
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.environment = Some(environment.to_string());
+        self
+    }
+
+    pub fn sample_rate(mut self, rate: f32) -> Self {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Replace the default scrubbing ([`DataScrubber::with_defaults`]).
+    pub fn scrubber(mut self, scrubber: DataScrubber) -> Self {
+        self.scrubber = scrubber;
+        self
+    }
+
+    /// Runs after scrubbing; return `None` to drop the event.
+    pub fn before_send(mut self, f: impl Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync + 'static) -> Self {
+        self.before_send = Some(Arc::new(f));
+        self
+    }
+
+    /// Deliver through `factory` instead of the SDK's default HTTP transport.
+    pub fn transport(mut self, factory: Arc<dyn TransportFactory>) -> Self {
+        self.transport = Some(factory);
+        self
+    }
+
+    /// An invalid or empty DSN gives a hub that drops everything.
+    pub fn build(self) -> LibraryHub {
+        let scrubber = self.scrubber;
+        let before_send = self.before_send;
+        let name = self.name;
+        let client = Client::from((
+            self.dsn.as_str(),
+            ClientOptions {
+                release: self.release.map(Into::into),
+                environment: self.environment.map(Into::into),
+                sample_rate: self.sample_rate,
+                default_integrations: false,
+                send_default_pii: false,
+                before_send: Some(Arc::new(move |mut event| {
+                    event.tags.entry(TAG.to_string()).or_insert_with(|| name.clone());
+                    scrubber.scrub_event(&mut event);
+                    match &before_send {
+                        Some(before_send) => before_send(event),
+                        None => Some(event),
+                    }
+                })),
+                transport: self.transport,
+                ..Default::default()
+            },
+        ));
+        LibraryHub {
+            hub: Arc::new(Hub::new(Some(Arc::new(client)), Arc::new(Scope::default()))),
+        }
+    }
+}
+
+/// A hub owned by a library. Deliberately doesn't hand out the inner
+/// `Hub`, so the only way to reach it is through these methods.
+pub struct LibraryHub {
+    hub: Arc<Hub>,
+}
+
+impl LibraryHub {
+    /// `name` is set as the `library` tag on every event.
+    pub fn builder(name: &str, dsn: &str) -> LibraryHubBuilder {
+        LibraryHubBuilder {
+            name: name.to_string(),
+            dsn: dsn.to_string(),
+            release: None,
+            environment: None,
+            sample_rate: 1.0,
+            scrubber: DataScrubber::with_defaults(),
+            before_send: None,
+            transport: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.hub.client().is_some_and(|client| client.is_enabled())
+    }
+
+    pub fn capture_error<E: std::error::Error + ?Sized>(&self, error: &E) -> Uuid {
+        self.hub.capture_error(error)
+    }
+
+    pub fn capture_message(&self, message: &str, level: Level) -> Uuid {
+        self.hub.capture_message(message, level)
+    }
+
+    pub fn capture_event(&self, event: Event<'static>) -> Uuid {
+        self.hub.capture_event(event)
+    }
+
+    pub fn add_breadcrumb(&self, breadcrumb: Breadcrumb) {
+        self.hub.add_breadcrumb(breadcrumb);
+    }
+
+    /// Change the library's scope; the host's scope is never affected.
+    pub fn configure_scope<R: Default>(&self, f: impl FnOnce(&mut Scope) -> R) -> R {
+        self.hub.configure_scope(f)
+    }
+
+    pub fn with_scope<R>(&self, configure: impl FnOnce(&mut Scope), f: impl FnOnce() -> R) -> R {
+        self.hub.with_scope(configure, f)
+    }
+
+    /// Run `f` with the library hub as the current hub.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        Hub::run(self.hub.clone(), f)
+    }
+
+    /// Poll `future` with the library hub as the current hub.
+    pub fn bind<F: Future>(&self, future: F) -> SentryFuture<F> {
+        future.bind_hub(self.hub.clone())
+    }
+
+    pub fn flush(&self, timeout: Duration) -> bool {
+        self.hub.client().is_none_or(|client| client.flush(Some(timeout)))
+    }
+}
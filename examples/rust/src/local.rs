@@ -0,0 +1,260 @@
+//! Pluggable envelope output for deployments without a reachable Bugsink.
+//!
+//! [`ObservabilityTransport`](local::ObservabilityTransport) is the extension
+//! point: [`FileTransport`](local::FileTransport) appends one JSON line per
+//! envelope to a size-rotated file, [`StdoutTransport`](local::StdoutTransport)
+//! prints the same lines for a log collector, and the HTTP transport implements
+//! it as well. Air-gapped installations ship the files through their own
+//! pipeline, or [replay](replay) them once Bugsink is reachable. The SDK still needs a DSN to enable the client; with a local
+//! transport it is only used for the envelope headers.
+//!
+//! ```toml
+//! [transport]
+//! kind = "file"    # or "stdout", "http"
+//! path = "/var/log/app/events.ndjson"
+//! max_bytes = 10485760
+//! max_files = 5
+//! ```
+
+use sentry::{
+    protocol::{Envelope, EnvelopeItem, Event, MonitorCheckIn, SessionAggregates, SessionUpdate, Transaction},
+    ClientOptions, Transport, TransportFactory,
+};
+use serde_json::{json, Value};
+use std::{
+    ffi::OsString,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::Duration,
+};
+
+/// Destination for envelopes. Called from the SDK's capture path, so
+/// implementations should not block for long.
+pub trait ObservabilityTransport: fmt::Debug + Send + Sync {
+    fn write(&self, envelope: &Envelope) -> io::Result<()>;
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// One NDJSON line: the event id and every item with its JSON payload.
+/// Attachments are listed by name and size only.
+pub fn to_json(envelope: &Envelope) -> Value {
+    let items: Vec<Value> = envelope
+        .items()
+        .filter_map(|item| {
+            let (ty, payload) = match item {
+                EnvelopeItem::Event(event) => ("event", serde_json::to_value(event)),
+                EnvelopeItem::Transaction(transaction) => ("transaction", serde_json::to_value(transaction)),
+                EnvelopeItem::SessionUpdate(session) => ("session", serde_json::to_value(session)),
+                EnvelopeItem::SessionAggregates(sessions) => ("sessions", serde_json::to_value(sessions)),
+                EnvelopeItem::MonitorCheckIn(check_in) => ("check_in", serde_json::to_value(check_in)),
+                EnvelopeItem::Attachment(attachment) => {
+                    return Some(json!({
+                        "type": "attachment",
+                        "filename": attachment.filename,
+                        "content_type": attachment.content_type,
+                        "length": attachment.buffer.len(),
+                    }))
+                }
+                _ => return None,
+            };
+            Some(json!({ "type": ty, "payload": payload.ok()? }))
+        })
+        .collect();
+
+    json!({
+        "event_id": envelope.uuid().map(|id| id.to_string()),
+        "items": items,
+    })
+}
+
+/// The envelope of a line written by [`to_json`]. Attachments were
+/// recorded by name and size only and are left out.
+pub fn from_json(line: &str) -> serde_json::Result<Envelope> {
+    let value: Value = serde_json::from_str(line)?;
+    let mut envelope = Envelope::new();
+    for item in value["items"].as_array().into_iter().flatten() {
+        let payload = item["payload"].clone();
+        match item["type"].as_str() {
+            Some("event") => envelope.add_item(serde_json::from_value::<Event>(payload)?),
+            Some("transaction") => envelope.add_item(serde_json::from_value::<Transaction>(payload)?),
+            Some("session") => envelope.add_item(serde_json::from_value::<SessionUpdate>(payload)?),
+            Some("sessions") => envelope.add_item(serde_json::from_value::<SessionAggregates>(payload)?),
+            Some("check_in") => envelope.add_item(serde_json::from_value::<MonitorCheckIn>(payload)?),
+            _ => {}
+        }
+    }
+    Ok(envelope)
+}
+
+fn to_line(envelope: &Envelope) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(&to_json(envelope))?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Appends envelopes as NDJSON; once the file exceeds `max_bytes` it is
+/// renamed to `<path>.1` (older files shift to `.2` ...) and a new one started.
+#[derive(Debug)]
+pub struct FileTransport {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Mutex<(File, u64)>,
+}
+
+impl FileTransport {
+    /// Open (or create) `path`, keeping 5 rotated files of 10 MiB.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            file: Mutex::new((file, size)),
+        })
+    }
+
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Rotated files kept next to the current one; 0 truncates instead.
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{}", index));
+        name.into()
+    }
+
+    fn rotate(&self) -> io::Result<File> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        append(&self.path)
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl ObservabilityTransport for FileTransport {
+    fn write(&self, envelope: &Envelope) -> io::Result<()> {
+        let line = to_line(envelope)?;
+        let mut guard = self.file.lock().unwrap();
+        let (file, size) = &mut *guard;
+        if *size > 0 && *size + line.len() as u64 > self.max_bytes {
+            *file = self.rotate()?;
+            *size = 0;
+        }
+        // One write per line keeps lines intact for concurrent readers
+        file.write_all(&line)?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.file.lock().unwrap().0.sync_data()
+    }
+}
+
+/// Prints envelopes as NDJSON to stdout, for container log collectors.
+#[derive(Debug, Default)]
+pub struct StdoutTransport;
+
+impl ObservabilityTransport for StdoutTransport {
+    fn write(&self, envelope: &Envelope) -> io::Result<()> {
+        io::stdout().lock().write_all(&to_line(envelope)?)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// SDK transport writing through an [`ObservabilityTransport`].
+pub struct LocalTransport(Arc<dyn ObservabilityTransport>);
+
+impl LocalTransport {
+    pub fn new(transport: Arc<dyn ObservabilityTransport>) -> Self {
+        Self(transport)
+    }
+}
+
+impl Transport for LocalTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        match self.0.write(&envelope) {
+            Ok(()) => crate::health::record_sent(),
+            Err(e) => {
+                crate::health::record_error(format!("{:?}: {}", self.0, e));
+                crate::health::record_dropped(crate::health::DropReason::Network);
+                eprintln!("Dropping envelope, {:?} failed: {}", self.0, e);
+            }
+        }
+    }
+
+    fn flush(&self, _timeout: Duration) -> bool {
+        self.0.flush().is_ok()
+    }
+}
+
+pub fn factory(transport: Arc<dyn ObservabilityTransport>) -> Arc<dyn TransportFactory> {
+    Arc::new(move |_: &ClientOptions| -> Arc<dyn Transport> { Arc::new(LocalTransport::new(transport.clone())) })
+}
+
+fn state() -> &'static RwLock<Option<Arc<dyn ObservabilityTransport>>> {
+    static TRANSPORT: OnceLock<RwLock<Option<Arc<dyn ObservabilityTransport>>>> = OnceLock::new();
+    TRANSPORT.get_or_init(Default::default)
+}
+
+/// Use `transport` (`None`: the default transport) for clients created afterwards.
+pub fn install(transport: Option<Arc<dyn ObservabilityTransport>>) {
+    *state().write().unwrap() = transport;
+}
+
+pub(crate) fn installed() -> Option<Arc<dyn ObservabilityTransport>> {
+    state().read().unwrap().clone()
+}
+
+#[cfg(feature = "http-transport")]
+impl ObservabilityTransport for crate::http::HttpTransport {
+    fn write(&self, envelope: &Envelope) -> io::Result<()> {
+        self.send_envelope(envelope.clone());
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match Transport::flush(self, Duration::from_secs(5)) {
+            true => Ok(()),
+            false => Err(io::ErrorKind::TimedOut.into()),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Captures `log` records from crates that don't use `tracing` (feature `log`).
+//!
+//! Every record is classified by the longest matching target prefix: records
+//! at or above its event level become events, records at or above its
+//! breadcrumb level become breadcrumbs, and the rest are ignored. Without a
+//! matching target, errors become events and warnings breadcrumbs. Records are
+//! still passed on to the wrapped logger, if any:
+//!
+//! ```ignore
+//! log_bridge::SentryLogger::new()
+//!     .wrap(env_logger::Builder::from_default_env().build())
+//!     .target("hyper", LevelFilter::Off, LevelFilter::Warn)
+//!     .target("legacy_billing", LevelFilter::Warn, LevelFilter::Info)
+//!     .install()?;
+//! ```
+//!
+//! Events carry the target as their logger, so `ignore_loggers` and the
+//! `logger` grouping in Bugsink apply to them. Don't combine with
+//! `tracing_log::LogTracer`, which would report the same records a second
+//! time through the tracing layer.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use sentry::{
+    protocol::{Breadcrumb, Event, Value},
+    Level,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogAction {
+    Ignore,
+    Breadcrumb,
+    Event,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Thresholds {
+    event: LevelFilter,
+    breadcrumb: LevelFilter,
+}
+
+impl Thresholds {
+    fn action(&self, level: log::Level) -> LogAction {
+        if level <= self.event {
+            LogAction::Event
+        } else if level <= self.breadcrumb {
+            LogAction::Breadcrumb
+        } else {
+            LogAction::Ignore
+        }
+    }
+
+    fn max(&self) -> LevelFilter {
+        self.event.max(self.breadcrumb)
+    }
+}
+
+pub struct SentryLogger {
+    default: Thresholds,
+    /// Target prefixes, longest first.
+    targets: Vec<(String, Thresholds)>,
+    dest: Option<Box<dyn Log>>,
+}
+
+impl Default for SentryLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SentryLogger {
+    /// Errors become events, warnings breadcrumbs.
+    pub fn new() -> Self {
+        Self {
+            default: Thresholds {
+                event: LevelFilter::Error,
+                breadcrumb: LevelFilter::Warn,
+            },
+            targets: Vec::new(),
+            dest: None,
+        }
+    }
+
+    /// Pass every record on to `dest` as well.
+    pub fn wrap(mut self, dest: impl Log + 'static) -> Self {
+        self.dest = Some(Box::new(dest));
+        self
+    }
+
+    /// Thresholds for targets without a more specific entry.
+    pub fn default_levels(mut self, event: LevelFilter, breadcrumb: LevelFilter) -> Self {
+        self.default = Thresholds { event, breadcrumb };
+        self
+    }
+
+    /// Thresholds for `prefix` and the targets below it (`hyper` covers `hyper::client`).
+    pub fn target(mut self, prefix: &str, event: LevelFilter, breadcrumb: LevelFilter) -> Self {
+        self.targets.retain(|(existing, _)| existing != prefix);
+        self.targets
+            .push((prefix.to_string(), Thresholds { event, breadcrumb }));
+        self.targets.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    pub fn action(&self, target: &str, level: log::Level) -> LogAction {
+        self.thresholds(target).action(level)
+    }
+
+    fn thresholds(&self, target: &str) -> Thresholds {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, thresholds)| *thresholds)
+    }
+
+    /// Highest level any target or the wrapped logger wants to see.
+    pub fn max_level(&self) -> LevelFilter {
+        let own = self
+            .targets
+            .iter()
+            .map(|(_, thresholds)| thresholds.max())
+            .fold(self.default.max(), Ord::max);
+        match &self.dest {
+            Some(_) => LevelFilter::Trace,
+            None => own,
+        }
+    }
+
+    /// Install as the global logger.
+    pub fn install(self) -> Result<(), log::SetLoggerError> {
+        let max_level = self.max_level();
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for SentryLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.action(metadata.target(), metadata.level()) != LogAction::Ignore
+            || self.dest.as_ref().is_some_and(|dest| dest.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        match self.action(record.target(), record.level()) {
+            LogAction::Event => {
+                sentry::capture_event(event_from_record(record));
+            }
+            LogAction::Breadcrumb => sentry::add_breadcrumb(breadcrumb_from_record(record)),
+            LogAction::Ignore => {}
+        }
+        if let Some(dest) = &self.dest {
+            if dest.enabled(record.metadata()) {
+                dest.log(record);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(dest) = &self.dest {
+            dest.flush();
+        }
+    }
+}
+
+pub fn level(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::Error,
+        log::Level::Warn => Level::Warning,
+        log::Level::Info => Level::Info,
+        log::Level::Debug | log::Level::Trace => Level::Debug,
+    }
+}
+
+pub fn event_from_record(record: &Record<'_>) -> Event<'static> {
+    let mut event = Event {
+        level: level(record.level()),
+        logger: Some(record.target().to_string()),
+        message: Some(record.args().to_string()),
+        ..Default::default()
+    };
+    if let Some(module) = record.module_path() {
+        event.extra.insert("log.module_path".to_string(), module.into());
+    }
+    if let (Some(file), Some(line)) = (record.file(), record.line()) {
+        event
+            .extra
+            .insert("log.location".to_string(), format!("{}:{}", file, line).into());
+    }
+    event
+}
+
+pub fn breadcrumb_from_record(record: &Record<'_>) -> Breadcrumb {
+    let mut breadcrumb = Breadcrumb {
+        ty: "log".to_string(),
+        category: Some(record.target().to_string()),
+        level: level(record.level()),
+        message: Some(record.args().to_string()),
+        ..Default::default()
+    };
+    if let Some(module) = record.module_path() {
+        breadcrumb.data.insert("module_path".to_string(), Value::from(module));
+    }
+    breadcrumb
+}
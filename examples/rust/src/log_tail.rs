@@ -0,0 +1,157 @@
+//! In-memory tail of the application's log output, attached as
+//! `log-tail.txt` to every error, fatal and crash event.
+//!
+//! Breadcrumbs keep a few curated lines; the tail keeps the last N KB of
+//! whatever was logged, which is usually what is needed to reconstruct the
+//! seconds before a failure. Feed it by teeing the log writer:
+//!
+//! ```ignore
+//! use tracing_subscriber::fmt::writer::MakeWriterExt;
+//!
+//! tracing_subscriber::fmt().with_writer(std::io::stderr.and(log_tail::writer)).init();
+//! ```
+//!
+//! Enabled with [`Config::log_tail_bytes`](config::Config::log_tail_bytes);
+//! the attachment is capped at the [attachment limit](attachments::AttachmentLimits)
+//! and starts at the first complete line.
+
+use sentry::{
+    protocol::{Envelope, EnvelopeItem},
+    ClientOptions, Level, Transport, TransportFactory,
+};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::Duration,
+};
+
+pub const FILENAME: &str = "log-tail.txt";
+
+/// Ring buffer holding the last `capacity` bytes written to it.
+#[derive(Debug)]
+pub struct LogTail {
+    buffer: Mutex<VecDeque<u8>>,
+    capacity: usize,
+}
+
+impl LogTail {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, bytes: &[u8]) {
+        let bytes = &bytes[bytes.len().saturating_sub(self.capacity)..];
+        let mut buffer = self.buffer.lock().unwrap();
+        let overflow = (buffer.len() + bytes.len()).saturating_sub(self.capacity);
+        buffer.drain(..overflow);
+        buffer.extend(bytes);
+    }
+
+    /// At most the last `max_bytes`, starting at a line boundary once the
+    /// buffer has wrapped or been cut.
+    pub fn snapshot(&self, max_bytes: usize) -> Vec<u8> {
+        let buffer = self.buffer.lock().unwrap();
+        let skip = buffer.len().saturating_sub(max_bytes);
+        let mut tail: Vec<u8> = buffer.iter().skip(skip).copied().collect();
+        let wrapped = skip > 0 || buffer.len() == self.capacity;
+        drop(buffer);
+        if wrapped {
+            let start = tail.iter().position(|&b| b == b'\n').map_or(tail.len(), |i| i + 1);
+            tail.drain(..start);
+        }
+        tail
+    }
+
+    pub fn clear(&self) {
+        self.buffer.lock().unwrap().clear();
+    }
+}
+
+fn state() -> &'static RwLock<Option<Arc<LogTail>>> {
+    static TAIL: OnceLock<RwLock<Option<Arc<LogTail>>>> = OnceLock::new();
+    TAIL.get_or_init(Default::default)
+}
+
+/// Keep the last `capacity` bytes of log output (`None` disables).
+pub fn install(capacity: Option<usize>) {
+    *state().write().unwrap() = capacity.map(|capacity| Arc::new(LogTail::new(capacity)));
+}
+
+pub fn installed() -> Option<Arc<LogTail>> {
+    state().read().unwrap().clone()
+}
+
+/// Writer into the installed tail; a no-op while none is installed.
+pub fn writer() -> LogTailWriter {
+    LogTailWriter(installed())
+}
+
+pub struct LogTailWriter(Option<Arc<LogTail>>);
+
+impl io::Write for LogTailWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(tail) = &self.0 {
+            tail.push(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Adds the tail to envelopes carrying an error-level event.
+pub struct LogTailTransport {
+    inner: Arc<dyn Transport>,
+    tail: Arc<LogTail>,
+}
+
+impl LogTailTransport {
+    pub fn new(inner: Arc<dyn Transport>, tail: Arc<LogTail>) -> Self {
+        Self { inner, tail }
+    }
+}
+
+impl Transport for LogTailTransport {
+    fn send_envelope(&self, mut envelope: Envelope) {
+        let is_error = envelope.event().is_some_and(|event| event.level >= Level::Error);
+        let attached = envelope.items().any(|item| match item {
+            EnvelopeItem::Attachment(attachment) => attachment.filename == FILENAME,
+            _ => false,
+        });
+        if is_error && !attached {
+            let tail = self.tail.snapshot(crate::attachments::limits().max_bytes);
+            if !tail.is_empty() {
+                let attachment = crate::attachments::Attachment::bytes(FILENAME, tail);
+                envelope.add_item(attachment.into_sentry());
+            }
+        }
+        self.inner.send_envelope(envelope);
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        self.inner.flush(timeout)
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        self.inner.shutdown(timeout)
+    }
+}
+
+pub(crate) fn wrap(factory: Arc<dyn TransportFactory>) -> Arc<dyn TransportFactory> {
+    let Some(tail) = installed() else {
+        return factory;
+    };
+    Arc::new(move |options: &ClientOptions| -> Arc<dyn Transport> {
+        Arc::new(LogTailTransport::new(factory.create_transport(options), tail.clone()))
+    })
+}
+
+pub(crate) fn is_enabled() -> bool {
+    installed().is_some()
+}
@@ -0,0 +1,1930 @@
+//! Bugsink/Sentry SDK Integration Example for Rust
+//! ================================================
+//!
+//! This example demonstrates comprehensive error tracking integration
+//! using the Sentry SDK with a self-hosted Bugsink server.
+//!
+//! Build and run (dependencies and optional features are declared in Cargo.toml):
+//!     cargo run
+//!     cargo run --features "http-transport offline-spool axum"
+//!
+//! Static musl builds (feature `rustls`, no OpenSSL / system CA store):
+//!     cargo build --release --target x86_64-unknown-linux-musl --no-default-features --features rustls
+//!
+//! DSN Format:
+//!     https://<project-key>@<your-bugsink-host>/<project-id>
+
+use sentry::{
+    integrations::tracing::EventFilter,
+    protocol::{Breadcrumb, Event, User, Value},
+    ClientOptions, Hub, Level, Scope, TransactionContext,
+};
+use std::{collections::BTreeMap, env, process::ExitCode, sync::Arc, time::Duration};
+use tracing::{instrument, warn};
+
+pub use capture::CaptureResultExt;
+use ops::Op;
+
+// =============================================================================
+// CONFIGURATION
+// =============================================================================
+
+pub mod config;
+
+// =============================================================================
+// TRANSPORT
+// =============================================================================
+
+mod transport;
+
+// =============================================================================
+// CUSTOM ERRORS
+// =============================================================================
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Business logic error: {0}")]
+    BusinessError(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+
+    #[error("External service error: {0}")]
+    ExternalServiceError(String),
+
+    #[error("Validation error: {0}")]
+    ValidationError(String),
+}
+
+/// Expected business error that should not be reported to Sentry
+#[derive(Debug, thiserror::Error)]
+#[error("Expected business error: {0}")]
+pub struct ExpectedBusinessError(pub String);
+
+// =============================================================================
+// SENTRY SERVICE
+// =============================================================================
+
+/// Service for Sentry operations.
+/// Provides comprehensive error tracking and performance monitoring.
+pub struct SentryService {
+    _guard: Option<sentry::ClientInitGuard>,
+}
+
+impl SentryService {
+    /// Create and initialize a new SentryService from environment variables.
+    pub fn new() -> Self {
+        Self::with_config(config::Config::from_env())
+    }
+
+    /// Create and initialize a new SentryService from explicit settings.
+    pub fn with_config(config: config::Config) -> Self {
+        let guard = Self::init_sentry(&config, None);
+        Self { _guard: guard }
+    }
+
+    /// Configure the service programmatically.
+    pub fn builder() -> SentryServiceBuilder {
+        SentryServiceBuilder::new()
+    }
+
+    /// Initialize Sentry SDK.
+    fn init_sentry(config: &config::Config, before_send: Option<BeforeSend>) -> Option<sentry::ClientInitGuard> {
+        if config.dsn.is_empty() || config.dsn.contains("your-project-key") {
+            println!("Sentry DSN not configured, running without error tracking");
+            return None;
+        }
+
+        // Application hook runs after the built-in processing
+        let pipeline: BeforeSend = match before_send {
+            Some(app_before_send) => Arc::new(move |event| app_before_send(before_send_handler(event)?)),
+            None => Arc::new(before_send_handler),
+        };
+        let before_send: BeforeSend = Arc::new(move |event| {
+            let event = pipeline(event);
+            if event.is_none() {
+                health::record_dropped(health::DropReason::BeforeSend);
+            }
+            event
+        });
+
+        // The transport factory picks up the local transport, limiter and retry policy
+        local::install(config.transport.clone());
+        routing::install(config.routes.clone());
+        replay::record_to(config.record_dir.clone());
+        sessions::configure(config.session_tracking);
+        attachments::configure(config.attachment_limits);
+        log_tail::install(config.log_tail_bytes);
+        if let Some(options) = config.memory_monitor.clone() {
+            memory::install(options);
+        }
+        #[cfg(feature = "kafka")]
+        kafka::configure(config.kafka.clone());
+        #[cfg(feature = "sqlx")]
+        sqlx_integration::configure(sqlx_integration::SqlxOptions {
+            slow_query_threshold: config.slow_query_threshold,
+        });
+        #[cfg(feature = "nats")]
+        nats_integration::configure(config.nats.clone());
+        #[cfg(feature = "profiling")]
+        profiling::configure(config.profiles_sample_rate);
+        ratelimit::install(config.rate_limits);
+        backoff::install_retry_policy(config.retry_policy.clone());
+        #[cfg(any(feature = "rustls", feature = "http-transport"))]
+        {
+            if let Err(e) = config.tls.validate() {
+                eprintln!("Invalid TLS settings: {}", e);
+            }
+            tls::configure(config.tls.clone());
+        }
+        #[cfg(feature = "http-transport")]
+        {
+            if let Err(e) = config.http.validate() {
+                eprintln!("Invalid HTTP transport settings, events will not be sent: {}", e);
+            }
+            http::configure(config.http.clone());
+        }
+
+        let retention = config.breadcrumb_retention();
+        let mut options = sentry::apply_defaults(ClientOptions {
+            release: Some(format!("my-app@{}", config.release).into()),
+            environment: Some(config.environment.clone().into()),
+            debug: config.debug(),
+            attach_stacktrace: true,
+            send_default_pii: false,
+            max_breadcrumbs: retention.scope_capacity(),
+            // Sampling happens in the pipeline so it can be changed at runtime
+            sample_rate: 1.0,
+            traces_sampler: Some(Arc::new(|ctx| {
+                if filters::current().ignores_transaction(ctx.name()) {
+                    return 0.0;
+                }
+                let rate = sampling::sample_rate(ctx, runtime_config::current().traces_sample_rate);
+                #[cfg(feature = "profiling")]
+                let rate = profiling::sample(ctx, rate);
+                rate
+            })),
+            before_send: Some(before_send),
+            before_breadcrumb: Some(Arc::new(before_breadcrumb_handler)),
+            auto_session_tracking: config.session_tracking.is_enabled(),
+            session_mode: config.session_tracking.session_mode(),
+            transport: transport::factory(),
+            integrations: vec![
+                Arc::new(system::SystemContextIntegration::new()),
+                Arc::new(kubernetes::KubernetesIntegration::new()),
+                Arc::new(cloud::CloudIntegration),
+                #[cfg(feature = "otel")]
+                Arc::new(otel::OtelIntegration),
+            ],
+            ..Default::default()
+        });
+        // Panics are reported by the crash module's hook, which also flushes;
+        // applications opt in with `install_panic_handler`
+        options.integrations.retain(|integration| integration.name() != "panic");
+        options.default_integrations = false;
+        let guard = sentry::init((config.dsn.as_str(), options));
+
+        runtime_config::ConfigHandle.replace(runtime_config::RuntimeConfig::from(config));
+        scrubbing::install(scrubbing::DataScrubber::from_config(config));
+        secrets::configure(config.detect_secrets.then(secrets::SecretScanner::default));
+        origins::install(config.origins.clone());
+        breadcrumbs::set_event_limits((!retention.per_category.is_empty()).then_some(retention));
+        fingerprint::install(fingerprint::FingerprintRules::new(config.fingerprint_rules.clone()));
+        ownership::install(ownership::OwnershipRules::new(config.ownership_rules.clone()));
+        severity::install(config.severity.clone());
+        sampling::install(config.sampling.clone());
+        error_sampling::install(config.error_sampling.clone());
+        dedupe::install(
+            config
+                .dedupe_window
+                .map(|window| dedupe::DedupePolicy::new(window, config.dedupe_limit)),
+        );
+        aggregation::install(config.aggregation.clone());
+        spike::install(config.spike_protection.clone());
+
+        if config.cloud_metadata {
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => {
+                    runtime.spawn(cloud::enrich(cloud::DEFAULT_TIMEOUT));
+                }
+                Err(_) => eprintln!("Cloud metadata lookup needs a tokio runtime, skipped"),
+            }
+        }
+
+        // Set global tags
+        sentry::configure_scope(|scope| {
+            scope.set_tag("app.component", "backend");
+            for (key, value) in release_info::current().extras() {
+                scope.set_extra(key, value);
+            }
+            for (key, value) in &config.tags {
+                scope.set_tag(key, value);
+            }
+        });
+
+        println!("Sentry initialized for environment: {}", config.environment);
+
+        Some(guard)
+    }
+
+    /// Set user context.
+    pub fn set_user(&self, id: &str, email: Option<&str>, username: Option<&str>, ip_address: Option<&str>) {
+        sentry::configure_scope(|scope| {
+            scope.set_user(Some(User {
+                id: Some(id.to_string()),
+                email: email.map(String::from),
+                username: username.map(String::from),
+                ip_address: ip_address.and_then(|s| s.parse().ok()),
+                ..Default::default()
+            }));
+        });
+    }
+
+    /// Set user with additional data.
+    pub fn set_user_with_data(
+        &self,
+        id: &str,
+        email: Option<&str>,
+        username: Option<&str>,
+        ip_address: Option<&str>,
+        data: BTreeMap<String, Value>,
+    ) {
+        sentry::configure_scope(|scope| {
+            scope.set_user(Some(User {
+                id: Some(id.to_string()),
+                email: email.map(String::from),
+                username: username.map(String::from),
+                ip_address: ip_address.and_then(|s| s.parse().ok()),
+                other: data,
+            }));
+        });
+    }
+
+    /// Clear user context.
+    pub fn clear_user(&self) {
+        sentry::configure_scope(|scope| {
+            scope.set_user(None);
+        });
+    }
+
+    /// Add a breadcrumb.
+    pub fn add_breadcrumb(&self, message: &str, category: &str, level: Level, data: Option<BTreeMap<String, Value>>) {
+        sentry::add_breadcrumb(Breadcrumb {
+            message: Some(message.to_string()),
+            category: Some(category.to_string()),
+            level,
+            data: data.unwrap_or_default(),
+            ..Default::default()
+        });
+    }
+
+    /// Set a tag.
+    pub fn set_tag(&self, key: &str, value: &str) {
+        sentry::configure_scope(|scope| {
+            scope.set_tag(key, value);
+        });
+    }
+
+    /// Set extra context.
+    pub fn set_extra<V: Into<Value>>(&self, key: &str, value: V) {
+        sentry::configure_scope(|scope| {
+            scope.set_extra(key, value.into());
+        });
+    }
+
+    /// Set custom context.
+    pub fn set_context(&self, name: &str, context: BTreeMap<String, Value>) {
+        sentry::configure_scope(|scope| {
+            scope.set_context(name, sentry::protocol::Context::Other(context));
+        });
+    }
+
+    /// Capture an error.
+    pub fn capture_error<E: std::error::Error + ?Sized>(&self, error: &E) -> sentry::types::Uuid {
+        sentry::capture_error(error)
+    }
+
+    /// Capture an error with extra context.
+    pub fn capture_error_with_context<E: std::error::Error + ?Sized>(
+        &self,
+        error: &E,
+        extra_context: BTreeMap<String, Value>,
+    ) -> sentry::types::Uuid {
+        sentry::with_scope(
+            |scope| {
+                for (key, value) in extra_context {
+                    scope.set_extra(&key, value);
+                }
+            },
+            || sentry::capture_error(error),
+        )
+    }
+
+    /// Capture a message.
+    pub fn capture_message(&self, message: &str, level: Level) -> sentry::types::Uuid {
+        sentry::capture_message(message, level)
+    }
+
+    /// Capture a message with extra context.
+    pub fn capture_message_with_context(
+        &self,
+        message: &str,
+        level: Level,
+        extra_context: BTreeMap<String, Value>,
+    ) -> sentry::types::Uuid {
+        sentry::with_scope(
+            |scope| {
+                for (key, value) in extra_context {
+                    scope.set_extra(&key, value);
+                }
+            },
+            || sentry::capture_message(message, level),
+        )
+    }
+
+    /// Execute a closure within a transaction.
+    pub fn with_transaction<F, R>(&self, name: &str, op: impl AsRef<str>, f: F) -> R
+    where
+        F: FnOnce(&sentry::TransactionOrSpan) -> R,
+    {
+        ops::debug_assert_canonical(op.as_ref());
+        let ctx = TransactionContext::new(name, op.as_ref());
+        let transaction = sentry::start_transaction(ctx);
+
+        // Bind transaction to scope
+        sentry::configure_scope(|scope| {
+            scope.set_span(Some(transaction.clone().into()));
+        });
+
+        let result = f(&transaction.clone().into());
+
+        transaction.finish();
+        result
+    }
+
+    /// Execute a closure within a child span.
+    pub fn with_span<F, R>(&self, parent: &sentry::TransactionOrSpan, op: impl AsRef<str>, description: &str, f: F) -> R
+    where
+        F: FnOnce(&sentry::Span) -> R,
+    {
+        ops::debug_assert_canonical(op.as_ref());
+        let span = parent.start_child(op.as_ref(), &scrubbing::current().scrub_str(description));
+        let result = f(&span);
+        span.finish();
+        result
+    }
+
+    /// Async `with_transaction`: the transaction stays the active span across
+    /// await points, also when the task moves between worker threads.
+    pub async fn with_transaction_async<F, Fut, R>(&self, name: &str, op: impl AsRef<str>, f: F) -> R
+    where
+        F: FnOnce(sentry::TransactionOrSpan) -> Fut,
+        Fut: std::future::Future<Output = R>,
+    {
+        ops::debug_assert_canonical(op.as_ref());
+        let transaction: sentry::TransactionOrSpan =
+            sentry::start_transaction(TransactionContext::new(name, op.as_ref())).into();
+        traced::run_async(transaction.clone(), f(transaction)).await
+    }
+
+    /// Async `with_span`.
+    pub async fn with_span_async<F, Fut, R>(
+        &self,
+        parent: &sentry::TransactionOrSpan,
+        op: impl AsRef<str>,
+        description: &str,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(sentry::TransactionOrSpan) -> Fut,
+        Fut: std::future::Future<Output = R>,
+    {
+        ops::debug_assert_canonical(op.as_ref());
+        let span: sentry::TransactionOrSpan = parent
+            .start_child(op.as_ref(), &scrubbing::current().scrub_str(description))
+            .into();
+        traced::run_async(span.clone(), f(span)).await
+    }
+
+    /// Execute a closure within a scope.
+    pub fn with_scope<C, F, R>(&self, configure: C, f: F) -> R
+    where
+        C: FnOnce(&mut Scope),
+        F: FnOnce() -> R,
+    {
+        sentry::with_scope(configure, f)
+    }
+}
+
+impl Default for SentryService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Application `before_send` hook.
+pub type BeforeSend = Arc<dyn Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync>;
+
+/// Programmatic configuration for SentryService.
+///
+/// ```ignore
+/// let sentry = SentryService::builder()
+///     .dsn(&settings.sentry_dsn)
+///     .environment("staging")
+///     .release(env!("CARGO_PKG_VERSION"))
+///     .traces_sample_rate(0.25)
+///     .build();
+/// ```
+pub struct SentryServiceBuilder {
+    config: config::Config,
+    before_send: Option<BeforeSend>,
+}
+
+impl SentryServiceBuilder {
+    /// Start from defaults; nothing is read from the environment.
+    pub fn new() -> Self {
+        Self::from_config(config::Config::default())
+    }
+
+    /// Start from SENTRY_DSN / ENVIRONMENT / APP_VERSION and override selectively.
+    pub fn from_env() -> Self {
+        Self::from_config(config::Config::from_env())
+    }
+
+    pub fn from_config(config: config::Config) -> Self {
+        Self {
+            config,
+            before_send: None,
+        }
+    }
+
+    pub fn dsn(mut self, dsn: &str) -> Self {
+        self.config.dsn = dsn.to_string();
+        self
+    }
+
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.config.environment = environment.to_string();
+        self
+    }
+
+    pub fn release(mut self, release: &str) -> Self {
+        self.config.release = release.to_string();
+        self
+    }
+
+    /// Error event sample rate (0.0 - 1.0).
+    pub fn sample_rate(mut self, rate: f32) -> Self {
+        self.config.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Add a per-error-class sample rate; rules are tried in the order added.
+    pub fn error_sampling_rule(mut self, rule: error_sampling::ErrorSamplingRule) -> Self {
+        self.config.error_sampling.push(rule);
+        self
+    }
+
+    /// Transaction sample rate (0.0 - 1.0).
+    pub fn traces_sample_rate(mut self, rate: f32) -> Self {
+        self.config.traces_sample_rate = Some(rate.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Add a per-transaction sample rate; rules are tried in the order added.
+    pub fn sampling_rule(mut self, rule: sampling::SamplingRule) -> Self {
+        self.config.sampling.push(rule);
+        self
+    }
+
+    /// Decide the sample rate of a transaction before the rules; return
+    /// `None` to leave it to them.
+    pub fn traces_sampler(
+        mut self,
+        sampler: impl Fn(&TransactionContext) -> Option<f32> + Send + Sync + 'static,
+    ) -> Self {
+        self.config.sampling = self.config.sampling.with_sampler(Arc::new(sampler));
+        self
+    }
+
+    pub fn max_breadcrumbs(mut self, max: usize) -> Self {
+        self.config.max_breadcrumbs = max;
+        self
+    }
+
+    /// Keep at most `limit` breadcrumbs of `category` per event, separate from `max_breadcrumbs`.
+    pub fn breadcrumb_limit(mut self, category: &str, limit: usize) -> Self {
+        self.config.breadcrumb_limits.insert(category.to_string(), limit);
+        self
+    }
+
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.config.debug = Some(debug);
+        self
+    }
+
+    /// Redact fields with this name everywhere in the event, in addition to the defaults.
+    pub fn scrub_key(mut self, key: &str) -> Self {
+        self.config.scrub_keys.push(key.to_string());
+        self
+    }
+
+    /// Replace matches of `regex` inside strings with `[REDACTED:<name>]`.
+    pub fn scrub_pattern(mut self, name: &str, regex: &str) -> Self {
+        self.config.scrub_patterns.insert(name.to_string(), regex.to_string());
+        self
+    }
+
+    /// Redact the value of this URL query parameter, in addition to the defaults.
+    pub fn scrub_query_param(mut self, param: &str) -> Self {
+        self.config.scrub_query_params.push(param.to_string());
+        self
+    }
+
+    /// Zero the last octet of IPv4 / the last 80 bits of IPv6 client addresses.
+    pub fn anonymize_ip(mut self, enabled: bool) -> Self {
+        self.config.anonymize_ip = enabled;
+        self
+    }
+
+    /// Replace credentials found anywhere in the event with `[SECRET:<type>]`.
+    pub fn detect_secrets(mut self, enabled: bool) -> Self {
+        self.config.detect_secrets = enabled;
+        self
+    }
+
+    /// Add a custom grouping rule; rules are tried in the order added.
+    pub fn fingerprint_rule(mut self, rule: fingerprint::FingerprintRule) -> Self {
+        self.config.fingerprint_rules.push(rule);
+        self
+    }
+
+    /// Add an ownership rule setting the `team` tag; rules are tried in the order added.
+    pub fn ownership_rule(mut self, rule: ownership::OwnershipRule) -> Self {
+        self.config.ownership_rules.push(rule);
+        self
+    }
+
+    /// Map the application's severities to event levels and escalate frequent ones.
+    pub fn severity(mut self, mapper: severity::SeverityMapper) -> Self {
+        self.config.severity = mapper;
+        self
+    }
+
+    /// Send at most `limit` identical events per `window`, counting the rest.
+    pub fn dedupe(mut self, window: Duration, limit: usize) -> Self {
+        self.config.dedupe_window = Some(window);
+        self.config.dedupe_limit = limit;
+        self
+    }
+
+    /// Never send errors matching these patterns (`Type` or `Type: value` globs).
+    pub fn ignore_errors<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config
+            .ignored_error_types
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Never sample transactions whose name matches these globs.
+    pub fn ignore_transactions<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config
+            .ignored_transactions
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Drop events and breadcrumbs from loggers (targets) matching these globs.
+    pub fn ignore_loggers<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.ignored_loggers.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Tag, demote or drop errors by the crate they originate in.
+    pub fn origins(mut self, policy: origins::OriginPolicy) -> Self {
+        self.config.origins = policy;
+        self
+    }
+
+    /// Send matching events or transactions to another project instead of the main DSN.
+    pub fn route(mut self, route: routing::Route) -> Self {
+        self.config.routes.push(route);
+        self
+    }
+
+    /// Send matching errors as one summary event per window instead of one event each.
+    pub fn aggregation_rule(mut self, rule: aggregation::AggregationRule) -> Self {
+        self.config.aggregation.push(rule);
+        self
+    }
+
+    /// Downsample issues that exceed `options.events_per_window` and report the suppressed counts.
+    pub fn spike_protection(mut self, options: spike::SpikeOptions) -> Self {
+        self.config.spike_protection = Some(options);
+        self
+    }
+
+    /// Client-side token-bucket limits for errors and transactions.
+    pub fn rate_limit(mut self, limits: ratelimit::RateLimits) -> Self {
+        self.config.rate_limits = Some(limits);
+        self
+    }
+
+    /// Retries of transient delivery failures (feature `http-transport`).
+    pub fn retry_policy(mut self, policy: backoff::RetryPolicy) -> Self {
+        self.config.retry_policy = policy;
+        self
+    }
+
+    /// Batch envelopes for up to `interval` and send them over one connection.
+    #[cfg(feature = "http-transport")]
+    pub fn batching(mut self, interval: Duration, max_envelopes: usize) -> Self {
+        self.config.http.batching = Some(http::Batching {
+            interval,
+            max_envelopes: max_envelopes.max(1),
+        });
+        self
+    }
+
+    #[cfg(feature = "http-transport")]
+    pub fn compression(mut self, compression: http::Compression) -> Self {
+        self.config.http.compression = compression;
+        self
+    }
+
+    /// Send through an HTTP/HTTPS proxy (feature `http-transport`).
+    #[cfg(feature = "http-transport")]
+    pub fn proxy(mut self, proxy: http::ProxyOptions) -> Self {
+        self.config.http.proxy = Some(proxy);
+        self
+    }
+
+    /// Send to a local relay listening on a Unix socket (feature `http-transport`).
+    #[cfg(feature = "http-transport")]
+    pub fn unix_socket(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.config.http.unix_socket = Some(path.into());
+        self
+    }
+
+    /// CA bundle, client certificate and minimum TLS version of the transport.
+    #[cfg(any(feature = "rustls", feature = "http-transport"))]
+    pub fn tls(mut self, tls: tls::TlsOptions) -> Self {
+        self.config.tls = tls;
+        self
+    }
+
+    /// Write envelopes to `transport` instead of sending them to the DSN.
+    pub fn transport(mut self, transport: impl local::ObservabilityTransport + 'static) -> Self {
+        self.config.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Also write every outgoing envelope to `dir`, replayable with [`replay::replay_dir`].
+    pub fn record_envelopes(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.config.record_dir = Some(dir.into());
+        self
+    }
+
+    /// Track release health sessions per process or per request.
+    pub fn session_tracking(mut self, tracking: sessions::SessionTracking) -> Self {
+        self.config.session_tracking = tracking;
+        self
+    }
+
+    /// Per-attachment and per-event size limits for attachments.
+    pub fn attachment_limits(mut self, limits: attachments::AttachmentLimits) -> Self {
+        self.config.attachment_limits = limits;
+        self
+    }
+
+    /// Keep the last `bytes` of log output and attach them to error events.
+    pub fn log_tail(mut self, bytes: usize) -> Self {
+        self.config.log_tail_bytes = Some(bytes);
+        self
+    }
+
+    /// Warn when memory usage crosses the thresholds and add memory stats to error events.
+    pub fn memory_monitor(mut self, options: memory::MemoryOptions) -> Self {
+        self.config.memory_monitor = Some(options);
+        self
+    }
+
+    /// Look up region, zone and instance type from the cloud metadata service at startup.
+    pub fn cloud_metadata(mut self, enabled: bool) -> Self {
+        self.config.cloud_metadata = enabled;
+        self
+    }
+
+    /// Profile this share of the sampled transactions.
+    #[cfg(feature = "profiling")]
+    pub fn profiles_sample_rate(mut self, rate: f32) -> Self {
+        self.config.profiles_sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Publish envelopes to a Kafka topic instead of sending them to the DSN.
+    #[cfg(feature = "kafka")]
+    pub fn kafka(mut self, options: kafka::KafkaOptions) -> Self {
+        self.config.kafka = Some(options);
+        self
+    }
+
+    /// Capture sqlx queries taking at least `threshold` as warnings.
+    #[cfg(feature = "sqlx")]
+    pub fn slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.config.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Capture (or not) NATS handler failures on subjects matching `pattern`;
+    /// the first matching rule wins.
+    #[cfg(feature = "nats")]
+    pub fn nats_subject(mut self, pattern: &str, capture_errors: bool) -> Self {
+        self.config
+            .nats
+            .subjects
+            .push(nats_integration::SubjectRule::new(pattern, capture_errors));
+        self
+    }
+
+    /// Application hook, called after the built-in scrubbing and filtering.
+    pub fn before_send<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync + 'static,
+    {
+        self.before_send = Some(Arc::new(f));
+        self
+    }
+
+    pub fn build(self) -> SentryService {
+        let guard = SentryService::init_sentry(&self.config, self.before_send);
+        SentryService { _guard: guard }
+    }
+}
+
+impl Default for SentryServiceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// =============================================================================
+// ERROR REFERENCE CODES
+// =============================================================================
+
+pub mod reference;
+
+impl SentryService {
+    /// Capture an error and return the reference code to show to the user.
+    pub fn capture_error_with_reference<E: std::error::Error + ?Sized>(&self, error: &E) -> reference::ErrorReference {
+        reference::ErrorReference::from_event_id(sentry::capture_error(error))
+    }
+}
+
+// =============================================================================
+// ERROR RESPONSES
+// =============================================================================
+
+pub mod error_response;
+
+// =============================================================================
+// USER FEEDBACK
+// =============================================================================
+
+pub mod feedback;
+
+impl SentryService {
+    /// Attach a customer's description to the event `event_id`.
+    /// Returns `false` when there is nothing to send or no client.
+    pub fn capture_user_feedback(
+        &self,
+        event_id: sentry::types::Uuid,
+        name: &str,
+        email: &str,
+        comments: &str,
+    ) -> bool {
+        let feedback = feedback::UserFeedback::new(event_id, name, email, comments);
+        if feedback.comments.is_empty() {
+            return false;
+        }
+        match Hub::current().client() {
+            Some(client) => {
+                client.send_envelope(feedback.to_envelope(client.options().dsn.as_ref()));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// =============================================================================
+// ATTACHMENTS
+// =============================================================================
+
+pub mod attachments;
+
+impl SentryService {
+    /// Capture an error with files attached to this event only.
+    pub fn capture_error_with_attachments<E: std::error::Error + ?Sized>(
+        &self,
+        error: &E,
+        attachments: &[attachments::Attachment],
+    ) -> sentry::types::Uuid {
+        let (kept, omitted) = attachments::prepare(attachments);
+        sentry::with_scope(
+            |scope| {
+                for attachment in kept {
+                    scope.add_attachment(attachment);
+                }
+                if !omitted.is_empty() {
+                    scope.set_extra("attachments.omitted", omitted.into());
+                }
+            },
+            || sentry::capture_error(error),
+        )
+    }
+
+    /// Attach a file to every event captured in the current scope.
+    /// Returns `false` when it exceeds the size limit and was left out.
+    pub fn add_attachment(&self, attachment: attachments::Attachment) -> bool {
+        match attachment.limited(attachments::limits().max_bytes) {
+            Some(attachment) => {
+                sentry::configure_scope(|scope| scope.add_attachment(attachment.into_sentry()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear_attachments(&self) {
+        sentry::configure_scope(|scope| scope.clear_attachments());
+    }
+}
+
+// =============================================================================
+// LOG TAIL
+// =============================================================================
+
+pub mod log_tail;
+
+// =============================================================================
+// LOCAL ALERTING
+// =============================================================================
+
+#[cfg(feature = "alerting")]
+pub mod alerting;
+
+// =============================================================================
+// LOCAL DASHBOARD
+// =============================================================================
+
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+
+// =============================================================================
+// ENVELOPE REPLAY
+// =============================================================================
+
+pub mod replay;
+
+// =============================================================================
+// SCOPE INSPECTION
+// =============================================================================
+
+pub mod scope_debug;
+
+impl SentryService {
+    /// Snapshot of the current scope, for debugging.
+    pub fn scope_snapshot(&self) -> scope_debug::ScopeSnapshot {
+        scope_debug::ScopeSnapshot::current()
+    }
+}
+
+// =============================================================================
+// BREADCRUMB STORE
+// =============================================================================
+
+pub mod breadcrumbs;
+
+// =============================================================================
+// PROCESS BREADCRUMBS
+// =============================================================================
+
+pub mod process_breadcrumbs;
+
+// =============================================================================
+// ERROR STATISTICS
+// =============================================================================
+
+pub mod stats;
+
+impl SentryService {
+    /// Rolling error statistics of this process.
+    pub fn error_stats(&self) -> &'static stats::ErrorStats {
+        stats::global()
+    }
+}
+
+// =============================================================================
+// RAW SENTRY COMPATIBILITY
+// =============================================================================
+
+pub mod compat;
+
+// =============================================================================
+// LIBRARY HUBS
+// =============================================================================
+
+pub mod library;
+
+// =============================================================================
+// SEVERITY ESCALATION
+// =============================================================================
+
+pub mod escalation;
+
+// =============================================================================
+// SEVERITY MAPPING
+// =============================================================================
+
+pub mod severity;
+
+// =============================================================================
+// OPERATION TAXONOMY
+// =============================================================================
+
+pub mod ops;
+
+// =============================================================================
+// RUNTIME CONFIGURATION
+// =============================================================================
+
+pub mod runtime_config;
+
+impl SentryService {
+    /// Handle for changing sampling, debug and filter settings at runtime.
+    pub fn config(&self) -> runtime_config::ConfigHandle {
+        runtime_config::ConfigHandle
+    }
+}
+
+// =============================================================================
+// TRACE SAMPLING
+// =============================================================================
+
+pub mod sampling;
+
+// =============================================================================
+// OFFLINE SPOOL
+// =============================================================================
+
+pub mod spool;
+
+// =============================================================================
+// CRASH HANDLING
+// =============================================================================
+
+pub mod crash;
+
+impl SentryService {
+    /// Capture panics as fatal events and wait up to `flush_timeout` for them
+    /// to be sent. Without this call panics are not reported. Every panic
+    /// waits for the flush, including ones later caught by `catch_unwind`
+    /// or a runtime, so keep the timeout short.
+    pub fn install_panic_handler(&self, flush_timeout: Duration) {
+        crash::install_panic_hook(flush_timeout);
+    }
+
+    /// Report a previous session that died without shutting down cleanly
+    /// (SIGKILL, OOM) and start tracking this one in `marker`.
+    pub fn enable_crash_detection(&self, marker: impl Into<std::path::PathBuf>) -> Option<sentry::types::Uuid> {
+        crash::arm(marker)
+    }
+
+    /// Record SIGSEGV/SIGBUS/SIGABRT crashes into the offline spool at `spool_dir`.
+    #[cfg(all(target_os = "linux", feature = "crash-signals"))]
+    pub fn install_signal_handlers(&self, spool_dir: &std::path::Path) -> std::io::Result<()> {
+        crash::signals::install(spool_dir)
+    }
+}
+
+impl Drop for SentryService {
+    fn drop(&mut self) {
+        if self._guard.is_some() {
+            crash::disarm();
+        }
+    }
+}
+
+// =============================================================================
+// RESULT CAPTURE
+// =============================================================================
+
+pub mod capture;
+
+pub use rust_example_macros::captured;
+
+// =============================================================================
+// ERROR LIBRARY INTEGRATIONS
+// =============================================================================
+
+pub mod error_chain;
+
+#[cfg(feature = "anyhow")]
+impl SentryService {
+    /// Capture an `anyhow::Error` with its full context chain and backtrace.
+    pub fn capture_anyhow(&self, error: &anyhow::Error) -> sentry::types::Uuid {
+        error_chain::capture_anyhow(error)
+    }
+}
+
+#[cfg(feature = "eyre")]
+impl SentryService {
+    /// Capture an `eyre::Report`, including color-eyre sections when available.
+    pub fn capture_eyre(&self, report: &eyre::Report) -> sentry::types::Uuid {
+        error_chain::capture_eyre(report)
+    }
+}
+
+#[cfg(feature = "error-stack")]
+impl SentryService {
+    /// Capture an `error_stack::Report` with every context frame and its attachments.
+    pub fn capture_report<C>(&self, report: &error_stack::Report<C>) -> sentry::types::Uuid {
+        error_chain::capture_report(report)
+    }
+}
+
+// =============================================================================
+// TRACED FUNCTIONS
+// =============================================================================
+
+pub mod traced;
+
+pub use rust_example_macros::traced_transaction;
+
+// =============================================================================
+// TASK HUB PROPAGATION
+// =============================================================================
+
+pub mod task;
+
+// =============================================================================
+// JOB CONTEXT
+// =============================================================================
+
+pub mod jobs;
+
+// =============================================================================
+// QUEUE JOBS
+// =============================================================================
+
+pub mod queue;
+
+// =============================================================================
+// RUNTIME LAG DETECTION
+// =============================================================================
+
+pub mod lag;
+
+// =============================================================================
+// TRACE PROPAGATION
+// =============================================================================
+
+pub mod propagation;
+
+// =============================================================================
+// CORRELATION IDS
+// =============================================================================
+
+pub mod correlation;
+
+// =============================================================================
+// SCOPED TAGS
+// =============================================================================
+
+pub mod scoped;
+
+impl SentryService {
+    /// Set tags until the returned guard is dropped.
+    pub fn push_tags<'a>(&self, tags: impl IntoIterator<Item = (&'a str, &'a str)>) -> scoped::ScopeGuard {
+        tags.into_iter()
+            .fold(scoped::ScopeGuard::new(), |guard, (key, value)| guard.tag(key, value))
+    }
+
+    /// Set an extra until the returned guard is dropped.
+    pub fn push_extra<V: Into<Value>>(&self, key: &str, value: V) -> scoped::ScopeGuard {
+        scoped::ScopeGuard::new().extra(key, value)
+    }
+}
+
+// =============================================================================
+// TYPED CONTEXTS
+// =============================================================================
+
+pub mod contexts;
+
+impl SentryService {
+    /// Set a typed context on the current scope.
+    pub fn set_typed_context<C: contexts::TypedContext>(&self, context: &C) {
+        sentry::configure_scope(|scope| {
+            scope.set_context(C::NAME, context.to_context());
+        });
+    }
+}
+
+// =============================================================================
+// DATA SCRUBBING
+// =============================================================================
+
+pub mod scrubbing;
+
+// =============================================================================
+// REDACTED PAYLOADS
+// =============================================================================
+
+pub mod redact;
+
+impl SentryService {
+    /// Set a redacted struct as an extra on the current scope.
+    pub fn set_redacted_extra<R: redact::Redact>(&self, key: &str, value: &R) {
+        sentry::configure_scope(|scope| {
+            scope.set_extra(key, value.to_value());
+        });
+    }
+
+    /// Set a redacted struct as a context on the current scope.
+    pub fn set_redacted_context<R: redact::Redact>(&self, name: &str, value: &R) {
+        sentry::configure_scope(|scope| {
+            scope.set_context(name, sentry::protocol::Context::Other(value.redacted()));
+        });
+    }
+}
+
+// =============================================================================
+// SECRET DETECTION
+// =============================================================================
+
+pub mod secrets;
+
+// =============================================================================
+// EVENT FILTERS
+// =============================================================================
+
+pub mod filters;
+
+// =============================================================================
+// EVENT ORIGINS
+// =============================================================================
+
+pub mod origins;
+
+// =============================================================================
+// ERROR CODES
+// =============================================================================
+
+pub mod error_codes;
+
+/// Declare a module of [`ErrorCode`](error_codes::ErrorCode) constants plus
+/// `ALL`, the slice to pass to [`error_codes::register`]. Levels are
+/// `sentry::Level` variant names.
+///
+/// ```ignore
+/// error_codes! {
+///     pub mod inventory_codes {
+///         OUT_OF_STOCK = "E-INV-0001" { level: Warning, owner: "inventory", title: "Item out of stock" };
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! error_codes {
+    (
+        $vis:vis mod $module:ident {
+            $(
+                $(#[$meta:meta])*
+                $name:ident = $code:literal { level: $level:ident, owner: $owner:literal, title: $title:literal $(,)? };
+            )*
+        }
+    ) => {
+        $vis mod $module {
+            $(
+                $(#[$meta])*
+                pub const $name: $crate::error_codes::ErrorCode =
+                    $crate::error_codes::ErrorCode::new($code, sentry::Level::$level, $owner, $title);
+            )*
+
+            /// Every code in this module.
+            pub const ALL: &[$crate::error_codes::ErrorCode] = &[$($name),*];
+        }
+    };
+}
+
+// =============================================================================
+// ISSUE OWNERSHIP
+// =============================================================================
+
+pub mod ownership;
+
+// =============================================================================
+// FINGERPRINT RULES
+// =============================================================================
+
+pub mod fingerprint;
+
+// =============================================================================
+// EVENT DEDUPLICATION
+// =============================================================================
+
+pub mod dedupe;
+
+// =============================================================================
+// ERROR SAMPLING
+// =============================================================================
+
+pub mod error_sampling;
+
+// =============================================================================
+// SPIKE PROTECTION
+// =============================================================================
+
+pub mod spike;
+
+// =============================================================================
+// ERROR AGGREGATION
+// =============================================================================
+
+pub mod aggregation;
+
+// =============================================================================
+// CLIENT RATE LIMITING
+// =============================================================================
+
+pub mod ratelimit;
+
+// =============================================================================
+// SERVER BACKOFF
+// =============================================================================
+
+pub mod backoff;
+
+impl SentryService {
+    /// Client rate limiter counters and server backoff state.
+    pub fn transport_status(&self) -> backoff::TransportStatus {
+        backoff::TransportStatus {
+            client: ratelimit::stats(),
+            server: backoff::global().status(),
+        }
+    }
+}
+
+// =============================================================================
+// HTTP TRANSPORT
+// =============================================================================
+
+#[cfg(feature = "http-transport")]
+pub mod http;
+
+// =============================================================================
+// PROJECT ROUTING
+// =============================================================================
+
+pub mod routing;
+
+// =============================================================================
+// LOCAL TRANSPORTS
+// =============================================================================
+
+pub mod local;
+
+// =============================================================================
+// KAFKA TRANSPORT
+// =============================================================================
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+
+// =============================================================================
+// TLS
+// =============================================================================
+
+#[cfg(any(feature = "rustls", feature = "http-transport"))]
+pub mod tls;
+
+// =============================================================================
+// PROFILING
+// =============================================================================
+
+#[cfg(feature = "profiling")]
+pub mod profiling;
+
+// =============================================================================
+// METRICS
+// =============================================================================
+
+pub mod metrics;
+
+// =============================================================================
+// FLUSH AND SHUTDOWN
+// =============================================================================
+
+/// Explicit delivery before the process exits.
+///
+/// Dropping the service flushes too, but containers stopped with SIGTERM and
+/// a short grace period often never get there. Call [`SentryService::close`]
+/// (or the async variant) from the shutdown path instead:
+///
+/// ```ignore
+/// let mut sigterm = tokio::signal::unix::signal(SignalKind::terminate())?;
+/// sigterm.recv().await;
+/// if !sentry.close_async(Duration::from_secs(2)).await {
+///     eprintln!("Not all events were delivered before shutdown");
+/// }
+/// ```
+impl SentryService {
+    fn client(&self) -> Option<Arc<sentry::Client>> {
+        self._guard.as_ref().and_then(|_| Hub::main().client())
+    }
+
+    /// Wait up to `timeout` for queued events; true once all were handed to
+    /// the server (or nothing was queued).
+    pub fn flush(&self, timeout: Duration) -> bool {
+        metrics::flush();
+        aggregation::flush();
+        self.client().is_none_or(|client| client.flush(Some(timeout)))
+    }
+
+    /// End the session, flush and shut the transport down. Events captured
+    /// afterwards are discarded.
+    pub fn close(&self, timeout: Duration) -> bool {
+        let Some(client) = self.client() else {
+            return true;
+        };
+        metrics::flush();
+        aggregation::flush();
+        sentry::end_session();
+        client.close(Some(timeout))
+    }
+
+    /// [`flush`](Self::flush) on the blocking thread pool, for tokio shutdown paths.
+    pub async fn flush_async(&self, timeout: Duration) -> bool {
+        let Some(client) = self.client() else {
+            return true;
+        };
+        metrics::flush();
+        aggregation::flush();
+        tokio::task::spawn_blocking(move || client.flush(Some(timeout)))
+            .await
+            .unwrap_or(false)
+    }
+
+    pub async fn close_async(&self, timeout: Duration) -> bool {
+        let Some(client) = self.client() else {
+            return true;
+        };
+        metrics::flush();
+        aggregation::flush();
+        sentry::end_session();
+        tokio::task::spawn_blocking(move || client.close(Some(timeout)))
+            .await
+            .unwrap_or(false)
+    }
+}
+
+// =============================================================================
+// SDK HEALTH
+// =============================================================================
+
+pub mod health;
+
+impl SentryService {
+    /// Health counters of the SDK pipeline, process-wide.
+    pub fn stats(&self) -> health::SdkStats {
+        health::snapshot()
+    }
+}
+
+// =============================================================================
+// TEST CAPTURE
+// =============================================================================
+
+pub mod testing;
+
+// =============================================================================
+// EVENT PROCESSORS
+// =============================================================================
+
+pub mod processors;
+
+// =============================================================================
+// HOOKS
+// =============================================================================
+
+/// Process events before sending.
+fn before_send_handler(event: Event<'static>) -> Option<Event<'static>> {
+    // Count every captured event, including ones filtered or sampled out below
+    stats::global().record(&event);
+
+    processors::run(event)
+}
+
+/// Readable name of an event's issue for logs and reports: type and value of
+/// the primary exception, or the message. Counting uses [`fingerprint::issue_key`].
+fn issue_title(event: &Event<'_>) -> String {
+    match event.exception.values.last() {
+        Some(exception) => format!("{}: {}", exception.ty, exception.value.as_deref().unwrap_or_default()),
+        None => event.message.clone().unwrap_or_else(|| "<no message>".to_string()),
+    }
+}
+
+/// Process breadcrumbs before adding.
+fn before_breadcrumb_handler(mut breadcrumb: Breadcrumb) -> Option<Breadcrumb> {
+    if filters::current().ignores_breadcrumb(&breadcrumb) {
+        return None;
+    }
+
+    // Filter health check requests
+    if breadcrumb.category.as_deref() == Some("http") {
+        if let Some(url) = breadcrumb.data.get("url") {
+            if url.as_str().map(|s| s.contains("/health")).unwrap_or(false) {
+                return None;
+            }
+        }
+    }
+
+    scrubbing::current().scrub_breadcrumb(&mut breadcrumb);
+
+    breadcrumbs::record(&breadcrumb);
+
+    Some(breadcrumb)
+}
+
+// =============================================================================
+// RELEASE DETECTION
+// =============================================================================
+
+pub mod release_info;
+
+// =============================================================================
+// RELEASE NOTIFICATION
+// =============================================================================
+
+#[cfg(feature = "releases")]
+pub mod releases;
+
+// =============================================================================
+// RELEASE HEALTH
+// =============================================================================
+
+pub mod sessions;
+
+// =============================================================================
+// CRON MONITORING
+// =============================================================================
+
+pub mod cron;
+
+pub use rust_example_macros::monitored_job;
+
+// =============================================================================
+// SYSTEMD WATCHDOG
+// =============================================================================
+
+#[cfg(feature = "systemd")]
+pub mod watchdog;
+
+// =============================================================================
+// HANG DETECTION
+// =============================================================================
+
+pub mod hang;
+
+// =============================================================================
+// MEMORY MONITORING
+// =============================================================================
+
+pub mod memory;
+
+// =============================================================================
+// SYSTEM CONTEXT
+// =============================================================================
+
+pub mod system;
+
+// =============================================================================
+// KUBERNETES CONTEXT
+// =============================================================================
+
+pub mod kubernetes;
+
+// =============================================================================
+// CLOUD METADATA
+// =============================================================================
+
+pub mod cloud;
+
+// =============================================================================
+// GLOBAL FACADE
+// =============================================================================
+
+pub mod global;
+pub use global as observability;
+
+/// Capture an error-level message (or an error value) through the global facade.
+///
+/// `error!("Payment failed for order {}", id)` or `error!(err = &db_error)`.
+#[macro_export]
+macro_rules! error {
+    (err = $error:expr) => {
+        $crate::global::capture_error($error)
+    };
+    ($($arg:tt)+) => {
+        $crate::global::capture_message(&format!($($arg)+), ::sentry::Level::Error)
+    };
+}
+
+// =============================================================================
+// INSTRUMENTED SERVICES
+// =============================================================================
+
+/// Decorator that adds breadcrumbs, a span and error capture around every call
+/// of a wrapped service — the pattern ExampleService writes out by hand.
+///
+/// Implement the service trait for the wrapper with `instrument_service!`:
+///
+/// ```ignore
+/// trait PaymentService {
+///     fn charge(&self, order_id: &str, amount: u64) -> Result<String, AppError>;
+/// }
+///
+/// instrument_service!(PaymentService {
+///     fn charge(&self, order_id: &str, amount: u64) -> Result<String, AppError>;
+/// });
+///
+/// let payments: Arc<dyn PaymentService> = Arc::new(Instrumented::new(StripePayments::new(), "payment"));
+/// ```
+pub struct Instrumented<T> {
+    inner: T,
+    component: &'static str,
+}
+
+impl<T> Instrumented<T> {
+    pub fn new(inner: T, component: &'static str) -> Self {
+        Self { inner, component }
+    }
+
+    /// The wrapped service.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Run one call of the wrapped service.
+    /// The span becomes a child of the active span, or a new transaction if there is none.
+    pub fn call<R, E, F>(&self, method: &str, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&T) -> Result<R, E>,
+        E: std::error::Error + 'static,
+    {
+        let name = format!("{}.{}", self.component, method);
+
+        sentry::add_breadcrumb(Breadcrumb {
+            message: Some(format!("Calling {}", name)),
+            category: Some(self.component.to_string()),
+            level: Level::Info,
+            ..Default::default()
+        });
+
+        let parent = sentry::configure_scope(|scope| scope.get_span());
+        let span: sentry::TransactionOrSpan = match &parent {
+            Some(parent) => parent.start_child(ops::Op::Function.as_str(), &name).into(),
+            None => sentry::start_transaction(TransactionContext::new(&name, ops::Op::Function.as_str())).into(),
+        };
+
+        // Bind the span so nested instrumented calls become its children
+        sentry::configure_scope(|scope| scope.set_span(Some(span.clone())));
+
+        let result = f(&self.inner).capture_with(|scope| {
+            scope.set_tag("service.component", self.component);
+            scope.set_tag("service.method", method);
+        });
+        span.set_status(match result {
+            Ok(_) => sentry::protocol::SpanStatus::Ok,
+            Err(_) => sentry::protocol::SpanStatus::InternalError,
+        });
+
+        span.finish();
+        sentry::configure_scope(|scope| scope.set_span(parent));
+        result
+    }
+}
+
+/// Implement a service trait for `Instrumented<T>` by forwarding every listed
+/// method through `Instrumented::call`. All methods must return `Result`.
+#[macro_export]
+macro_rules! instrument_service {
+    ($service:path {
+        $(fn $method:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) -> Result<$ok:ty, $err:ty>;)*
+    }) => {
+        impl<T: $service> $service for $crate::Instrumented<T> {
+            $(
+                fn $method(&self $(, $arg: $ty)*) -> Result<$ok, $err> {
+                    self.call(stringify!($method), |inner| inner.$method($($arg),*))
+                }
+            )*
+        }
+    };
+}
+
+// =============================================================================
+// EXAMPLE SERVICE
+// =============================================================================
+
+/// Example service demonstrating Sentry integration patterns.
+pub struct ExampleService {
+    sentry: Arc<SentryService>,
+}
+
+impl ExampleService {
+    pub fn new(sentry: Arc<SentryService>) -> Self {
+        Self { sentry }
+    }
+
+    /// Example method with error tracking.
+    #[instrument(skip(self))]
+    pub fn fetch_data(&self, id: &str) -> Result<String, AppError> {
+        self.sentry.add_breadcrumb(
+            &format!("Fetching data for {}", id),
+            "service",
+            Level::Info,
+            Some({
+                let mut data = BTreeMap::new();
+                data.insert("id".to_string(), Value::from(id));
+                data
+            }),
+        );
+
+        if id == "error" {
+            return Err(AppError::DatabaseError("Failed to fetch data".to_string()));
+        }
+
+        Ok(format!("Data for {}", id))
+    }
+
+    /// Example method with transaction tracking.
+    pub fn process_batch(&self, items: &[&str]) -> usize {
+        self.sentry.with_transaction("process_batch", Op::Task, |transaction| {
+            let mut processed = 0;
+
+            for item in items {
+                self.sentry
+                    .with_span(transaction, Op::Task, &format!("process_{}", item), |_span| {
+                        std::thread::sleep(Duration::from_millis(50)); // Simulate work
+                        processed += 1;
+                    });
+            }
+
+            processed
+        })
+    }
+
+    /// Async example method.
+    #[instrument(skip(self))]
+    pub async fn async_operation(&self, input: &str) -> Result<String, AppError> {
+        self.sentry.add_breadcrumb(
+            &format!("Processing async operation for {}", input),
+            "async",
+            Level::Info,
+            None,
+        );
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        Ok(format!("Processed: {}", input))
+    }
+}
+
+// =============================================================================
+// ACTIX-WEB INTEGRATION
+// =============================================================================
+
+#[cfg(feature = "actix")]
+pub mod actix_integration;
+
+// =============================================================================
+// AXUM INTEGRATION
+// =============================================================================
+
+#[cfg(feature = "axum")]
+pub mod axum_integration;
+
+// =============================================================================
+// GRPC INTEGRATION
+// =============================================================================
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+// =============================================================================
+// OUTBOUND HTTP
+// =============================================================================
+
+#[cfg(feature = "reqwest-middleware")]
+pub mod reqwest_integration;
+
+// =============================================================================
+// SQLX INSTRUMENTATION
+// =============================================================================
+
+#[cfg(feature = "sqlx")]
+pub mod sqlx_integration;
+
+// =============================================================================
+// REDIS INSTRUMENTATION
+// =============================================================================
+
+#[cfg(feature = "redis")]
+pub mod redis_integration;
+
+// =============================================================================
+// KAFKA TRACING
+// =============================================================================
+
+#[cfg(feature = "kafka")]
+pub mod kafka_tracing;
+
+// =============================================================================
+// RABBITMQ INTEGRATION
+// =============================================================================
+
+#[cfg(feature = "lapin")]
+pub mod lapin_integration;
+
+// =============================================================================
+// NATS INTEGRATION
+// =============================================================================
+
+#[cfg(feature = "nats")]
+pub mod nats_integration;
+
+// =============================================================================
+// TRACING LAYER
+// =============================================================================
+
+pub mod tracing_layer;
+
+// =============================================================================
+// LOG BRIDGE
+// =============================================================================
+
+#[cfg(feature = "log")]
+pub mod log_bridge;
+
+// =============================================================================
+// SLOG DRAIN
+// =============================================================================
+
+#[cfg(feature = "slog")]
+pub mod slog_drain;
+
+// =============================================================================
+// OPENTELEMETRY BRIDGE
+// =============================================================================
+
+#[cfg(feature = "otel")]
+pub mod otel;
+
+// =============================================================================
+// MAIN EXAMPLE
+// =============================================================================
+
+fn main() -> ExitCode {
+    // Tool subcommands instead of the walkthrough
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("envelope-replay") => return replay::cli(args),
+        #[cfg(feature = "releases")]
+        Some("observability-release") => return releases::cli(args),
+        _ => {}
+    }
+
+    println!("{}", "=".repeat(60));
+    println!("Bugsink/Sentry Rust SDK Integration Example");
+    println!("{}", "=".repeat(60));
+
+    // Initialize Sentry service
+    let sentry = Arc::new(SentryService::new());
+    sentry.install_panic_handler(Duration::from_secs(2));
+    sentry.enable_crash_detection(std::env::temp_dir().join("rust-example.session"));
+
+    // Set user context
+    let mut user_data = BTreeMap::new();
+    user_data.insert("subscription_tier".to_string(), Value::from("premium"));
+
+    sentry.set_user_with_data(
+        "user-123",
+        Some("developer@example.com"),
+        Some("developer"),
+        Some("127.0.0.1"),
+        user_data,
+    );
+
+    sentry.set_typed_context(&contexts::DeploymentContext {
+        region: "eu-central-1".to_string(),
+        instance: env::var("HOSTNAME").ok(),
+        ..Default::default()
+    });
+
+    // Add breadcrumbs
+    sentry.add_breadcrumb("Application started", "app", Level::Info, None);
+    sentry.add_breadcrumb("User authenticated", "auth", Level::Info, None);
+
+    // Example 1: Capture handled exception
+    println!("\n1. Capturing handled exception...");
+    let error = AppError::DatabaseError("Connection refused".to_string());
+    let mut context = BTreeMap::new();
+    context.insert("operation".to_string(), Value::from("database_connect"));
+    context.insert("host".to_string(), Value::from("localhost:5432"));
+
+    let event_id = sentry.capture_error_with_context(&error, context);
+    println!("   Exception captured: {}", event_id);
+
+    // Example 2: Capture message
+    println!("\n2. Capturing info message...");
+    let mut msg_context = BTreeMap::new();
+    msg_context.insert("steps_completed".to_string(), Value::from(5));
+    msg_context.insert("time_taken_seconds".to_string(), Value::from(120));
+
+    let event_id = sentry.capture_message_with_context("User completed onboarding flow", Level::Info, msg_context);
+    println!("   Message captured: {}", event_id);
+
+    // Example 3: Use example service
+    println!("\n3. Using example service...");
+    let service = ExampleService::new(Arc::clone(&sentry));
+    match service.fetch_data("123").capture() {
+        Ok(data) => println!("   Data fetched: {}", data),
+        Err(_) => println!("   Error handled"),
+    }
+
+    // Example 4: Transaction with service
+    println!("\n4. Processing batch with transaction...");
+    let processed = service.process_batch(&["a", "b", "c"]);
+    println!("   Processed {} items", processed);
+
+    // Example 5: Scoped context
+    println!("\n5. Using scoped context...");
+    sentry.with_scope(
+        |scope| {
+            scope.set_tag("feature", "new_checkout");
+            scope.set_extra("cart_items", Value::from(3));
+            scope.set_extra("total_amount", Value::from(99.99));
+        },
+        || {
+            sentry::capture_message("Checkout initiated", Level::Info);
+        },
+    );
+    println!("   Scoped message captured");
+
+    // Example 6: Manual transaction with spans
+    println!("\n6. Creating transaction with spans...");
+    sentry.with_transaction("order_processing", Op::Task, |transaction| {
+        sentry.with_span(transaction, Op::DbQuery, "Fetch order", |_| {
+            std::thread::sleep(Duration::from_millis(50));
+        });
+
+        sentry.with_span(transaction, Op::HttpClient, "Payment API", |_| {
+            std::thread::sleep(Duration::from_millis(100));
+        });
+
+        sentry.with_span(transaction, Op::DbQuery, "Update order status", |_| {
+            std::thread::sleep(Duration::from_millis(50));
+        });
+    });
+    println!("   Transaction with spans recorded");
+
+    // Clean up
+    sentry.clear_user();
+
+    println!("\n{}", "=".repeat(60));
+    println!("All examples completed!");
+    println!("Check your Bugsink dashboard");
+    println!("{}", "=".repeat(60));
+
+    // Deliver queued events before exiting instead of relying on drop
+    if !sentry.close(Duration::from_secs(2)) {
+        println!("Some events were not delivered before shutdown");
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests;
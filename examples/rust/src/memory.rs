@@ -0,0 +1,257 @@
+//! Memory usage on error events and warnings before the OOM killer strikes.
+//!
+//! Once [installed](memory::install), a sampler thread checks the resident set
+//! size every `interval` and captures a warning, with the `memory` context,
+//! each time usage rises above one of the thresholds (again only after it fell
+//! below). Every error event gets the same context: RSS and its high-water
+//! mark from `/proc/self/status`, the cgroup memory limit and, with the
+//! tracking allocator installed, the heap in use and its peak.
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: memory::TrackingAllocator = memory::TrackingAllocator::new(std::alloc::System);
+//! ```
+//!
+//! RSS and the cgroup limit are only available on Linux.
+
+use sentry::{
+    protocol::{Context, Event},
+    Hub, Level,
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::BTreeMap,
+    fmt, fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryThreshold {
+    Bytes(u64),
+    /// Share of the cgroup memory limit; never crossed without a limit.
+    LimitRatio(f64),
+}
+
+impl MemoryThreshold {
+    fn exceeded(&self, stats: &MemoryStats) -> bool {
+        let Some(used) = stats.used_bytes() else {
+            return false;
+        };
+        match *self {
+            MemoryThreshold::Bytes(bytes) => used >= bytes,
+            MemoryThreshold::LimitRatio(ratio) => stats
+                .limit_bytes
+                .is_some_and(|limit| used as f64 >= limit as f64 * ratio),
+        }
+    }
+}
+
+impl fmt::Display for MemoryThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryThreshold::Bytes(bytes) => write!(f, "{} MiB", bytes / (1024 * 1024)),
+            MemoryThreshold::LimitRatio(ratio) => write!(f, "{}% of the memory limit", (ratio * 100.0).round()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryOptions {
+    pub interval: Duration,
+    pub thresholds: Vec<MemoryThreshold>,
+}
+
+impl Default for MemoryOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            thresholds: vec![MemoryThreshold::LimitRatio(0.8), MemoryThreshold::LimitRatio(0.95)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub rss_bytes: Option<u64>,
+    pub peak_rss_bytes: Option<u64>,
+    pub virtual_bytes: Option<u64>,
+    /// Heap in use and its peak, with the [`TrackingAllocator`] installed.
+    pub heap_bytes: Option<u64>,
+    pub peak_heap_bytes: Option<u64>,
+    pub limit_bytes: Option<u64>,
+}
+
+impl MemoryStats {
+    pub fn current() -> Self {
+        let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+        let peak_heap = PEAK_HEAP.load(Ordering::Relaxed) as u64;
+        Self {
+            rss_bytes: status_kb(&status, "VmRSS:"),
+            peak_rss_bytes: status_kb(&status, "VmHWM:"),
+            virtual_bytes: status_kb(&status, "VmSize:"),
+            heap_bytes: (peak_heap > 0).then(|| HEAP.load(Ordering::Relaxed) as u64),
+            peak_heap_bytes: (peak_heap > 0).then_some(peak_heap),
+            limit_bytes: cgroup_limit(),
+        }
+    }
+
+    /// RSS, or the tracked heap where RSS is not available.
+    pub fn used_bytes(&self) -> Option<u64> {
+        self.rss_bytes.or(self.heap_bytes)
+    }
+
+    pub fn to_context(&self) -> Context {
+        let mut map = BTreeMap::new();
+        let fields = [
+            ("rss_bytes", self.rss_bytes),
+            ("peak_rss_bytes", self.peak_rss_bytes),
+            ("virtual_bytes", self.virtual_bytes),
+            ("heap_bytes", self.heap_bytes),
+            ("peak_heap_bytes", self.peak_heap_bytes),
+            ("limit_bytes", self.limit_bytes),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                map.insert(key.to_string(), value.into());
+            }
+        }
+        if let (Some(used), Some(limit)) = (self.used_bytes(), self.limit_bytes) {
+            map.insert("usage_ratio".to_string(), (used as f64 / limit as f64).into());
+        }
+        Context::Other(map)
+    }
+}
+
+/// `VmRSS:     1234 kB` -> bytes
+fn status_kb(status: &str, key: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with(key))?;
+    let kb: u64 = line[key.len()..].trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// cgroup v2 `memory.max`, falling back to v1; `None` when unlimited.
+pub(crate) fn cgroup_limit() -> Option<u64> {
+    [
+        "/sys/fs/cgroup/memory.max",
+        "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+    ]
+    .iter()
+    .find_map(|path| fs::read_to_string(path).ok())
+    .and_then(|limit| limit.trim().parse().ok())
+    // v1 reports "unlimited" as a huge page-aligned number
+    .filter(|limit| *limit < 1 << 60)
+}
+
+static HEAP: AtomicUsize = AtomicUsize::new(0);
+static PEAK_HEAP: AtomicUsize = AtomicUsize::new(0);
+
+/// Global allocator wrapper counting live heap bytes.
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> TrackingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+fn allocated(size: usize) {
+    let heap = HEAP.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_HEAP.fetch_max(heap, Ordering::Relaxed);
+}
+
+// SAFETY: every call is forwarded to the wrapped allocator unchanged
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        HEAP.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            HEAP.fetch_sub(layout.size(), Ordering::Relaxed);
+            allocated(new_size);
+        }
+        new_ptr
+    }
+}
+
+static INSTALLED: OnceLock<MemoryOptions> = OnceLock::new();
+
+/// Start the sampler and attach memory stats to error events.
+/// Only the first call has an effect.
+pub fn install(options: MemoryOptions) {
+    if INSTALLED.set(options.clone()).is_err() {
+        return;
+    }
+    let hub = Hub::current();
+    let spawned = thread::Builder::new()
+        .name("memory-monitor".to_string())
+        .spawn(move || run(options, hub));
+    if let Err(e) = spawned {
+        eprintln!("Cannot start memory monitor: {}", e);
+    }
+}
+
+pub fn is_installed() -> bool {
+    INSTALLED.get().is_some()
+}
+
+fn run(options: MemoryOptions, hub: std::sync::Arc<Hub>) {
+    let mut above = vec![false; options.thresholds.len()];
+    loop {
+        let stats = MemoryStats::current();
+        for (threshold, above) in options.thresholds.iter().zip(above.iter_mut()) {
+            let exceeded = threshold.exceeded(&stats);
+            if exceeded && !*above {
+                hub.capture_event(threshold_event(threshold, &stats));
+            }
+            *above = exceeded;
+        }
+        thread::sleep(options.interval);
+    }
+}
+
+/// Warning for usage crossing `threshold`.
+pub fn threshold_event(threshold: &MemoryThreshold, stats: &MemoryStats) -> Event<'static> {
+    let mut event = Event {
+        level: Level::Warning,
+        message: Some(format!("Memory usage above {}", threshold)),
+        ..Default::default()
+    };
+    event.contexts.insert("memory".to_string(), stats.to_context());
+    event.tags.insert("memory.threshold".to_string(), threshold.to_string());
+    event
+}
+
+/// Add the `memory` context to error events while installed.
+pub(crate) fn attach(event: &mut Event<'static>) {
+    if is_installed() && event.level >= Level::Error && !event.contexts.contains_key("memory") {
+        event
+            .contexts
+            .insert("memory".to_string(), MemoryStats::current().to_context());
+    }
+}
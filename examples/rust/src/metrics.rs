@@ -0,0 +1,278 @@
+//! Counters, gauges and distributions next to the error data, aggregated
+//! client-side in 10 second buckets.
+//!
+//! ```ignore
+//! let metrics = metrics::global();
+//! metrics.incr("checkout.failed", 1.0, &[("provider", "stripe")]);
+//! metrics.gauge("queue.depth", depth as f64, &[]);
+//! metrics.timing("checkout.duration", started.elapsed(), &[("result", "ok")]);
+//! ```
+//!
+//! [`global`](metrics::global) flushes every 10 seconds, and on
+//! [`SentryService::flush`] / [`close`](SentryService::close), as `statsd`
+//! envelope items through the current client. Another backend plugs in as a
+//! [`MetricsSink`](metrics::MetricsSink).
+
+use sentry::{protocol::Envelope, Hub};
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const BUCKET_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Distribution,
+}
+
+impl MetricKind {
+    fn statsd_type(self) -> &'static str {
+        match self {
+            MetricKind::Counter => "c",
+            MetricKind::Gauge => "g",
+            MetricKind::Distribution => "d",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MetricKey {
+    pub kind: MetricKind,
+    pub name: String,
+    pub unit: String,
+    pub tags: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricValue {
+    Counter(f64),
+    Gauge {
+        last: f64,
+        min: f64,
+        max: f64,
+        sum: f64,
+        count: u64,
+    },
+    Distribution(Vec<f64>),
+}
+
+impl MetricValue {
+    fn new(kind: MetricKind, value: f64) -> Self {
+        match kind {
+            MetricKind::Counter => MetricValue::Counter(value),
+            MetricKind::Gauge => MetricValue::Gauge {
+                last: value,
+                min: value,
+                max: value,
+                sum: value,
+                count: 1,
+            },
+            MetricKind::Distribution => MetricValue::Distribution(vec![value]),
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        match self {
+            MetricValue::Counter(sum) => *sum += value,
+            MetricValue::Gauge {
+                last,
+                min,
+                max,
+                sum,
+                count,
+            } => {
+                *last = value;
+                *min = min.min(value);
+                *max = max.max(value);
+                *sum += value;
+                *count += 1;
+            }
+            MetricValue::Distribution(values) => values.push(value),
+        }
+    }
+}
+
+/// One aggregated metric for one 10 second window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bucket {
+    /// Window start, seconds since the epoch.
+    pub timestamp: u64,
+    pub key: MetricKey,
+    pub value: MetricValue,
+}
+
+impl Bucket {
+    /// `name@unit:values|type|#tags|Ttimestamp`
+    pub fn to_statsd(&self) -> String {
+        let mut line = format!("{}@{}", sanitize(&self.key.name), self.key.unit);
+        match &self.value {
+            MetricValue::Counter(sum) => write!(line, ":{}", sum),
+            MetricValue::Gauge {
+                last,
+                min,
+                max,
+                sum,
+                count,
+            } => {
+                write!(line, ":{}:{}:{}:{}:{}", last, min, max, sum, count)
+            }
+            MetricValue::Distribution(values) => values.iter().try_for_each(|value| write!(line, ":{}", value)),
+        }
+        .unwrap();
+        write!(line, "|{}", self.key.kind.statsd_type()).unwrap();
+        for (i, (key, value)) in self.key.tags.iter().enumerate() {
+            let separator = if i == 0 { "|#" } else { "," };
+            write!(
+                line,
+                "{}{}:{}",
+                separator,
+                sanitize(key),
+                value.replace([',', '|', '\n'], "_")
+            )
+            .unwrap();
+        }
+        write!(line, "|T{}", self.timestamp).unwrap();
+        line
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+pub trait MetricsSink: Send + Sync {
+    fn emit(&self, buckets: Vec<Bucket>);
+}
+
+/// Sends buckets as a `statsd` envelope item through the current client.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvelopeSink;
+
+impl EnvelopeSink {
+    pub fn envelope(buckets: &[Bucket]) -> Envelope {
+        let payload: String = buckets.iter().map(|bucket| bucket.to_statsd() + "\n").collect();
+        let item_header = json!({ "type": "statsd", "length": payload.len() });
+        let bytes = format!("{{}}\n{}\n{}", item_header, payload).into_bytes();
+        Envelope::from_bytes_raw(bytes).expect("raw envelopes are not parsed")
+    }
+}
+
+impl MetricsSink for EnvelopeSink {
+    fn emit(&self, buckets: Vec<Bucket>) {
+        if let Some(client) = Hub::current().client() {
+            client.send_envelope(Self::envelope(&buckets));
+        }
+    }
+}
+
+pub struct ObservabilityMetrics {
+    buckets: Mutex<BTreeMap<(u64, MetricKey), MetricValue>>,
+    sink: RwLock<Arc<dyn MetricsSink>>,
+}
+
+impl ObservabilityMetrics {
+    pub fn new(sink: Arc<dyn MetricsSink>) -> Self {
+        Self {
+            buckets: Mutex::new(BTreeMap::new()),
+            sink: RwLock::new(sink),
+        }
+    }
+
+    pub fn set_sink(&self, sink: Arc<dyn MetricsSink>) {
+        *self.sink.write().unwrap() = sink;
+    }
+
+    pub fn incr(&self, name: &str, value: f64, tags: &[(&str, &str)]) {
+        self.record(MetricKind::Counter, name, "none", value, tags);
+    }
+
+    pub fn gauge(&self, name: &str, value: f64, tags: &[(&str, &str)]) {
+        self.record(MetricKind::Gauge, name, "none", value, tags);
+    }
+
+    pub fn distribution(&self, name: &str, value: f64, unit: &str, tags: &[(&str, &str)]) {
+        self.record(MetricKind::Distribution, name, unit, value, tags);
+    }
+
+    /// Distribution in milliseconds.
+    pub fn timing(&self, name: &str, duration: Duration, tags: &[(&str, &str)]) {
+        let millis = duration.as_secs_f64() * 1000.0;
+        self.record(MetricKind::Distribution, name, "millisecond", millis, tags);
+    }
+
+    fn record(&self, kind: MetricKind, name: &str, unit: &str, value: f64, tags: &[(&str, &str)]) {
+        if !value.is_finite() {
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let key = MetricKey {
+            kind,
+            name: name.to_string(),
+            unit: unit.to_string(),
+            tags: tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+        self.buckets
+            .lock()
+            .unwrap()
+            .entry((now - now % BUCKET_SECS, key))
+            .and_modify(|aggregate| aggregate.add(value))
+            .or_insert_with(|| MetricValue::new(kind, value));
+    }
+
+    /// Hand every aggregated bucket to the sink.
+    pub fn flush(&self) {
+        let buckets = std::mem::take(&mut *self.buckets.lock().unwrap());
+        if buckets.is_empty() {
+            return;
+        }
+        let buckets = buckets
+            .into_iter()
+            .map(|((timestamp, key), value)| Bucket { timestamp, key, value })
+            .collect();
+        self.sink.read().unwrap().emit(buckets);
+    }
+}
+
+static GLOBAL: OnceLock<ObservabilityMetrics> = OnceLock::new();
+
+/// Process-wide metrics sending through [`EnvelopeSink`], flushed every 10 seconds.
+pub fn global() -> &'static ObservabilityMetrics {
+    GLOBAL.get_or_init(|| {
+        let hub = Hub::current();
+        let spawned = thread::Builder::new().name("metrics-flush".to_string()).spawn(move || {
+            Hub::run(hub, || loop {
+                thread::sleep(Duration::from_secs(BUCKET_SECS));
+                global().flush();
+            })
+        });
+        if let Err(e) = spawned {
+            eprintln!("Cannot start metrics flush thread: {}", e);
+        }
+        ObservabilityMetrics::new(Arc::new(EnvelopeSink))
+    })
+}
+
+/// Flush the global metrics if they were used.
+pub fn flush() {
+    if let Some(metrics) = GLOBAL.get() {
+        metrics.flush();
+    }
+}
@@ -0,0 +1,179 @@
+//! Message tracing for async-nats (feature `nats`).
+//!
+//! [`publish`](nats_integration::publish) sends in a `queue.publish` span and
+//! carries `sentry-trace` / `baggage` in the NATS headers. For subscriptions,
+//! [`process`](nats_integration::process) runs the handler for one message in
+//! a `queue.process` transaction continued from those headers, on its own hub,
+//! and captures the handler's error:
+//!
+//! ```ignore
+//! let mut orders = client.subscribe("orders.>").await?;
+//! while let Some(message) = orders.next().await {
+//!     nats_integration::process(&message, handle_order(&message)).await.ok();
+//! }
+//! ```
+//!
+//! Error capture can be switched off per subject pattern (`*` matches one
+//! token, `>` the rest), e.g. for best-effort telemetry subjects; the first
+//! matching [`SubjectRule`](nats_integration::SubjectRule) wins and
+//! unmatched subjects are captured.
+
+use crate::{ops::Op, propagation, traced};
+use async_nats::{Client, HeaderMap, Message, PublishError};
+use bytes::Bytes;
+use sentry::{protocol::SpanStatus, Hub, SentryFutureExt, TransactionContext, TransactionOrSpan};
+use std::{
+    error::Error,
+    future::Future,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubjectRule {
+    /// NATS subject pattern, e.g. `telemetry.>` or `orders.*.created`.
+    pub pattern: String,
+    /// Capture handler failures for matching subjects.
+    pub capture_errors: bool,
+}
+
+impl SubjectRule {
+    pub fn new(pattern: &str, capture_errors: bool) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            capture_errors,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NatsOptions {
+    pub subjects: Vec<SubjectRule>,
+}
+
+impl NatsOptions {
+    /// Whether a handler failure on `subject` is captured.
+    pub fn captures_errors(&self, subject: &str) -> bool {
+        self.subjects
+            .iter()
+            .find(|rule| subject_matches(&rule.pattern, subject))
+            .is_none_or(|rule| rule.capture_errors)
+    }
+}
+
+fn state() -> &'static RwLock<NatsOptions> {
+    static OPTIONS: OnceLock<RwLock<NatsOptions>> = OnceLock::new();
+    OPTIONS.get_or_init(Default::default)
+}
+
+pub fn configure(options: NatsOptions) {
+    *state().write().unwrap() = options;
+}
+
+pub fn options() -> NatsOptions {
+    state().read().unwrap().clone()
+}
+
+/// NATS wildcard matching: `*` matches one token, a trailing `>` one or more.
+pub fn subject_matches(pattern: &str, subject: &str) -> bool {
+    let mut subject = subject.split('.');
+    for token in pattern.split('.') {
+        match (token, subject.next()) {
+            (">", Some(_)) => return true,
+            ("*", Some(_)) => {}
+            (token, Some(actual)) if token == actual => {}
+            _ => return false,
+        }
+    }
+    subject.next().is_none()
+}
+
+/// `headers` with the trace headers of `span` added.
+pub fn inject(span: &TransactionOrSpan, headers: Option<HeaderMap>) -> HeaderMap {
+    let mut headers = headers.unwrap_or_default();
+    for (key, value) in propagation::headers(span) {
+        headers.insert(key, value.as_str());
+    }
+    headers
+}
+
+/// Message headers, first value per name, for continuing a trace.
+pub fn header_pairs(message: &Message) -> Vec<(String, String)> {
+    let Some(headers) = &message.headers else {
+        return Vec::new();
+    };
+    headers
+        .iter()
+        .filter_map(|(name, values)| {
+            let value = values.first()?;
+            Some((name.to_string(), value.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// Publish with trace headers; see the [module docs](self).
+pub async fn publish(
+    client: &Client,
+    subject: &str,
+    headers: Option<HeaderMap>,
+    payload: Bytes,
+) -> Result<(), PublishError> {
+    let span = traced::start(subject, Op::QueuePublish);
+    span.set_data("messaging.system", "nats".into());
+    span.set_data("messaging.destination.name", subject.into());
+    span.set_data("messaging.message.body.size", payload.len().into());
+    let headers = inject(&span, headers);
+
+    let result = client.publish_with_headers(subject.to_string(), headers, payload).await;
+    if let Err(e) = &result {
+        span.set_status(SpanStatus::InternalError);
+        let hub = Hub::current();
+        hub.with_scope(
+            |scope| scope.set_tag("messaging.destination", subject),
+            || hub.capture_error(e),
+        );
+    } else {
+        span.set_status(SpanStatus::Ok);
+    }
+    span.finish();
+    result
+}
+
+/// Run `handler` for `message` in a `queue.process` transaction; see the [module docs](self).
+pub async fn process<T, E, F>(message: &Message, handler: F) -> Result<T, E>
+where
+    E: Error,
+    F: Future<Output = Result<T, E>>,
+{
+    let subject = message.subject.as_str();
+    let headers = header_pairs(message);
+    let headers = headers.iter().map(|(key, value)| (key.as_str(), value.as_str()));
+    let ctx = TransactionContext::continue_from_headers(subject, Op::QueueProcess.as_str(), headers);
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    let transaction = hub.start_transaction(ctx);
+    transaction.set_data("messaging.system", "nats".into());
+    transaction.set_data("messaging.message.body.size", message.payload.len().into());
+    hub.configure_scope(|scope| {
+        scope.set_span(Some(transaction.clone().into()));
+        scope.set_tag("messaging.destination", subject);
+    });
+
+    let result = handler.bind_hub(hub.clone()).await;
+    match &result {
+        Ok(_) => transaction.set_status(SpanStatus::Ok),
+        Err(e) => {
+            transaction.set_status(SpanStatus::InternalError);
+            if options().captures_errors(subject) {
+                hub.with_scope(
+                    |scope| {
+                        if let Some(reply) = &message.reply {
+                            scope.set_extra("messaging.nats.reply_to", reply.as_str().into());
+                        }
+                    },
+                    || hub.capture_error(e),
+                );
+            }
+        }
+    }
+    transaction.finish();
+    result
+}
@@ -0,0 +1,151 @@
+//! Canonical span/transaction operations, so performance data from all
+//! services aggregates under the same op names in Bugsink.
+//!
+//! `with_transaction`/`with_span` accept an `Op` or a string; free-form strings
+//! that are not canonical trip a debug assertion.
+
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    HttpServer,
+    HttpClient,
+    GrpcServer,
+    GrpcClient,
+    DbQuery,
+    DbRedis,
+    CacheGet,
+    CachePut,
+    QueuePublish,
+    QueueProcess,
+    Task,
+    Function,
+    Serialize,
+    FileIo,
+}
+
+impl Op {
+    pub const ALL: [Op; 14] = [
+        Op::HttpServer,
+        Op::HttpClient,
+        Op::GrpcServer,
+        Op::GrpcClient,
+        Op::DbQuery,
+        Op::DbRedis,
+        Op::CacheGet,
+        Op::CachePut,
+        Op::QueuePublish,
+        Op::QueueProcess,
+        Op::Task,
+        Op::Function,
+        Op::Serialize,
+        Op::FileIo,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Op::HttpServer => "http.server",
+            Op::HttpClient => "http.client",
+            Op::GrpcServer => "grpc.server",
+            Op::GrpcClient => "grpc.client",
+            Op::DbQuery => "db.query",
+            Op::DbRedis => "db.redis",
+            Op::CacheGet => "cache.get",
+            Op::CachePut => "cache.put",
+            Op::QueuePublish => "queue.publish",
+            Op::QueueProcess => "queue.process",
+            Op::Task => "task",
+            Op::Function => "function",
+            Op::Serialize => "serialize",
+            Op::FileIo => "file.io",
+        }
+    }
+}
+
+impl AsRef<str> for Op {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Op {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Op::ALL
+            .into_iter()
+            .find(|op| op.as_str() == s)
+            .ok_or_else(|| format!("non-canonical span op: {:?}", s))
+    }
+}
+
+pub fn is_canonical(op: &str) -> bool {
+    op.parse::<Op>().is_ok()
+}
+
+/// Debug-build lint against free-form op strings.
+pub fn debug_assert_canonical(op: &str) {
+    debug_assert!(
+        is_canonical(op),
+        "span op {:?} is not part of the taxonomy, use ops::Op (one of: {})",
+        op,
+        Op::ALL.map(Op::as_str).join(", ")
+    );
+}
+
+/// Span status for an HTTP response code, for server and client spans.
+pub fn http_span_status(status: u16) -> sentry::protocol::SpanStatus {
+    use sentry::protocol::SpanStatus;
+
+    match status {
+        100..=399 => SpanStatus::Ok,
+        400 => SpanStatus::InvalidArgument,
+        401 => SpanStatus::Unauthenticated,
+        403 => SpanStatus::PermissionDenied,
+        404 => SpanStatus::NotFound,
+        409 => SpanStatus::AlreadyExists,
+        429 => SpanStatus::ResourceExhausted,
+        499 => SpanStatus::Cancelled,
+        501 => SpanStatus::Unimplemented,
+        503 => SpanStatus::Unavailable,
+        504 => SpanStatus::DeadlineExceeded,
+        _ if (400..500).contains(&status) => SpanStatus::FailedPrecondition,
+        _ if (500..600).contains(&status) => SpanStatus::InternalError,
+        _ => SpanStatus::UnknownError,
+    }
+}
+
+/// Naming conventions for transaction names and span descriptions.
+pub mod naming {
+    /// `GET /api/users/{id}` — uppercase method and a templated route.
+    pub fn http_transaction(method: &str, path: &str) -> String {
+        format!("{} {}", method.to_ascii_uppercase(), normalize_route(path))
+    }
+
+    /// Replace IDs in a path (numbers, UUIDs, long hex strings) with `{id}`,
+    /// so transactions group by route rather than by entity.
+    pub fn normalize_route(path: &str) -> String {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        path.split('/')
+            .map(|segment| if is_identifier(segment) { "{id}" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// `module::function` name for tasks and jobs.
+    pub fn task_transaction(module_path: &str, name: &str) -> String {
+        format!("{}::{}", module_path, name)
+    }
+
+    fn is_identifier(segment: &str) -> bool {
+        let hex_or_dash = |c: char| c.is_ascii_hexdigit() || c == '-';
+        (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+            || (segment.len() >= 16 && segment.chars().all(hex_or_dash) && segment.chars().any(|c| c.is_ascii_digit()))
+    }
+}
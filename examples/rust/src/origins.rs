@@ -0,0 +1,164 @@
+//! Allow/deny lists for where an error originated.
+//!
+//! The origin is the innermost stack frame outside the standard library and
+//! the SDK, falling back to the exception's module for events without a
+//! stack trace. Its module path is compared against two prefix lists:
+//!
+//! - `in_app`: our own crates; when set, anything else is third-party
+//! - `deny`: crates that are always third-party
+//!
+//! Third-party events get the crate as the `origin.crate` tag. With
+//! `only_in_app` they are dropped, except panics, which are kept (a panic in a
+//! dependency still takes our process down); `demote_third_party_panics`
+//! lowers those to warnings. Events whose origin is unknown are always kept.
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .origins(OriginPolicy::new(&["my_app", "my_lib"]).deny(&["h2"]).only_in_app(true))
+//!     .build();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [origins]
+//! in_app = ["my_app", "my_lib"]
+//! deny = ["h2"]
+//! only_in_app = true
+//! demote_third_party_panics = true
+//! ```
+
+use sentry::{protocol::Event, Level};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Tag naming the third-party crate an event originated in.
+pub const TAG: &str = "origin.crate";
+
+/// Frames skipped when looking for the origin.
+const RUNTIME_PREFIXES: &[&str] = &[
+    "std::",
+    "core::",
+    "alloc::",
+    "backtrace::",
+    "sentry",
+    "__rust",
+    "rust_begin_unwind",
+    "rust_panic",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OriginPolicy {
+    pub in_app: Vec<String>,
+    pub deny: Vec<String>,
+    pub only_in_app: bool,
+    pub demote_third_party_panics: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    InApp,
+    ThirdParty(String),
+    Unknown,
+}
+
+impl OriginPolicy {
+    /// Treat modules starting with one of `prefixes` as our own code.
+    pub fn new(prefixes: &[&str]) -> Self {
+        Self {
+            in_app: prefixes.iter().map(|prefix| prefix.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn deny(mut self, prefixes: &[&str]) -> Self {
+        self.deny.extend(prefixes.iter().map(|prefix| prefix.to_string()));
+        self
+    }
+
+    pub fn only_in_app(mut self, enabled: bool) -> Self {
+        self.only_in_app = enabled;
+        self
+    }
+
+    pub fn demote_third_party_panics(mut self, enabled: bool) -> Self {
+        self.demote_third_party_panics = enabled;
+        self
+    }
+
+    fn is_noop(&self) -> bool {
+        self.in_app.is_empty() && self.deny.is_empty()
+    }
+
+    /// Classify a module path such as `hyper::proto::h1::conn`.
+    pub fn classify_module(&self, module: &str) -> Origin {
+        let module = module.trim_start_matches('<');
+        let matches = |prefixes: &[String]| prefixes.iter().any(|prefix| module.starts_with(prefix.as_str()));
+        let crate_name = module.split("::").next().unwrap_or(module).to_string();
+        if matches(&self.deny) {
+            Origin::ThirdParty(crate_name)
+        } else if self.in_app.is_empty() || matches(&self.in_app) {
+            Origin::InApp
+        } else {
+            Origin::ThirdParty(crate_name)
+        }
+    }
+
+    pub fn origin(&self, event: &Event<'_>) -> Origin {
+        let exception = event.exception.values.last();
+        let frame_module = exception
+            .and_then(|exception| exception.stacktrace.as_ref())
+            .or(event.stacktrace.as_ref())
+            .and_then(|stacktrace| {
+                stacktrace
+                    .frames
+                    .iter()
+                    .rev()
+                    .filter_map(|frame| frame.module.as_deref().or(frame.function.as_deref()))
+                    .find(|module| {
+                        let module = module.trim_start_matches('<');
+                        !RUNTIME_PREFIXES.iter().any(|prefix| module.starts_with(prefix))
+                    })
+            });
+        match frame_module.or_else(|| exception.and_then(|exception| exception.module.as_deref())) {
+            Some(module) => self.classify_module(module),
+            None => Origin::Unknown,
+        }
+    }
+
+    /// Tag, demote or drop the event; `None` drops it.
+    pub fn apply<'a>(&self, mut event: Event<'a>) -> Option<Event<'a>> {
+        if self.is_noop() {
+            return Some(event);
+        }
+        let Origin::ThirdParty(crate_name) = self.origin(&event) else {
+            return Some(event);
+        };
+        let panic = event.exception.values.iter().any(|exception| {
+            exception
+                .mechanism
+                .as_ref()
+                .is_some_and(|mechanism| mechanism.ty == "panic")
+        });
+        if !panic && self.only_in_app {
+            return None;
+        }
+        if panic && self.demote_third_party_panics {
+            event.level = Level::Warning;
+        }
+        event.tags.insert(TAG.to_string(), crate_name);
+        Some(event)
+    }
+}
+
+fn state() -> &'static RwLock<Arc<OriginPolicy>> {
+    static STATE: OnceLock<RwLock<Arc<OriginPolicy>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+pub fn install(policy: OriginPolicy) {
+    *state().write().unwrap() = Arc::new(policy);
+}
+
+pub fn current() -> Arc<OriginPolicy> {
+    state().read().unwrap().clone()
+}
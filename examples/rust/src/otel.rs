@@ -0,0 +1,331 @@
+//! Bugsink performance data for services instrumented with OpenTelemetry
+//! (feature `otel`).
+//!
+//! [`SentrySpanProcessor`](otel::SentrySpanProcessor) turns every local root
+//! span into a transaction and its descendants into spans of that
+//! transaction, keeping the OTel trace and span ids, so the same trace is
+//! visible in Bugsink and any other OTel backend. Sampling is left to the OTel
+//! sampler; unsampled spans are ignored. Ops follow the
+//! [taxonomy](ops::Op), derived from the semantic-convention attributes
+//! (`http.request.method`, `db.system`, `messaging.system`, `rpc.system`) or
+//! taken from a `sentry.op` attribute.
+//!
+//! [`SentryPropagator`](otel::SentryPropagator) reads and writes
+//! `sentry-trace` / `baggage`, so traces continue across services that use
+//! the Sentry SDK directly:
+//!
+//! ```ignore
+//! let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+//!     .with_span_processor(otel::SentrySpanProcessor::new())
+//!     .build();
+//! opentelemetry::global::set_tracer_provider(provider);
+//! opentelemetry::global::set_text_map_propagator(otel::SentryPropagator::new());
+//! ```
+//!
+//! Errors captured while an OTel span is active are linked to it by
+//! [`OtelIntegration`](otel::OtelIntegration), which `init_sentry` installs.
+
+use crate::{ops::Op, propagation};
+use opentelemetry::{
+    propagation::{text_map_propagator::FieldIter, Extractor, Injector, TextMapPropagator},
+    trace::{self as otel_trace, SpanContext, SpanKind, TraceContextExt, TraceFlags, TraceState},
+    Context,
+};
+use opentelemetry_sdk::{
+    export::trace::SpanData,
+    trace::{Span, SpanProcessor},
+};
+use sentry::{
+    protocol::{self, Event, SpanStatus, TraceContext, Transaction},
+    ClientOptions, Hub, Integration,
+};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, OnceLock},
+};
+
+const SENTRY_TRACE: &str = "sentry-trace";
+
+#[derive(Debug, Default)]
+struct State {
+    /// Root of every open local span.
+    roots: HashMap<otel_trace::SpanId, otel_trace::SpanId>,
+    /// Finished spans waiting for their root to end.
+    children: HashMap<otel_trace::SpanId, Vec<protocol::Span>>,
+}
+
+/// Sends OTel spans to the hub's client as transactions.
+#[derive(Debug, Default)]
+pub struct SentrySpanProcessor {
+    state: Mutex<State>,
+}
+
+impl SentrySpanProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SpanProcessor for SentrySpanProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        use opentelemetry::trace::Span as _;
+
+        let span_context = span.span_context();
+        if !span_context.is_sampled() {
+            return;
+        }
+        let id = span_context.span_id();
+        let parent = cx.span().span_context().clone();
+        let mut state = self.state.lock().unwrap();
+        let parent_root = (parent.is_valid() && !parent.is_remote())
+            .then(|| state.roots.get(&parent.span_id()).copied())
+            .flatten();
+        match parent_root {
+            Some(root) => {
+                state.roots.insert(id, root);
+            }
+            None => {
+                state.roots.insert(id, id);
+                state.children.insert(id, Vec::new());
+            }
+        }
+    }
+
+    fn on_end(&self, data: SpanData) {
+        let id = data.span_context.span_id();
+        let mut state = self.state.lock().unwrap();
+        let Some(root) = state.roots.remove(&id) else {
+            return;
+        };
+        if root != id {
+            // Spans ending after their transaction are dropped
+            if let Some(children) = state.children.get_mut(&root) {
+                children.push(to_span(&data));
+            }
+            return;
+        }
+        let spans = state.children.remove(&id).unwrap_or_default();
+        drop(state);
+
+        let hub = Hub::current();
+        if let Some(client) = hub.client() {
+            client.send_envelope(to_transaction(&data, spans, client.options()).into());
+        }
+    }
+
+    fn force_flush(&self) -> opentelemetry::trace::TraceResult<()> {
+        Ok(())
+    }
+
+    fn shutdown(&self) -> opentelemetry::trace::TraceResult<()> {
+        Ok(())
+    }
+}
+
+fn trace_id(id: otel_trace::TraceId) -> protocol::TraceId {
+    id.to_string().parse().unwrap_or_default()
+}
+
+fn span_id(id: otel_trace::SpanId) -> protocol::SpanId {
+    id.to_string().parse().unwrap_or_default()
+}
+
+fn attribute<'a>(data: &'a SpanData, keys: &[&str]) -> Option<&'a opentelemetry::Value> {
+    data.attributes
+        .iter()
+        .find(|kv| keys.contains(&kv.key.as_str()))
+        .map(|kv| &kv.value)
+}
+
+/// Canonical op from the semantic-convention attributes.
+pub fn op(data: &SpanData) -> String {
+    if let Some(op) = attribute(data, &["sentry.op"]) {
+        return op.as_str().into_owned();
+    }
+    let client = matches!(data.span_kind, SpanKind::Client | SpanKind::Producer);
+    let op = if attribute(data, &["rpc.system"]).is_some() {
+        if client {
+            Op::GrpcClient
+        } else {
+            Op::GrpcServer
+        }
+    } else if attribute(data, &["http.request.method", "http.method"]).is_some() {
+        if client {
+            Op::HttpClient
+        } else {
+            Op::HttpServer
+        }
+    } else if let Some(system) = attribute(data, &["db.system"]) {
+        if system.as_str() == "redis" {
+            Op::DbRedis
+        } else {
+            Op::DbQuery
+        }
+    } else if attribute(data, &["messaging.system"]).is_some() {
+        if client {
+            Op::QueuePublish
+        } else {
+            Op::QueueProcess
+        }
+    } else {
+        Op::Function
+    };
+    op.as_str().to_string()
+}
+
+fn status(data: &SpanData) -> SpanStatus {
+    let http_status =
+        attribute(data, &["http.response.status_code", "http.status_code"]).and_then(|status| match status {
+            opentelemetry::Value::I64(status) => u16::try_from(*status).ok(),
+            other => other.as_str().parse().ok(),
+        });
+    match (&data.status, http_status) {
+        (_, Some(status)) => crate::ops::http_span_status(status),
+        (otel_trace::Status::Error { .. }, None) => SpanStatus::InternalError,
+        _ => SpanStatus::Ok,
+    }
+}
+
+fn to_value(value: &opentelemetry::Value) -> Value {
+    match value {
+        opentelemetry::Value::Bool(value) => (*value).into(),
+        opentelemetry::Value::I64(value) => (*value).into(),
+        opentelemetry::Value::F64(value) => (*value).into(),
+        other => other.as_str().into_owned().into(),
+    }
+}
+
+fn span_data(data: &SpanData) -> BTreeMap<String, Value> {
+    data.attributes
+        .iter()
+        .filter(|kv| kv.key.as_str() != "sentry.op")
+        .map(|kv| (kv.key.to_string(), to_value(&kv.value)))
+        .collect()
+}
+
+fn to_span(data: &SpanData) -> protocol::Span {
+    protocol::Span {
+        span_id: span_id(data.span_context.span_id()),
+        trace_id: trace_id(data.span_context.trace_id()),
+        parent_span_id: Some(span_id(data.parent_span_id)),
+        op: Some(op(data)),
+        description: Some(data.name.to_string()),
+        start_timestamp: data.start_time,
+        timestamp: Some(data.end_time),
+        status: Some(status(data)),
+        data: span_data(data),
+        ..Default::default()
+    }
+}
+
+fn to_transaction(data: &SpanData, spans: Vec<protocol::Span>, options: &ClientOptions) -> Transaction<'static> {
+    // A remote parent is the upstream service's span
+    let parent_span_id = (data.parent_span_id != otel_trace::SpanId::INVALID).then(|| span_id(data.parent_span_id));
+    let trace = TraceContext {
+        span_id: span_id(data.span_context.span_id()),
+        trace_id: trace_id(data.span_context.trace_id()),
+        parent_span_id,
+        op: Some(op(data)),
+        status: Some(status(data)),
+        ..Default::default()
+    };
+    let mut transaction = Transaction {
+        name: Some(data.name.to_string()),
+        release: options.release.clone(),
+        environment: options.environment.clone(),
+        start_timestamp: data.start_time,
+        timestamp: Some(data.end_time),
+        spans,
+        extra: span_data(data),
+        ..Default::default()
+    };
+    transaction.contexts.insert("trace".to_string(), trace.into());
+    transaction
+}
+
+/// `sentry-trace` and `baggage` propagation for OTel instrumentations.
+///
+/// Writes its own `baggage`; use it instead of, not together with, the
+/// W3C baggage propagator.
+#[derive(Debug, Default)]
+pub struct SentryPropagator;
+
+impl SentryPropagator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl TextMapPropagator for SentryPropagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        let span = cx.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return;
+        }
+        injector.set(
+            SENTRY_TRACE,
+            format!(
+                "{}-{}-{}",
+                span_context.trace_id(),
+                span_context.span_id(),
+                if span_context.is_sampled() { 1 } else { 0 }
+            ),
+        );
+        if let Some(baggage) = propagation::baggage(trace_id(span_context.trace_id())) {
+            injector.set("baggage", baggage);
+        }
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        match extractor.get(SENTRY_TRACE).and_then(parse_sentry_trace) {
+            Some(span_context) => cx.with_remote_span_context(span_context),
+            None => cx.clone(),
+        }
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        static FIELDS: OnceLock<[String; 2]> = OnceLock::new();
+        FieldIter::new(FIELDS.get_or_init(|| [SENTRY_TRACE.to_string(), "baggage".to_string()]))
+    }
+}
+
+/// `<trace_id>-<span_id>[-<sampled>]` as a remote span context.
+pub fn parse_sentry_trace(header: &str) -> Option<SpanContext> {
+    let mut parts = header.trim().split('-');
+    let trace_id = otel_trace::TraceId::from_hex(parts.next()?).ok()?;
+    let span_id = otel_trace::SpanId::from_hex(parts.next()?).ok()?;
+    // OTel cannot defer the decision; a missing flag counts as sampled
+    let flags = match parts.next() {
+        Some("0") => TraceFlags::default(),
+        _ => TraceFlags::SAMPLED,
+    };
+    let span_context = SpanContext::new(trace_id, span_id, flags, true, TraceState::default());
+    span_context.is_valid().then_some(span_context)
+}
+
+/// Points the trace context of captured events at the active OTel span.
+#[derive(Debug, Default)]
+pub struct OtelIntegration;
+
+impl Integration for OtelIntegration {
+    fn name(&self) -> &'static str {
+        "opentelemetry"
+    }
+
+    fn process_event(&self, mut event: Event<'static>, _options: &ClientOptions) -> Option<Event<'static>> {
+        let cx = Context::current();
+        let span = cx.span();
+        let span_context = span.span_context();
+        if span_context.is_valid() {
+            let trace = TraceContext {
+                span_id: span_id(span_context.span_id()),
+                trace_id: trace_id(span_context.trace_id()),
+                ..Default::default()
+            };
+            event.contexts.insert("trace".to_string(), trace.into());
+        }
+        Some(event)
+    }
+}
@@ -0,0 +1,153 @@
+//! Team tags from ownership rules, so Bugsink alerts can be routed per team
+//! with the rules kept next to the code they describe.
+//!
+//! A rule names a team and matches by module path prefix, by
+//! [error code](error_codes) prefix, or both. Modules are compared with the
+//! place the error arose: the module of each exception and its innermost
+//! frame outside the standard library, not every frame it passed through.
+//! The first matching rule sets the `team` tag; without one, the owner of a
+//! registered error code is used. A `team` tag set by the application is
+//! kept.
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .ownership_rule(OwnershipRule::new("payments").code("E-PAY-"))
+//!     .ownership_rule(OwnershipRule::new("billing").module("my_app::billing"))
+//!     .ownership_rule(OwnershipRule::new("platform"))    // everything else
+//!     .build();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [[ownership]]
+//! team = "payments"
+//! code = "E-PAY-"
+//!
+//! [[ownership]]
+//! team = "billing"
+//! module = "my_app::billing"
+//! ```
+
+use super::error_codes;
+use sentry::protocol::{Event, Stacktrace};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Tag naming the owning team.
+pub const TAG: &str = "team";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnershipRule {
+    pub team: String,
+    /// Module path prefix, e.g. `my_app::billing`.
+    pub module: Option<String>,
+    /// Error code prefix, e.g. `E-PAY-`.
+    pub code: Option<String>,
+}
+
+impl OwnershipRule {
+    /// A rule that matches every event until conditions are added.
+    pub fn new(team: &str) -> Self {
+        Self {
+            team: team.to_string(),
+            module: None,
+            code: None,
+        }
+    }
+
+    pub fn module(mut self, prefix: &str) -> Self {
+        self.module = Some(prefix.to_string());
+        self
+    }
+
+    pub fn code(mut self, prefix: &str) -> Self {
+        self.code = Some(prefix.to_string());
+        self
+    }
+
+    pub fn matches(&self, event: &Event<'_>) -> bool {
+        if let Some(prefix) = &self.code {
+            let code = event.tags.get(error_codes::TAG);
+            if !code.is_some_and(|code| code.starts_with(prefix.as_str())) {
+                return false;
+            }
+        }
+        match &self.module {
+            Some(prefix) => origin_modules(event).any(|module| module.starts_with(prefix.as_str())),
+            None => true,
+        }
+    }
+}
+
+/// Exception modules and the innermost non-library frame of each stack trace.
+fn origin_modules<'a>(event: &'a Event<'_>) -> impl Iterator<Item = &'a str> {
+    let exceptions = &event.exception.values;
+    let frames = exceptions
+        .iter()
+        .filter_map(|exception| exception.stacktrace.as_ref())
+        .chain(event.stacktrace.as_ref())
+        .filter_map(innermost_frame);
+    exceptions
+        .iter()
+        .filter_map(|exception| exception.module.as_deref())
+        .chain(frames)
+}
+
+fn innermost_frame(stacktrace: &Stacktrace) -> Option<&str> {
+    // Frames are ordered outermost first; std and core frames are marked not in-app
+    let frame = stacktrace
+        .frames
+        .iter()
+        .rev()
+        .find(|frame| frame.in_app != Some(false))?;
+    frame.module.as_deref().or(frame.function.as_deref())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OwnershipRules {
+    rules: Vec<OwnershipRule>,
+}
+
+impl OwnershipRules {
+    pub fn new(rules: Vec<OwnershipRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn rules(&self) -> &[OwnershipRule] {
+        &self.rules
+    }
+
+    /// The team for `event`: the first matching rule's, else its error code's owner.
+    pub fn owner<'a>(&'a self, event: &'a Event<'_>) -> Option<&'a str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(event))
+            .map(|rule| rule.team.as_str())
+            .or_else(|| event.tags.get(error_codes::OWNER_TAG).map(String::as_str))
+    }
+
+    /// Set the `team` tag unless the event already has one.
+    pub fn apply(&self, event: &mut Event<'_>) {
+        if event.tags.contains_key(TAG) {
+            return;
+        }
+        if let Some(team) = self.owner(event).map(str::to_string) {
+            event.tags.insert(TAG.to_string(), team);
+        }
+    }
+}
+
+fn state() -> &'static RwLock<Arc<OwnershipRules>> {
+    static STATE: OnceLock<RwLock<Arc<OwnershipRules>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Replace the rules consulted by `before_send`.
+pub fn install(rules: OwnershipRules) {
+    *state().write().unwrap() = Arc::new(rules);
+}
+
+pub(crate) fn apply(event: &mut Event<'_>) {
+    let rules = state().read().unwrap().clone();
+    rules.apply(event);
+}
@@ -0,0 +1,264 @@
+//! Breadcrumbs for what happened to the process, not the request: signals,
+//! config reloads, threads starting and exiting, connection pools running dry.
+//!
+//! Config reloads through [`ConfigHandle::reload_from_file`](runtime_config::ConfigHandle::reload_from_file)
+//! are recorded automatically. The other sources are hooks to install once:
+//!
+//! ```ignore
+//! process_breadcrumbs::watch_signals(&[SignalKind::hangup(), SignalKind::user_defined1()])?;
+//!
+//! let runtime = process_breadcrumbs::on_runtime_threads(&mut tokio::runtime::Builder::new_multi_thread())
+//!     .enable_all()
+//!     .build()?;
+//! process_breadcrumbs::spawn_thread(thread::Builder::new().name("exporter".into()), run_exporter)?;
+//!
+//! // from a periodic metrics task
+//! let redis = process_breadcrumbs::PoolWatcher::new("redis");
+//! let state = redis_pool.state();
+//! redis.observe(state.connections, state.idle_connections as usize, MAX_CONNECTIONS);
+//!
+//! process_breadcrumbs::watch_sqlx_pool("postgres", pg_pool.clone(), Duration::from_secs(5));    // feature `sqlx`
+//! ```
+//!
+//! Breadcrumbs go to the main hub, so requests started afterwards inherit
+//! them, and to the current hub when that is a different one. Their
+//! categories are `signal`, `config`, `thread` and `pool`, which can be given
+//! their own [limits](breadcrumbs::Retention) so they aren't pushed out by
+//! request traffic.
+
+use sentry::{
+    protocol::{Breadcrumb, Value},
+    Hub, Level,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Add to the main hub and, if different, the current one.
+pub fn record(breadcrumb: Breadcrumb) {
+    let main = Hub::main();
+    let current = Hub::current();
+    if !Arc::ptr_eq(&main, &current) {
+        current.add_breadcrumb(breadcrumb.clone());
+    }
+    main.add_breadcrumb(breadcrumb);
+}
+
+fn breadcrumb(category: &str, level: Level, message: String, data: BTreeMap<String, Value>) -> Breadcrumb {
+    Breadcrumb {
+        category: Some(category.to_string()),
+        level,
+        message: Some(message),
+        data: data.into_iter().collect(),
+        ..Default::default()
+    }
+}
+
+pub fn signal_received(name: &str) {
+    record(breadcrumb(
+        "signal",
+        Level::Info,
+        format!("Received {}", name),
+        BTreeMap::new(),
+    ));
+}
+
+/// Listen for `signals` on the tokio runtime and record each delivery.
+///
+/// A listener replaces the signal's default action: SIGHUP and SIGTERM no
+/// longer terminate the process. Only watch signals the application
+/// handles itself or that are harmless to ignore.
+#[cfg(unix)]
+pub fn watch_signals(signals: &[tokio::signal::unix::SignalKind]) -> std::io::Result<Vec<tokio::task::JoinHandle<()>>> {
+    signals
+        .iter()
+        .map(|kind| {
+            let mut stream = tokio::signal::unix::signal(*kind)?;
+            let name = signal_name(*kind);
+            Ok(tokio::spawn(async move {
+                while stream.recv().await.is_some() {
+                    signal_received(&name);
+                }
+            }))
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn signal_name(kind: tokio::signal::unix::SignalKind) -> String {
+    use tokio::signal::unix::SignalKind;
+
+    [
+        (SignalKind::hangup(), "SIGHUP"),
+        (SignalKind::interrupt(), "SIGINT"),
+        (SignalKind::quit(), "SIGQUIT"),
+        (SignalKind::terminate(), "SIGTERM"),
+        (SignalKind::user_defined1(), "SIGUSR1"),
+        (SignalKind::user_defined2(), "SIGUSR2"),
+        (SignalKind::pipe(), "SIGPIPE"),
+        (SignalKind::child(), "SIGCHLD"),
+        (SignalKind::alarm(), "SIGALRM"),
+        (SignalKind::window_change(), "SIGWINCH"),
+    ]
+    .into_iter()
+    .find(|(known, _)| *known == kind)
+    .map_or_else(
+        || format!("signal {}", kind.as_raw_value()),
+        |(_, name)| name.to_string(),
+    )
+}
+
+#[cfg(feature = "config-file")]
+pub(crate) fn config_reloaded(path: &std::path::Path, changes: &[&str]) {
+    let mut data = BTreeMap::new();
+    data.insert("path".to_string(), path.display().to_string().into());
+    data.insert("changed".to_string(), changes.iter().map(|c| Value::from(*c)).collect());
+    let message = if changes.is_empty() {
+        "Reloaded config, no runtime changes".to_string()
+    } else {
+        format!("Reloaded config: {}", changes.join(", "))
+    };
+    record(breadcrumb("config", Level::Info, message, data));
+}
+
+fn thread_event(what: &str, level: Level) {
+    let current = thread::current();
+    let mut data = BTreeMap::new();
+    data.insert("thread.id".to_string(), format!("{:?}", current.id()).into());
+    let name = current.name().unwrap_or("<unnamed>");
+    record(breadcrumb("thread", level, format!("Thread {} {}", name, what), data));
+}
+
+/// Records the exit when dropped, including unwinding out of a panic.
+struct ThreadGuard;
+
+impl Drop for ThreadGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            thread_event("panicked", Level::Error);
+        } else {
+            thread_event("exited", Level::Info);
+        }
+    }
+}
+
+/// `builder.spawn(f)` recording the thread's start and exit.
+pub fn spawn_thread<F, T>(builder: thread::Builder, f: F) -> std::io::Result<thread::JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    builder.spawn(move || {
+        thread_event("started", Level::Info);
+        let _guard = ThreadGuard;
+        f()
+    })
+}
+
+/// Record worker and blocking-pool threads of a tokio runtime. Replaces
+/// `on_thread_start`/`on_thread_stop` hooks set before.
+pub fn on_runtime_threads(builder: &mut tokio::runtime::Builder) -> &mut tokio::runtime::Builder {
+    builder
+        .on_thread_start(|| thread_event("started", Level::Info))
+        .on_thread_stop(|| thread_event("exited", Level::Info))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolState {
+    /// No open connections.
+    Empty,
+    /// A connection is idle or can still be opened.
+    Available,
+    /// All allowed connections are in use.
+    Exhausted,
+    Closed,
+}
+
+impl PoolState {
+    pub fn from_counts(size: u32, idle: usize, max: u32) -> Self {
+        if size == 0 {
+            PoolState::Empty
+        } else if idle == 0 && size >= max {
+            PoolState::Exhausted
+        } else {
+            PoolState::Available
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            PoolState::Empty => "empty",
+            PoolState::Available => "available",
+            PoolState::Exhausted => "exhausted",
+            PoolState::Closed => "closed",
+        }
+    }
+}
+
+/// Records a breadcrumb whenever a pool's observed state changes.
+#[derive(Debug, Clone)]
+pub struct PoolWatcher {
+    name: String,
+    last: Arc<Mutex<Option<PoolState>>>,
+}
+
+impl PoolWatcher {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            last: Default::default(),
+        }
+    }
+
+    /// Feed the current counts, e.g. from a periodic task; returns the state.
+    pub fn observe(&self, size: u32, idle: usize, max: u32) -> PoolState {
+        let state = PoolState::from_counts(size, idle, max);
+        let mut data = BTreeMap::new();
+        data.insert("size".to_string(), size.into());
+        data.insert("idle".to_string(), (idle as u64).into());
+        data.insert("max".to_string(), max.into());
+        self.set(state, data);
+        state
+    }
+
+    pub fn closed(&self) {
+        self.set(PoolState::Closed, BTreeMap::new());
+    }
+
+    fn set(&self, state: PoolState, data: BTreeMap<String, Value>) {
+        let previous = self.last.lock().unwrap().replace(state);
+        // A pool first seen healthy is not worth a breadcrumb
+        if previous == Some(state) || (previous.is_none() && state == PoolState::Available) {
+            return;
+        }
+        let level = match state {
+            PoolState::Exhausted | PoolState::Closed => Level::Warning,
+            PoolState::Empty | PoolState::Available => Level::Info,
+        };
+        let message = format!("Pool {} {}", self.name, state.as_str());
+        record(breadcrumb("pool", level, message, data));
+    }
+}
+
+/// Poll a sqlx pool every `interval` until it is closed (feature `sqlx`).
+#[cfg(feature = "sqlx")]
+pub fn watch_sqlx_pool<DB: sqlx::Database>(
+    name: &str,
+    pool: sqlx::Pool<DB>,
+    interval: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    let watcher = PoolWatcher::new(name);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if pool.is_closed() {
+                watcher.closed();
+                break;
+            }
+            watcher.observe(pool.size(), pool.num_idle(), pool.options().get_max_connections());
+        }
+    })
+}
@@ -0,0 +1,455 @@
+//! The ordered pipeline `before_send` runs every event through.
+//!
+//! Each concern (scrubbing, filtering, enrichment, sampling, truncation) is an
+//! [`EventProcessor`](processors::EventProcessor) registered for a
+//! [`Stage`](processors::Stage). Stages run in declaration order; within a
+//! stage, processors run in registration order. A processor returning `None`
+//! drops the event and ends the pipeline. The built-in processors are
+//! registered by default under the names listed in
+//! [`BUILTIN`](processors::BUILTIN); modules and applications add their own:
+//!
+//! ```ignore
+//! processors::register(Stage::Enrich, processors::from_fn("tenant", |mut event| {
+//!     event.tags.insert("tenant".into(), current_tenant());
+//!     Some(event)
+//! }));
+//! processors::register(Stage::Truncate, SizeLimits::default().max_extra_bytes(16 * 1024));
+//! ```
+//!
+//! Registering a processor under an existing name replaces it, so the same
+//! setup code can run more than once. The application hook passed to
+//! `SentryServiceBuilder::before_send` still runs after the whole pipeline.
+
+use super::{
+    aggregation, breadcrumbs, crash, dedupe, error_codes, error_sampling, escalation, filters, fingerprint,
+    issue_title, memory, origins, ownership, reference, runtime_config, scrubbing, secrets, severity, spike,
+};
+use sentry::{
+    protocol::{Event, Value},
+    Hub,
+};
+use std::{
+    fmt,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// Remove personal data before anything else sees the event.
+    Scrub,
+    /// Drop unwanted events.
+    Filter,
+    /// Add grouping, tags and context.
+    Enrich,
+    /// Drop events to save quota; escalated events are exempt.
+    Sample,
+    /// Shrink what is left before it is sent.
+    Truncate,
+}
+
+pub trait EventProcessor: Send + Sync {
+    /// Unique name, shown in debug output when the processor drops an event.
+    fn name(&self) -> &str;
+
+    fn process(&self, event: Event<'static>) -> Option<Event<'static>>;
+}
+
+struct FnProcessor<F> {
+    name: String,
+    f: F,
+}
+
+impl<F> EventProcessor for FnProcessor<F>
+where
+    F: Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn process(&self, event: Event<'static>) -> Option<Event<'static>> {
+        (self.f)(event)
+    }
+}
+
+/// A processor from a closure.
+pub fn from_fn<F>(name: &str, f: F) -> impl EventProcessor
+where
+    F: Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync,
+{
+    FnProcessor {
+        name: name.to_string(),
+        f,
+    }
+}
+
+/// Caps free-form payloads: the message and exception values, every `extra`
+/// value and breadcrumb messages and data. Longer strings are cut at a
+/// character boundary and end in "..."; objects and arrays that serialize
+/// larger than the limit are replaced by their truncated JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimits {
+    pub max_message_bytes: usize,
+    /// Per `extra` value.
+    pub max_extra_bytes: usize,
+    /// Per breadcrumb message and data value.
+    pub max_breadcrumb_bytes: usize,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        Self {
+            max_message_bytes: 8 * 1024,
+            max_extra_bytes: 16 * 1024,
+            max_breadcrumb_bytes: 1024,
+        }
+    }
+}
+
+impl SizeLimits {
+    pub fn max_message_bytes(mut self, max: usize) -> Self {
+        self.max_message_bytes = max;
+        self
+    }
+
+    pub fn max_extra_bytes(mut self, max: usize) -> Self {
+        self.max_extra_bytes = max;
+        self
+    }
+
+    pub fn max_breadcrumb_bytes(mut self, max: usize) -> Self {
+        self.max_breadcrumb_bytes = max;
+        self
+    }
+}
+
+impl EventProcessor for SizeLimits {
+    fn name(&self) -> &str {
+        "size-limits"
+    }
+
+    fn process(&self, mut event: Event<'static>) -> Option<Event<'static>> {
+        if let Some(message) = &mut event.message {
+            truncate(message, self.max_message_bytes);
+        }
+        if let Some(entry) = &mut event.logentry {
+            truncate(&mut entry.message, self.max_message_bytes);
+        }
+        for value in event.exception.values.iter_mut().filter_map(|exc| exc.value.as_mut()) {
+            truncate(value, self.max_message_bytes);
+        }
+        for value in event.extra.values_mut() {
+            truncate_value(value, self.max_extra_bytes);
+        }
+        for breadcrumb in &mut event.breadcrumbs.values {
+            if let Some(message) = &mut breadcrumb.message {
+                truncate(message, self.max_breadcrumb_bytes);
+            }
+            for value in breadcrumb.data.values_mut() {
+                truncate_value(value, self.max_breadcrumb_bytes);
+            }
+        }
+        Some(event)
+    }
+}
+
+fn truncate(text: &mut String, max: usize) {
+    if text.len() <= max {
+        return;
+    }
+    let mut cut = max.saturating_sub(3);
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    text.truncate(cut);
+    text.push_str("...");
+}
+
+fn truncate_value(value: &mut Value, max: usize) {
+    match value {
+        Value::String(text) => truncate(text, max),
+        Value::Array(_) | Value::Object(_) => {
+            let mut json = value.to_string();
+            if json.len() > max {
+                truncate(&mut json, max);
+                *value = Value::String(json);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Names of the built-in processors, in pipeline order.
+pub const BUILTIN: &[&str] = &[
+    "scrubbing",
+    "secrets",
+    "filters",
+    "origins",
+    "error-codes",
+    "ownership",
+    "fingerprint",
+    "severity",
+    "escalation",
+    "reference",
+    "memory",
+    "observers",
+    "aggregation",
+    "dedupe",
+    "spike-protection",
+    "sampling",
+    "breadcrumb-limits",
+    "size-limits",
+    "crash-checkpoint",
+];
+
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    processors: Vec<(Stage, Arc<dyn EventProcessor>)>,
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.processors
+                    .iter()
+                    .map(|(stage, processor)| (stage, processor.name())),
+            )
+            .finish()
+    }
+}
+
+impl Pipeline {
+    /// The built-in processors.
+    pub fn builtin() -> Self {
+        let mut pipeline = Self::default();
+        pipeline.register(
+            Stage::Scrub,
+            from_fn("scrubbing", |mut event| {
+                scrubbing::current().scrub_event(&mut event);
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Scrub,
+            from_fn("secrets", |mut event| {
+                if let Some(scanner) = secrets::scanner() {
+                    scanner.scan_event(&mut event);
+                }
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Filter,
+            from_fn("filters", |event| {
+                (!filters::current().ignores_event(&event)).then_some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Filter,
+            from_fn("origins", |event| origins::current().apply(event)),
+        );
+        // Before the fingerprint rules, so a code's fingerprint wins
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("error-codes", |mut event| {
+                error_codes::apply(&mut event);
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("ownership", |mut event| {
+                ownership::apply(&mut event);
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("fingerprint", |mut event| {
+                fingerprint::apply(&mut event);
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("severity", |mut event| {
+                severity::apply(&mut event);
+                Some(event)
+            }),
+        );
+        // Raise recurring low-level issues
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("escalation", |mut event| {
+                escalation::apply(&mut event);
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("reference", |mut event| {
+                let reference = reference::ErrorReference::from_event_id(event.event_id);
+                event.tags.insert(reference::TAG.to_string(), reference.to_string());
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("memory", |mut event| {
+                memory::attach(&mut event);
+                Some(event)
+            }),
+        );
+        // Alerting and the dashboard see every event that is not filtered
+        pipeline.register(
+            Stage::Enrich,
+            from_fn("observers", |event| {
+                #[cfg(feature = "alerting")]
+                super::alerting::observe(&event);
+                #[cfg(feature = "dashboard")]
+                super::dashboard::record(&event);
+                Some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Sample,
+            from_fn("aggregation", |event| {
+                (!aggregation::aggregate(&event)).then_some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Sample,
+            from_fn("dedupe", |mut event| dedupe::check(&mut event).then_some(event)),
+        );
+        pipeline.register(
+            Stage::Sample,
+            from_fn("spike-protection", |mut event| {
+                spike::check(&mut event).then_some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Sample,
+            from_fn("sampling", |mut event| {
+                let keep = error_sampling::check(&mut event).unwrap_or_else(|| {
+                    Hub::current()
+                        .client()
+                        .is_none_or(|client| client.sample_should_send(runtime_config::current().sample_rate))
+                });
+                keep.then_some(event)
+            }),
+        );
+        pipeline.register(
+            Stage::Truncate,
+            from_fn("breadcrumb-limits", |mut event| {
+                breadcrumbs::trim_event(&mut event);
+                Some(event)
+            }),
+        );
+        pipeline.register(Stage::Truncate, SizeLimits::default());
+        // Keep the last known scope for crash reporting after SIGKILL/OOM; only
+        // events that are actually sent, and at most one write per second
+        pipeline.register(
+            Stage::Truncate,
+            from_fn("crash-checkpoint", |event| {
+                crash::checkpoint(&event);
+                Some(event)
+            }),
+        );
+        pipeline
+    }
+
+    /// Add `processor` at the end of `stage`, or replace the one with the same name in place.
+    pub fn register(&mut self, stage: Stage, processor: impl EventProcessor + 'static) {
+        let processor: Arc<dyn EventProcessor> = Arc::new(processor);
+        if let Some(slot) = self
+            .processors
+            .iter_mut()
+            .find(|(existing, p)| *existing == stage && p.name() == processor.name())
+        {
+            slot.1 = processor;
+            return;
+        }
+        self.processors.retain(|(_, p)| p.name() != processor.name());
+        let at = self.processors.partition_point(|(existing, _)| *existing <= stage);
+        self.processors.insert(at, (stage, processor));
+    }
+
+    /// Remove the processor with this name; true if there was one.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let before = self.processors.len();
+        self.processors.retain(|(_, processor)| processor.name() != name);
+        self.processors.len() != before
+    }
+
+    /// Names in the order they run.
+    pub fn names(&self) -> Vec<String> {
+        self.processors
+            .iter()
+            .map(|(_, processor)| processor.name().to_string())
+            .collect()
+    }
+
+    /// Run the event through every processor; `None` once one drops it.
+    pub fn run(&self, event: Event<'static>) -> Option<Event<'static>> {
+        self.run_where(event, |_| true)
+    }
+
+    /// Like [`run`](Self::run), with only the processors of `stages`.
+    pub fn run_stages(&self, event: Event<'static>, stages: &[Stage]) -> Option<Event<'static>> {
+        self.run_where(event, |stage| stages.contains(&stage))
+    }
+
+    fn run_where(&self, mut event: Event<'static>, include: impl Fn(Stage) -> bool) -> Option<Event<'static>> {
+        let debug = runtime_config::current().debug;
+        for (stage, processor) in &self.processors {
+            if !include(*stage) || (*stage == Stage::Sample && escalation::is_escalated(&event)) {
+                continue;
+            }
+            let dropped = debug.then(|| (event.event_id, issue_title(&event)));
+            match processor.process(event) {
+                Some(processed) => event = processed,
+                None => {
+                    if let Some((event_id, key)) = dropped {
+                        eprintln!("[observability] dropped {} by {} ({})", event_id, processor.name(), key);
+                    }
+                    return None;
+                }
+            }
+        }
+        if debug {
+            eprintln!("[observability] sent {} ({})", event.event_id, issue_title(&event));
+        }
+        Some(event)
+    }
+}
+
+fn state() -> &'static RwLock<Pipeline> {
+    static STATE: OnceLock<RwLock<Pipeline>> = OnceLock::new();
+    STATE.get_or_init(|| RwLock::new(Pipeline::builtin()))
+}
+
+/// Add a processor to the global pipeline (see [`Pipeline::register`]).
+pub fn register(stage: Stage, processor: impl EventProcessor + 'static) {
+    state().write().unwrap().register(stage, processor);
+}
+
+pub fn unregister(name: &str) -> bool {
+    state().write().unwrap().unregister(name)
+}
+
+pub fn current() -> Pipeline {
+    state().read().unwrap().clone()
+}
+
+pub(crate) fn run(event: Event<'static>) -> Option<Event<'static>> {
+    let pipeline = current();
+    pipeline.run(event)
+}
+
+/// Run the global pipeline limited to `stages`, e.g. to re-apply the
+/// scrubbing and filter rules to stored events without sampling them again.
+pub fn run_stages(event: Event<'static>, stages: &[Stage]) -> Option<Event<'static>> {
+    let pipeline = current();
+    pipeline.run_stages(event, stages)
+}
@@ -0,0 +1,291 @@
+//! Stack sampling for transactions (feature `profiling`, Unix only).
+//!
+//! With `profiles_sample_rate` above zero, a share of the sampled
+//! transactions starts pprof-rs at 100 Hz; when the transaction reaches the
+//! transport the samples are converted to Sentry's profile format and sent as
+//! a `profile` item in the same envelope.
+//!
+//! pprof samples the whole process through `SIGPROF`, so only one transaction
+//! is profiled at a time: transactions starting while another one is being
+//! profiled are not profiled, and the profile covers every thread that was
+//! busy meanwhile. Samples are aggregated per stack, each stack is timed at
+//! its first occurrence. A profile not picked up within
+//! [`MAX_DURATION`](profiling::MAX_DURATION) (the transaction was dropped or
+//! not sampled) is discarded on the next start.
+
+use pprof::{ProfilerGuard, ProfilerGuardBuilder};
+use sentry::{
+    protocol::{Envelope, EnvelopeItem, Transaction},
+    ClientOptions, TransactionContext, Transport, TransportFactory,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub const MAX_DURATION: Duration = Duration::from_secs(30);
+const FREQUENCY: i32 = 100;
+
+fn rate_state() -> &'static RwLock<f32> {
+    static RATE: OnceLock<RwLock<f32>> = OnceLock::new();
+    RATE.get_or_init(Default::default)
+}
+
+/// Share (0.0 - 1.0) of sampled transactions to profile.
+pub fn configure(profiles_sample_rate: f32) {
+    *rate_state().write().unwrap() = profiles_sample_rate.clamp(0.0, 1.0);
+}
+
+pub fn profiles_sample_rate() -> f32 {
+    *rate_state().read().unwrap()
+}
+
+struct Running {
+    trace_id: String,
+    name: String,
+    started: Instant,
+    guard: ProfilerGuard<'static>,
+}
+
+fn running() -> &'static Mutex<Option<Running>> {
+    static RUNNING: OnceLock<Mutex<Option<Running>>> = OnceLock::new();
+    RUNNING.get_or_init(Default::default)
+}
+
+fn roll(rate: f32) -> bool {
+    rate >= 1.0 || (sentry::types::random_uuid().as_u128() % 10_000) as f32 / 10_000.0 < rate
+}
+
+/// Sampling decision for a new transaction (`traces_sampler`); starts the
+/// profiler for a share of the sampled ones.
+pub fn sample(ctx: &TransactionContext, traces_sample_rate: f32) -> f32 {
+    let profiles_sample_rate = profiles_sample_rate();
+    if profiles_sample_rate <= 0.0 {
+        return traces_sample_rate;
+    }
+    if !roll(traces_sample_rate) {
+        return 0.0;
+    }
+    if roll(profiles_sample_rate) {
+        start(ctx);
+    }
+    1.0
+}
+
+fn start(ctx: &TransactionContext) {
+    let mut running = running().lock().unwrap();
+    if running.as_ref().is_some_and(|r| r.started.elapsed() < MAX_DURATION) {
+        return;
+    }
+    // Drop a stale profiler before starting the next one
+    *running = None;
+    let guard = ProfilerGuardBuilder::default()
+        .frequency(FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build();
+    match guard {
+        Ok(guard) => {
+            *running = Some(Running {
+                trace_id: ctx.trace_id().to_string(),
+                name: ctx.name().to_string(),
+                started: Instant::now(),
+                guard,
+            })
+        }
+        Err(e) => eprintln!("Cannot start profiler: {}", e),
+    }
+}
+
+/// Stop the profiler if it belongs to `transaction` and build its profile.
+fn finish(transaction: &Transaction<'static>) -> Option<Value> {
+    let trace_id = transaction.contexts.get("trace").and_then(|context| match context {
+        sentry::protocol::Context::Trace(trace) => Some(trace.trace_id.to_string()),
+        _ => None,
+    })?;
+    let name = transaction.name.as_deref().unwrap_or_default();
+    let mut running = running().lock().unwrap();
+    if !running
+        .as_ref()
+        .is_some_and(|r| r.trace_id == trace_id && r.name == name)
+    {
+        return None;
+    }
+    let Running { guard, .. } = running.take()?;
+    drop(running);
+
+    let report = guard.report().build().ok()?;
+    drop(guard);
+    let samples = report.data.iter().map(|(frames, count)| {
+        let stack = frames
+            .frames
+            .iter()
+            .flatten()
+            .map(|symbol| (symbol.name(), symbol.filename().into_owned(), symbol.lineno()))
+            .collect();
+        Sample {
+            stack,
+            thread_id: frames.thread_id,
+            thread_name: frames.thread_name.clone(),
+            timestamp: frames.sample_timestamp,
+            count: (*count).max(0) as usize,
+        }
+    });
+    Some(profile(transaction, &trace_id, samples))
+}
+
+/// Aggregated samples of one stack, innermost frame first.
+pub struct Sample {
+    pub stack: Vec<(String, String, u32)>,
+    pub thread_id: u64,
+    pub thread_name: String,
+    pub timestamp: SystemTime,
+    pub count: usize,
+}
+
+/// Sentry sample-format profile (version 1) for `transaction`.
+pub fn profile(transaction: &Transaction<'static>, trace_id: &str, samples: impl IntoIterator<Item = Sample>) -> Value {
+    let start = transaction.start_timestamp;
+    let mut frames: Vec<Value> = Vec::new();
+    let mut frame_ids: HashMap<(String, String, u32), usize> = HashMap::new();
+    let mut stacks: Vec<Vec<usize>> = Vec::new();
+    let mut stack_ids: HashMap<Vec<usize>, usize> = HashMap::new();
+    let mut threads = serde_json::Map::new();
+    let mut sample_values = Vec::new();
+
+    for sample in samples {
+        let stack: Vec<usize> = sample
+            .stack
+            .into_iter()
+            .map(|frame| {
+                *frame_ids.entry(frame.clone()).or_insert_with(|| {
+                    let (function, filename, lineno) = frame;
+                    frames.push(json!({ "function": function, "filename": filename, "lineno": lineno }));
+                    frames.len() - 1
+                })
+            })
+            .collect();
+        let stack_id = *stack_ids.entry(stack.clone()).or_insert_with(|| {
+            stacks.push(stack);
+            stacks.len() - 1
+        });
+        threads
+            .entry(sample.thread_id.to_string())
+            .or_insert_with(|| json!({ "name": sample.thread_name }));
+        let elapsed = sample.timestamp.duration_since(start).unwrap_or_default();
+        for _ in 0..sample.count {
+            sample_values.push(json!({
+                "stack_id": stack_id,
+                "thread_id": sample.thread_id.to_string(),
+                "elapsed_since_start_ns": elapsed.as_nanos().to_string(),
+            }));
+        }
+    }
+
+    json!({
+        "version": "1",
+        "event_id": sentry::types::random_uuid().simple().to_string(),
+        "platform": "rust",
+        "timestamp": rfc3339(start),
+        "release": transaction.release.as_deref().unwrap_or_default(),
+        "environment": transaction.environment.as_deref().unwrap_or_default(),
+        "os": { "name": std::env::consts::OS, "version": "", "build_number": "" },
+        "device": { "architecture": std::env::consts::ARCH },
+        "transaction": {
+            "id": transaction.event_id.simple().to_string(),
+            "name": transaction.name.as_deref().unwrap_or_default(),
+            "trace_id": trace_id,
+            "active_thread_id": "0",
+        },
+        "debug_meta": { "images": [] },
+        "profile": {
+            "samples": sample_values,
+            "stacks": stacks,
+            "frames": frames,
+            "thread_metadata": threads,
+        },
+    })
+}
+
+/// `2024-05-01T12:00:00.123456Z`
+fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_micros()
+    )
+}
+
+/// Appends the profile to envelopes carrying the profiled transaction.
+pub struct ProfilingTransport {
+    inner: Arc<dyn Transport>,
+}
+
+impl Transport for ProfilingTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        let profile = envelope.items().find_map(|item| match item {
+            EnvelopeItem::Transaction(transaction) => finish(transaction),
+            _ => None,
+        });
+        let Some(profile) = profile else {
+            return self.inner.send_envelope(envelope);
+        };
+        // The SDK has no profile item type: append it to the serialized envelope
+        let mut bytes = Vec::new();
+        if envelope.to_writer(&mut bytes).is_err() {
+            return self.inner.send_envelope(envelope);
+        }
+        let payload = profile.to_string();
+        bytes.extend_from_slice(
+            json!({ "type": "profile", "length": payload.len() })
+                .to_string()
+                .as_bytes(),
+        );
+        bytes.push(b'\n');
+        bytes.extend_from_slice(payload.as_bytes());
+        bytes.push(b'\n');
+        match Envelope::from_bytes_raw(bytes) {
+            Ok(with_profile) => self.inner.send_envelope(with_profile),
+            Err(_) => self.inner.send_envelope(envelope),
+        }
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        self.inner.flush(timeout)
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        self.inner.shutdown(timeout)
+    }
+}
+
+pub(crate) fn wrap(factory: Arc<dyn TransportFactory>) -> Arc<dyn TransportFactory> {
+    Arc::new(move |options: &ClientOptions| -> Arc<dyn Transport> {
+        Arc::new(ProfilingTransport {
+            inner: factory.create_transport(options),
+        })
+    })
+}
+
+pub(crate) fn is_enabled() -> bool {
+    profiles_sample_rate() > 0.0
+}
@@ -0,0 +1,280 @@
+//! Trace headers for clients, servers and message queues.
+//!
+//! The SDK only emits `sentry-trace`; [`headers`](propagation::headers) adds a
+//! `baggage` header with the dynamic sampling context (trace id, environment,
+//! release, public key) from the current client and a W3C `traceparent`, so
+//! downstream services, whether they use a Sentry SDK or OpenTelemetry, and
+//! Bugsink see the same trace.
+//!
+//! For hand-written clients and servers, [`inject_trace_context`] and
+//! [`extract_trace_context`] work on any [`HeaderCarrier`]: string maps,
+//! `http::HeaderMap` (features `axum` / `grpc`), the actix-web and reqwest
+//! header maps (features `actix` / `reqwest-middleware`) and tonic metadata
+//! (feature `grpc`).
+//! Extraction prefers `sentry-trace` and falls back to `traceparent`:
+//!
+//! ```ignore
+//! let ctx = propagation::continue_from_headers("GET /orders", Op::HttpServer.as_str(), request.headers());
+//! let transaction = Hub::current().start_transaction(ctx);
+//! ```
+//!
+//! [`inject_trace_context`]: propagation::inject_trace_context
+//! [`extract_trace_context`]: propagation::extract_trace_context
+//! [`HeaderCarrier`]: propagation::HeaderCarrier
+
+use sentry::{
+    protocol::{SpanId, TraceId},
+    Hub, TransactionContext, TransactionOrSpan,
+};
+use std::collections::{BTreeMap, HashMap};
+
+pub const SENTRY_TRACE: &str = "sentry-trace";
+pub const TRACEPARENT: &str = "traceparent";
+pub const BAGGAGE: &str = "baggage";
+
+/// The upstream span a trace continues from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceParent {
+    pub trace_id: TraceId,
+    pub parent_span_id: SpanId,
+    /// `None` when the upstream deferred the sampling decision.
+    pub sampled: Option<bool>,
+}
+
+impl TraceParent {
+    /// `<trace_id>-<span_id>[-<sampled>]`
+    pub fn parse_sentry_trace(header: &str) -> Option<Self> {
+        let mut parts = header.trim().splitn(3, '-');
+        Some(Self {
+            trace_id: parts.next()?.parse().ok()?,
+            parent_span_id: parts.next()?.parse().ok()?,
+            sampled: match parts.next() {
+                Some("1") => Some(true),
+                Some("0") => Some(false),
+                _ => None,
+            },
+        })
+    }
+
+    /// `<version>-<trace_id>-<span_id>-<flags>`; all-zero ids are invalid.
+    pub fn parse_traceparent(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let version = parts.next()?;
+        let (trace_id, span_id, flags) = (parts.next()?, parts.next()?, parts.next()?);
+        // Version 00 has exactly four fields; later versions may append more
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        if trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+            return None;
+        }
+        if trace_id.bytes().all(|b| b == b'0') || span_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(Self {
+            trace_id: trace_id.parse().ok()?,
+            parent_span_id: span_id.parse().ok()?,
+            sampled: Some(flags & 1 == 1),
+        })
+    }
+
+    pub fn sentry_trace(&self) -> String {
+        match self.sampled {
+            Some(sampled) => format!("{}-{}-{}", self.trace_id, self.parent_span_id, u8::from(sampled)),
+            None => format!("{}-{}", self.trace_id, self.parent_span_id),
+        }
+    }
+
+    /// W3C has no deferred decision; unknown counts as not sampled.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id,
+            self.parent_span_id,
+            u8::from(self.sampled == Some(true))
+        )
+    }
+
+    /// Context for a transaction continuing this trace.
+    pub fn transaction(&self, name: &str, op: &str) -> TransactionContext {
+        TransactionContext::continue_from_headers(name, op, [(SENTRY_TRACE, self.sentry_trace().as_str())])
+    }
+}
+
+/// Header storage the trace context is read from and written to.
+/// Lookups are case-insensitive.
+pub trait HeaderCarrier {
+    fn get_header(&self, name: &str) -> Option<&str>;
+    fn set_header(&mut self, name: &'static str, value: String);
+}
+
+impl HeaderCarrier for HashMap<String, String> {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn set_header(&mut self, name: &'static str, value: String) {
+        self.retain(|key, _| !key.eq_ignore_ascii_case(name));
+        self.insert(name.to_string(), value);
+    }
+}
+
+impl HeaderCarrier for BTreeMap<String, String> {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn set_header(&mut self, name: &'static str, value: String) {
+        self.retain(|key, _| !key.eq_ignore_ascii_case(name));
+        self.insert(name.to_string(), value);
+    }
+}
+
+#[cfg(any(feature = "axum", feature = "grpc"))]
+mod http1 {
+    #[cfg(feature = "axum")]
+    pub use axum::http::{HeaderMap, HeaderValue};
+    #[cfg(not(feature = "axum"))]
+    pub use tonic::codegen::http::{HeaderMap, HeaderValue};
+}
+
+#[cfg(any(feature = "axum", feature = "grpc"))]
+impl HeaderCarrier for http1::HeaderMap {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.get(name)?.to_str().ok()
+    }
+
+    fn set_header(&mut self, name: &'static str, value: String) {
+        if let Ok(value) = http1::HeaderValue::from_str(&value) {
+            self.insert(name, value);
+        }
+    }
+}
+
+#[cfg(feature = "actix")]
+impl HeaderCarrier for actix_web::http::header::HeaderMap {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.get(name)?.to_str().ok()
+    }
+
+    fn set_header(&mut self, name: &'static str, value: String) {
+        if let Ok(value) = actix_web::http::header::HeaderValue::from_str(&value) {
+            self.insert(actix_web::http::header::HeaderName::from_static(name), value);
+        }
+    }
+}
+
+#[cfg(feature = "reqwest-middleware")]
+impl HeaderCarrier for reqwest::header::HeaderMap {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.get(name)?.to_str().ok()
+    }
+
+    fn set_header(&mut self, name: &'static str, value: String) {
+        if let Ok(value) = reqwest::header::HeaderValue::from_str(&value) {
+            self.insert(name, value);
+        }
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl HeaderCarrier for tonic::metadata::MetadataMap {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.get(name)?.to_str().ok()
+    }
+
+    fn set_header(&mut self, name: &'static str, value: String) {
+        if let Ok(value) = value.parse() {
+            self.insert(name, value);
+        }
+    }
+}
+
+/// The upstream span from `sentry-trace`, or else `traceparent`.
+pub fn extract_trace_context(headers: &(impl HeaderCarrier + ?Sized)) -> Option<TraceParent> {
+    headers
+        .get_header(SENTRY_TRACE)
+        .and_then(TraceParent::parse_sentry_trace)
+        .or_else(|| headers.get_header(TRACEPARENT).and_then(TraceParent::parse_traceparent))
+}
+
+/// Transaction context continuing the trace in `headers`, or starting a
+/// new trace without them.
+pub fn continue_from_headers(name: &str, op: &str, headers: &(impl HeaderCarrier + ?Sized)) -> TransactionContext {
+    match extract_trace_context(headers) {
+        Some(parent) => parent.transaction(name, op),
+        None => TransactionContext::new(name, op),
+    }
+}
+
+/// Write the headers of the current span, if any, into `headers`.
+/// An existing `baggage` header keeps its entries from other vendors.
+pub fn inject_trace_context(headers: &mut (impl HeaderCarrier + ?Sized)) {
+    let Some(span) = Hub::current().configure_scope(|scope| scope.get_span()) else {
+        return;
+    };
+    for (name, value) in self::headers(&span) {
+        let value = match headers.get_header(name) {
+            Some(existing) if name == BAGGAGE && !existing.contains("sentry-") => format!("{},{}", existing, value),
+            _ => value,
+        };
+        headers.set_header(name, value);
+    }
+}
+
+/// `sentry-trace`, `baggage` and `traceparent` headers continuing
+/// `span`'s trace.
+pub fn headers(span: &TransactionOrSpan) -> Vec<(&'static str, String)> {
+    let mut headers: Vec<_> = span.iter_headers().collect();
+    let traceparent = headers
+        .iter()
+        .find(|(name, _)| *name == SENTRY_TRACE)
+        .and_then(|(_, value)| TraceParent::parse_sentry_trace(value));
+    if let Some(baggage) = baggage(span.get_trace_context().trace_id) {
+        headers.push((BAGGAGE, baggage));
+    }
+    if let Some(traceparent) = traceparent {
+        headers.push((TRACEPARENT, traceparent.traceparent()));
+    }
+    headers
+}
+
+/// W3C baggage with the `sentry-` entries for `trace_id`, if a client is bound.
+pub fn baggage(trace_id: TraceId) -> Option<String> {
+    let client = Hub::current().client()?;
+    let options = client.options();
+    let mut entries = vec![("trace_id", trace_id.to_string())];
+    if let Some(environment) = &options.environment {
+        entries.push(("environment", environment.to_string()));
+    }
+    if let Some(release) = &options.release {
+        entries.push(("release", release.to_string()));
+    }
+    if let Some(dsn) = &options.dsn {
+        entries.push(("public_key", dsn.public_key().to_string()));
+    }
+    let entries: Vec<String> = entries
+        .into_iter()
+        .map(|(key, value)| format!("sentry-{}={}", key, encode(&value)))
+        .collect();
+    Some(entries.join(","))
+}
+
+/// Percent-encode everything outside the characters baggage values may carry unescaped.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' | b':' | b'/' | b'+' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
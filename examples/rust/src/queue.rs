@@ -0,0 +1,240 @@
+//! Trace continuation for any job queue, for libraries without a dedicated
+//! integration (Redis lists, database-backed queues, in-process channels).
+//!
+//! The producer wraps the push in an [`EnqueueContext`](queue::EnqueueContext),
+//! which runs a `queue.publish` span (a transaction when nothing is active)
+//! and writes the trace and the [job context](jobs) into the job's headers.
+//! The worker rebuilds a [`DequeueContext`](queue::DequeueContext) from them
+//! and runs the job in a `queue.process` transaction continuing that trace,
+//! on a hub carrying the producer's user and tags:
+//!
+//! ```ignore
+//! // producer
+//! let enqueue = EnqueueContext::start("emails", "redis");
+//! let mut job = Job { headers: BTreeMap::new(), payload };
+//! enqueue.inject(&mut job.headers);
+//! let result = redis.rpush("emails", serde_json::to_string(&job)?).await;
+//! enqueue.finish(&result);
+//!
+//! // worker
+//! let job: Job = serde_json::from_str(&raw)?;
+//! DequeueContext::from_headers("emails", "redis", &job.headers)
+//!     .retry_count(job.attempts)
+//!     .process(send_email(job.payload))
+//!     .await?;
+//! ```
+//!
+//! Headers are `sentry-trace`, `baggage` and `traceparent`, plus
+//! `sentry-job-scope` (user, tags and origin as JSON) and
+//! `sentry-job-enqueued-at`, which gives the transaction's
+//! `messaging.message.receive.latency`. Queues without header support can
+//! store the map next to the payload, as above.
+
+use super::{
+    jobs::JobContext,
+    ops::Op,
+    propagation::{self, HeaderCarrier},
+    traced,
+};
+use sentry::{
+    protocol::{SpanStatus, Value},
+    Hub, SentryFutureExt, Transaction, TransactionOrSpan,
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    future::Future,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// User, tags and origin of the enqueuing scope, as JSON.
+pub const SCOPE_HEADER: &str = "sentry-job-scope";
+
+/// Enqueue time in milliseconds since the Unix epoch.
+pub const ENQUEUED_AT_HEADER: &str = "sentry-job-enqueued-at";
+
+/// The producer side of a job; see the [module docs](self).
+pub struct EnqueueContext {
+    queue: String,
+    span: TransactionOrSpan,
+    context: JobContext,
+}
+
+impl EnqueueContext {
+    /// Start the `queue.publish` span for a job pushed to `queue`;
+    /// `system` names the queue technology (`redis`, `postgres`, ...).
+    pub fn start(queue: &str, system: &str) -> Self {
+        let span = traced::start(queue, Op::QueuePublish);
+        span.set_data("messaging.system", system.into());
+        span.set_data("messaging.destination.name", queue.into());
+        Self {
+            queue: queue.to_string(),
+            span,
+            context: JobContext::capture(),
+        }
+    }
+
+    pub fn message_id(&self, id: &str) {
+        self.span.set_data("messaging.message.id", id.into());
+    }
+
+    /// Write the trace and job context into the job's `headers`.
+    pub fn inject(&self, headers: &mut (impl HeaderCarrier + ?Sized)) {
+        for (name, value) in propagation::headers(&self.span) {
+            headers.set_header(name, value);
+        }
+        headers.set_header(SCOPE_HEADER, encode_scope(&self.context));
+        headers.set_header(ENQUEUED_AT_HEADER, now_millis().to_string());
+    }
+
+    /// [`inject`](Self::inject) into a new map.
+    pub fn headers(&self) -> BTreeMap<String, String> {
+        let mut headers = BTreeMap::new();
+        self.inject(&mut headers);
+        headers
+    }
+
+    /// Finish the span with the push's outcome; an error is captured.
+    pub fn finish<T, E: Error>(self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.span.set_status(SpanStatus::Ok),
+            Err(e) => {
+                self.span.set_status(SpanStatus::InternalError);
+                let hub = Hub::current();
+                hub.with_scope(
+                    |scope| scope.set_tag("messaging.destination", &self.queue),
+                    || hub.capture_error(e),
+                );
+            }
+        }
+        self.span.finish();
+    }
+}
+
+/// The worker side of a job; see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct DequeueContext {
+    queue: String,
+    system: String,
+    context: JobContext,
+    enqueued_at: Option<u64>,
+    message_id: Option<String>,
+    retry_count: Option<u64>,
+}
+
+impl DequeueContext {
+    /// Read trace and job context from the job's `headers`; missing or
+    /// malformed headers start a new trace with an empty context.
+    pub fn from_headers(queue: &str, system: &str, headers: &(impl HeaderCarrier + ?Sized)) -> Self {
+        let mut context = headers.get_header(SCOPE_HEADER).map(decode_scope).unwrap_or_default();
+        context.trace = propagation::extract_trace_context(headers);
+        Self {
+            queue: queue.to_string(),
+            system: system.to_string(),
+            context,
+            enqueued_at: headers
+                .get_header(ENQUEUED_AT_HEADER)
+                .and_then(|v| v.trim().parse().ok()),
+            message_id: None,
+            retry_count: None,
+        }
+    }
+
+    pub fn message_id(mut self, id: &str) -> Self {
+        self.message_id = Some(id.to_string());
+        self
+    }
+
+    /// Earlier attempts at this job.
+    pub fn retry_count(mut self, count: u64) -> Self {
+        self.retry_count = Some(count);
+        self
+    }
+
+    pub fn context(&self) -> &JobContext {
+        &self.context
+    }
+
+    /// Run `handler` in the `queue.process` transaction; an error is captured.
+    pub async fn process<T, E, F>(self, handler: F) -> Result<T, E>
+    where
+        E: Error,
+        F: Future<Output = Result<T, E>>,
+    {
+        let (hub, transaction) = self.begin();
+        let result = handler.bind_hub(hub.clone()).await;
+        end(&hub, transaction, &result);
+        result
+    }
+
+    /// [`process`](Self::process) for jobs run on a plain thread.
+    pub fn process_blocking<T, E: Error>(self, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let (hub, transaction) = self.begin();
+        let result = Hub::run(hub.clone(), f);
+        end(&hub, transaction, &result);
+        result
+    }
+
+    fn begin(&self) -> (Arc<Hub>, Transaction) {
+        let hub = self.context.hub();
+        let ctx = self.context.transaction(&self.queue, Op::QueueProcess.as_str());
+        let transaction = hub.start_transaction(ctx);
+        transaction.set_data("messaging.system", self.system.as_str().into());
+        transaction.set_data("messaging.destination.name", self.queue.as_str().into());
+        if let Some(id) = &self.message_id {
+            transaction.set_data("messaging.message.id", id.as_str().into());
+        }
+        if let Some(count) = self.retry_count {
+            transaction.set_data("messaging.message.retry.count", count.into());
+        }
+        if let Some(enqueued_at) = self.enqueued_at {
+            let latency = now_millis().saturating_sub(enqueued_at);
+            transaction.set_data("messaging.message.receive.latency", latency.into());
+        }
+        hub.configure_scope(|scope| {
+            scope.set_span(Some(transaction.clone().into()));
+            scope.set_tag("messaging.destination", &self.queue);
+        });
+        (hub, transaction)
+    }
+}
+
+fn end<T, E: Error>(hub: &Hub, transaction: Transaction, result: &Result<T, E>) {
+    match result {
+        Ok(_) => transaction.set_status(SpanStatus::Ok),
+        Err(e) => {
+            transaction.set_status(SpanStatus::InternalError);
+            hub.capture_error(e);
+        }
+    }
+    transaction.finish();
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+fn encode_scope(context: &JobContext) -> String {
+    serde_json::json!({
+        "user": context.user,
+        "tags": context.tags,
+        "origin": context.origin,
+    })
+    .to_string()
+}
+
+fn decode_scope(header: &str) -> JobContext {
+    let Ok(Value::Object(mut fields)) = serde_json::from_str(header) else {
+        return JobContext::default();
+    };
+    let mut field = |name: &str| fields.remove(name).unwrap_or_default();
+    JobContext {
+        user: serde_json::from_value(field("user")).unwrap_or_default(),
+        tags: serde_json::from_value(field("tags")).unwrap_or_default(),
+        trace: None,
+        origin: serde_json::from_value(field("origin")).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,356 @@
+//! Token-bucket rate limiting in the transport, per category.
+//!
+//! Error events and transactions get separate buckets (`per_minute` refill,
+//! `burst` capacity); envelopes of other kinds (sessions, check-ins) are never
+//! limited. What happens once a bucket is empty is set by [`Overflow`]:
+//! drop, keep a fraction, or hold a bounded number of envelopes until tokens
+//! are available again. Queued envelopes only live in memory: flush and
+//! shutdown release them as tokens allow within their timeout, and shutdown
+//! counts whatever is left as dropped.
+//!
+//! ```ignore
+//! let sentry = SentryService::builder()
+//!     .rate_limit(RateLimits {
+//!         errors: Some(Limit::per_minute(600)),
+//!         transactions: Some(Limit::per_minute(3000)),
+//!         overflow: Overflow::Queue(500),
+//!     })
+//!     .build();
+//! ```
+
+use sentry::{
+    protocol::{Envelope, EnvelopeItem},
+    ClientOptions, Transport, TransportFactory,
+};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Error,
+    Transaction,
+    Other,
+}
+
+impl Category {
+    pub fn of(envelope: &Envelope) -> Self {
+        envelope
+            .items()
+            .find_map(|item| match item {
+                EnvelopeItem::Event(_) => Some(Category::Error),
+                EnvelopeItem::Transaction(_) => Some(Category::Transaction),
+                _ => None,
+            })
+            .unwrap_or(Category::Other)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub per_minute: u32,
+    /// Bucket capacity: envelopes that can be sent at once after a quiet period.
+    pub burst: u32,
+}
+
+impl Limit {
+    /// `per_minute` envelopes, with a burst of up to a full minute's budget.
+    pub fn per_minute(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            burst: per_minute,
+        }
+    }
+
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+}
+
+/// What to do with envelopes once the bucket is empty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    Drop,
+    /// Send this fraction of the overflow (every n-th envelope), drop the rest.
+    Sample(f32),
+    /// Hold up to this many envelopes and send them as tokens refill.
+    Queue(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimits {
+    pub errors: Option<Limit>,
+    pub transactions: Option<Limit>,
+    pub overflow: Overflow,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            errors: None,
+            transactions: None,
+            overflow: Overflow::Drop,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitStats {
+    pub dropped_errors: u64,
+    pub dropped_transactions: u64,
+    pub queued: usize,
+}
+
+struct Bucket {
+    limit: Limit,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(limit: Limit) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.burst.max(1)),
+            refilled: Instant::now(),
+        }
+    }
+
+    fn try_take(&mut self) -> bool {
+        let now = Instant::now();
+        let refill = now.duration_since(self.refilled).as_secs_f64() * f64::from(self.limit.per_minute) / 60.0;
+        self.tokens = (self.tokens + refill).min(f64::from(self.limit.burst.max(1)));
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub struct RateLimiter {
+    limits: RateLimits,
+    errors: Option<Mutex<Bucket>>,
+    transactions: Option<Mutex<Bucket>>,
+    overflowed: AtomicU64,
+    dropped_errors: AtomicU64,
+    dropped_transactions: AtomicU64,
+    queue: Mutex<VecDeque<(Category, Envelope)>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            errors: limits.errors.map(|limit| Mutex::new(Bucket::new(limit))),
+            transactions: limits.transactions.map(|limit| Mutex::new(Bucket::new(limit))),
+            overflowed: AtomicU64::new(0),
+            dropped_errors: AtomicU64::new(0),
+            dropped_transactions: AtomicU64::new(0),
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.limits
+    }
+
+    /// Take a token for the category; unlimited categories always succeed.
+    pub fn try_acquire(&self, category: Category) -> bool {
+        let bucket = match category {
+            Category::Error => &self.errors,
+            Category::Transaction => &self.transactions,
+            Category::Other => return true,
+        };
+        bucket.as_ref().is_none_or(|bucket| bucket.lock().unwrap().try_take())
+    }
+
+    /// Apply the overflow policy; returns the envelope if it should be sent now.
+    pub(crate) fn overflow(&self, category: Category, envelope: Envelope) -> Option<Envelope> {
+        match self.limits.overflow {
+            Overflow::Drop => {}
+            Overflow::Sample(rate) if rate > 0.0 => {
+                let every = (1.0 / rate.min(1.0)).round() as u64;
+                if self.overflowed.fetch_add(1, Ordering::Relaxed).is_multiple_of(every) {
+                    return Some(envelope);
+                }
+            }
+            Overflow::Sample(_) => {}
+            Overflow::Queue(max) => {
+                let mut queue = self.queue.lock().unwrap();
+                if queue.len() < max {
+                    queue.push_back((category, envelope));
+                    return None;
+                }
+            }
+        }
+        self.record_drop(category);
+        None
+    }
+
+    fn record_drop(&self, category: Category) {
+        let counter = match category {
+            Category::Error => &self.dropped_errors,
+            _ => &self.dropped_transactions,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        crate::health::record_dropped(crate::health::DropReason::RateLimit);
+    }
+
+    /// Queued envelopes that have a token available now, oldest first.
+    fn release(&self) -> Vec<Envelope> {
+        let mut queue = self.queue.lock().unwrap();
+        let mut released = Vec::new();
+        let mut held = VecDeque::with_capacity(queue.len());
+        while let Some((category, envelope)) = queue.pop_front() {
+            if self.try_acquire(category) {
+                released.push(envelope);
+            } else {
+                held.push_back((category, envelope));
+            }
+        }
+        *queue = held;
+        released
+    }
+
+    /// Release queued envelopes into `inner` as tokens refill, until the
+    /// queue is empty (true) or `deadline` passes.
+    fn drain(&self, inner: &dyn Transport, deadline: Instant) -> bool {
+        loop {
+            self.release()
+                .into_iter()
+                .for_each(|envelope| inner.send_envelope(envelope));
+            if self.queue.lock().unwrap().is_empty() {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            thread::sleep((deadline - now).min(Duration::from_millis(50)));
+        }
+    }
+
+    /// Drop everything still queued, counting it.
+    fn discard_queue(&self) {
+        let queue = std::mem::take(&mut *self.queue.lock().unwrap());
+        for (category, _) in queue {
+            self.record_drop(category);
+        }
+    }
+
+    pub fn stats(&self) -> RateLimitStats {
+        RateLimitStats {
+            dropped_errors: self.dropped_errors.load(Ordering::Relaxed),
+            dropped_transactions: self.dropped_transactions.load(Ordering::Relaxed),
+            queued: self.queue.lock().unwrap().len(),
+        }
+    }
+}
+
+/// Transport applying a [`RateLimiter`] before the wrapped transport.
+pub struct RateLimitedTransport {
+    inner: Arc<dyn Transport>,
+    limiter: Arc<RateLimiter>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl RateLimitedTransport {
+    /// Fails if the thread releasing queued envelopes cannot be started.
+    pub fn new(inner: Arc<dyn Transport>, limiter: Arc<RateLimiter>) -> io::Result<Self> {
+        let stopped = Arc::new(AtomicBool::new(false));
+        if matches!(limiter.limits.overflow, Overflow::Queue(_)) {
+            let (inner, limiter, stopped) = (inner.clone(), limiter.clone(), stopped.clone());
+            thread::Builder::new()
+                .name("sentry-ratelimit".to_string())
+                .spawn(move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        thread::sleep(Duration::from_millis(250));
+                        limiter
+                            .release()
+                            .into_iter()
+                            .for_each(|envelope| inner.send_envelope(envelope));
+                    }
+                })?;
+        }
+        Ok(Self {
+            inner,
+            limiter,
+            stopped,
+        })
+    }
+}
+
+impl Transport for RateLimitedTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        let category = Category::of(&envelope);
+        if self.limiter.try_acquire(category) {
+            self.inner.send_envelope(envelope);
+        } else if let Some(envelope) = self.limiter.overflow(category, envelope) {
+            self.inner.send_envelope(envelope);
+        }
+    }
+
+    /// Queued envelopes that get no token before the timeout stay queued.
+    fn flush(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let drained = self.limiter.drain(&*self.inner, deadline);
+        self.inner.flush(deadline.saturating_duration_since(Instant::now())) && drained
+    }
+
+    /// Queued envelopes that get no token before the timeout are dropped.
+    fn shutdown(&self, timeout: Duration) -> bool {
+        self.stopped.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + timeout;
+        self.limiter.drain(&*self.inner, deadline);
+        self.limiter.discard_queue();
+        self.inner.shutdown(deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+fn state() -> &'static RwLock<Option<Arc<RateLimiter>>> {
+    static STATE: OnceLock<RwLock<Option<Arc<RateLimiter>>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Enable (`Some`) or disable (`None`) rate limiting for transports created afterwards.
+pub fn install(limits: Option<RateLimits>) {
+    *state().write().unwrap() = limits.map(|limits| Arc::new(RateLimiter::new(limits)));
+}
+
+/// Counters of the installed limiter.
+pub fn stats() -> Option<RateLimitStats> {
+    state().read().unwrap().as_ref().map(|limiter| limiter.stats())
+}
+
+/// Wrap a transport factory with the installed limiter, if any. If the
+/// limiter's thread cannot be started, the transport is used unlimited.
+pub fn wrap(factory: Arc<dyn TransportFactory>) -> Arc<dyn TransportFactory> {
+    let Some(limiter) = state().read().unwrap().clone() else {
+        return factory;
+    };
+    Arc::new(move |options: &ClientOptions| -> Arc<dyn Transport> {
+        let inner = factory.create_transport(options);
+        match RateLimitedTransport::new(inner.clone(), limiter.clone()) {
+            Ok(transport) => Arc::new(transport),
+            Err(e) => {
+                eprintln!("Cannot start rate limiter: {}, delivering without it", e);
+                inner
+            }
+        }
+    })
+}
+
+pub(crate) fn is_enabled() -> bool {
+    state().read().unwrap().is_some()
+}
@@ -0,0 +1,73 @@
+//! Domain structs that sanitize themselves before they reach an event.
+//!
+//! `#[derive(Redact)]` marks fields `#[redact]` (replaced), `#[redact(hash)]`
+//! (stable hash, still usable for correlating events) or `#[redact(last4)]`
+//! (only the last four characters kept). The derive implements `Debug` with
+//! the redacted form as well, so `{:?}` in a log line or message no longer
+//! leaks customer fields:
+//!
+//! ```ignore
+//! #[derive(Clone, Redact)]
+//! pub struct Customer {
+//!     pub id: u64,
+//!     #[redact(hash)]
+//!     pub email: String,
+//!     #[redact(last4)]
+//!     pub iban: String,
+//!     #[redact]
+//!     pub date_of_birth: Option<String>,
+//! }
+//!
+//! sentry.set_redacted_extra("customer", &customer);
+//! ```
+
+use sentry::protocol::Value;
+use std::collections::BTreeMap;
+
+pub use rust_example_macros::Redact;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Replace the value with `[REDACTED]`.
+    Full,
+    /// Replace the value with a stable 64-bit FNV-1a hash.
+    Hash,
+    /// Keep only the last four characters.
+    Last4,
+}
+
+impl Strategy {
+    pub fn apply(self, value: Value) -> Value {
+        let text = match value {
+            Value::Null => return Value::Null,
+            Value::String(text) => text,
+            other => other.to_string(),
+        };
+        Value::String(match self {
+            Strategy::Full => crate::scrubbing::REDACTED.to_string(),
+            Strategy::Hash => format!("hash:{:016x}", fnv1a(text.as_bytes())),
+            Strategy::Last4 => {
+                let chars: Vec<char> = text.chars().collect();
+                match chars.len() {
+                    0..=4 => "****".to_string(),
+                    len => format!("****{}", chars[len - 4..].iter().collect::<String>()),
+                }
+            }
+        })
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub trait Redact {
+    /// Field values with the redaction strategies applied.
+    fn redacted(&self) -> BTreeMap<String, Value>;
+
+    fn to_value(&self) -> Value {
+        Value::Object(self.redacted().into_iter().collect())
+    }
+}
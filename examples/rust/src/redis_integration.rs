@@ -0,0 +1,186 @@
+//! `db.redis` spans for redis-rs async connections (feature `redis`).
+//!
+//! [`TracedConnection`](redis_integration::TracedConnection) wraps any
+//! `redis::aio::ConnectionLike`; inside an active span every command becomes
+//! a child span described by the command name (`GET`, `pipeline(3): SET
+//! EXPIRE GET`). Arguments are never recorded, keys and values stay out of
+//! Bugsink. Timeouts and dropped connections leave a breadcrumb.
+//!
+//! With feature `deadpool-redis`, [`get`](redis_integration::get) checks out a
+//! pooled connection, records pool timeouts as breadcrumbs, and attaches the
+//! pool size, available connections and checkout wait to each command span:
+//!
+//! ```ignore
+//! let mut conn = redis_integration::get(&pool).await?;
+//! let cached: Option<String> = conn.get(&key).await?;
+//! ```
+
+use crate::ops::Op;
+use redis::{aio::ConnectionLike, Arg, Cmd, Pipeline, RedisError, RedisFuture, RedisResult, Value};
+use sentry::{
+    protocol::{Breadcrumb, SpanStatus},
+    Hub, Level,
+};
+use std::{future::Future, time::Duration};
+
+/// Pool state when the connection was checked out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    pub max_size: usize,
+    pub size: usize,
+    /// Idle connections; negative while callers wait for one.
+    pub available: isize,
+    pub wait: Duration,
+}
+
+pub struct TracedConnection<C> {
+    inner: C,
+    pool: Option<PoolStats>,
+}
+
+impl<C> TracedConnection<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner, pool: None }
+    }
+
+    pub fn pool_stats(&self) -> Option<PoolStats> {
+        self.pool
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: ConnectionLike + Send> ConnectionLike for TracedConnection<C> {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        let operation = command_name(cmd);
+        let db = self.inner.get_db();
+        Box::pin(traced(operation, db, self.pool, self.inner.req_packed_command(cmd)))
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        let names: Vec<String> = cmd.cmd_iter().map(command_name).collect();
+        let operation = format!("pipeline({}): {}", names.len(), names.join(" "));
+        let db = self.inner.get_db();
+        Box::pin(traced(
+            operation,
+            db,
+            self.pool,
+            self.inner.req_packed_commands(cmd, offset, count),
+        ))
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+}
+
+/// Upper-cased command name, without any arguments.
+fn command_name(cmd: &Cmd) -> String {
+    match cmd.args_iter().next() {
+        Some(Arg::Simple(name)) => String::from_utf8_lossy(name).to_ascii_uppercase(),
+        _ => "UNKNOWN".to_string(),
+    }
+}
+
+async fn traced<T>(
+    operation: String,
+    db: i64,
+    pool: Option<PoolStats>,
+    command: impl Future<Output = RedisResult<T>>,
+) -> RedisResult<T> {
+    let hub = Hub::current();
+    let span = hub
+        .configure_scope(|scope| scope.get_span())
+        .map(|parent| parent.start_child(Op::DbRedis.as_str(), &operation));
+    if let Some(span) = &span {
+        span.set_data("db.system", "redis".into());
+        span.set_data("db.redis.database_index", db.into());
+        if let Some(pool) = pool {
+            span.set_data("db.redis.pool.max_size", pool.max_size.into());
+            span.set_data("db.redis.pool.size", pool.size.into());
+            span.set_data("db.redis.pool.available", pool.available.into());
+            span.set_data("db.redis.pool.wait_ms", (pool.wait.as_millis() as u64).into());
+        }
+    }
+
+    let result = command.await;
+    if let Err(e) = &result {
+        record_failure(&hub, &operation, e);
+    }
+    if let Some(span) = span {
+        span.set_status(match &result {
+            Ok(_) => SpanStatus::Ok,
+            Err(e) if e.is_timeout() => SpanStatus::DeadlineExceeded,
+            Err(e) if e.is_connection_dropped() || e.is_connection_refusal() => SpanStatus::Unavailable,
+            Err(_) => SpanStatus::InternalError,
+        });
+        span.finish();
+    }
+    result
+}
+
+fn record_failure(hub: &Hub, operation: &str, error: &RedisError) {
+    let message = if error.is_timeout() {
+        format!("redis {} timed out", operation)
+    } else if error.is_connection_dropped() || error.is_connection_refusal() {
+        format!("redis connection lost during {}, reconnecting", operation)
+    } else {
+        return;
+    };
+    hub.add_breadcrumb(Breadcrumb {
+        category: Some("redis".to_string()),
+        message: Some(message),
+        level: Level::Warning,
+        ..Default::default()
+    });
+}
+
+/// Check out a connection from `pool`, recording the pool state for its spans.
+#[cfg(feature = "deadpool-redis")]
+pub async fn get(
+    pool: &deadpool_redis::Pool,
+) -> Result<TracedConnection<deadpool_redis::Connection>, deadpool_redis::PoolError> {
+    use deadpool_redis::PoolError;
+
+    let started = std::time::Instant::now();
+    let result = pool.get().await;
+    let status = pool.status();
+    let stats = PoolStats {
+        max_size: status.max_size,
+        size: status.size,
+        available: status.available,
+        wait: started.elapsed(),
+    };
+    match result {
+        Ok(connection) => Ok(TracedConnection {
+            inner: connection,
+            pool: Some(stats),
+        }),
+        Err(e) => {
+            let message = match &e {
+                PoolError::Timeout(_) => format!(
+                    "redis pool timed out after {} ms ({}/{} connections in use)",
+                    stats.wait.as_millis(),
+                    stats.size as isize - stats.available.max(0),
+                    stats.max_size
+                ),
+                PoolError::Backend(e) => format!("redis connect failed: {}", e),
+                other => format!("redis pool error: {}", other),
+            };
+            Hub::current().add_breadcrumb(Breadcrumb {
+                category: Some("redis".to_string()),
+                message: Some(message),
+                level: Level::Warning,
+                ..Default::default()
+            });
+            Err(e)
+        }
+    }
+}
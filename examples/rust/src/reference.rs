@@ -0,0 +1,48 @@
+//! Short user-facing reference codes (e.g. `ERR-7F3K2`) derived from the event ID.
+//!
+//! The code is also set as the `error.reference` tag on the event, so support
+//! can search Bugsink for the code shown on a customer's screenshot.
+
+use sentry::types::Uuid;
+use std::fmt;
+
+/// Tag carrying the reference code on every event.
+pub const TAG: &str = "error.reference";
+
+/// Crockford base32: no I, L, O or U, so codes survive being read aloud.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ErrorReference(String);
+
+impl ErrorReference {
+    /// Derive the code from the first 25 bits of the event ID.
+    pub fn from_event_id(event_id: Uuid) -> Self {
+        let bytes = event_id.as_bytes();
+        let bits = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let code: String = (0..5)
+            .map(|i| ALPHABET[((bits >> (27 - i * 5)) & 0x1f) as usize] as char)
+            .collect();
+        Self(format!("ERR-{}", code))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Default message shown to end users.
+    pub fn user_message(&self) -> String {
+        self.format("Something went wrong. Please contact support and quote reference {reference}.")
+    }
+
+    /// Render a custom message; `{reference}` is replaced with the code.
+    pub fn format(&self, template: &str) -> String {
+        template.replace("{reference}", &self.0)
+    }
+}
+
+impl fmt::Display for ErrorReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,163 @@
+//! Release names derived from the build instead of a hand-maintained
+//! `APP_VERSION`.
+//!
+//! The crate's `build.rs` includes this file and calls
+//! [`emit_build_env`](release_info::emit_build_env) to bake the git state
+//! into the binary. An application embedding the module does the same:
+//!
+//! ```ignore
+//! // build.rs
+//! #[allow(dead_code)]
+//! #[path = "src/release_info.rs"]
+//! mod release_info;
+//!
+//! fn main() {
+//!     release_info::emit_build_env();
+//! }
+//! ```
+//!
+//! The version is the tag pointing at HEAD (without a leading `v`) or the
+//! package version, followed by the short commit: `1.4.2+abc1234def56`.
+//! `Config::default` only uses the build-time values. `Config::from_env`
+//! also falls back to `GIT_COMMIT`, `GITHUB_SHA` or `CI_COMMIT_SHA`, and
+//! finally to `git` in the working directory; `APP_VERSION`, when set, still
+//! wins there. Branch, tag and dirty state are recorded as `release.*` extras.
+
+use std::{env, process::Command, sync::OnceLock};
+
+const SHA_LEN: usize = 12;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseInfo {
+    pub package_version: String,
+    pub sha: Option<String>,
+    /// Tag pointing exactly at the commit.
+    pub tag: Option<String>,
+    pub branch: Option<String>,
+    pub dirty: Option<bool>,
+}
+
+impl ReleaseInfo {
+    /// Values baked in by [`emit_build_env`], or just the package version.
+    /// Reads neither the environment nor `git`.
+    pub fn built() -> Self {
+        let baked = |value: Option<&str>| value.filter(|value| !value.is_empty()).map(String::from);
+        Self {
+            package_version: env!("CARGO_PKG_VERSION").to_string(),
+            sha: baked(option_env!("OBSERVABILITY_GIT_SHA")),
+            tag: baked(option_env!("OBSERVABILITY_GIT_TAG")),
+            branch: baked(option_env!("OBSERVABILITY_GIT_BRANCH")),
+            dirty: option_env!("OBSERVABILITY_GIT_DIRTY").map(|dirty| dirty == "true"),
+        }
+    }
+
+    /// Build-time values, falling back to CI variables and `git`.
+    pub fn detect() -> Self {
+        let built = Self::built();
+        if built.sha.is_some() {
+            return built;
+        }
+        let ci_sha = ["GIT_COMMIT", "GITHUB_SHA", "CI_COMMIT_SHA"]
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|sha| !sha.is_empty()));
+        match ci_sha {
+            Some(sha) => Self {
+                sha: Some(sha.chars().take(SHA_LEN).collect()),
+                ..built
+            },
+            None => Self {
+                package_version: built.package_version,
+                ..from_git()
+            },
+        }
+    }
+
+    /// `1.4.2+abc1234def56`, or just the version without a commit.
+    pub fn version(&self) -> String {
+        let version = self
+            .tag
+            .as_deref()
+            .map(|tag| tag.strip_prefix('v').unwrap_or(tag))
+            .unwrap_or(&self.package_version);
+        match &self.sha {
+            Some(sha) => format!("{}+{}", version, sha),
+            None => version.to_string(),
+        }
+    }
+
+    /// `release.git_*` extras for the known fields.
+    pub fn extras(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let mut extras = Vec::new();
+        if let Some(sha) = &self.sha {
+            extras.push(("release.git_sha", sha.as_str().into()));
+        }
+        if let Some(tag) = &self.tag {
+            extras.push(("release.git_tag", tag.as_str().into()));
+        }
+        if let Some(branch) = &self.branch {
+            extras.push(("release.git_branch", branch.as_str().into()));
+        }
+        if let Some(dirty) = self.dirty {
+            extras.push(("release.git_dirty", dirty.into()));
+        }
+        extras
+    }
+}
+
+static DETECTED: OnceLock<ReleaseInfo> = OnceLock::new();
+
+/// [Detected](ReleaseInfo::detect) once per process; runs `git` the first
+/// time when no build-time or CI values exist.
+pub fn detected() -> &'static ReleaseInfo {
+    DETECTED.get_or_init(ReleaseInfo::detect)
+}
+
+/// What [`detected`] found if it ran, otherwise the [build-time
+/// values](ReleaseInfo::built). Never reads the environment or runs `git`.
+pub fn current() -> &'static ReleaseInfo {
+    static BUILT: OnceLock<ReleaseInfo> = OnceLock::new();
+    DETECTED.get().unwrap_or_else(|| BUILT.get_or_init(ReleaseInfo::built))
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let stdout = stdout.trim();
+    (output.status.success() && !stdout.is_empty()).then(|| stdout.to_string())
+}
+
+/// Git state of the current directory; empty outside a repository.
+fn from_git() -> ReleaseInfo {
+    let Some(sha) = git(&["rev-parse", &format!("--short={}", SHA_LEN), "HEAD"]) else {
+        return ReleaseInfo::default();
+    };
+    ReleaseInfo {
+        package_version: String::new(),
+        sha: Some(sha),
+        tag: git(&["describe", "--tags", "--exact-match", "HEAD"]),
+        // "HEAD" when detached, as on most CI checkouts
+        branch: git(&["rev-parse", "--abbrev-ref", "HEAD"]).filter(|branch| branch != "HEAD"),
+        dirty: Some(git(&["status", "--porcelain", "--untracked-files=no"]).is_some()),
+    }
+}
+
+/// For `build.rs`: pass the git state to the compiler as
+/// `OBSERVABILITY_GIT_*` variables and rebuild when HEAD moves.
+pub fn emit_build_env() {
+    let info = from_git();
+    println!("cargo:rustc-env=OBSERVABILITY_GIT_SHA={}", info.sha.unwrap_or_default());
+    println!("cargo:rustc-env=OBSERVABILITY_GIT_TAG={}", info.tag.unwrap_or_default());
+    println!(
+        "cargo:rustc-env=OBSERVABILITY_GIT_BRANCH={}",
+        info.branch.unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=OBSERVABILITY_GIT_DIRTY={}",
+        info.dirty.unwrap_or(false)
+    );
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/index", git_dir);
+        println!("cargo:rerun-if-changed={}/refs/tags", git_dir);
+    }
+}
@@ -0,0 +1,364 @@
+//! Announce releases and deploys from CI/CD.
+//!
+//! [`ReleaseClient`](releases::ReleaseClient) talks to either API:
+//!
+//! - Bugsink (`/api/canonical/0/releases/`): creates the release for a
+//!   project. Bugsink has no commit or deploy endpoints; those calls return
+//!   [`Outcome::Unsupported`](releases::Outcome::Unsupported).
+//! - Sentry (`/api/0/organizations/<org>/releases/`): creates the release for
+//!   its projects, associates commits and records deploys to an environment.
+//!
+//! Creating a release that already exists is not an error, so the same
+//! pipeline step can run on every deploy. The version defaults to
+//! `my-app@<release_info version>`, matching [`config::release`].
+//!
+//! Self-healing code can close the issue it just fixed with
+//! [`resolve_matching`](releases::ReleaseClient::resolve_matching), which
+//! searches the unresolved issues and resolves every match (Sentry only;
+//! Bugsink's API is read-only for issues):
+//!
+//! ```ignore
+//! if pool.reconnect().is_ok() {
+//!     client.resolve_matching(&IssueQuery::Rule("db-timeouts".into()))?;
+//! }
+//! ```
+//!
+//! The example binary runs [`cli`](releases::cli) when its first argument is
+//! `observability-release`:
+//!
+//! ```text
+//! BUGSINK_API_TOKEN=... rust_example observability-release https://errors.example.com --bugsink-project 7
+//! SENTRY_AUTH_TOKEN=... rust_example observability-release https://sentry.io --org acme --project api \
+//!     --commit $GITHUB_SHA --repo acme/api --deploy production
+//! ```
+
+use serde_json::{json, Value};
+use std::{env, process::ExitCode, time::Duration};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Bugsink {
+        project: u64,
+    },
+    Sentry {
+        organization: String,
+        projects: Vec<String>,
+    },
+}
+
+/// A commit range to associate with a release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitRef {
+    /// Repository name as configured in the organization, e.g. `acme/api`.
+    pub repository: String,
+    pub commit: String,
+    /// Last commit of the previous release; `None` lets the server find it.
+    pub previous_commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    /// Not available on this backend.
+    Unsupported,
+}
+
+/// Issues to resolve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueQuery {
+    /// Issues grouped by a [fingerprint rule](crate::fingerprint::FingerprintRule).
+    Rule(String),
+    Tag(String, String),
+    /// Raw search, e.g. `error.type:TimeoutError environment:production`.
+    Search(String),
+}
+
+impl IssueQuery {
+    /// Search string for the issues API.
+    pub fn to_search(&self) -> String {
+        let tag = |key: &str, value: &str| format!("{}:\"{}\"", key, value.replace('"', "\\\""));
+        match self {
+            IssueQuery::Rule(name) => tag(crate::fingerprint::TAG, name),
+            IssueQuery::Tag(key, value) => tag(key, value),
+            IssueQuery::Search(query) => query.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// IDs of the issues that were resolved; empty when nothing matched.
+    Resolved(Vec<String>),
+    Unsupported,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReleaseError {
+    #[error("Release API request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Release API returned {0}: {1}")]
+    Status(u16, String),
+}
+
+pub struct ReleaseClient {
+    base_url: String,
+    token: String,
+    backend: Backend,
+    http: reqwest::blocking::Client,
+}
+
+impl ReleaseClient {
+    /// `base_url` is the server root, e.g. `https://errors.example.com`.
+    pub fn new(base_url: &str, token: &str, backend: Backend) -> Result<Self, ReleaseError> {
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            backend,
+            http: reqwest::blocking::Client::builder().timeout(TIMEOUT).build()?,
+        })
+    }
+
+    /// Create the release, with `commits` on Sentry. Existing releases are
+    /// left as they are.
+    pub fn create_release(&self, version: &str, commits: &[CommitRef]) -> Result<Outcome, ReleaseError> {
+        match &self.backend {
+            Backend::Bugsink { project } => {
+                let url = format!("{}/api/canonical/0/releases/", self.base_url);
+                self.send(
+                    reqwest::Method::POST,
+                    &url,
+                    &json!({ "project": project, "version": version }),
+                )
+            }
+            Backend::Sentry { organization, projects } => {
+                let url = format!("{}/api/0/organizations/{}/releases/", self.base_url, organization);
+                let mut body = json!({ "version": version, "projects": projects });
+                if !commits.is_empty() {
+                    body["refs"] = refs(commits);
+                }
+                self.send(reqwest::Method::POST, &url, &body)
+            }
+        }
+    }
+
+    /// Associate commits with an existing release.
+    pub fn set_commits(&self, version: &str, commits: &[CommitRef]) -> Result<Outcome, ReleaseError> {
+        let Backend::Sentry { organization, .. } = &self.backend else {
+            return Ok(Outcome::Unsupported);
+        };
+        let url = format!(
+            "{}/api/0/organizations/{}/releases/{}/",
+            self.base_url,
+            organization,
+            encode(version)
+        );
+        self.send(reqwest::Method::PUT, &url, &json!({ "refs": refs(commits) }))
+    }
+
+    /// Record that `version` was deployed to `environment`.
+    pub fn create_deploy(&self, version: &str, environment: &str, name: Option<&str>) -> Result<Outcome, ReleaseError> {
+        let Backend::Sentry { organization, .. } = &self.backend else {
+            return Ok(Outcome::Unsupported);
+        };
+        let url = format!(
+            "{}/api/0/organizations/{}/releases/{}/deploys/",
+            self.base_url,
+            organization,
+            encode(version)
+        );
+        let mut body = json!({ "environment": environment });
+        if let Some(name) = name {
+            body["name"] = name.into();
+        }
+        self.send(reqwest::Method::POST, &url, &body)
+    }
+
+    /// Resolve all unresolved issues matching `query` in the client's
+    /// projects.
+    pub fn resolve_matching(&self, query: &IssueQuery) -> Result<Resolution, ReleaseError> {
+        let Backend::Sentry { organization, projects } = &self.backend else {
+            return Ok(Resolution::Unsupported);
+        };
+        let search = format!("is:unresolved {}", query.to_search());
+        let mut ids = Vec::new();
+        for project in projects {
+            let url = format!("{}/api/0/projects/{}/{}/issues/", self.base_url, organization, project);
+            let response = self
+                .http
+                .get(&url)
+                .bearer_auth(&self.token)
+                .query(&[("query", search.as_str()), ("limit", "100")])
+                .send()?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(ReleaseError::Status(
+                    status.as_u16(),
+                    response.text().unwrap_or_default(),
+                ));
+            }
+            let issues: Vec<Value> = response.json()?;
+            ids.extend(issues.iter().filter_map(|issue| issue["id"].as_str().map(String::from)));
+        }
+        if ids.is_empty() {
+            return Ok(Resolution::Resolved(ids));
+        }
+
+        let url = format!("{}/api/0/organizations/{}/issues/", self.base_url, organization);
+        let params: Vec<(&str, &str)> = ids.iter().map(|id| ("id", id.as_str())).collect();
+        self.send_request(self.http.put(&url).query(&params), &json!({ "status": "resolved" }))?;
+        Ok(Resolution::Resolved(ids))
+    }
+
+    fn send(&self, method: reqwest::Method, url: &str, body: &Value) -> Result<Outcome, ReleaseError> {
+        self.send_request(self.http.request(method, url), body)
+    }
+
+    fn send_request(&self, request: reqwest::blocking::RequestBuilder, body: &Value) -> Result<Outcome, ReleaseError> {
+        let response = request.bearer_auth(&self.token).json(body).send()?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(Outcome::Done);
+        }
+        let text = response.text().unwrap_or_default();
+        // Bugsink rejects duplicates with a unique-constraint message
+        if status == reqwest::StatusCode::BAD_REQUEST && text.contains("already exists") {
+            return Ok(Outcome::Done);
+        }
+        Err(ReleaseError::Status(status.as_u16(), text))
+    }
+}
+
+fn refs(commits: &[CommitRef]) -> Value {
+    commits
+        .iter()
+        .map(|commit| {
+            let mut value = json!({ "repository": commit.repository, "commit": commit.commit });
+            if let Some(previous) = &commit.previous_commit {
+                value["previousCommit"] = previous.as_str().into();
+            }
+            value
+        })
+        .collect()
+}
+
+/// Percent-encode a version for use as a path segment (`my-app@1.2+abc`).
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Version announced when none is given.
+pub fn default_version() -> String {
+    format!("my-app@{}", crate::release_info::detected().version())
+}
+
+pub fn cli(mut args: impl Iterator<Item = String>) -> ExitCode {
+    const USAGE: &str = "usage: observability-release <base-url> (--bugsink-project ID | --org ORG --project P...) \
+                             [--version V] [--commit SHA --repo NAME [--previous SHA]] [--deploy ENV [--name N]]";
+    let Some(base_url) = args.next() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let (mut bugsink_project, mut organization, mut projects) = (None, None, Vec::new());
+    let (mut version, mut commit, mut repository, mut previous) = (None, None, None, None);
+    let (mut environment, mut deploy_name) = (None, None);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().filter(|value| !value.starts_with("--"));
+        let slot = match arg.as_str() {
+            "--org" => &mut organization,
+            "--version" => &mut version,
+            "--commit" => &mut commit,
+            "--repo" => &mut repository,
+            "--previous" => &mut previous,
+            "--deploy" => &mut environment,
+            "--name" => &mut deploy_name,
+            "--project" => match value() {
+                Some(project) => {
+                    projects.push(project);
+                    continue;
+                }
+                None => {
+                    eprintln!("--project expects a value");
+                    return ExitCode::from(2);
+                }
+            },
+            "--bugsink-project" => match value().and_then(|id| id.parse::<u64>().ok()) {
+                Some(id) => {
+                    bugsink_project = Some(id);
+                    continue;
+                }
+                None => {
+                    eprintln!("--bugsink-project expects a number");
+                    return ExitCode::from(2);
+                }
+            },
+            other => {
+                eprintln!("unknown argument: {}", other);
+                return ExitCode::from(2);
+            }
+        };
+        match value() {
+            Some(value) => *slot = Some(value),
+            None => {
+                eprintln!("{} expects a value", arg);
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let (backend, token_var) = match (bugsink_project, organization) {
+        (Some(project), None) => (Backend::Bugsink { project }, "BUGSINK_API_TOKEN"),
+        (None, Some(organization)) if !projects.is_empty() => {
+            (Backend::Sentry { organization, projects }, "SENTRY_AUTH_TOKEN")
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let Some(token) = env::var(token_var).ok().filter(|token| !token.is_empty()) else {
+        eprintln!("{} is not set", token_var);
+        return ExitCode::from(2);
+    };
+    let commits: Vec<CommitRef> = match (commit, repository) {
+        (Some(commit), Some(repository)) => vec![CommitRef {
+            repository,
+            commit,
+            previous_commit: previous,
+        }],
+        (None, None) => Vec::new(),
+        _ => {
+            eprintln!("--commit and --repo must be given together");
+            return ExitCode::from(2);
+        }
+    };
+    let version = version.unwrap_or_else(default_version);
+
+    let result = ReleaseClient::new(&base_url, &token, backend).and_then(|client| {
+        client.create_release(&version, &commits)?;
+        println!("release {}: created", version);
+        if let Some(environment) = &environment {
+            match client.create_deploy(&version, environment, deploy_name.as_deref())? {
+                Outcome::Done => println!("release {}: deployed to {}", version, environment),
+                Outcome::Unsupported => println!("release {}: deploys are not supported by this server", version),
+            }
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Re-submit spooled or exported envelopes after extended Bugsink downtime.
+//!
+//! Reads one envelope per `*.envelope` file (Sentry envelope wire format) and
+//! one per line of the NDJSON files of a [`FileTransport`](local::FileTransport)
+//! (`events.ndjson` and its rotations, oldest first), optionally re-applies the current scrubbing and filter
+//! [processors] to the contained events, and sends them straight to a DSN at a
+//! bounded rate.
+//!
+//! With a record directory configured, [`RecordingTransport`](replay::RecordingTransport)
+//! writes every outgoing envelope there in spool format while still sending
+//! it. Replaying such a recording with `preserve_timing` reproduces the
+//! original traffic shape against another DSN, e.g. a Bugsink upgrade candidate.
+//!
+//! The example binary runs [`cli`](replay::cli) when its first argument is
+//! `envelope-replay`:
+//!
+//! ```text
+//! rust_example envelope-replay /var/lib/app/spool https://key@errors.example.com/2 --rate 20
+//! ```
+
+use super::{
+    local,
+    processors::{self, Stage},
+    spool::{self, Spool, SpoolLimits},
+};
+use sentry::{
+    protocol::{Envelope, EnvelopeItem},
+    transports::DefaultTransportFactory,
+    ClientOptions, Transport, TransportFactory,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, OnceLock, RwLock},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// Longest pause between two envelopes when replaying with recorded timing.
+const MAX_GAP: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Target DSN.
+    pub dsn: String,
+    /// Re-apply the current scrubbing and filter processors to events;
+    /// enrichment and sampling already ran when they were captured.
+    pub rescrub: bool,
+    /// Upper bound on envelopes sent per second.
+    pub max_per_second: u32,
+    /// Remove files once all envelopes have been flushed.
+    pub delete_after_send: bool,
+    /// Reproduce the recorded gaps between envelopes (capped at a minute)
+    /// instead of sending at `max_per_second`.
+    pub preserve_timing: bool,
+}
+
+impl ReplayOptions {
+    pub fn new(dsn: &str) -> Self {
+        Self {
+            dsn: dsn.to_string(),
+            rescrub: true,
+            max_per_second: 10,
+            delete_after_send: false,
+            preserve_timing: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub sent: usize,
+    /// Envelopes whose events were all dropped by the current rules.
+    pub dropped: usize,
+    /// Files that could not be read or parsed.
+    pub failed: Vec<(PathBuf, String)>,
+    /// Whether the transport confirmed delivery within the flush timeout.
+    pub flushed: bool,
+}
+
+/// How a file in the replay directory is read.
+enum Format {
+    /// One envelope in wire format.
+    Envelope,
+    /// A [`FileTransport`](local::FileTransport) file; `rotation` 0 is the
+    /// current file, higher numbers are older.
+    Ndjson { rotation: u32 },
+}
+
+fn format(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_str()?;
+    if name.ends_with(".envelope") {
+        return Some(Format::Envelope);
+    }
+    if name.ends_with(".ndjson") {
+        return Some(Format::Ndjson { rotation: 0 });
+    }
+    let (base, rotation) = name.rsplit_once('.')?;
+    let rotation = rotation.parse().ok()?;
+    base.ends_with(".ndjson").then_some(Format::Ndjson { rotation })
+}
+
+/// File name order, with rotated NDJSON files before the ones replacing them.
+fn sort_key(path: &Path) -> (String, std::cmp::Reverse<u32>) {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match format(path) {
+        Some(Format::Ndjson { rotation }) if rotation > 0 => {
+            let base = name
+                .rsplit_once('.')
+                .map_or(name.as_str(), |(base, _)| base)
+                .to_string();
+            (base, std::cmp::Reverse(rotation))
+        }
+        _ => (name, std::cmp::Reverse(0)),
+    }
+}
+
+/// The envelopes of a file with their creation times in milliseconds, or why it cannot be read.
+fn read(path: &Path, format: Format) -> Result<Vec<(Envelope, Option<u64>)>, String> {
+    match format {
+        Format::Envelope => {
+            let envelope = Envelope::from_path(path).map_err(|e| e.to_string())?;
+            Ok(vec![(envelope, spool::created_millis(path))])
+        }
+        Format::Ndjson { .. } => fs::read_to_string(path)
+            .map_err(|e| e.to_string())?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let envelope = local::from_json(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+                let created = timestamp_millis(&envelope);
+                Ok((envelope, created))
+            })
+            .collect(),
+    }
+}
+
+/// Timestamp of the envelope's event or transaction.
+fn timestamp_millis(envelope: &Envelope) -> Option<u64> {
+    let timestamp = envelope.items().find_map(|item| match item {
+        EnvelopeItem::Event(event) => Some(event.timestamp),
+        EnvelopeItem::Transaction(transaction) => transaction.timestamp,
+        _ => None,
+    })?;
+    Some(timestamp.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// Replay every `*.envelope` and NDJSON file in `dir`.
+pub fn replay_dir(dir: &Path, options: &ReplayOptions) -> io::Result<ReplayReport> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| format(path).is_some())
+        .collect();
+    paths.sort_by_cached_key(|path| sort_key(path));
+
+    // Straight to the target: recording, local output, routing, the spool
+    // and the rate limiter of this process's own client must not apply
+    let dsn = options
+        .dsn
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid target DSN"))?;
+    let client = sentry::Client::from_config(ClientOptions {
+        dsn: Some(dsn),
+        transport: Some(Arc::new(DefaultTransportFactory)),
+        ..Default::default()
+    });
+
+    let interval = Duration::from_secs(1) / options.max_per_second.max(1);
+    let mut report = ReplayReport::default();
+    let mut sent_paths = Vec::new();
+    let mut previous = None;
+
+    for path in paths {
+        let envelopes = match format(&path).map(|format| read(&path, format)) {
+            Some(Ok(envelopes)) => envelopes,
+            Some(Err(e)) => {
+                report.failed.push((path, e));
+                continue;
+            }
+            None => continue,
+        };
+
+        for (envelope, created) in envelopes {
+            let envelope = if options.rescrub {
+                rescrub(envelope)
+            } else {
+                Some(envelope)
+            };
+            match envelope {
+                Some(envelope) if options.preserve_timing => {
+                    if let (Some(previous), Some(created)) = (previous, created) {
+                        thread::sleep(Duration::from_millis(created.saturating_sub(previous)).min(MAX_GAP));
+                    }
+                    previous = created.or(previous);
+                    client.send_envelope(envelope);
+                    report.sent += 1;
+                }
+                Some(envelope) => {
+                    client.send_envelope(envelope);
+                    report.sent += 1;
+                    thread::sleep(interval);
+                }
+                None => report.dropped += 1,
+            }
+        }
+        sent_paths.push(path);
+    }
+
+    report.flushed = client.close(Some(Duration::from_secs(30)));
+    if report.flushed && options.delete_after_send {
+        for path in sent_paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Run events through the current scrub and filter stages; other items pass unchanged.
+fn rescrub(envelope: Envelope) -> Option<Envelope> {
+    let mut scrubbed = Envelope::new();
+    for item in envelope.items() {
+        match item {
+            EnvelopeItem::Event(event) => {
+                if let Some(event) = processors::run_stages(event.clone(), &[Stage::Scrub, Stage::Filter]) {
+                    scrubbed.add_item(event);
+                }
+            }
+            other => scrubbed.add_item(other.clone()),
+        }
+    }
+    scrubbed.items().next().is_some().then_some(scrubbed)
+}
+
+/// Records every envelope to a spool-format directory, then hands it to the
+/// wrapped transport. Recording errors never block delivery.
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    spool: Spool,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn Transport>, spool: Spool) -> Self {
+        Self { inner, spool }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        if let Err(e) = self.spool.push(&envelope) {
+            eprintln!("Cannot record envelope in {}: {}", self.spool.dir().display(), e);
+        }
+        self.inner.send_envelope(envelope);
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        self.inner.flush(timeout)
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        self.inner.shutdown(timeout)
+    }
+}
+
+fn record_state() -> &'static RwLock<Option<PathBuf>> {
+    static DIR: OnceLock<RwLock<Option<PathBuf>>> = OnceLock::new();
+    DIR.get_or_init(Default::default)
+}
+
+/// Record outgoing envelopes to `dir` (`None` stops) in clients created afterwards.
+pub fn record_to(dir: Option<PathBuf>) {
+    *record_state().write().unwrap() = dir;
+}
+
+/// Wrap a transport factory with the configured recorder, if any. The
+/// recording uses the default spool limits; the oldest files are evicted.
+pub(crate) fn wrap(factory: Arc<dyn TransportFactory>) -> Arc<dyn TransportFactory> {
+    let Some(dir) = record_state().read().unwrap().clone() else {
+        return factory;
+    };
+    Arc::new(move |options: &ClientOptions| -> Arc<dyn Transport> {
+        let inner = factory.create_transport(options);
+        match Spool::open(&dir, SpoolLimits::default()) {
+            Ok(spool) => Arc::new(RecordingTransport::new(inner, spool)),
+            Err(e) => {
+                eprintln!("Cannot open record directory {}: {}, not recording", dir.display(), e);
+                inner
+            }
+        }
+    })
+}
+
+pub(crate) fn is_recording() -> bool {
+    record_state().read().unwrap().is_some()
+}
+
+/// Command line entry point: `envelope-replay <dir> <dsn> [--raw] [--rate N] [--timing] [--delete]`.
+pub fn cli(mut args: impl Iterator<Item = String>) -> ExitCode {
+    let (Some(dir), Some(dsn)) = (args.next(), args.next()) else {
+        eprintln!("usage: envelope-replay <dir> <dsn> [--raw] [--rate N] [--timing] [--delete]");
+        return ExitCode::from(2);
+    };
+
+    let mut options = ReplayOptions::new(&dsn);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--raw" => options.rescrub = false,
+            "--timing" => options.preserve_timing = true,
+            "--delete" => options.delete_after_send = true,
+            "--rate" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(rate) => options.max_per_second = rate,
+                None => {
+                    eprintln!("--rate expects a number");
+                    return ExitCode::from(2);
+                }
+            },
+            other => {
+                eprintln!("unknown argument: {}", other);
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    match replay_dir(Path::new(&dir), &options) {
+        Ok(report) => {
+            println!(
+                "sent: {}, dropped: {}, failed: {}, flushed: {}",
+                report.sent,
+                report.dropped,
+                report.failed.len(),
+                report.flushed
+            );
+            for (path, error) in &report.failed {
+                eprintln!("  {}: {}", path.display(), error);
+            }
+            if report.failed.is_empty() && report.flushed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            eprintln!("replay failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
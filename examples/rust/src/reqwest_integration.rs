@@ -0,0 +1,141 @@
+//! reqwest-middleware integration for outgoing requests (feature
+//! `reqwest-middleware`), replacing hand-written `with_span(Op::HttpClient, ..)`
+//! around every call.
+//!
+//! Inside an active span each request gets an `http.client` child span
+//! (`GET https://payments.internal/v1/charges`) with the response status,
+//! and the trace continues downstream through `sentry-trace` and `baggage`
+//! headers, and the current correlation ID through `X-Correlation-ID`
+//! (headers the caller already set are kept). Every request leaves an
+//! `http` breadcrumb with method, URL, status and duration; connection errors
+//! and timeouts are captured as events. URLs are scrubbed like everything else.
+//!
+//! ```ignore
+//! let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+//!     .with(reqwest_integration::SentryMiddleware::new())
+//!     .build();
+//! let charge = client.post(url).json(&request).send().await?;
+//! ```
+
+use crate::{
+    correlation,
+    ops::{self, Op},
+    propagation, scrubbing,
+};
+use reqwest::{header::HeaderValue, Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use sentry::{
+    protocol::{Breadcrumb, Map, SpanStatus},
+    Hub, Level, TransactionOrSpan,
+};
+use std::time::Instant;
+use task_local_extensions::Extensions;
+
+#[derive(Debug, Clone)]
+pub struct SentryMiddleware {
+    capture_errors: bool,
+}
+
+impl Default for SentryMiddleware {
+    fn default() -> Self {
+        Self { capture_errors: true }
+    }
+}
+
+impl SentryMiddleware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture connection errors and timeouts as events (default on).
+    pub fn capture_errors(mut self, enabled: bool) -> Self {
+        self.capture_errors = enabled;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for SentryMiddleware {
+    async fn handle(&self, mut request: Request, extensions: &mut Extensions, next: Next<'_>) -> Result<Response> {
+        let hub = Hub::current();
+        let method = request.method().to_string();
+        let url = scrubbing::current().scrub_url(request.url().as_str()).into_owned();
+        let host = request.url().host_str().unwrap_or_default().to_string();
+
+        let span: Option<TransactionOrSpan> = hub.configure_scope(|scope| scope.get_span()).map(|parent| {
+            let description = format!("{} {}", method, url);
+            parent.start_child(Op::HttpClient.as_str(), &description).into()
+        });
+        if let Some(span) = &span {
+            span.set_data("http.request.method", method.clone().into());
+            span.set_data("url", url.clone().into());
+            for (name, value) in propagation::headers(span) {
+                if request.headers().contains_key(name) {
+                    continue;
+                }
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    request.headers_mut().insert(name, value);
+                }
+            }
+        }
+
+        correlation::inject_correlation_id(request.headers_mut());
+
+        let started = Instant::now();
+        let result = next.run(request, extensions).await;
+        let duration = started.elapsed();
+
+        let mut fields = Map::new();
+        fields.insert("method".to_string(), method.clone().into());
+        fields.insert("url".to_string(), url.into());
+        fields.insert("duration_ms".to_string(), (duration.as_millis() as u64).into());
+        let (level, status) = match &result {
+            Ok(response) => {
+                let code = response.status().as_u16();
+                fields.insert("status_code".to_string(), code.into());
+                let level = match code {
+                    500.. => Level::Error,
+                    400.. => Level::Warning,
+                    _ => Level::Info,
+                };
+                (level, ops::http_span_status(code))
+            }
+            Err(e) => {
+                let status = match e {
+                    Error::Reqwest(e) if e.is_timeout() => SpanStatus::DeadlineExceeded,
+                    Error::Reqwest(e) if e.is_connect() => SpanStatus::Unavailable,
+                    _ => SpanStatus::UnknownError,
+                };
+                (Level::Error, status)
+            }
+        };
+        hub.add_breadcrumb(Breadcrumb {
+            ty: "http".to_string(),
+            category: Some("http".to_string()),
+            level,
+            data: fields,
+            ..Default::default()
+        });
+
+        if let Some(span) = span {
+            if let Ok(response) = &result {
+                span.set_data("http.response.status_code", response.status().as_u16().into());
+            }
+            span.set_status(status);
+            span.finish();
+        }
+
+        if let Err(Error::Reqwest(e)) = &result {
+            if self.capture_errors && (e.is_connect() || e.is_timeout()) {
+                hub.with_scope(
+                    |scope| {
+                        scope.set_tag("http.method", &method);
+                        scope.set_tag("http.host", &host);
+                    },
+                    || hub.capture_error(e),
+                );
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,173 @@
+//! Several Bugsink projects from one process, for services shared by teams.
+//!
+//! Each envelope goes to the DSN of the first [`Route`](routing::Route) that
+//! matches its event or transaction, and to the main DSN otherwise. A route
+//! can require the kind of item and tag values; sessions and client reports
+//! always go to the main DSN.
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .dsn("https://key@errors.observability.app.bauer-group.com/1")
+//!     .route(Route::new(payments_dsn).events().tag("team", "payments"))
+//!     .route(Route::new(performance_dsn).transactions())
+//!     .build();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [[routes]]
+//! dsn = "https://key@errors.observability.app.bauer-group.com/7"
+//! kind = "event"    # or "transaction"; both when left out
+//! tags = { team = "payments" }
+//!
+//! [[routes]]
+//! dsn = "https://key@errors.observability.app.bauer-group.com/3"
+//! kind = "transaction"
+//! ```
+//!
+//! Every route gets its own transport from the same factory, so local
+//! output, rate limits and retries apply to all projects alike. Routes are
+//! read when the client is created.
+
+use sentry::{
+    protocol::{Envelope, EnvelopeItem},
+    types::Dsn,
+    ClientOptions, Transport, TransportFactory,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Event,
+    Transaction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub dsn: Dsn,
+    /// `None` matches events and transactions.
+    pub kind: Option<ItemKind>,
+    /// Tags that must all be set to these values.
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Route {
+    pub fn new(dsn: Dsn) -> Self {
+        Self {
+            dsn,
+            kind: None,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    pub fn events(mut self) -> Self {
+        self.kind = Some(ItemKind::Event);
+        self
+    }
+
+    pub fn transactions(mut self) -> Self {
+        self.kind = Some(ItemKind::Transaction);
+        self
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn matches(&self, envelope: &Envelope) -> bool {
+        let Some((kind, tags)) = classify(envelope) else {
+            return false;
+        };
+        self.kind.is_none_or(|expected| expected == kind)
+            && self.tags.iter().all(|(key, value)| tags.get(key) == Some(value))
+    }
+}
+
+fn classify(envelope: &Envelope) -> Option<(ItemKind, &BTreeMap<String, String>)> {
+    if let Some(event) = envelope.event() {
+        return Some((ItemKind::Event, &event.tags));
+    }
+    envelope.items().find_map(|item| match item {
+        EnvelopeItem::Transaction(transaction) => Some((ItemKind::Transaction, &transaction.tags)),
+        _ => None,
+    })
+}
+
+/// Sends each envelope through the transport of the first matching route.
+pub struct RoutingTransport {
+    default: Arc<dyn Transport>,
+    routes: Vec<(Route, Arc<dyn Transport>)>,
+}
+
+impl RoutingTransport {
+    pub fn new(default: Arc<dyn Transport>, routes: Vec<(Route, Arc<dyn Transport>)>) -> Self {
+        Self { default, routes }
+    }
+
+    fn transports(&self) -> impl Iterator<Item = &Arc<dyn Transport>> {
+        std::iter::once(&self.default).chain(self.routes.iter().map(|(_, transport)| transport))
+    }
+}
+
+impl Transport for RoutingTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        let transport = self
+            .routes
+            .iter()
+            .find(|(route, _)| route.matches(&envelope))
+            .map_or(&self.default, |(_, transport)| transport);
+        transport.send_envelope(envelope);
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        // Not short-circuiting: every transport gets to flush
+        self.transports()
+            .fold(true, |flushed, transport| transport.flush(timeout) & flushed)
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        self.transports()
+            .fold(true, |shut_down, transport| transport.shutdown(timeout) & shut_down)
+    }
+}
+
+fn state() -> &'static RwLock<Vec<Route>> {
+    static ROUTES: OnceLock<RwLock<Vec<Route>>> = OnceLock::new();
+    ROUTES.get_or_init(Default::default)
+}
+
+/// Routes used by clients created from now on; an empty list sends everything to the main DSN.
+pub fn install(routes: Vec<Route>) {
+    *state().write().unwrap() = routes;
+}
+
+pub fn routes() -> Vec<Route> {
+    state().read().unwrap().clone()
+}
+
+pub(crate) fn is_enabled() -> bool {
+    !state().read().unwrap().is_empty()
+}
+
+pub(crate) fn wrap(factory: Arc<dyn TransportFactory>) -> Arc<dyn TransportFactory> {
+    let routes = routes();
+    Arc::new(move |options: &ClientOptions| -> Arc<dyn Transport> {
+        let routes = routes
+            .iter()
+            .map(|route| {
+                let options = ClientOptions {
+                    dsn: Some(route.dsn.clone()),
+                    ..options.clone()
+                };
+                (route.clone(), factory.create_transport(&options))
+            })
+            .collect();
+        Arc::new(RoutingTransport::new(factory.create_transport(options), routes))
+    })
+}
@@ -0,0 +1,198 @@
+//! Settings that can change while the process runs, e.g. to turn sampling up
+//! to 100% while debugging a production incident.
+//!
+//! Sampling is applied by the pipeline rather than the SDK client, so changes
+//! take effect on the next event. `debug` controls the pipeline's per-event
+//! decision log; the SDK's own debug output keeps its init setting.
+//!
+//! ```ignore
+//! let handle = sentry.config();
+//! handle.temporarily(Duration::from_secs(900), |c| c.traces_sample_rate = 1.0);
+//! handle.watch_file("observability.toml", Duration::from_secs(10));
+//! ```
+
+use super::config::Config;
+use std::{
+    sync::{OnceLock, RwLock},
+    thread,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeConfig {
+    pub sample_rate: f32,
+    pub traces_sample_rate: f32,
+    pub debug: bool,
+    /// Error patterns that are never sent.
+    pub ignored_error_types: Vec<String>,
+    pub ignored_transactions: Vec<String>,
+    pub ignored_loggers: Vec<String>,
+}
+
+impl From<&Config> for RuntimeConfig {
+    fn from(config: &Config) -> Self {
+        Self {
+            sample_rate: config.sample_rate,
+            traces_sample_rate: config.traces_sample_rate(),
+            debug: config.debug(),
+            ignored_error_types: config.ignored_error_types.clone(),
+            ignored_transactions: config.ignored_transactions.clone(),
+            ignored_loggers: config.ignored_loggers.clone(),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    /// Names of the settings that differ in `newer`.
+    pub fn changes(&self, newer: &RuntimeConfig) -> Vec<&'static str> {
+        [
+            ("sample_rate", self.sample_rate != newer.sample_rate),
+            (
+                "traces_sample_rate",
+                self.traces_sample_rate != newer.traces_sample_rate,
+            ),
+            ("debug", self.debug != newer.debug),
+            (
+                "ignored_error_types",
+                self.ignored_error_types != newer.ignored_error_types,
+            ),
+            (
+                "ignored_transactions",
+                self.ignored_transactions != newer.ignored_transactions,
+            ),
+            ("ignored_loggers", self.ignored_loggers != newer.ignored_loggers),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+}
+
+fn state() -> &'static RwLock<RuntimeConfig> {
+    static STATE: OnceLock<RwLock<RuntimeConfig>> = OnceLock::new();
+    STATE.get_or_init(|| RwLock::new(RuntimeConfig::from(&Config::default())))
+}
+
+/// Current settings.
+pub fn current() -> RuntimeConfig {
+    state().read().unwrap().clone()
+}
+
+/// Handle for changing the runtime settings; cheap to clone and share with admin endpoints.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConfigHandle;
+
+impl ConfigHandle {
+    pub fn get(&self) -> RuntimeConfig {
+        current()
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut RuntimeConfig)) {
+        let mut config = state().write().unwrap();
+        let lists = |c: &RuntimeConfig| {
+            (
+                c.ignored_error_types.clone(),
+                c.ignored_transactions.clone(),
+                c.ignored_loggers.clone(),
+            )
+        };
+        let previous = lists(&config);
+        f(&mut config);
+        config.sample_rate = config.sample_rate.clamp(0.0, 1.0);
+        config.traces_sample_rate = config.traces_sample_rate.clamp(0.0, 1.0);
+        let current = lists(&config);
+        drop(config);
+        // Recompile the matchers only when a list changed
+        if current != previous {
+            let (errors, transactions, loggers) = current;
+            super::filters::install(super::filters::EventFilters::new(&errors, &transactions, &loggers));
+        }
+    }
+
+    pub fn replace(&self, config: RuntimeConfig) {
+        self.update(|current| *current = config);
+    }
+
+    pub fn set_sample_rate(&self, rate: f32) {
+        self.update(|c| c.sample_rate = rate);
+    }
+
+    pub fn set_traces_sample_rate(&self, rate: f32) {
+        self.update(|c| c.traces_sample_rate = rate);
+    }
+
+    pub fn set_debug(&self, debug: bool) {
+        self.update(|c| c.debug = debug);
+    }
+
+    pub fn set_ignored_error_types(&self, types: Vec<String>) {
+        self.update(|c| c.ignored_error_types = types);
+    }
+
+    pub fn set_ignored_transactions(&self, patterns: Vec<String>) {
+        self.update(|c| c.ignored_transactions = patterns);
+    }
+
+    pub fn set_ignored_loggers(&self, patterns: Vec<String>) {
+        self.update(|c| c.ignored_loggers = patterns);
+    }
+
+    /// Apply a change for `duration`, then restore the settings as they were before it.
+    pub fn temporarily(&self, duration: Duration, f: impl FnOnce(&mut RuntimeConfig)) {
+        let previous = self.get();
+        self.update(f);
+        let handle = *self;
+        thread::spawn(move || {
+            thread::sleep(duration);
+            handle.replace(previous);
+        });
+    }
+
+    /// Re-read sample rates, debug, filter lists, sampling and scrubbing rules from a config file.
+    #[cfg(feature = "config-file")]
+    pub fn reload_from_file(&self, path: impl AsRef<std::path::Path>) -> Result<(), super::config::ConfigError> {
+        let path = path.as_ref();
+        let config = super::config::from_file(path)?;
+        let runtime = RuntimeConfig::from(&config);
+        let changes = self.get().changes(&runtime);
+        self.replace(runtime);
+        super::scrubbing::install(super::scrubbing::DataScrubber::from_config(&config));
+        super::secrets::configure(config.detect_secrets.then(super::secrets::SecretScanner::default));
+        super::origins::install(config.origins);
+        super::fingerprint::install(super::fingerprint::FingerprintRules::new(config.fingerprint_rules));
+        super::ownership::install(super::ownership::OwnershipRules::new(config.ownership_rules));
+        super::severity::install(config.severity);
+        super::sampling::set_rules(config.sampling.rules().to_vec());
+        super::error_sampling::install(config.error_sampling);
+        super::aggregation::install(config.aggregation);
+        super::dedupe::install(
+            config
+                .dedupe_window
+                .map(|window| super::dedupe::DedupePolicy::new(window, config.dedupe_limit)),
+        );
+        super::process_breadcrumbs::config_reloaded(path, &changes);
+        Ok(())
+    }
+
+    /// Poll a config file and reload it whenever its modification time changes.
+    #[cfg(feature = "config-file")]
+    pub fn watch_file(&self, path: impl Into<std::path::PathBuf>, interval: Duration) -> thread::JoinHandle<()> {
+        let path = path.into();
+        let handle = *self;
+        thread::spawn(move || {
+            let modified = || std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            let mut last = modified();
+            loop {
+                thread::sleep(interval);
+                let current = modified();
+                if current.is_some() && current != last {
+                    match handle.reload_from_file(&path) {
+                        Ok(()) => eprintln!("Reloaded observability config from {}", path.display()),
+                        Err(e) => eprintln!("Config reload failed: {}", e),
+                    }
+                    last = current;
+                }
+            }
+        })
+    }
+}
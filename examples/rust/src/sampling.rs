@@ -0,0 +1,209 @@
+//! Per-transaction sample rates instead of one global `traces_sample_rate`.
+//!
+//! For every new transaction the rate is decided by, in order:
+//!
+//! 1. the custom sampler, if it returns a rate
+//! 2. the first matching [`SamplingRule`](sampling::SamplingRule)
+//! 3. the upstream service's decision, for continued traces
+//! 4. the runtime `traces_sample_rate`
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .sampling_rule(SamplingRule::new(1.0).transaction("* /checkout*"))
+//!     .sampling_rule(SamplingRule::new(0.0).transaction("GET /health"))
+//!     .traces_sample_rate(0.01)
+//!     .traces_sampler(|ctx| (ctx.operation() == "queue.process").then_some(0.5))
+//!     .init();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [[sampling]]
+//! transaction = "GET /health"
+//! rate = 0.0
+//!
+//! [[sampling]]
+//! op = "http.server"
+//! tags = { tenant = "enterprise" }
+//! parent_sampled = true
+//! rate = 1.0
+//! ```
+//!
+//! Names are matched with `*` (any run of characters) and `?` (one
+//! character). Tags are looked up in the transaction context's custom data
+//! (`TransactionContext::custom_insert`).
+
+use sentry::TransactionContext;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+/// Custom sampling decision; `None` falls through to the rules.
+pub type TracesSampler = dyn Fn(&TransactionContext) -> Option<f32> + Send + Sync;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplingRule {
+    /// Glob over the transaction name.
+    pub transaction: Option<String>,
+    pub op: Option<String>,
+    pub tags: BTreeMap<String, String>,
+    /// Match only traces the upstream service did (or did not) sample.
+    pub parent_sampled: Option<bool>,
+    pub rate: f32,
+}
+
+impl SamplingRule {
+    /// A rule that matches every transaction until conditions are added.
+    pub fn new(rate: f32) -> Self {
+        Self {
+            transaction: None,
+            op: None,
+            tags: BTreeMap::new(),
+            parent_sampled: None,
+            rate: rate.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn transaction(mut self, glob: &str) -> Self {
+        self.transaction = Some(glob.to_string());
+        self
+    }
+
+    pub fn op(mut self, op: impl AsRef<str>) -> Self {
+        self.op = Some(op.as_ref().to_string());
+        self
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn parent_sampled(mut self, sampled: bool) -> Self {
+        self.parent_sampled = Some(sampled);
+        self
+    }
+
+    pub fn matches(&self, ctx: &TransactionContext) -> bool {
+        if let Some(glob) = &self.transaction {
+            if !glob_match(glob, ctx.name()) {
+                return false;
+            }
+        }
+        if self.op.as_deref().is_some_and(|op| op != ctx.operation()) {
+            return false;
+        }
+        if self.parent_sampled.is_some() && self.parent_sampled != ctx.sampled() {
+            return false;
+        }
+        self.tags.iter().all(|(key, value)| {
+            ctx.custom()
+                .and_then(|custom| custom.get(key))
+                .and_then(|custom| custom.as_str())
+                == Some(value.as_str())
+        })
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SamplingRules {
+    rules: Vec<SamplingRule>,
+    sampler: Option<Arc<TracesSampler>>,
+}
+
+impl fmt::Debug for SamplingRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SamplingRules")
+            .field("rules", &self.rules)
+            .field("sampler", &self.sampler.is_some())
+            .finish()
+    }
+}
+
+impl SamplingRules {
+    pub fn new(rules: Vec<SamplingRule>) -> Self {
+        Self { rules, sampler: None }
+    }
+
+    pub fn with_sampler(mut self, sampler: Arc<TracesSampler>) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    pub fn push(&mut self, rule: SamplingRule) {
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &[SamplingRule] {
+        &self.rules
+    }
+
+    /// Rate for a new transaction; `default` applies when nothing else decides.
+    pub fn sample_rate(&self, ctx: &TransactionContext, default: f32) -> f32 {
+        if let Some(rate) = self.sampler.as_ref().and_then(|sampler| sampler(ctx)) {
+            return rate.clamp(0.0, 1.0);
+        }
+        if let Some(rule) = self.rules.iter().find(|rule| rule.matches(ctx)) {
+            return rule.rate;
+        }
+        match ctx.sampled() {
+            Some(sampled) => f32::from(u8::from(sampled)),
+            None => default,
+        }
+    }
+}
+
+fn state() -> &'static RwLock<SamplingRules> {
+    static STATE: OnceLock<RwLock<SamplingRules>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+pub fn install(rules: SamplingRules) {
+    *state().write().unwrap() = rules;
+}
+
+/// Replace the rules, keeping the custom sampler.
+pub fn set_rules(rules: Vec<SamplingRule>) {
+    state().write().unwrap().rules = rules;
+}
+
+pub fn current() -> SamplingRules {
+    state().read().unwrap().clone()
+}
+
+/// Rate for a new transaction under the installed rules.
+pub fn sample_rate(ctx: &TransactionContext, default: f32) -> f32 {
+    state().read().unwrap().sample_rate(ctx, default)
+}
+
+/// `*` matches any run of characters, `?` exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
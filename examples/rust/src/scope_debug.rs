@@ -0,0 +1,177 @@
+//! Debugging helpers that render what the current scope would add to an event.
+//!
+//! ```ignore
+//! let before = ScopeSnapshot::current();
+//! tenant_middleware(&request);
+//! println!("{}", scope_debug::diff(&before, &ScopeSnapshot::current()));
+//! ```
+
+use sentry::{
+    protocol::{Breadcrumb, Context, Event, User, Value},
+    Scope,
+};
+use std::{collections::BTreeMap, fmt};
+
+/// Tags, user, extras, contexts and breadcrumbs bound to a scope at one point in time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScopeSnapshot {
+    pub user: Option<User>,
+    pub tags: BTreeMap<String, String>,
+    pub extra: BTreeMap<String, Value>,
+    pub contexts: BTreeMap<String, Context>,
+    pub breadcrumbs: Vec<Breadcrumb>,
+    pub transaction: Option<String>,
+}
+
+impl ScopeSnapshot {
+    /// Snapshot of the current hub's scope.
+    pub fn current() -> Self {
+        sentry::configure_scope(|scope| scope.snapshot())
+    }
+}
+
+pub trait ScopeSnapshotExt {
+    fn snapshot(&self) -> ScopeSnapshot;
+}
+
+impl ScopeSnapshotExt for Scope {
+    /// Scope fields are private, so apply the scope to an empty event and read it back.
+    fn snapshot(&self) -> ScopeSnapshot {
+        let Some(event) = self.apply_to_event(Event::default()) else {
+            return ScopeSnapshot::default();
+        };
+        ScopeSnapshot {
+            user: event.user,
+            tags: event.tags.into_iter().collect(),
+            extra: event.extra.into_iter().collect(),
+            contexts: event.contexts.into_iter().collect(),
+            breadcrumbs: event.breadcrumbs.values,
+            transaction: event.transaction,
+        }
+    }
+}
+
+impl fmt::Display for ScopeSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.user {
+            Some(user) => writeln!(f, "user: {}", render_user(user))?,
+            None => writeln!(f, "user: <none>")?,
+        }
+        if let Some(transaction) = &self.transaction {
+            writeln!(f, "transaction: {}", transaction)?;
+        }
+        for (key, value) in &self.tags {
+            writeln!(f, "tag {} = {}", key, value)?;
+        }
+        for (key, value) in &self.extra {
+            writeln!(f, "extra {} = {}", key, value)?;
+        }
+        for (key, value) in &self.contexts {
+            writeln!(f, "context {} = {}", key, render_context(value))?;
+        }
+        for breadcrumb in &self.breadcrumbs {
+            writeln!(f, "breadcrumb {}", render_breadcrumb(breadcrumb))?;
+        }
+        Ok(())
+    }
+}
+
+/// Changes between two snapshots; `Display` renders one `+`/`-`/`~` line per change.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScopeDiff {
+    pub changes: Vec<String>,
+}
+
+impl ScopeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for ScopeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "(no changes)");
+        }
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two snapshots.
+pub fn diff(a: &ScopeSnapshot, b: &ScopeSnapshot) -> ScopeDiff {
+    let mut changes = Vec::new();
+
+    if a.user != b.user {
+        let render = |user: &Option<User>| user.as_ref().map_or("<none>".to_string(), render_user);
+        changes.push(format!("~ user: {} -> {}", render(&a.user), render(&b.user)));
+    }
+    if a.transaction != b.transaction {
+        changes.push(format!("~ transaction: {:?} -> {:?}", a.transaction, b.transaction));
+    }
+
+    diff_maps("tag", &a.tags, &b.tags, |v| v.clone(), &mut changes);
+    diff_maps("extra", &a.extra, &b.extra, |v| v.to_string(), &mut changes);
+    diff_maps("context", &a.contexts, &b.contexts, render_context, &mut changes);
+
+    // Breadcrumbs are append-only in practice: report what was added or evicted
+    let common = a
+        .breadcrumbs
+        .iter()
+        .zip(&b.breadcrumbs)
+        .take_while(|(x, y)| x == y)
+        .count();
+    for breadcrumb in &a.breadcrumbs[common..] {
+        changes.push(format!("- breadcrumb {}", render_breadcrumb(breadcrumb)));
+    }
+    for breadcrumb in &b.breadcrumbs[common..] {
+        changes.push(format!("+ breadcrumb {}", render_breadcrumb(breadcrumb)));
+    }
+
+    ScopeDiff { changes }
+}
+
+fn diff_maps<V: PartialEq>(
+    kind: &str,
+    a: &BTreeMap<String, V>,
+    b: &BTreeMap<String, V>,
+    render: impl Fn(&V) -> String,
+    changes: &mut Vec<String>,
+) {
+    for (key, old) in a {
+        match b.get(key) {
+            None => changes.push(format!("- {} {} = {}", kind, key, render(old))),
+            Some(new) if new != old => changes.push(format!("~ {} {}: {} -> {}", kind, key, render(old), render(new))),
+            Some(_) => {}
+        }
+    }
+    for (key, new) in b {
+        if !a.contains_key(key) {
+            changes.push(format!("+ {} {} = {}", kind, key, render(new)));
+        }
+    }
+}
+
+fn render_user(user: &User) -> String {
+    format!(
+        "id={} email={} username={}",
+        user.id.as_deref().unwrap_or("-"),
+        user.email.as_deref().unwrap_or("-"),
+        user.username.as_deref().unwrap_or("-")
+    )
+}
+
+fn render_context(context: &Context) -> String {
+    serde_json::to_string(context).unwrap_or_else(|_| format!("{:?}", context))
+}
+
+fn render_breadcrumb(breadcrumb: &Breadcrumb) -> String {
+    format!(
+        "[{}] {}: {}",
+        breadcrumb.level,
+        breadcrumb.category.as_deref().unwrap_or("default"),
+        breadcrumb.message.as_deref().unwrap_or_default()
+    )
+}
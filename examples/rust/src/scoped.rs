@@ -0,0 +1,81 @@
+//! Tags and extras that are undone when a guard is dropped, for scope data
+//! that has to live across an `.await` or past a block boundary, where a
+//! `with_scope` closure doesn't fit:
+//!
+//! ```ignore
+//! let _guard = sentry.push_tags([("tenant", tenant.as_str()), ("job", "import")]);
+//! import_batch(batch).await?; // events captured here carry both tags
+//! // tags removed (or restored to their previous values) here
+//! ```
+//!
+//! The guard remembers the hub it was created on and restores that hub's
+//! scope on drop. Guards should be dropped in reverse creation order.
+
+use super::scope_debug::ScopeSnapshotExt;
+use sentry::{protocol::Value, Hub};
+use std::sync::Arc;
+
+#[must_use = "the tags are removed as soon as the guard is dropped"]
+pub struct ScopeGuard {
+    hub: Arc<Hub>,
+    /// Keys with the values they had before the guard set them.
+    tags: Vec<(String, Option<String>)>,
+    extra: Vec<(String, Option<Value>)>,
+}
+
+impl ScopeGuard {
+    pub fn new() -> Self {
+        Self {
+            hub: Hub::current(),
+            tags: Vec::new(),
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        let previous = self.hub.configure_scope(|scope| {
+            let previous = scope.snapshot().tags.remove(key);
+            scope.set_tag(key, value);
+            previous
+        });
+        self.tags.push((key.to_string(), previous));
+        self
+    }
+
+    pub fn extra(mut self, key: &str, value: impl Into<Value>) -> Self {
+        let previous = self.hub.configure_scope(|scope| {
+            let previous = scope.snapshot().extra.remove(key);
+            scope.set_extra(key, value.into());
+            previous
+        });
+        self.extra.push((key.to_string(), previous));
+        self
+    }
+}
+
+impl Default for ScopeGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let tags = std::mem::take(&mut self.tags);
+        let extra = std::mem::take(&mut self.extra);
+        self.hub.configure_scope(|scope| {
+            for (key, previous) in tags.into_iter().rev() {
+                match previous {
+                    Some(value) => scope.set_tag(&key, value),
+                    None => scope.remove_tag(&key),
+                }
+            }
+            for (key, previous) in extra.into_iter().rev() {
+                match previous {
+                    Some(value) => scope.set_extra(&key, value),
+                    None => scope.remove_extra(&key),
+                }
+            }
+        });
+    }
+}
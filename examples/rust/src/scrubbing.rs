@@ -0,0 +1,581 @@
+//! PII scrubbing for everything an event carries.
+//!
+//! A [`DataScrubber`] holds two kinds of rules:
+//!
+//! - key rules redact the whole value of any object field, header or tag whose
+//!   name has the key as a word (case-insensitive, split at `-`, `_`, `.` and
+//!   camel case), so `password` also covers `user_password` and `X-Password`
+//!   while `token` leaves `max_tokens` alone. The crate's own [`SDK_TAGS`]
+//!   are exempt;
+//! - pattern rules replace matches inside any string with `[REDACTED:<rule>]`.
+//!
+//! URLs found in any string lose their userinfo, and the values of sensitive
+//! query parameters ([`DEFAULT_QUERY_PARAMS`] plus configured ones) are
+//! redacted; this also applies to the request URL and query string, and to
+//! span descriptions passed to `with_span`.
+//!
+//! With IP anonymization enabled (`anonymize_ip`), the last octet of IPv4 and
+//! the last 80 bits of IPv6 addresses are zeroed in `user.ip_address`,
+//! `REMOTE_ADDR` and forwarding headers, so user context can stay on without
+//! storing full addresses.
+//!
+//! Rules are applied recursively to the request body, headers and cookies,
+//! extras, contexts, tags, messages, exception values and breadcrumb data.
+//! The scrubber runs first in `before_send`, so no later hook, spool or crash
+//! checkpoint ever sees the raw values.
+//!
+//! ```ignore
+//! scrubbing::install(
+//!     DataScrubber::with_defaults()
+//!         .key("customer_number")
+//!         .rule(Rule::pattern("order_token", r"\bot_[A-Za-z0-9]{24}\b")?),
+//! );
+//! ```
+
+use super::config::Config;
+use regex::Regex;
+use sentry::protocol::{Breadcrumb, Context, Event, IpAddress, Map, Value};
+use std::{
+    borrow::Cow,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::{Arc, OnceLock, RwLock},
+};
+
+/// Replacement for values matched by a key rule.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Field names redacted by [`DataScrubber::with_defaults`].
+pub const DEFAULT_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "authorization",
+    "cookie",
+    "session",
+    "sessionid",
+    "accesstoken",
+    "refreshtoken",
+    "creditcard",
+    "cardnumber",
+    "cvv",
+    "iban",
+    "ssn",
+];
+
+/// Tags set by this crate whose names look sensitive but whose values are not.
+pub const SDK_TAGS: &[&str] = &["session.crashed", super::secrets::TAG];
+
+/// Request headers carrying client addresses, anonymized with `anonymize_ip`.
+pub const IP_HEADERS: &[&str] = &[
+    "X-Forwarded-For",
+    "X-Real-IP",
+    "X-Client-IP",
+    "CF-Connecting-IP",
+    "True-Client-IP",
+];
+
+/// Query parameters whose values are redacted by [`DataScrubber::with_defaults`].
+pub const DEFAULT_QUERY_PARAMS: &[&str] = &["token", "api_key", "session", "password", "secret", "signature"];
+
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// Redact the value of any field with this key among the words of its
+    /// name, split at `_`, `-`, `.`, spaces and camel case: `token` matches
+    /// `access_token` and `X-Auth-Token` but not `max_tokens`. Compound
+    /// keys span words, so `apikey` matches `x-api-key` and `apiKey`.
+    Key(String),
+    /// Replace matches inside strings; `validate` can reject false positives.
+    Pattern {
+        name: String,
+        regex: Regex,
+        validate: Option<fn(&str) -> bool>,
+    },
+}
+
+impl Rule {
+    pub fn key(key: &str) -> Self {
+        Rule::Key(normalize(key))
+    }
+
+    pub fn pattern(name: &str, regex: &str) -> Result<Self, regex::Error> {
+        Ok(Rule::Pattern {
+            name: name.to_string(),
+            regex: Regex::new(regex)?,
+            validate: None,
+        })
+    }
+
+    pub fn email() -> Self {
+        builtin("email", r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b", None)
+    }
+
+    /// 13-19 digit card numbers, optionally grouped; Luhn-checked.
+    pub fn credit_card() -> Self {
+        builtin("credit_card", r"\b\d(?:[ -]?\d){12,18}\b", Some(luhn_valid))
+    }
+
+    /// IBANs, optionally grouped in blocks of four; checksum-verified.
+    pub fn iban() -> Self {
+        builtin(
+            "iban",
+            r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b",
+            Some(iban_valid),
+        )
+    }
+
+    /// `Bearer <token>` / `Basic <credentials>` as they appear in logged headers.
+    pub fn auth_token() -> Self {
+        builtin("token", r"(?i)\b(?:bearer|basic)\s+[A-Za-z0-9\-._~+/]+=*", None)
+    }
+
+    fn replaces(&self, other: &Rule) -> bool {
+        match (self, other) {
+            (Rule::Key(a), Rule::Key(b)) => a == b,
+            (Rule::Pattern { name: a, .. }, Rule::Pattern { name: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+fn builtin(name: &str, regex: &str, validate: Option<fn(&str) -> bool>) -> Rule {
+    Rule::Pattern {
+        name: name.to_string(),
+        regex: Regex::new(regex).expect("built-in scrubbing pattern"),
+        validate,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DataScrubber {
+    rules: Vec<Rule>,
+    headers: Vec<String>,
+    query_params: Vec<String>,
+    anonymize_ip: bool,
+}
+
+impl DataScrubber {
+    /// A scrubber without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// [`DEFAULT_KEYS`], [`DEFAULT_QUERY_PARAMS`] and the email, credit card,
+    /// IBAN and auth token patterns.
+    pub fn with_defaults() -> Self {
+        let scrubber = DEFAULT_KEYS.iter().fold(Self::new(), |scrubber, key| scrubber.key(key));
+        DEFAULT_QUERY_PARAMS
+            .iter()
+            .fold(scrubber, |scrubber, param| scrubber.query_param(param))
+            .rule(Rule::email())
+            .rule(Rule::credit_card())
+            .rule(Rule::iban())
+            .rule(Rule::auth_token())
+    }
+
+    /// Defaults plus the headers, keys, query parameters and patterns from the settings.
+    /// Invalid patterns are reported and skipped.
+    pub fn from_config(config: &Config) -> Self {
+        let mut scrubber = Self::with_defaults()
+            .headers(config.sensitive_headers.iter().cloned())
+            .anonymize_ip(config.anonymize_ip);
+        for key in &config.scrub_keys {
+            scrubber = scrubber.key(key);
+        }
+        for param in &config.scrub_query_params {
+            scrubber = scrubber.query_param(param);
+        }
+        for (name, regex) in &config.scrub_patterns {
+            match Rule::pattern(name, regex) {
+                Ok(rule) => scrubber = scrubber.rule(rule),
+                Err(e) => eprintln!("Invalid scrubbing pattern {}: {}", name, e),
+            }
+        }
+        scrubber
+    }
+
+    pub fn key(self, key: &str) -> Self {
+        self.rule(Rule::key(key))
+    }
+
+    /// Add a rule; a pattern with the same name replaces the existing one.
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.retain(|existing| !rule.replaces(existing));
+        self.rules.push(rule);
+        self
+    }
+
+    /// Redact the value of query parameters matching `param` as a [key rule](Rule::Key) would.
+    pub fn query_param(mut self, param: &str) -> Self {
+        let param = normalize(param);
+        if !self.query_params.contains(&param) {
+            self.query_params.push(param);
+        }
+        self
+    }
+
+    /// Zero the host part of client IP addresses.
+    pub fn anonymize_ip(mut self, enabled: bool) -> Self {
+        self.anonymize_ip = enabled;
+        self
+    }
+
+    /// Request headers that are always redacted, regardless of the key rules.
+    pub fn headers(mut self, headers: impl IntoIterator<Item = String>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Whether a field of this name is redacted entirely.
+    pub fn is_sensitive_key(&self, key: &str) -> bool {
+        let segments = segments(key);
+        self.rules
+            .iter()
+            .any(|rule| matches!(rule, Rule::Key(k) if spans_segments(&segments, k)))
+    }
+
+    /// Scrub the URLs in a string, then apply the pattern rules.
+    pub fn scrub_str<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = self.scrub_urls(text);
+        for rule in &self.rules {
+            let Rule::Pattern { name, regex, validate } = rule else {
+                continue;
+            };
+            if !regex.is_match(&text) {
+                continue;
+            }
+            let replaced = regex
+                .replace_all(&text, |caps: &regex::Captures<'_>| {
+                    let found = &caps[0];
+                    match validate {
+                        Some(valid) if !valid(found) => found.to_string(),
+                        _ => format!("[REDACTED:{}]", name),
+                    }
+                })
+                .into_owned();
+            text = Cow::Owned(replaced);
+        }
+        text
+    }
+
+    /// Strip userinfo and redact sensitive query parameters of every URL in `text`,
+    /// absolute (`scheme://...`) or a relative path with a query string.
+    pub fn scrub_urls<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        static URL: OnceLock<Regex> = OnceLock::new();
+        let url = URL.get_or_init(|| {
+            Regex::new(r#"(?i)\b[a-z][a-z0-9+.-]*://[^\s"'<>]+|(?:^|\s)/[^\s"'<>?]*\?[^\s"'<>]+"#).expect("URL pattern")
+        });
+        if !text.contains("://") && !text.contains('?') {
+            return Cow::Borrowed(text);
+        }
+        url.replace_all(text, |caps: &regex::Captures<'_>| self.scrub_url(&caps[0]).into_owned())
+    }
+
+    /// Strip userinfo and redact sensitive query parameters of a single URL.
+    pub fn scrub_url<'a>(&self, url: &'a str) -> Cow<'a, str> {
+        let (rest, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+        let (base, query) = match rest.split_once('?') {
+            Some((base, query)) => (base, Some(query)),
+            None => (rest, None),
+        };
+
+        let mut base = Cow::Borrowed(base);
+        if let Some(scheme_end) = base.find("://") {
+            let authority_start = scheme_end + 3;
+            let authority_len = base[authority_start..]
+                .find('/')
+                .unwrap_or(base.len() - authority_start);
+            if let Some(at) = base[authority_start..authority_start + authority_len].rfind('@') {
+                base = Cow::Owned(format!(
+                    "{}{}",
+                    &base[..authority_start],
+                    &base[authority_start + at + 1..]
+                ));
+            }
+        }
+        let query = query.map(|query| self.scrub_query(query));
+
+        if matches!(base, Cow::Borrowed(_)) && !matches!(query, Some(Cow::Owned(_))) {
+            return Cow::Borrowed(url);
+        }
+        let mut scrubbed = base.into_owned();
+        if let Some(query) = query {
+            scrubbed.push('?');
+            scrubbed.push_str(&query);
+        }
+        if let Some(fragment) = fragment {
+            scrubbed.push('#');
+            scrubbed.push_str(fragment);
+        }
+        Cow::Owned(scrubbed)
+    }
+
+    /// Redact the values of sensitive parameters in a `a=1&b=2` query string.
+    pub fn scrub_query<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        let sensitive = |name: &str| {
+            let segments = segments(name);
+            self.query_params.iter().any(|param| spans_segments(&segments, param))
+        };
+        if !query
+            .split('&')
+            .any(|pair| pair.split_once('=').is_some_and(|(name, _)| sensitive(name)))
+        {
+            return Cow::Borrowed(query);
+        }
+        let pairs: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if sensitive(name) => format!("{}={}", name, REDACTED),
+                _ => pair.to_string(),
+            })
+            .collect();
+        Cow::Owned(pairs.join("&"))
+    }
+
+    /// Scrub a JSON value in place, recursing into arrays and objects.
+    pub fn scrub_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => self.scrub_string(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.scrub_value(item)),
+            Value::Object(map) => map.iter_mut().for_each(|(key, value)| self.scrub_field(key, value)),
+            _ => {}
+        }
+    }
+
+    pub fn scrub_map(&self, map: &mut Map<String, Value>) {
+        map.iter_mut().for_each(|(key, value)| self.scrub_field(key, value));
+    }
+
+    /// Redact the value if the key is sensitive, otherwise scrub it recursively.
+    pub fn scrub_field(&self, key: &str, value: &mut Value) {
+        if self.is_sensitive_key(key) && !value.is_null() {
+            *value = Value::String(REDACTED.to_string());
+        } else {
+            self.scrub_value(value);
+        }
+    }
+
+    pub fn scrub_string(&self, text: &mut String) {
+        if let Cow::Owned(scrubbed) = self.scrub_str(text) {
+            *text = scrubbed;
+        }
+    }
+
+    pub fn scrub_breadcrumb(&self, breadcrumb: &mut Breadcrumb) {
+        if let Some(message) = breadcrumb.message.as_mut() {
+            self.scrub_string(message);
+        }
+        self.scrub_map(&mut breadcrumb.data);
+    }
+
+    pub fn scrub_event(&self, event: &mut Event<'_>) {
+        if let Some(message) = event.message.as_mut() {
+            self.scrub_string(message);
+        }
+        if let Some(logentry) = event.logentry.as_mut() {
+            self.scrub_string(&mut logentry.message);
+        }
+        for exception in event.exception.values.iter_mut() {
+            if let Some(value) = exception.value.as_mut() {
+                self.scrub_string(value);
+            }
+        }
+
+        if let Some(request) = event.request.as_mut() {
+            if let Some(url) = request.url.as_mut() {
+                if let Cow::Owned(scrubbed) = self.scrub_url(url.as_str()) {
+                    if let Ok(scrubbed) = scrubbed.parse() {
+                        *url = scrubbed;
+                    }
+                }
+            }
+            if let Some(query) = request.query_string.as_mut() {
+                if let Cow::Owned(scrubbed) = self.scrub_query(query) {
+                    *query = scrubbed;
+                }
+            }
+            if let Some(body) = request.data.as_mut() {
+                self.scrub_body(body);
+            }
+            if request.cookies.is_some() {
+                request.cookies = Some(REDACTED.to_string());
+            }
+            for (name, value) in request.headers.iter_mut() {
+                if self.headers.iter().any(|h| h.eq_ignore_ascii_case(name)) || self.is_sensitive_key(name) {
+                    *value = REDACTED.to_string();
+                } else if self.anonymize_ip && IP_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+                    *value = anonymize_ip_list(value);
+                } else {
+                    self.scrub_string(value);
+                }
+            }
+            for (name, value) in request.env.iter_mut() {
+                if self.is_sensitive_key(name) {
+                    *value = REDACTED.to_string();
+                } else if self.anonymize_ip && name == "REMOTE_ADDR" {
+                    *value = anonymize_ip_list(value);
+                }
+            }
+        }
+
+        if self.anonymize_ip {
+            if let Some(user) = event.user.as_mut() {
+                user.ip_address = match user.ip_address.take() {
+                    Some(IpAddress::Exact(ip)) => Some(IpAddress::Exact(anonymize_ip(ip))),
+                    // `Auto` would let the server store the full connection address
+                    _ => None,
+                };
+            }
+        }
+
+        for (key, value) in event.tags.iter_mut() {
+            if SDK_TAGS.contains(&key.as_str()) {
+                continue;
+            }
+            if self.is_sensitive_key(key) {
+                *value = REDACTED.to_string();
+            } else {
+                self.scrub_string(value);
+            }
+        }
+        self.scrub_map(&mut event.extra);
+        for context in event.contexts.values_mut() {
+            if let Context::Other(map) = context {
+                self.scrub_map(map);
+            }
+        }
+        for breadcrumb in event.breadcrumbs.values.iter_mut() {
+            self.scrub_breadcrumb(breadcrumb);
+        }
+    }
+
+    /// JSON bodies are scrubbed field by field; anything else as plain text.
+    fn scrub_body(&self, body: &mut String) {
+        match serde_json::from_str::<Value>(body) {
+            Ok(mut json @ (Value::Object(_) | Value::Array(_))) => {
+                self.scrub_value(&mut json);
+                *body = json.to_string();
+            }
+            _ => self.scrub_string(body),
+        }
+    }
+}
+
+/// Zero the last octet of an IPv4 address or the last 80 bits of an IPv6 address.
+pub fn anonymize_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !((1u128 << 80) - 1))),
+    }
+}
+
+/// Anonymize every address in a comma-separated list; anything unparsable is redacted.
+fn anonymize_ip_list(value: &str) -> String {
+    value
+        .split(',')
+        .map(|part| match part.trim().parse() {
+            Ok(ip) => anonymize_ip(ip).to_string(),
+            Err(_) => REDACTED.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn normalize(key: &str) -> String {
+    key.chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' ' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Lower-cased words of a field name: `X-Api-Key` and `apiKey` both give `api`, `key`.
+fn segments(key: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut previous_lower = false;
+    for c in key.chars() {
+        if matches!(c, '-' | '_' | ' ' | '.') {
+            segments.push(String::new());
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            segments.push(String::new());
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        segments.last_mut().unwrap().extend(c.to_lowercase());
+    }
+    segments.retain(|segment| !segment.is_empty());
+    segments
+}
+
+/// Whether consecutive `segments` spell the normalized `key`.
+fn spans_segments(segments: &[String], key: &str) -> bool {
+    (0..segments.len()).any(|start| {
+        let mut rest = key;
+        for segment in &segments[start..] {
+            match rest.strip_prefix(segment.as_str()) {
+                Some("") => return true,
+                Some(tail) => rest = tail,
+                None => return false,
+            }
+        }
+        false
+    })
+}
+
+fn luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn iban_valid(candidate: &str) -> bool {
+    let compact: String = candidate.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.len() < 15 {
+        return false;
+    }
+    let (head, tail) = compact.split_at(4);
+    tail.chars().chain(head.chars()).try_fold(0u32, |rem, c| {
+        let n = c.to_digit(36)?;
+        Some(if n >= 10 {
+            (rem * 100 + n) % 97
+        } else {
+            (rem * 10 + n) % 97
+        })
+    }) == Some(1)
+}
+
+fn state() -> &'static RwLock<Arc<DataScrubber>> {
+    static STATE: OnceLock<RwLock<Arc<DataScrubber>>> = OnceLock::new();
+    STATE.get_or_init(|| RwLock::new(Arc::new(DataScrubber::from_config(&Config::default()))))
+}
+
+/// Replace the scrubber used by the event pipeline.
+pub fn install(scrubber: DataScrubber) {
+    *state().write().unwrap() = Arc::new(scrubber);
+}
+
+/// The scrubber used by the event pipeline.
+pub fn current() -> Arc<DataScrubber> {
+    state().read().unwrap().clone()
+}
@@ -0,0 +1,201 @@
+//! Opt-in heuristics for credentials that slipped into an event.
+//!
+//! Unlike the [`scrubbing`] rules, which target known field names and PII
+//! formats, the scanner looks for things that *look like* secrets anywhere in
+//! the event: AWS access key IDs, JWTs, PEM private keys and long high-entropy
+//! tokens. Matches are replaced with `[SECRET:<type>]` and the event is tagged
+//! `secrets.detected=<types>`, so searching Bugsink for the tag shows which
+//! code paths leak credentials.
+//!
+//! Enable with `detect_secrets` in the settings or [`configure`].
+
+use regex::Regex;
+use sentry::protocol::{Context, Event, Value};
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    sync::{OnceLock, RwLock},
+};
+
+/// Tag listing the kinds of secrets found in the event.
+pub const TAG: &str = "secrets.detected";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SecretKind {
+    PrivateKey,
+    AwsAccessKey,
+    Jwt,
+    HighEntropy,
+}
+
+impl SecretKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SecretKind::PrivateKey => "private_key",
+            SecretKind::AwsAccessKey => "aws_access_key",
+            SecretKind::Jwt => "jwt",
+            SecretKind::HighEntropy => "high_entropy",
+        }
+    }
+}
+
+fn patterns() -> &'static [(SecretKind, Regex)] {
+    static PATTERNS: OnceLock<Vec<(SecretKind, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (
+                SecretKind::PrivateKey,
+                r"-----BEGIN (?:[A-Z]+ )*PRIVATE KEY-----(?s:.*?)(?:-----END (?:[A-Z]+ )*PRIVATE KEY-----|\z)",
+            ),
+            (
+                SecretKind::AwsAccessKey,
+                r"\b(?:AKIA|ASIA|AGPA|AIDA|AROA|AIPA|ANPA|ANVA)[A-Z0-9]{16}\b",
+            ),
+            (
+                SecretKind::Jwt,
+                r"\beyJ[A-Za-z0-9_-]{5,}\.eyJ[A-Za-z0-9_-]{5,}\.[A-Za-z0-9_-]{10,}",
+            ),
+        ]
+        .into_iter()
+        .map(|(kind, regex)| (kind, Regex::new(regex).expect("built-in secret pattern")))
+        .collect()
+    })
+}
+
+fn token_pattern() -> &'static Regex {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    TOKEN.get_or_init(|| Regex::new(r"[A-Za-z0-9+/=_-]{20,}").expect("token pattern"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecretScanner {
+    /// Minimum Shannon entropy (bits per character) of a high-entropy token.
+    /// The default of 4.2 stays above hex digests and UUIDs (at most 4.0).
+    pub min_entropy: f64,
+    /// Minimum length of a high-entropy token.
+    pub min_length: usize,
+}
+
+impl Default for SecretScanner {
+    fn default() -> Self {
+        Self {
+            min_entropy: 4.2,
+            min_length: 20,
+        }
+    }
+}
+
+impl SecretScanner {
+    /// Replace secrets in `text` with `[SECRET:<type>]`, recording the kinds found.
+    pub fn scan_str<'a>(&self, text: &'a str, found: &mut BTreeSet<SecretKind>) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for (kind, regex) in patterns() {
+            if regex.is_match(&text) {
+                found.insert(*kind);
+                text = Cow::Owned(regex.replace_all(&text, marker(*kind)).into_owned());
+            }
+        }
+
+        let high_entropy = |token: &str| {
+            token.len() >= self.min_length
+                && token.chars().any(|c| c.is_ascii_digit())
+                && token.chars().any(|c| c.is_ascii_alphabetic())
+                && entropy(token) >= self.min_entropy
+        };
+        if token_pattern().find_iter(&text).any(|m| high_entropy(m.as_str())) {
+            found.insert(SecretKind::HighEntropy);
+            let replaced = token_pattern().replace_all(&text, |caps: &regex::Captures<'_>| match &caps[0] {
+                token if high_entropy(token) => marker(SecretKind::HighEntropy),
+                token => token.to_string(),
+            });
+            text = Cow::Owned(replaced.into_owned());
+        }
+        text
+    }
+
+    /// Scan every string in the event and tag it with the kinds found.
+    pub fn scan_event(&self, event: &mut Event<'_>) -> BTreeSet<SecretKind> {
+        let mut found = BTreeSet::new();
+        visit_strings(event, &mut |text| {
+            if let Cow::Owned(scanned) = self.scan_str(text, &mut found) {
+                *text = scanned;
+            }
+        });
+        if !found.is_empty() {
+            let kinds: Vec<&str> = found.iter().map(|kind| kind.as_str()).collect();
+            event.tags.insert(TAG.to_string(), kinds.join(","));
+        }
+        found
+    }
+}
+
+fn marker(kind: SecretKind) -> String {
+    format!("[SECRET:{}]", kind.as_str())
+}
+
+/// Shannon entropy in bits per character.
+fn entropy(token: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in token.bytes() {
+        counts[usize::from(byte)] += 1;
+    }
+    let len = token.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn visit_strings(event: &mut Event<'_>, f: &mut dyn FnMut(&mut String)) {
+    fn visit_value(value: &mut Value, f: &mut dyn FnMut(&mut String)) {
+        match value {
+            Value::String(text) => f(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| visit_value(item, f)),
+            Value::Object(map) => map.values_mut().for_each(|item| visit_value(item, f)),
+            _ => {}
+        }
+    }
+
+    event.message.iter_mut().for_each(&mut *f);
+    if let Some(logentry) = event.logentry.as_mut() {
+        f(&mut logentry.message);
+    }
+    for exception in event.exception.values.iter_mut() {
+        exception.value.iter_mut().for_each(&mut *f);
+    }
+    if let Some(request) = event.request.as_mut() {
+        request.data.iter_mut().for_each(&mut *f);
+        request.query_string.iter_mut().for_each(&mut *f);
+        request.headers.values_mut().for_each(&mut *f);
+    }
+    event.tags.values_mut().for_each(&mut *f);
+    event.extra.values_mut().for_each(|value| visit_value(value, f));
+    for context in event.contexts.values_mut() {
+        if let Context::Other(map) = context {
+            map.values_mut().for_each(|value| visit_value(value, f));
+        }
+    }
+    for breadcrumb in event.breadcrumbs.values.iter_mut() {
+        breadcrumb.message.iter_mut().for_each(&mut *f);
+        breadcrumb.data.values_mut().for_each(|value| visit_value(value, f));
+    }
+}
+
+fn state() -> &'static RwLock<Option<SecretScanner>> {
+    static STATE: OnceLock<RwLock<Option<SecretScanner>>> = OnceLock::new();
+    STATE.get_or_init(|| RwLock::new(None))
+}
+
+/// Enable (`Some`) or disable (`None`) secret detection in the event pipeline.
+pub fn configure(scanner: Option<SecretScanner>) {
+    *state().write().unwrap() = scanner;
+}
+
+/// The active scanner, if detection is enabled.
+pub fn scanner() -> Option<SecretScanner> {
+    *state().read().unwrap()
+}
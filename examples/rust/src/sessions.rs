@@ -0,0 +1,71 @@
+//! Session tracking, so Bugsink can compute crash-free rates per release.
+//!
+//! With [`SessionTracking::Process`](sessions::SessionTracking::Process)
+//! (workers, CLIs) one session spans the process: it starts on init and ends
+//! when the [`SentryService`] is dropped. With
+//! [`SessionTracking::Request`](sessions::SessionTracking::Request) the web
+//! and gRPC integrations open one session per request; those are aggregated
+//! before sending.
+//!
+//! Captured errors mark the session errored and unhandled panics crashed.
+//! A worker that gives up on a wedged state before being restarted reports
+//! that with [`end_abnormal`](sessions::end_abnormal).
+
+use sentry::{protocol::SessionStatus, Hub, SessionMode};
+use std::sync::{OnceLock, RwLock};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionTracking {
+    #[default]
+    Disabled,
+    /// One session per process.
+    Process,
+    /// One session per handled request.
+    Request,
+}
+
+impl SessionTracking {
+    pub fn is_enabled(self) -> bool {
+        self != SessionTracking::Disabled
+    }
+
+    /// The SDK's session mode; request sessions are aggregated before sending.
+    pub fn session_mode(self) -> SessionMode {
+        match self {
+            SessionTracking::Request => SessionMode::Request,
+            _ => SessionMode::Application,
+        }
+    }
+}
+
+fn state() -> &'static RwLock<SessionTracking> {
+    static TRACKING: OnceLock<RwLock<SessionTracking>> = OnceLock::new();
+    TRACKING.get_or_init(Default::default)
+}
+
+pub fn configure(tracking: SessionTracking) {
+    *state().write().unwrap() = tracking;
+}
+
+pub fn tracking() -> SessionTracking {
+    *state().read().unwrap()
+}
+
+/// Open a session on the request's `hub` in request mode.
+pub fn start_request(hub: &Hub) {
+    if tracking() == SessionTracking::Request {
+        hub.start_session();
+    }
+}
+
+/// Close the request's session; an unhandled panic has already marked it crashed.
+pub fn end_request(hub: &Hub) {
+    if tracking() == SessionTracking::Request {
+        hub.end_session();
+    }
+}
+
+/// End the process session as abnormal, e.g. before exiting on an unrecoverable stall.
+pub fn end_abnormal() {
+    Hub::current().end_session_with_status(SessionStatus::Abnormal);
+}
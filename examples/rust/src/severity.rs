@@ -0,0 +1,179 @@
+//! Levels from the application's own severity scale instead of a 1:1
+//! mapping, with escalation for severities that recur too often.
+//!
+//! Errors report their domain severity through
+//! [`DomainSeverity`](severity::DomainSeverity); it travels as the `severity`
+//! tag and the [`SeverityMapper`](severity::SeverityMapper) turns it into the
+//! event level. An escalation raises the level of an issue once it exceeds a
+//! rate, like the [escalation rules](escalation), and tags it `escalated`:
+//!
+//! ```ignore
+//! impl DomainSeverity for PaymentError {
+//!     fn severity(&self) -> &str {
+//!         match self {
+//!             PaymentError::Retryable(_) => "degraded",
+//!             PaymentError::Rejected(_) => "notice",
+//!             PaymentError::Ledger(_) => "outage",
+//!         }
+//!     }
+//! }
+//!
+//! SentryService::builder()
+//!     .severity(
+//!         SeverityMapper::new()
+//!             .map("notice", Level::Info)
+//!             .map("degraded", Level::Warning)
+//!             .map("outage", Level::Fatal)
+//!             .escalate("degraded", 100, Duration::from_secs(60), Level::Error),
+//!     )
+//!     .build();
+//!
+//! severity::capture_error(&error);
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [severity.levels]
+//! notice = "info"
+//! degraded = "warning"
+//! outage = "fatal"
+//!
+//! [[severity.escalate]]
+//! severity = "degraded"
+//! threshold = 100
+//! window_secs = 60
+//! level = "error"
+//! ```
+//!
+//! Events without the tag, or with an unmapped severity, keep their level.
+//! The mapped level replaces one set by an [error code](error_codes).
+
+use super::escalation::{EscalationPolicy, EscalationRule};
+use sentry::{protocol::Event, Hub, Level};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+/// Tag carrying the domain severity.
+pub const TAG: &str = "severity";
+
+/// Errors that know their severity on the application's scale.
+pub trait DomainSeverity {
+    fn severity(&self) -> &str;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeverityEscalation {
+    pub severity: String,
+    /// Escalate once more than `threshold` occurrences of an issue fall within `window`.
+    pub threshold: usize,
+    pub window: Duration,
+    pub level: Level,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeverityMapper {
+    pub levels: BTreeMap<String, Level>,
+    pub escalations: Vec<SeverityEscalation>,
+}
+
+impl SeverityMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn map(mut self, severity: &str, level: Level) -> Self {
+        self.levels.insert(severity.to_string(), level);
+        self
+    }
+
+    /// Raise an issue of `severity` to `level` once it recurs more than
+    /// `threshold` times within `window`.
+    pub fn escalate(mut self, severity: &str, threshold: usize, window: Duration, level: Level) -> Self {
+        self.escalations.push(SeverityEscalation {
+            severity: severity.to_string(),
+            threshold,
+            window,
+            level,
+        });
+        self
+    }
+
+    pub fn level(&self, severity: &str) -> Option<Level> {
+        self.levels.get(severity).copied()
+    }
+
+    /// Escalation rules counting events at the mapped level; an unmapped
+    /// severity is counted at `error`, the level `capture_error` gives.
+    fn escalation_rules(&self) -> Vec<EscalationRule> {
+        self.escalations
+            .iter()
+            .map(|escalation| {
+                let from = self.level(&escalation.severity).unwrap_or(Level::Error);
+                let name = format!("severity.{}", escalation.severity);
+                EscalationRule::new(&name, escalation.threshold, escalation.window)
+                    .levels(from, escalation.level)
+                    .tag(TAG, &escalation.severity)
+            })
+            .collect()
+    }
+}
+
+/// A mapper with the counters for its escalations.
+struct Installed {
+    mapper: SeverityMapper,
+    escalation: EscalationPolicy,
+}
+
+impl Installed {
+    fn apply(&self, event: &mut Event<'_>) {
+        if let Some(level) = event.tags.get(TAG).and_then(|severity| self.mapper.level(severity)) {
+            event.level = level;
+        }
+        self.escalation.apply(event);
+    }
+}
+
+fn state() -> &'static RwLock<Option<Arc<Installed>>> {
+    static STATE: OnceLock<RwLock<Option<Arc<Installed>>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Replace the mapper consulted by `before_send`; escalation counts start over.
+pub fn install(mapper: SeverityMapper) {
+    let installed = (!mapper.levels.is_empty() || !mapper.escalations.is_empty()).then(|| {
+        Arc::new(Installed {
+            escalation: EscalationPolicy::new(mapper.escalation_rules()),
+            mapper,
+        })
+    });
+    *state().write().unwrap() = installed;
+}
+
+pub fn current() -> SeverityMapper {
+    state()
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|installed| installed.mapper.clone())
+        .unwrap_or_default()
+}
+
+pub(crate) fn apply(event: &mut Event<'_>) {
+    let installed = state().read().unwrap().clone();
+    if let Some(installed) = installed {
+        installed.apply(event);
+    }
+}
+
+/// Capture `error` on the current hub with its severity as the `severity` tag.
+pub fn capture_error<E: std::error::Error + DomainSeverity + ?Sized>(error: &E) -> sentry::types::Uuid {
+    let hub = Hub::current();
+    hub.with_scope(
+        |scope| scope.set_tag(TAG, error.severity()),
+        || hub.capture_error(error),
+    )
+}
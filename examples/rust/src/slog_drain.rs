@@ -0,0 +1,178 @@
+//! Captures `slog` records from services that haven't moved to `tracing`
+//! (feature `slog`).
+//!
+//! [`SentryDrain`](slog_drain::SentryDrain) wraps the service's existing drain:
+//! records at or above the event level become events, records at or above the
+//! breadcrumb level breadcrumbs, and every record is passed on unchanged. The
+//! record's key-value pairs and those of its logger become event extras or
+//! breadcrumb data, keeping numbers and booleans typed:
+//!
+//! ```ignore
+//! let drain = slog_term::FullFormat::new(slog_term::TermDecorator::new().build()).build().fuse();
+//! let drain = slog_drain::SentryDrain::new(drain).breadcrumb_level(slog::Level::Info);
+//! let log = slog::Logger::root(slog_async::Async::new(drain.fuse()).build().fuse(), slog::o!("service" => "billing"));
+//!
+//! slog::error!(log, "invoice failed"; "invoice_id" => 42, "retry" => false);
+//! ```
+//!
+//! Events carry the record's module as their logger, so `ignore_loggers`
+//! applies to them like to `tracing` and `log` events.
+
+use sentry::{
+    protocol::{Breadcrumb, Event, Value},
+    Level,
+};
+use slog::{Drain, Key, OwnedKVList, Record, Serializer, KV};
+use std::{collections::BTreeMap, fmt};
+
+pub struct SentryDrain<D> {
+    drain: D,
+    event_level: slog::Level,
+    breadcrumb_level: slog::Level,
+}
+
+impl<D> SentryDrain<D> {
+    /// Errors and critical records become events, warnings breadcrumbs.
+    pub fn new(drain: D) -> Self {
+        Self {
+            drain,
+            event_level: slog::Level::Error,
+            breadcrumb_level: slog::Level::Warning,
+        }
+    }
+
+    pub fn event_level(mut self, level: slog::Level) -> Self {
+        self.event_level = level;
+        self
+    }
+
+    pub fn breadcrumb_level(mut self, level: slog::Level) -> Self {
+        self.breadcrumb_level = level;
+        self
+    }
+}
+
+impl<D: Drain> Drain for SentryDrain<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+
+    fn log(&self, record: &Record<'_>, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        if record.level().is_at_least(self.event_level) {
+            sentry::capture_event(event_from_record(record, values));
+        } else if record.level().is_at_least(self.breadcrumb_level) {
+            sentry::add_breadcrumb(breadcrumb_from_record(record, values));
+        }
+        self.drain.log(record, values)
+    }
+
+    fn is_enabled(&self, level: slog::Level) -> bool {
+        level.is_at_least(self.breadcrumb_level) || self.drain.is_enabled(level)
+    }
+}
+
+pub fn level(level: slog::Level) -> Level {
+    match level {
+        slog::Level::Critical => Level::Fatal,
+        slog::Level::Error => Level::Error,
+        slog::Level::Warning => Level::Warning,
+        slog::Level::Info => Level::Info,
+        slog::Level::Debug | slog::Level::Trace => Level::Debug,
+    }
+}
+
+/// Key-value pairs of the logger, overridden by those of the record.
+pub fn fields(record: &Record<'_>, values: &OwnedKVList) -> BTreeMap<String, Value> {
+    let mut fields = FieldSerializer(BTreeMap::new());
+    // Serializing into a map cannot fail
+    let _ = values.serialize(record, &mut fields);
+    let _ = record.kv().serialize(record, &mut fields);
+    fields.0
+}
+
+pub fn event_from_record(record: &Record<'_>, values: &OwnedKVList) -> Event<'static> {
+    let mut event = Event {
+        level: level(record.level()),
+        logger: Some(record.module().to_string()),
+        message: Some(record.msg().to_string()),
+        extra: fields(record, values),
+        ..Default::default()
+    };
+    event.extra.insert(
+        "slog.location".to_string(),
+        format!("{}:{}", record.file(), record.line()).into(),
+    );
+    if !record.tag().is_empty() {
+        event.tags.insert("slog.tag".to_string(), record.tag().to_string());
+    }
+    event
+}
+
+pub fn breadcrumb_from_record(record: &Record<'_>, values: &OwnedKVList) -> Breadcrumb {
+    Breadcrumb {
+        ty: "log".to_string(),
+        category: Some(record.module().to_string()),
+        level: level(record.level()),
+        message: Some(record.msg().to_string()),
+        data: fields(record, values).into_iter().collect(),
+        ..Default::default()
+    }
+}
+
+struct FieldSerializer(BTreeMap<String, Value>);
+
+impl FieldSerializer {
+    fn insert(&mut self, key: Key, value: impl Into<Value>) -> slog::Result {
+        self.0.insert(key.to_string(), value.into());
+        Ok(())
+    }
+}
+
+impl Serializer for FieldSerializer {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments<'_>) -> slog::Result {
+        self.insert(key, val.to_string())
+    }
+
+    fn emit_str(&mut self, key: Key, val: &str) -> slog::Result {
+        self.insert(key, val)
+    }
+
+    fn emit_bool(&mut self, key: Key, val: bool) -> slog::Result {
+        self.insert(key, val)
+    }
+
+    fn emit_i32(&mut self, key: Key, val: i32) -> slog::Result {
+        self.insert(key, val)
+    }
+
+    fn emit_u32(&mut self, key: Key, val: u32) -> slog::Result {
+        self.insert(key, val)
+    }
+
+    fn emit_i64(&mut self, key: Key, val: i64) -> slog::Result {
+        self.insert(key, val)
+    }
+
+    fn emit_u64(&mut self, key: Key, val: u64) -> slog::Result {
+        self.insert(key, val)
+    }
+
+    fn emit_isize(&mut self, key: Key, val: isize) -> slog::Result {
+        self.insert(key, val as i64)
+    }
+
+    fn emit_usize(&mut self, key: Key, val: usize) -> slog::Result {
+        self.insert(key, val as u64)
+    }
+
+    fn emit_f64(&mut self, key: Key, val: f64) -> slog::Result {
+        self.insert(key, val)
+    }
+
+    fn emit_unit(&mut self, key: Key) -> slog::Result {
+        self.insert(key, Value::Null)
+    }
+
+    fn emit_none(&mut self, key: Key) -> slog::Result {
+        self.insert(key, Value::Null)
+    }
+}
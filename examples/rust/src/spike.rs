@@ -0,0 +1,196 @@
+//! Automatic downsampling of issues whose event rate spikes.
+//!
+//! Events are counted per issue (see [`fingerprint::issue_key`]). Once an
+//! issue exceeds `events_per_window` within a window, only every n-th of its
+//! events is sent, with n growing with the rate so that roughly
+//! `events_per_window` get through. Each following window starts with the
+//! factor the previous one ended with, so the rate recovers to 100% one window
+//! after the spike subsides. Sent events of a throttled issue carry the
+//! `spike.sample_rate` tag.
+//!
+//! Every `summary_interval` a warning is captured for each issue with
+//! suppressed events, stating how many were dropped:
+//!
+//! ```ignore
+//! SentryService::builder()
+//!     .spike_protection(SpikeOptions { events_per_window: 100, ..Default::default() })
+//!     .build();
+//! ```
+//!
+//! In the config file (feature `config-file`):
+//!
+//! ```toml
+//! [spike_protection]
+//! enabled = true
+//! events_per_window = 100
+//! ```
+
+use super::{fingerprint::issue_key, issue_title};
+use sentry::{protocol::Event, Hub, Level};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, RwLock, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Tag with the share of a throttled issue's events that is sent.
+pub const TAG: &str = "spike.sample_rate";
+
+/// Issues tracked before idle ones are purged.
+const MAX_KEYS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpikeOptions {
+    /// Events per issue and window sent at full rate.
+    pub events_per_window: u64,
+    pub window: Duration,
+    pub summary_interval: Duration,
+}
+
+impl Default for SpikeOptions {
+    fn default() -> Self {
+        Self {
+            events_per_window: 60,
+            window: Duration::from_secs(60),
+            summary_interval: Duration::from_secs(300),
+        }
+    }
+}
+
+struct IssueRate {
+    title: String,
+    window_started: Instant,
+    count: u64,
+    /// Send every n-th event; 1 while the issue is not spiking.
+    factor: u64,
+    suppressed: u64,
+}
+
+pub struct SpikeProtection {
+    options: SpikeOptions,
+    issues: Mutex<HashMap<u64, IssueRate>>,
+}
+
+impl SpikeProtection {
+    pub fn new(options: SpikeOptions) -> Self {
+        Self {
+            options: SpikeOptions {
+                events_per_window: options.events_per_window.max(1),
+                ..options
+            },
+            issues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count the event; returns whether it should be sent.
+    pub fn check(&self, event: &mut Event<'_>) -> bool {
+        let key = issue_key(event);
+        let now = Instant::now();
+        let limit = self.options.events_per_window;
+        let mut issues = self.issues.lock().unwrap();
+        if issues.len() >= MAX_KEYS && !issues.contains_key(&key) {
+            issues.retain(|_, issue| issue.factor > 1 || issue.suppressed > 0);
+        }
+
+        let issue = issues.entry(key).or_insert_with(|| IssueRate {
+            title: issue_title(event),
+            window_started: now,
+            count: 0,
+            factor: 1,
+            suppressed: 0,
+        });
+        if now.duration_since(issue.window_started) >= self.options.window {
+            // A window below the limit ends the spike
+            issue.factor = issue.count.div_ceil(limit).max(1);
+            issue.window_started = now;
+            issue.count = 0;
+        }
+        issue.count += 1;
+        if issue.count > limit {
+            issue.factor = issue.factor.max(issue.count.div_ceil(limit));
+        }
+
+        if issue.factor == 1 {
+            return true;
+        }
+        if issue.count.is_multiple_of(issue.factor) {
+            event
+                .tags
+                .insert(TAG.to_string(), (1.0 / issue.factor as f64).to_string());
+            true
+        } else {
+            issue.suppressed += 1;
+            false
+        }
+    }
+
+    /// Warnings for the events suppressed since the last call.
+    pub fn take_summaries(&self) -> Vec<Event<'static>> {
+        let mut issues = self.issues.lock().unwrap();
+        issues
+            .values_mut()
+            .filter(|issue| issue.suppressed > 0)
+            .map(|issue| summary_event(&issue.title, std::mem::take(&mut issue.suppressed), issue.factor))
+            .collect()
+    }
+}
+
+fn summary_event(title: &str, suppressed: u64, factor: u64) -> Event<'static> {
+    let mut event = Event {
+        level: Level::Warning,
+        message: Some(format!(
+            "Spike protection suppressed {} events of {}",
+            suppressed, title
+        )),
+        fingerprint: vec!["spike-protection".into(), title.to_string().into()].into(),
+        ..Default::default()
+    };
+    event.extra.insert("spike.suppressed".to_string(), suppressed.into());
+    event.extra.insert("spike.issue".to_string(), title.into());
+    event.tags.insert(TAG.to_string(), (1.0 / factor as f64).to_string());
+    event
+}
+
+fn state() -> &'static RwLock<Option<Arc<SpikeProtection>>> {
+    static STATE: OnceLock<RwLock<Option<Arc<SpikeProtection>>>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Enable (`Some`) or disable (`None`) spike protection in `before_send`.
+pub fn install(options: Option<SpikeOptions>) {
+    let protection = options.map(|options| Arc::new(SpikeProtection::new(options)));
+    if let Some(protection) = &protection {
+        let weak = Arc::downgrade(protection);
+        let hub = Hub::current();
+        let spawned = thread::Builder::new()
+            .name("spike-summary".to_string())
+            .spawn(move || report(weak, hub));
+        if let Err(e) = spawned {
+            eprintln!("Cannot start spike protection summaries: {}", e);
+        }
+    }
+    *state().write().unwrap() = protection;
+}
+
+/// Capture summaries until the protection is replaced.
+fn report(protection: Weak<SpikeProtection>, hub: Arc<Hub>) {
+    loop {
+        let Some(interval) = protection.upgrade().map(|p| p.options.summary_interval) else {
+            return;
+        };
+        thread::sleep(interval);
+        let Some(protection) = protection.upgrade() else {
+            return;
+        };
+        for event in protection.take_summaries() {
+            hub.capture_event(event);
+        }
+    }
+}
+
+/// Whether the event should be sent; always true while protection is off.
+pub(crate) fn check(event: &mut Event<'_>) -> bool {
+    let protection = state().read().unwrap().clone();
+    protection.is_none_or(|protection| protection.check(event))
+}
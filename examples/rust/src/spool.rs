@@ -0,0 +1,384 @@
+//! Disk-backed queue that keeps envelopes across Bugsink outages.
+//!
+//! Every envelope is written to the spool directory before delivery is
+//! attempted and removed once the server accepts (or permanently rejects) it,
+//! so events survive both network outages and process restarts. Files use the
+//! same `*.envelope` format as [`replay`], so a spool directory can also be
+//! replayed by hand.
+//!
+//! The directory is scanned once when the spool is opened; afterwards the
+//! file list and the byte total are kept in memory, so pushing and flushing
+//! never list the directory again.
+//!
+//! With the `offline-spool` feature and `SENTRY_SPOOL_DIR` set, the client
+//! transport delivers through the spool.
+
+use sentry::protocol::Envelope;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpoolLimits {
+    /// Oldest envelopes are evicted once the spool grows beyond this size.
+    pub max_bytes: u64,
+    pub max_files: usize,
+    /// Envelopes older than this are discarded instead of delivered.
+    pub max_age: Duration,
+}
+
+impl Default for SpoolLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 50 * 1024 * 1024,
+            max_files: 10_000,
+            max_age: Duration::from_secs(7 * 24 * 3600),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Spool {
+    dir: PathBuf,
+    limits: SpoolLimits,
+    seq: AtomicU64,
+    index: Mutex<Index>,
+}
+
+/// Spooled files, oldest first (names start with the creation time), with their sizes.
+#[derive(Debug, Default)]
+struct Index {
+    files: BTreeMap<PathBuf, u64>,
+    bytes: u64,
+}
+
+impl Index {
+    fn insert(&mut self, path: PathBuf, size: u64) {
+        if let Some(previous) = self.files.insert(path, size) {
+            self.bytes -= previous;
+        }
+        self.bytes += size;
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(size) = self.files.remove(path) {
+            self.bytes -= size;
+        }
+    }
+}
+
+impl Spool {
+    /// Open (or create) a spool directory and recover it from an unclean shutdown.
+    pub fn open(dir: impl Into<PathBuf>, limits: SpoolLimits) -> io::Result<Self> {
+        let spool = Self {
+            dir: dir.into(),
+            limits,
+            seq: AtomicU64::new(0),
+            index: Mutex::new(Index::default()),
+        };
+        fs::create_dir_all(&spool.dir)?;
+        spool.recover()?;
+        let mut index = spool.index.lock().unwrap();
+        for (path, size) in spool.files() {
+            index.insert(path, size);
+        }
+        drop(index);
+        Ok(spool)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Persist an envelope. The file only becomes visible once fully written.
+    /// Envelopes larger than `max_bytes` are rejected.
+    pub fn push(&self, envelope: &Envelope) -> io::Result<PathBuf> {
+        let mut bytes = Vec::new();
+        envelope.to_writer(&mut bytes)?;
+        if bytes.len() as u64 > self.limits.max_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "envelope of {} bytes exceeds the spool limit of {} bytes",
+                    bytes.len(),
+                    self.limits.max_bytes
+                ),
+            ));
+        }
+
+        let name = format!(
+            "{:013}-{}-{:06}",
+            now_millis(),
+            process::id(),
+            self.seq.fetch_add(1, Ordering::Relaxed)
+        );
+        let tmp = self.dir.join(format!("{}.tmp", name));
+        let path = self.dir.join(format!("{}.envelope", name));
+
+        {
+            let file = fs::File::create(&tmp)?;
+            io::Write::write_all(&mut &file, &bytes)?;
+            file.sync_all()?;
+        }
+        let mut index = self.index.lock().unwrap();
+        fs::rename(&tmp, &path)?;
+        index.insert(path.clone(), bytes.len() as u64);
+        self.enforce_limits(&mut index);
+        Ok(path)
+    }
+
+    /// Spooled envelopes, oldest first. Expired files are removed.
+    pub fn pending(&self) -> Vec<PathBuf> {
+        let mut index = self.index.lock().unwrap();
+        let cutoff = now_millis().saturating_sub(self.limits.max_age.as_millis() as u64);
+        let expired: Vec<PathBuf> = index
+            .files
+            .keys()
+            .filter(|path| created_millis(path).is_some_and(|created| created < cutoff))
+            .cloned()
+            .collect();
+        for path in &expired {
+            let _ = fs::remove_file(path);
+            index.remove(path);
+        }
+        index.files.keys().cloned().collect()
+    }
+
+    /// Drop a delivered envelope.
+    pub fn remove(&self, path: &Path) {
+        let mut index = self.index.lock().unwrap();
+        let _ = fs::remove_file(path);
+        index.remove(path);
+    }
+
+    /// Move an envelope that no longer parses out of the queue, keeping it for inspection.
+    pub fn quarantine(&self, path: &Path) {
+        eprintln!("Quarantining corrupt spool file {}", path.display());
+        let mut index = self.index.lock().unwrap();
+        let _ = fs::rename(path, path.with_extension("corrupt"));
+        index.remove(path);
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.lock().unwrap().files.len()
+    }
+
+    /// Total size of the spooled envelopes in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.index.lock().unwrap().bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove half-written files and quarantine envelopes that no longer parse.
+    fn recover(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("tmp") => {
+                    let _ = fs::remove_file(&path);
+                }
+                Some("envelope") if Envelope::from_path(&path).is_err() => self.quarantine(&path),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Evict the oldest envelopes until the spool fits its limits.
+    fn enforce_limits(&self, index: &mut Index) {
+        while index.bytes > self.limits.max_bytes || index.files.len() > self.limits.max_files {
+            let Some((path, size)) = index.files.pop_first() else {
+                break;
+            };
+            index.bytes -= size;
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    /// Envelope files currently in the directory, with their sizes.
+    fn files(&self) -> Vec<(PathBuf, u64)> {
+        let mut files: Vec<(PathBuf, u64)> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "envelope"))
+            .map(|entry| (entry.path(), entry.metadata().map(|m| m.len()).unwrap_or(0)))
+            .collect();
+        files.sort();
+        files
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub(crate) fn created_millis(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.split('-').next()?.parse().ok()
+}
+
+/// Transport factory delivering through a spool in `dir`; falls back to the
+/// SDK's default transport if the directory cannot be opened or the
+/// delivery thread cannot be started.
+#[cfg(feature = "offline-spool")]
+pub fn factory(dir: PathBuf) -> std::sync::Arc<dyn sentry::TransportFactory> {
+    use sentry::{transports::DefaultTransportFactory, ClientOptions, Transport, TransportFactory};
+    use std::sync::Arc;
+
+    Arc::new(move |options: &ClientOptions| -> Arc<dyn Transport> {
+        match Spool::open(&dir, SpoolLimits::default()).and_then(|spool| SpoolTransport::new(options, spool)) {
+            Ok(transport) => Arc::new(transport),
+            Err(e) => {
+                eprintln!("Cannot open spool {}: {}, delivering without it", dir.display(), e);
+                DefaultTransportFactory.create_transport(options)
+            }
+        }
+    })
+}
+
+#[cfg(feature = "offline-spool")]
+pub use self::delivery::SpoolTransport;
+
+#[cfg(feature = "offline-spool")]
+mod delivery {
+    use super::Spool;
+    use crate::{
+        http::{Outcome, Sender},
+        ratelimit::Category,
+    };
+    use sentry::{protocol::Envelope, ClientOptions, Transport};
+    use std::{
+        fs, io,
+        sync::{mpsc, Arc},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const IDLE_POLL: Duration = Duration::from_secs(30);
+    const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+    /// Writes envelopes to the spool and delivers them from a background thread,
+    /// backing off while the server is unreachable or rate limiting.
+    pub struct SpoolTransport {
+        spool: Arc<Spool>,
+        wake: mpsc::Sender<()>,
+    }
+
+    impl SpoolTransport {
+        /// Fails without a DSN to deliver to, or if the delivery thread cannot be started.
+        pub fn new(options: &ClientOptions, spool: Spool) -> io::Result<Self> {
+            let sender = Sender::new(options)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no DSN to deliver the spool to"))?;
+            let spool = Arc::new(spool);
+            let (wake, rx) = mpsc::channel();
+            let worker = Worker {
+                spool: spool.clone(),
+                sender,
+            };
+            thread::Builder::new()
+                .name("sentry-spool".to_string())
+                .spawn(move || worker.run(rx))?;
+            // Deliver whatever a previous run left behind
+            let _ = wake.send(());
+            Ok(Self { spool, wake })
+        }
+    }
+
+    impl Transport for SpoolTransport {
+        fn send_envelope(&self, envelope: Envelope) {
+            match self.spool.push(&envelope) {
+                Ok(_) => {
+                    let _ = self.wake.send(());
+                }
+                Err(e) => eprintln!("Failed to spool envelope: {}", e),
+            }
+        }
+
+        /// True once the spool is empty; during an outage the envelopes stay
+        /// on disk for the next run.
+        fn flush(&self, timeout: Duration) -> bool {
+            let deadline = Instant::now() + timeout;
+            let _ = self.wake.send(());
+            while !self.spool.is_empty() {
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            true
+        }
+    }
+
+    enum Delivery {
+        Done,
+        Retry(Option<Duration>),
+    }
+
+    struct Worker {
+        spool: Arc<Spool>,
+        sender: Sender,
+    }
+
+    impl Worker {
+        fn run(self, wake: mpsc::Receiver<()>) {
+            let mut failures = 0u32;
+            let mut wait = IDLE_POLL;
+            while wake.recv_timeout(wait) != Err(mpsc::RecvTimeoutError::Disconnected) {
+                wait = match self.drain() {
+                    Delivery::Done => {
+                        if failures > 0 {
+                            eprintln!("Bugsink reachable again, spool drained");
+                        }
+                        failures = 0;
+                        IDLE_POLL
+                    }
+                    Delivery::Retry(retry_after) => {
+                        failures += 1;
+                        retry_after
+                            .unwrap_or(Duration::from_secs(1 << failures.min(9)))
+                            .min(MAX_BACKOFF)
+                    }
+                };
+            }
+        }
+
+        fn drain(&self) -> Delivery {
+            for path in self.spool.pending() {
+                let body = match fs::read(&path) {
+                    Ok(body) => body,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        self.spool.remove(&path);
+                        continue;
+                    }
+                    Err(e) => {
+                        eprintln!("Cannot read spool file {}: {}", path.display(), e);
+                        return Delivery::Retry(None);
+                    }
+                };
+                let Ok(envelope) = Envelope::from_slice(&body) else {
+                    self.spool.quarantine(&path);
+                    continue;
+                };
+                match self.sender.send(Category::of(&envelope), body) {
+                    Outcome::Done => self.spool.remove(&path),
+                    Outcome::Retry(retry_after) => return Delivery::Retry(retry_after),
+                }
+            }
+            Delivery::Done
+        }
+    }
+}
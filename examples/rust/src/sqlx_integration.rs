@@ -0,0 +1,176 @@
+//! Database spans, breadcrumbs and slow-query events for sqlx (feature `sqlx`).
+//!
+//! Wrap a query future with the statement it runs:
+//!
+//! ```ignore
+//! use observability::sqlx_integration::QueryFutureExt;
+//!
+//! const SQL: &str = "SELECT * FROM orders WHERE customer_id = $1";
+//! let orders = sqlx::query_as::<_, Order>(SQL).bind(id).fetch_all(&pool).traced_query(SQL).await?;
+//! ```
+//!
+//! Inside an active span the query becomes a `db.query` child described by
+//! the [sanitized](sqlx_integration::sanitize) statement, with the number of
+//! rows returned or affected (row counts of `execute` results need the
+//! `sqlx-postgres` / `sqlx-mysql` features). Every query leaves a breadcrumb;
+//! with a [`slow_query_threshold`](sqlx_integration::SqlxOptions::slow_query_threshold)
+//! set, slower queries are captured as warnings grouped per statement.
+
+use crate::ops::Op;
+use regex::Regex;
+use sentry::{
+    protocol::{Breadcrumb, Event, Map, SpanStatus},
+    Hub, Level,
+};
+use std::{
+    borrow::Cow,
+    future::Future,
+    sync::{OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SqlxOptions {
+    /// Capture a warning for queries taking at least this long; `None` disables it.
+    pub slow_query_threshold: Option<Duration>,
+}
+
+fn state() -> &'static RwLock<SqlxOptions> {
+    static OPTIONS: OnceLock<RwLock<SqlxOptions>> = OnceLock::new();
+    OPTIONS.get_or_init(Default::default)
+}
+
+pub fn configure(options: SqlxOptions) {
+    *state().write().unwrap() = options;
+}
+
+pub fn options() -> SqlxOptions {
+    *state().read().unwrap()
+}
+
+/// Replace string and numeric literals with `?` and collapse whitespace,
+/// so statements group regardless of inlined values. Bind placeholders
+/// (`$1`, `?`) are kept.
+pub fn sanitize(sql: &str) -> String {
+    static PATTERNS: OnceLock<[(Regex, &str); 3]> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            (Regex::new(r"'(?:[^']|'')*'").unwrap(), "?"),
+            (Regex::new(r"(^|[^$\w.])-?\d+(?:\.\d+)?\b").unwrap(), "${1}?"),
+            (Regex::new(r"\s+").unwrap(), " "),
+        ]
+    });
+    let mut sql = Cow::Borrowed(sql.trim());
+    for (regex, replacement) in patterns {
+        if let Cow::Owned(replaced) = regex.replace_all(&sql, *replacement) {
+            sql = Cow::Owned(replaced);
+        }
+    }
+    sql.into_owned()
+}
+
+/// Rows returned or affected by a query result.
+pub trait RowCount {
+    fn row_count(&self) -> Option<u64>;
+}
+
+impl<T> RowCount for Vec<T> {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.len() as u64)
+    }
+}
+
+impl<T> RowCount for Option<T> {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.is_some() as u64)
+    }
+}
+
+#[cfg(feature = "sqlx-postgres")]
+impl RowCount for sqlx::postgres::PgQueryResult {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.rows_affected())
+    }
+}
+
+#[cfg(feature = "sqlx-mysql")]
+impl RowCount for sqlx::mysql::MySqlQueryResult {
+    fn row_count(&self) -> Option<u64> {
+        Some(self.rows_affected())
+    }
+}
+
+/// Run `query` as the statement `sql`; see the [module docs](self).
+pub async fn instrument<T, F>(sql: &str, query: F) -> Result<T, sqlx::Error>
+where
+    T: RowCount,
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    let hub = Hub::current();
+    let statement = sanitize(sql);
+    let span = hub
+        .configure_scope(|scope| scope.get_span())
+        .map(|parent| parent.start_child(Op::DbQuery.as_str(), &statement));
+
+    let started = Instant::now();
+    let result = query.await;
+    let duration = started.elapsed();
+    let rows = result.as_ref().ok().and_then(RowCount::row_count);
+
+    if let Some(span) = span {
+        if let Some(rows) = rows {
+            span.set_data("db.rows", rows.into());
+        }
+        span.set_status(match &result {
+            Ok(_) => SpanStatus::Ok,
+            Err(sqlx::Error::RowNotFound) => SpanStatus::NotFound,
+            Err(_) => SpanStatus::InternalError,
+        });
+        span.finish();
+    }
+
+    let mut fields = Map::new();
+    fields.insert("duration_ms".to_string(), (duration.as_millis() as u64).into());
+    if let Some(rows) = rows {
+        fields.insert("rows".to_string(), rows.into());
+    }
+    hub.add_breadcrumb(Breadcrumb {
+        ty: "query".to_string(),
+        category: Some(Op::DbQuery.as_str().to_string()),
+        message: Some(statement.clone()),
+        level: if result.is_ok() { Level::Info } else { Level::Error },
+        data: fields,
+        ..Default::default()
+    });
+
+    if let Some(threshold) = options().slow_query_threshold {
+        if duration >= threshold {
+            hub.capture_event(slow_query_event(&statement, duration, threshold));
+        }
+    }
+    result
+}
+
+fn slow_query_event(statement: &str, duration: Duration, threshold: Duration) -> Event<'static> {
+    let mut event = Event {
+        level: Level::Warning,
+        message: Some(format!("Slow query ({} ms): {}", duration.as_millis(), statement)),
+        fingerprint: vec!["slow-query".into(), statement.to_string().into()].into(),
+        ..Default::default()
+    };
+    event
+        .extra
+        .insert("duration_ms".to_string(), (duration.as_millis() as u64).into());
+    event
+        .extra
+        .insert("threshold_ms".to_string(), (threshold.as_millis() as u64).into());
+    event
+}
+
+pub trait QueryFutureExt<T: RowCount>: Future<Output = Result<T, sqlx::Error>> + Sized {
+    fn traced_query(self, sql: &str) -> impl Future<Output = Result<T, sqlx::Error>> {
+        instrument(sql, self)
+    }
+}
+
+impl<T: RowCount, F: Future<Output = Result<T, sqlx::Error>>> QueryFutureExt<T> for F {}
@@ -0,0 +1,174 @@
+//! Rolling per-issue error counts for this process.
+//!
+//! Counted in `before_send` before filtering and sampling, so the numbers
+//! reflect what the process produced, not what reached Bugsink. Useful for
+//! admission control ("shed load while the DB errors exceed 50/min").
+//!
+//! Issues are told apart by [`fingerprint::issue_key`] and shown by their
+//! title, the primary exception's type and value or the message.
+
+use super::{fingerprint::issue_key, issue_title};
+use sentry::protocol::Event;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Distinct issues tracked before the least recently seen one is evicted.
+const MAX_ISSUES: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct IssueStats {
+    pub key: u64,
+    /// Title of the first event counted.
+    pub issue: String,
+    pub error_type: Option<String>,
+    pub total: u64,
+    pub in_window: usize,
+    pub per_minute: f64,
+    pub last_seen: SystemTime,
+}
+
+struct Counter {
+    issue: String,
+    error_type: Option<String>,
+    total: u64,
+    recent: VecDeque<Instant>,
+    last_seen: SystemTime,
+}
+
+pub struct ErrorStats {
+    window: Duration,
+    counters: Mutex<HashMap<u64, Counter>>,
+}
+
+impl ErrorStats {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    pub fn record(&self, event: &Event<'_>) {
+        let error_type = event.exception.values.last().map(|e| e.ty.clone());
+        self.record_key(issue_key(event), || issue_title(event), error_type);
+    }
+
+    /// Count an issue known by title only; it is keyed like an event with that message.
+    pub fn record_issue(&self, issue: &str, error_type: Option<String>) {
+        let event = Event {
+            message: Some(issue.to_string()),
+            ..Default::default()
+        };
+        self.record_key(issue_key(&event), || issue.to_string(), error_type);
+    }
+
+    fn record_key(&self, key: u64, issue: impl FnOnce() -> String, error_type: Option<String>) {
+        let now = Instant::now();
+        let mut counters = self.counters.lock().unwrap();
+
+        if !counters.contains_key(&key) && counters.len() >= MAX_ISSUES {
+            let stalest = counters.iter().min_by_key(|(_, c)| c.last_seen).map(|(k, _)| *k);
+            if let Some(stalest) = stalest {
+                counters.remove(&stalest);
+            }
+        }
+
+        let counter = counters.entry(key).or_insert_with(|| Counter {
+            issue: issue(),
+            error_type,
+            total: 0,
+            recent: VecDeque::new(),
+            last_seen: SystemTime::now(),
+        });
+        counter.total += 1;
+        counter.last_seen = SystemTime::now();
+        counter.recent.push_back(now);
+        Self::trim(&mut counter.recent, self.window, now);
+    }
+
+    /// Occurrences within the window of the issues with this title.
+    pub fn count(&self, issue: &str) -> usize {
+        let now = Instant::now();
+        let mut counters = self.counters.lock().unwrap();
+        counters
+            .values_mut()
+            .filter(|c| c.issue == issue)
+            .map(|c| {
+                Self::trim(&mut c.recent, self.window, now);
+                c.recent.len()
+            })
+            .sum()
+    }
+
+    /// Occurrences within the window of the issue `event` belongs to.
+    pub fn count_event(&self, event: &Event<'_>) -> usize {
+        let now = Instant::now();
+        let mut counters = self.counters.lock().unwrap();
+        counters.get_mut(&issue_key(event)).map_or(0, |c| {
+            Self::trim(&mut c.recent, self.window, now);
+            c.recent.len()
+        })
+    }
+
+    /// Occurrences of an error type (e.g. "DatabaseError") within the window, across issues.
+    pub fn count_by_type(&self, error_type: &str) -> usize {
+        let now = Instant::now();
+        let mut counters = self.counters.lock().unwrap();
+        counters
+            .values_mut()
+            .filter(|c| c.error_type.as_deref() == Some(error_type))
+            .map(|c| {
+                Self::trim(&mut c.recent, self.window, now);
+                c.recent.len()
+            })
+            .sum()
+    }
+
+    /// Occurrences per minute of an issue, averaged over the window.
+    pub fn rate_per_minute(&self, issue: &str) -> f64 {
+        self.count(issue) as f64 * 60.0 / self.window.as_secs_f64()
+    }
+
+    /// All tracked issues, busiest first.
+    pub fn snapshot(&self) -> Vec<IssueStats> {
+        let now = Instant::now();
+        let mut counters = self.counters.lock().unwrap();
+        let mut issues: Vec<IssueStats> = counters
+            .iter_mut()
+            .map(|(key, c)| {
+                Self::trim(&mut c.recent, self.window, now);
+                IssueStats {
+                    key: *key,
+                    issue: c.issue.clone(),
+                    error_type: c.error_type.clone(),
+                    total: c.total,
+                    in_window: c.recent.len(),
+                    per_minute: c.recent.len() as f64 * 60.0 / self.window.as_secs_f64(),
+                    last_seen: c.last_seen,
+                }
+            })
+            .collect();
+        issues.sort_by(|a, b| b.in_window.cmp(&a.in_window).then(b.total.cmp(&a.total)));
+        issues
+    }
+
+    fn trim(recent: &mut VecDeque<Instant>, window: Duration, now: Instant) {
+        while recent.front().is_some_and(|t| now.duration_since(*t) > window) {
+            recent.pop_front();
+        }
+    }
+}
+
+static STATS: OnceLock<ErrorStats> = OnceLock::new();
+
+/// Process-wide statistics with a 5 minute window.
+pub fn global() -> &'static ErrorStats {
+    STATS.get_or_init(|| ErrorStats::new(Duration::from_secs(300)))
+}
@@ -0,0 +1,226 @@
+//! Host, OS and build details on every event.
+//!
+//! The SDK's context integration reports the kernel, rustc and CPU
+//! architecture; [`SystemContextIntegration`](system::SystemContextIntegration)
+//! adds what is needed to tell hosts and builds apart:
+//!
+//! - `os`: distribution from `/etc/os-release`
+//! - `device`: hostname, CPU count and total memory
+//! - `app`: package name, version and build profile (debug / release)
+//! - `container`: cgroup version and the memory and CPU limits, when running
+//!   in a container
+//!
+//! Everything is read once, on the first event. Fields already set on the
+//! event are kept.
+
+use sentry::{
+    protocol::{AppContext, Context, DeviceContext, Event, OsContext, RuntimeContext},
+    ClientOptions, Integration,
+};
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, path::Path, sync::OnceLock};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemInfo {
+    pub hostname: Option<String>,
+    /// `PRETTY_NAME`, `ID` and `VERSION_ID` from os-release.
+    pub distribution: Option<(String, String, String)>,
+    pub cpu_count: Option<usize>,
+    pub memory_bytes: Option<u64>,
+    pub container: Option<ContainerInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainerInfo {
+    pub cgroup_version: u8,
+    pub memory_limit_bytes: Option<u64>,
+    /// CPUs granted by the CFS quota.
+    pub cpu_limit: Option<f64>,
+}
+
+impl SystemInfo {
+    pub fn detect() -> Self {
+        let hostname = fs::read_to_string("/proc/sys/kernel/hostname")
+            .ok()
+            .map(|name| name.trim().to_string())
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .or_else(|| std::env::var("COMPUTERNAME").ok())
+            .filter(|name| !name.is_empty());
+        let distribution = fs::read_to_string("/etc/os-release")
+            .ok()
+            .and_then(|content| parse_os_release(&content));
+        let memory_bytes = fs::read_to_string("/proc/meminfo").ok().and_then(|meminfo| {
+            let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+            let kb: u64 = line["MemTotal:".len()..]
+                .trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse()
+                .ok()?;
+            Some(kb * 1024)
+        });
+        Self {
+            hostname,
+            distribution,
+            cpu_count: std::thread::available_parallelism().ok().map(usize::from),
+            memory_bytes,
+            container: detect_container(),
+        }
+    }
+
+    fn contexts(&self) -> Vec<(&'static str, Context)> {
+        let mut os = OsContext::default();
+        if let Some((pretty_name, id, version)) = &self.distribution {
+            os.other.insert("distribution".to_string(), pretty_name.as_str().into());
+            os.other.insert("distribution_name".to_string(), id.as_str().into());
+            os.other
+                .insert("distribution_version".to_string(), version.as_str().into());
+        }
+
+        let mut device = DeviceContext {
+            name: self.hostname.clone(),
+            memory_size: self.memory_bytes,
+            ..Default::default()
+        };
+        if let Some(cpu_count) = self.cpu_count {
+            device.other.insert("processor_count".to_string(), cpu_count.into());
+        }
+
+        let app = AppContext {
+            app_name: Some(env!("CARGO_PKG_NAME").to_string()),
+            app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            build_type: Some(if cfg!(debug_assertions) { "debug" } else { "release" }.to_string()),
+            ..Default::default()
+        };
+
+        let mut contexts = vec![("os", os.into()), ("device", device.into()), ("app", app.into())];
+        if let Some(container) = &self.container {
+            let mut map = BTreeMap::new();
+            map.insert("cgroup_version".to_string(), container.cgroup_version.into());
+            if let Some(limit) = container.memory_limit_bytes {
+                map.insert("memory_limit_bytes".to_string(), limit.into());
+            }
+            if let Some(cpus) = container.cpu_limit {
+                map.insert("cpu_limit".to_string(), cpus.into());
+            }
+            contexts.push(("container", Context::Other(map)));
+        }
+        contexts
+    }
+}
+
+/// `(PRETTY_NAME, ID, VERSION_ID)`; missing keys are left empty.
+pub fn parse_os_release(content: &str) -> Option<(String, String, String)> {
+    let values: BTreeMap<&str, String> = content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches('"').to_string()))
+        .collect();
+    let value = |key| values.get(key).cloned().unwrap_or_default();
+    let id = value("ID");
+    if id.is_empty() {
+        return None;
+    }
+    Some((value("PRETTY_NAME"), id, value("VERSION_ID")))
+}
+
+fn detect_container() -> Option<ContainerInfo> {
+    let cgroup = fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+    let in_container = Path::new("/.dockerenv").exists()
+        || Path::new("/run/.containerenv").exists()
+        || ["docker", "kubepods", "containerd", "libpod"]
+            .iter()
+            .any(|marker| cgroup.contains(marker))
+        || std::env::var_os("KUBERNETES_SERVICE_HOST").is_some();
+    if !in_container {
+        return None;
+    }
+
+    let v2 = Path::new("/sys/fs/cgroup/cgroup.controllers").exists();
+    let cpu_limit = if v2 {
+        // "max 100000" or "<quota> <period>"
+        fs::read_to_string("/sys/fs/cgroup/cpu.max").ok().and_then(|max| {
+            let (quota, period) = max.trim().split_once(' ')?;
+            Some(quota.parse::<f64>().ok()? / period.parse::<f64>().ok()?)
+        })
+    } else {
+        let read = |name: &str| {
+            fs::read_to_string(format!("/sys/fs/cgroup/cpu/{}", name))
+                .ok()?
+                .trim()
+                .parse::<f64>()
+                .ok()
+        };
+        read("cpu.cfs_quota_us")
+            .filter(|quota| *quota > 0.0)
+            .zip(read("cpu.cfs_period_us"))
+            .map(|(quota, period)| quota / period)
+    };
+    Some(ContainerInfo {
+        cgroup_version: if v2 { 2 } else { 1 },
+        memory_limit_bytes: crate::memory::cgroup_limit(),
+        cpu_limit,
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct SystemContextIntegration {
+    info: OnceLock<SystemInfo>,
+}
+
+impl SystemContextIntegration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use fixed system details instead of detecting them.
+    pub fn with_info(info: SystemInfo) -> Self {
+        Self {
+            info: OnceLock::from(info),
+        }
+    }
+}
+
+impl Integration for SystemContextIntegration {
+    fn name(&self) -> &'static str {
+        "system-context"
+    }
+
+    fn process_event(&self, mut event: Event<'static>, _options: &ClientOptions) -> Option<Event<'static>> {
+        let info = self.info.get_or_init(SystemInfo::detect);
+        for (key, context) in info.contexts() {
+            match event.contexts.remove(key) {
+                Some(existing) => {
+                    event.contexts.insert(key.to_string(), merge(existing, context));
+                }
+                None => {
+                    event.contexts.insert(key.to_string(), context);
+                }
+            }
+        }
+        if !event.contexts.contains_key("rust") {
+            // The SDK's context integration is disabled; keep at least the runtime name
+            let runtime = RuntimeContext {
+                name: Some("rustc".to_string()),
+                ..Default::default()
+            };
+            event.contexts.insert("rust".to_string(), runtime.into());
+        }
+        Some(event)
+    }
+}
+
+/// Fill fields missing from `existing` with the ones from `added`.
+fn merge(existing: Context, added: Context) -> Context {
+    let (Ok(Value::Object(mut merged)), Ok(Value::Object(added))) =
+        (serde_json::to_value(&existing), serde_json::to_value(&added))
+    else {
+        return existing;
+    };
+    for (key, value) in added {
+        if key != "type" {
+            merged.entry(key).or_insert(value);
+        }
+    }
+    serde_json::from_value(Value::Object(merged)).unwrap_or(existing)
+}
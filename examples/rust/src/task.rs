@@ -0,0 +1,61 @@
+//! Scope inheritance for tokio tasks.
+//!
+//! `Hub::current()` is thread-local: a spawned task sees whatever hub its
+//! worker thread happens to have, so breadcrumbs, tags and spans set inside
+//! it end up on an unrelated scope. These helpers give each task its own fork
+//! of the spawning task's hub (user, tags, active span), bound for the task's
+//! whole lifetime.
+//!
+//! ```ignore
+//! use observability::task::{spawn_with_hub, TaskHubExt};
+//!
+//! spawn_with_hub(async move { send_receipt(order).await });
+//! let stream = consume(topic).fork_hub();
+//! ```
+
+use sentry::{Hub, SentryFutureExt};
+use std::{future::Future, sync::Arc};
+use tokio::task::JoinHandle;
+
+/// A new hub inheriting the current scope.
+pub fn fork_current() -> Arc<Hub> {
+    Arc::new(Hub::new_from_top(Hub::current()))
+}
+
+/// `tokio::spawn` with the task bound to a fork of the current hub.
+pub fn spawn_with_hub<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future.bind_hub(fork_current()))
+}
+
+/// `tokio::task::spawn_blocking` with the closure run on a fork of the current hub.
+pub fn spawn_blocking_with_hub<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let hub = fork_current();
+    tokio::task::spawn_blocking(move || Hub::run(hub, f))
+}
+
+pub trait TaskHubExt: Future + Sized {
+    /// Bind to a fork of the current hub, taken now rather than at first poll.
+    /// (Named to avoid clashing with `SentryFutureExt::bind_hub`, which takes the hub.)
+    fn fork_hub(self) -> sentry::SentryFuture<Self> {
+        self.bind_hub(fork_current())
+    }
+
+    /// Spawn on tokio with a fork of the current hub.
+    fn spawn_with_hub(self) -> JoinHandle<Self::Output>
+    where
+        Self: Send + 'static,
+        Self::Output: Send + 'static,
+    {
+        spawn_with_hub(self)
+    }
+}
+
+impl<F: Future> TaskHubExt for F {}
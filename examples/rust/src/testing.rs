@@ -0,0 +1,215 @@
+//! In-memory transport for tests and dry runs.
+//!
+//! [`TestTransport::hub`](testing::TestTransport::hub) returns a hub whose
+//! client runs the crate's `before_send` pipeline and records the result, so
+//! a test can assert on exactly what would have been sent:
+//!
+//! ```ignore
+//! let transport = TestTransport::new();
+//! Hub::run(transport.hub(), || service.fetch_data("error"));
+//! transport.assert_event_captured(|event| event.level == Level::Error);
+//! ```
+//!
+//! As an [`ObservabilityTransport`](local::ObservabilityTransport) it also
+//! gives a dry-run mode: `SentryService::builder().transport(TestTransport::new())`.
+//!
+//! [`snapshot`](testing::snapshot) renders an event as stable JSON for
+//! snapshot tests of the `before_send` and scrubbing output. Timestamps,
+//! UUIDs, trace ids, the error reference, absolute path prefixes, line
+//! numbers and machine-specific fields are replaced or dropped:
+//!
+//! ```ignore
+//! insta::assert_snapshot!(testing::snapshot(&transport.last_event().unwrap()));
+//! ```
+
+use super::{before_breadcrumb_handler, before_send_handler, reference};
+use regex::Regex;
+use sentry::{
+    protocol::{Envelope, EnvelopeItem, Event, MonitorCheckIn, Transaction},
+    ClientOptions, Hub, Transport,
+};
+use serde_json::Value;
+use std::{
+    io,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+/// Fields that differ between machines, builds or code edits.
+const VOLATILE_FIELDS: &[&str] = &[
+    "server_name",
+    "sdk",
+    "debug_meta",
+    "abs_path",
+    "lineno",
+    "colno",
+    "instruction_addr",
+    "symbol_addr",
+    "image_addr",
+];
+const VOLATILE_CONTEXTS: &[&str] = &["os", "device", "rust"];
+const TIMESTAMP_FIELDS: &[&str] = &["timestamp", "start_timestamp"];
+const ID_FIELDS: &[&str] = &["trace_id", "span_id", "parent_span_id"];
+
+#[derive(Debug, Clone, Default)]
+pub struct TestTransport {
+    envelopes: Arc<Mutex<Vec<Envelope>>>,
+}
+
+impl TestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hub with an enabled client sending through the crate's pipeline into this transport.
+    pub fn hub(&self) -> Arc<Hub> {
+        let transport = self.clone();
+        let client = sentry::Client::from(ClientOptions {
+            dsn: "https://test@localhost/1".parse().ok(),
+            before_send: Some(Arc::new(before_send_handler)),
+            before_breadcrumb: Some(Arc::new(before_breadcrumb_handler)),
+            transport: Some(Arc::new(move |_: &ClientOptions| {
+                Arc::new(transport.clone()) as Arc<dyn Transport>
+            })),
+            ..Default::default()
+        });
+        Arc::new(Hub::new(Some(Arc::new(client)), Default::default()))
+    }
+
+    pub fn envelopes(&self) -> Vec<Envelope> {
+        self.envelopes.lock().unwrap().clone()
+    }
+
+    pub fn events(&self) -> Vec<Event<'static>> {
+        self.items(|item| match item {
+            EnvelopeItem::Event(event) => Some(event.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn transactions(&self) -> Vec<Transaction<'static>> {
+        self.items(|item| match item {
+            EnvelopeItem::Transaction(transaction) => Some(transaction.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn check_ins(&self) -> Vec<MonitorCheckIn> {
+        self.items(|item| match item {
+            EnvelopeItem::MonitorCheckIn(check_in) => Some(check_in.clone()),
+            _ => None,
+        })
+    }
+
+    fn items<T>(&self, f: impl Fn(&EnvelopeItem) -> Option<T>) -> Vec<T> {
+        let envelopes = self.envelopes.lock().unwrap();
+        envelopes
+            .iter()
+            .flat_map(|envelope| envelope.items().filter_map(&f))
+            .collect()
+    }
+
+    pub fn last_event(&self) -> Option<Event<'static>> {
+        self.events().pop()
+    }
+
+    /// Messages of captured message events, in capture order.
+    pub fn captured_messages(&self) -> Vec<String> {
+        self.events()
+            .into_iter()
+            .filter_map(|event| event.message.or_else(|| event.logentry.map(|entry| entry.message)))
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.envelopes.lock().unwrap().clear();
+    }
+
+    /// Panic unless a captured event matches, listing what was captured.
+    #[track_caller]
+    pub fn assert_event_captured(&self, predicate: impl Fn(&Event<'static>) -> bool) {
+        let events = self.events();
+        if !events.iter().any(predicate) {
+            let captured: Vec<String> = events.iter().map(super::issue_title).collect();
+            panic!("no matching event captured; captured: {:?}", captured);
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_no_events(&self) {
+        let events = self.events();
+        let captured: Vec<String> = events.iter().map(super::issue_title).collect();
+        assert!(captured.is_empty(), "expected no events; captured: {:?}", captured);
+    }
+}
+
+impl Transport for TestTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        self.envelopes.lock().unwrap().push(envelope);
+    }
+}
+
+impl crate::local::ObservabilityTransport for TestTransport {
+    fn write(&self, envelope: &Envelope) -> io::Result<()> {
+        self.send_envelope(envelope.clone());
+        Ok(())
+    }
+}
+
+/// The event as JSON with everything run-specific normalized away.
+pub fn normalize(event: &Event<'static>) -> Value {
+    let mut value = serde_json::to_value(event).unwrap_or_default();
+    if let Some(Value::Object(contexts)) = value.get_mut("contexts") {
+        contexts.retain(|key, _| !VOLATILE_CONTEXTS.contains(&key.as_str()));
+    }
+    if let Some(reference) = value.get_mut("tags").and_then(|tags| tags.get_mut(reference::TAG)) {
+        *reference = Value::from("[reference]");
+    }
+    normalize_value(&mut value);
+    value
+}
+
+/// Pretty-printed [`normalize`] output with sorted keys.
+pub fn snapshot(event: &Event<'static>) -> String {
+    serde_json::to_string_pretty(&normalize(event)).unwrap_or_default()
+}
+
+fn normalize_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !VOLATILE_FIELDS.contains(&key.as_str()));
+            for (key, value) in map.iter_mut() {
+                if value.is_null() {
+                    continue;
+                }
+                if TIMESTAMP_FIELDS.contains(&key.as_str()) {
+                    *value = Value::from("[timestamp]");
+                } else if ID_FIELDS.contains(&key.as_str()) {
+                    *value = Value::from("[id]");
+                } else {
+                    normalize_value(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(normalize_value),
+        Value::String(s) => *s = normalize_str(s),
+        _ => {}
+    }
+}
+
+fn normalize_str(s: &str) -> String {
+    static PATTERNS: OnceLock<[(Regex, &str); 3]> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            (
+                Regex::new(r"(?i)\b[0-9a-f]{8}-?[0-9a-f]{4}-?[0-9a-f]{4}-?[0-9a-f]{4}-?[0-9a-f]{12}\b").unwrap(),
+                "[uuid]",
+            ),
+            // Unix directories at the start of the string or after whitespace, quotes or `=`
+            (Regex::new(r#"(^|[\s"'(=])(?:/[\w.@+-]+)+/"#).unwrap(), "${1}[path]/"),
+            (Regex::new(r"\b[A-Za-z]:\\(?:[\w.@+ -]+\\)+").unwrap(), r"[path]\"),
+        ]
+    });
+    patterns.iter().fold(s.to_string(), |s, (regex, replacement)| {
+        regex.replace_all(&s, *replacement).into_owned()
+    })
+}
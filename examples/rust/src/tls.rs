@@ -0,0 +1,146 @@
+//! TLS settings for the reqwest-based transports (features `rustls` and
+//! `http-transport`), so self-hosted Bugsink behind an internal CA works
+//! without disabling certificate verification.
+//!
+//! Root certificates from [`TlsOptions::ca_bundle`](tls::TlsOptions::ca_bundle)
+//! (or `SENTRY_CA_BUNDLE`) are trusted in addition to the bundled webpki
+//! roots. A client certificate and key enable mutual TLS, and a minimum
+//! protocol version can be enforced. Unreadable or invalid files are reported
+//! at init.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+impl From<TlsVersion> for reqwest::tls::Version {
+    fn from(version: TlsVersion) -> Self {
+        match version {
+            TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+        }
+    }
+}
+
+impl FromStr for TlsVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1.2" => Ok(TlsVersion::Tls12),
+            "1.3" => Ok(TlsVersion::Tls13),
+            other => Err(format!(
+                "unsupported TLS version {:?} (expected \"1.2\" or \"1.3\")",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsOptions {
+    /// PEM bundle of additional root certificates; `None` reads SENTRY_CA_BUNDLE.
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM certificate chain presented for mutual TLS.
+    pub client_cert: Option<PathBuf>,
+    /// PEM (PKCS#8 or RSA) private key of `client_cert`.
+    pub client_key: Option<PathBuf>,
+    pub min_version: Option<TlsVersion>,
+}
+
+impl TlsOptions {
+    pub fn ca_bundle(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_bundle = Some(path.into());
+        self
+    }
+
+    pub fn client_certificate(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.client_cert = Some(cert.into());
+        self.client_key = Some(key.into());
+        self
+    }
+
+    pub fn min_version(mut self, version: TlsVersion) -> Self {
+        self.min_version = Some(version);
+        self
+    }
+
+    pub fn root_certificates(&self) -> Result<Vec<reqwest::Certificate>, String> {
+        let path = match &self.ca_bundle {
+            Some(path) => path.clone(),
+            None => match crate::config::ca_bundle() {
+                Some(path) => path.into(),
+                None => return Ok(Vec::new()),
+            },
+        };
+        reqwest::Certificate::from_pem_bundle(&read(&path)?)
+            .map_err(|e| format!("invalid CA bundle {}: {}", path.display(), e))
+    }
+
+    pub fn identity(&self) -> Result<Option<reqwest::Identity>, String> {
+        let (cert, key) = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => (cert, key),
+            (None, None) => return Ok(None),
+            _ => return Err("client certificate and key must be set together".to_string()),
+        };
+        let mut pem = read(key)?;
+        pem.extend(read(cert)?);
+        reqwest::Identity::from_pem(&pem)
+            .map(Some)
+            .map_err(|e| format!("invalid client certificate {}: {}", cert.display(), e))
+    }
+
+    /// Check that all configured files can be loaded.
+    pub fn validate(&self) -> Result<(), String> {
+        self.root_certificates()?;
+        self.identity()?;
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))
+}
+
+/// Apply [`TlsOptions`] to a reqwest `ClientBuilder`, async or blocking.
+macro_rules! apply {
+    ($options:expr, $builder:expr) => {
+        (|| -> Result<_, String> {
+            let options: &$crate::tls::TlsOptions = $options;
+            let mut builder = $builder.use_rustls_tls().tls_built_in_root_certs(true);
+            for cert in options.root_certificates()? {
+                builder = builder.add_root_certificate(cert);
+            }
+            if let Some(identity) = options.identity()? {
+                builder = builder.identity(identity);
+            }
+            if let Some(version) = options.min_version {
+                builder = builder.min_tls_version(version.into());
+            }
+            Ok(builder)
+        })()
+    };
+}
+pub(crate) use apply;
+
+fn state() -> &'static RwLock<TlsOptions> {
+    static OPTIONS: OnceLock<RwLock<TlsOptions>> = OnceLock::new();
+    OPTIONS.get_or_init(Default::default)
+}
+
+/// Set the options used by transports created afterwards.
+pub fn configure(options: TlsOptions) {
+    *state().write().unwrap() = options;
+}
+
+pub fn options() -> TlsOptions {
+    state().read().unwrap().clone()
+}
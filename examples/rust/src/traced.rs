@@ -0,0 +1,64 @@
+//! Performance instrumentation for whole functions without restructuring
+//! them into `with_transaction` / `with_span` closures.
+//!
+//! The function runs inside a child of the active span, or a new transaction
+//! when there is none. The name defaults to the function path and the op to
+//! `function`; both can be overridden:
+//!
+//! ```ignore
+//! #[traced_transaction(op = Op::DbQuery, name = "users.load")]
+//! pub async fn load_user(&self, id: u64) -> Result<User, AppError> {
+//!     ...
+//! }
+//!
+//! #[traced_transaction]
+//! fn render_invoice(order: &Order) -> String { ... }
+//! ```
+
+use super::ops;
+use sentry::{Hub, SentryFutureExt, TransactionContext, TransactionOrSpan};
+use std::{future::Future, sync::Arc};
+
+/// Start a child of the active span, or a transaction when there is none.
+pub fn start(name: &str, op: impl AsRef<str>) -> TransactionOrSpan {
+    let op = op.as_ref();
+    ops::debug_assert_canonical(op);
+    match sentry::configure_scope(|scope| scope.get_span()) {
+        Some(parent) => parent.start_child(op, name).into(),
+        None => sentry::start_transaction(TransactionContext::new(name, op)).into(),
+    }
+}
+
+/// Run `f` with `span` as the active span, then finish it.
+pub fn run<R>(span: TransactionOrSpan, f: impl FnOnce() -> R) -> R {
+    let parent = sentry::configure_scope(|scope| scope.get_span());
+    sentry::configure_scope(|scope| scope.set_span(Some(span.clone())));
+    let output = f();
+    span.finish();
+    sentry::configure_scope(|scope| scope.set_span(parent));
+    output
+}
+
+/// Await `future` with `span` as the active span on a hub bound to the
+/// future, so the binding holds across await points and worker threads.
+pub async fn run_async<T, F: Future<Output = T>>(span: TransactionOrSpan, future: F) -> T {
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    hub.configure_scope(|scope| scope.set_span(Some(span.clone())));
+    let output = future.bind_hub(hub).await;
+    span.finish();
+    output
+}
+
+/// `tracing::Instrument`-style span binding for any future:
+///
+/// ```ignore
+/// let body = client.get(url).send().in_span(Op::HttpClient, "GET /prices").await?;
+/// ```
+pub trait SpanFutureExt: Future + Sized {
+    /// Run the future inside a child of the active span (or a new transaction).
+    fn in_span(self, op: impl AsRef<str>, name: &str) -> impl Future<Output = Self::Output> {
+        run_async(start(name, op), self)
+    }
+}
+
+impl<F: Future> SpanFutureExt for F {}
@@ -0,0 +1,170 @@
+//! A preconfigured `sentry-tracing` layer with per-target decisions.
+//!
+//! Event rules are tried in order; the first whose target glob matches and
+//! whose minimum level the event reaches decides between ignore, breadcrumb,
+//! message event and exception event. Unmatched events keep the SDK default
+//! (errors are exceptions, warnings and info breadcrumbs). Spans become
+//! transactions and child spans unless turned off, globally or per target.
+//!
+//! ```ignore
+//! use sentry::integrations::tracing::EventFilter;
+//!
+//! let layer = TracingLayer::new()
+//!     .target("sqlx::query", EventFilter::Breadcrumb)
+//!     .target_at("my_app::*", Level::ERROR, EventFilter::Event)
+//!     .target("hyper::*", EventFilter::Ignore)
+//!     .span_target("tower_http::*", false)
+//!     .build();
+//! tracing_subscriber::registry().with(fmt::layer()).with(layer).init();
+//! ```
+//!
+//! In the config file (feature `config-file`), built from `Config::tracing`:
+//!
+//! ```toml
+//! [tracing]
+//! spans = true
+//! span_attributes = false
+//!
+//! [[tracing.targets]]
+//! target = "sqlx::query"
+//! action = "breadcrumb"      # or "ignore", "event", "exception"
+//!
+//! [[tracing.targets]]
+//! target = "my_app::*"
+//! level = "error"
+//! action = "event"
+//! ```
+
+use super::{sampling::glob_match, EventFilter};
+use sentry::integrations::tracing::SentryLayer;
+use tracing::{Level, Metadata, Subscriber};
+use tracing_subscriber::registry::LookupSpan;
+
+#[derive(Debug, Clone)]
+pub struct TargetRule {
+    /// Glob over the event target.
+    pub target: String,
+    /// Least severe level the rule applies to; `None` for all levels.
+    pub level: Option<Level>,
+    pub action: EventFilter,
+}
+
+impl TargetRule {
+    pub fn matches(&self, metadata: &Metadata<'_>) -> bool {
+        // More severe levels compare as smaller
+        self.level.is_none_or(|level| *metadata.level() <= level) && glob_match(&self.target, metadata.target())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TracingLayer {
+    pub rules: Vec<TargetRule>,
+    /// Map spans to transactions and child spans.
+    pub spans: bool,
+    /// Per-target span toggles, first match wins.
+    pub span_targets: Vec<(String, bool)>,
+    /// Copy span fields onto events recorded inside them.
+    pub span_attributes: bool,
+}
+
+impl Default for TracingLayer {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            spans: true,
+            span_targets: Vec::new(),
+            span_attributes: false,
+        }
+    }
+}
+
+impl TracingLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle all events from targets matching `glob` with `action`.
+    pub fn target(self, glob: &str, action: EventFilter) -> Self {
+        self.rule(TargetRule {
+            target: glob.to_string(),
+            level: None,
+            action,
+        })
+    }
+
+    /// Handle events at `level` or more severe from targets matching `glob` with `action`.
+    pub fn target_at(self, glob: &str, level: Level, action: EventFilter) -> Self {
+        self.rule(TargetRule {
+            target: glob.to_string(),
+            level: Some(level),
+            action,
+        })
+    }
+
+    pub fn rule(mut self, rule: TargetRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn spans(mut self, enabled: bool) -> Self {
+        self.spans = enabled;
+        self
+    }
+
+    pub fn span_target(mut self, glob: &str, enabled: bool) -> Self {
+        self.span_targets.push((glob.to_string(), enabled));
+        self
+    }
+
+    pub fn span_attributes(mut self, enabled: bool) -> Self {
+        self.span_attributes = enabled;
+        self
+    }
+
+    pub fn event_filter(&self, metadata: &Metadata<'_>) -> EventFilter {
+        self.rules.iter().find(|rule| rule.matches(metadata)).map_or_else(
+            || sentry::integrations::tracing::default_event_filter(metadata),
+            |rule| rule.action,
+        )
+    }
+
+    pub fn span_filter(&self, metadata: &Metadata<'_>) -> bool {
+        if !self.spans {
+            return false;
+        }
+        self.span_targets
+            .iter()
+            .find(|(glob, _)| glob_match(glob, metadata.target()))
+            .map_or_else(
+                || sentry::integrations::tracing::default_span_filter(metadata),
+                |(_, enabled)| *enabled,
+            )
+    }
+
+    pub fn build<S>(self) -> SentryLayer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let events = self.clone();
+        let spans = self.clone();
+        let layer = sentry::integrations::tracing::layer()
+            .event_filter(move |metadata| events.event_filter(metadata))
+            .span_filter(move |metadata| spans.span_filter(metadata));
+        if self.span_attributes {
+            layer.enable_span_attributes()
+        } else {
+            layer
+        }
+    }
+}
+
+/// `"ignore"`, `"breadcrumb"`, `"event"` or `"exception"`.
+pub fn parse_action(action: &str) -> Option<EventFilter> {
+    match action {
+        "ignore" => Some(EventFilter::Ignore),
+        "breadcrumb" => Some(EventFilter::Breadcrumb),
+        "event" => Some(EventFilter::Event),
+        "exception" => Some(EventFilter::Exception),
+        _ => None,
+    }
+}
@@ -0,0 +1,85 @@
+use sentry::TransportFactory;
+use std::sync::Arc;
+
+/// Transport factory for the client options, behind the client-side rate
+/// limiter when one is installed. Returns `None` to keep the SDK's default transport.
+pub fn factory() -> Option<Arc<dyn TransportFactory>> {
+    let mut factory = base_factory();
+    if crate::routing::is_enabled() {
+        factory = Some(crate::routing::wrap(factory.unwrap_or_else(default_factory)));
+    }
+    #[cfg(feature = "profiling")]
+    if crate::profiling::is_enabled() {
+        factory = Some(crate::profiling::wrap(factory.unwrap_or_else(default_factory)));
+    }
+    if crate::replay::is_recording() {
+        factory = Some(crate::replay::wrap(factory.unwrap_or_else(default_factory)));
+    }
+    if crate::log_tail::is_enabled() {
+        factory = Some(crate::log_tail::wrap(factory.unwrap_or_else(default_factory)));
+    }
+    if !crate::ratelimit::is_enabled() {
+        return factory;
+    }
+    Some(crate::ratelimit::wrap(factory.unwrap_or_else(default_factory)))
+}
+
+fn default_factory() -> Arc<dyn TransportFactory> {
+    Arc::new(sentry::transports::DefaultTransportFactory)
+}
+
+fn base_factory() -> Option<Arc<dyn TransportFactory>> {
+    if let Some(local) = crate::local::installed() {
+        return Some(crate::local::factory(local));
+    }
+
+    #[cfg(feature = "kafka")]
+    if let Some(options) = crate::kafka::options() {
+        return Some(crate::kafka::factory(options));
+    }
+
+    #[cfg(feature = "offline-spool")]
+    if let Some(dir) = crate::config::spool_dir() {
+        return Some(crate::spool::factory(dir));
+    }
+
+    #[cfg(feature = "http-transport")]
+    {
+        return Some(crate::http::factory());
+    }
+
+    #[cfg(all(feature = "rustls", not(feature = "http-transport")))]
+    {
+        return Some(rustls::factory());
+    }
+
+    #[allow(unreachable_code)]
+    None
+}
+
+/// Pure-rustls transport: bundled webpki roots plus the [`crate::tls`] options,
+/// no native TLS and no system certificate store. Suitable for static musl
+/// binaries running in scratch containers. Not used with `http-transport`,
+/// which takes over delivery.
+#[cfg(all(feature = "rustls", not(feature = "http-transport")))]
+mod rustls {
+    use sentry::{transports::ReqwestHttpTransport, ClientOptions, Transport, TransportFactory};
+    use std::sync::Arc;
+
+    pub fn factory() -> Arc<dyn TransportFactory> {
+        Arc::new(|options: &ClientOptions| -> Arc<dyn Transport> {
+            Arc::new(ReqwestHttpTransport::with_client(options, client()))
+        })
+    }
+
+    fn client() -> reqwest::Client {
+        let builder = crate::tls::apply!(&crate::tls::options(), reqwest::Client::builder());
+        match builder.and_then(|builder| builder.build().map_err(|e| e.to_string())) {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("Failed to build rustls client, using defaults: {}", e);
+                reqwest::Client::new()
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! systemd `sd_notify` integration for appliance deployments (feature `systemd`).
+//!
+//! Run the unit with `Type=notify` and `WatchdogSec=...`, call `ready()` once
+//! startup is complete and `pet()` from the main loop. Keep-alive pings are sent
+//! only while the loop keeps petting; shortly before the deadline is missed an
+//! event with the recent breadcrumbs is captured and flushed, so the restart
+//! systemd is about to perform shows up in Bugsink with its history.
+
+use sd_notify::NotifyState;
+use sentry::{Hub, Level};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+struct WatchdogState {
+    last_pet: Mutex<Instant>,
+    stopped: AtomicBool,
+}
+
+pub struct SystemdWatchdog {
+    state: Arc<WatchdogState>,
+    timeout: Duration,
+}
+
+impl SystemdWatchdog {
+    /// Start the keep-alive thread.
+    /// Returns `None` when the process does not run under a systemd watchdog.
+    pub fn start() -> Option<Self> {
+        let mut usec = 0;
+        if !sd_notify::watchdog_enabled(false, &mut usec) || usec == 0 {
+            return None;
+        }
+
+        let timeout = Duration::from_micros(usec);
+        let state = Arc::new(WatchdogState {
+            last_pet: Mutex::new(Instant::now()),
+            stopped: AtomicBool::new(false),
+        });
+
+        // Capture on the hub of the thread that owns the main loop,
+        // so its breadcrumbs end up on the event.
+        let hub = Hub::current();
+        let thread_state = Arc::clone(&state);
+        thread::Builder::new()
+            .name("systemd-watchdog".to_string())
+            .spawn(move || run(thread_state, hub, timeout))
+            .ok()?;
+
+        Some(Self { state, timeout })
+    }
+
+    /// Report READY=1 once startup is complete.
+    pub fn ready(&self) {
+        if let Err(e) = sd_notify::notify(false, &[NotifyState::Ready]) {
+            eprintln!("sd_notify READY failed: {}", e);
+        }
+    }
+
+    /// Signal that the main loop is alive.
+    pub fn pet(&self) {
+        *self.state.last_pet.lock().unwrap() = Instant::now();
+    }
+
+    /// Watchdog timeout configured by systemd (`WatchdogSec`).
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl Drop for SystemdWatchdog {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, Ordering::SeqCst);
+        let _ = sd_notify::notify(false, &[NotifyState::Stopping]);
+    }
+}
+
+fn run(state: Arc<WatchdogState>, hub: Arc<Hub>, timeout: Duration) {
+    let tick = timeout / 4;
+    let mut reported = false;
+
+    while !state.stopped.load(Ordering::SeqCst) {
+        let stalled_for = state.last_pet.lock().unwrap().elapsed();
+
+        if stalled_for < timeout / 2 {
+            let _ = sd_notify::notify(false, &[NotifyState::Watchdog]);
+            reported = false;
+        } else if stalled_for >= timeout * 3 / 4 && !reported {
+            hub.with_scope(
+                |scope| {
+                    scope.set_tag("watchdog", "systemd");
+                    scope.set_extra("stalled_ms", (stalled_for.as_millis() as u64).into());
+                    scope.set_extra("timeout_ms", (timeout.as_millis() as u64).into());
+                },
+                || hub.capture_message("Main loop is about to miss the systemd watchdog deadline", Level::Fatal),
+            );
+            if let Some(client) = hub.client() {
+                client.flush(Some(tick));
+            }
+            reported = true;
+        }
+
+        thread::sleep(tick);
+    }
+}